@@ -0,0 +1,125 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::config::DatabaseConfig;
+use crate::connection::{DatabaseConnectionFactory, SqlxMySqlConnection, TiberiusConnection};
+use crate::extract::extractor::DatabaseExtractor;
+use crate::insert::inserter::DatabaseInserter;
+use crate::mappings::Mappings;
+use crate::migrate::migration_options::MigrationOptions;
+use crate::migrate::migration_report::MigrationReport;
+use crate::migrate::migrator::DatabaseMigrator;
+use crate::migrate::observer::MigrationObserver;
+
+// Entry point for applications embedding the migrator, the equivalent of the `migrate` command
+pub struct Migrator {
+    source: DatabaseConfig,
+    target: DatabaseConfig,
+    mappings: Mappings,
+    options: MigrationOptions,
+}
+
+#[derive(Default)]
+pub struct MigratorBuilder {
+    source: Option<DatabaseConfig>,
+    target: Option<DatabaseConfig>,
+    mappings: Option<Mappings>,
+    mappings_file: Option<PathBuf>,
+    options: Option<MigrationOptions>,
+    observers: Vec<Arc<dyn MigrationObserver>>,
+}
+
+impl Migrator {
+    pub fn builder() -> MigratorBuilder {
+        MigratorBuilder::default()
+    }
+
+    pub async fn run(&self) -> Result<MigrationReport> {
+        let tiberius_connection =
+            DatabaseConnectionFactory::<TiberiusConnection>::new(self.source.clone())
+                .create_connection(self.options.source_pool_size())
+                .await?;
+        let sqlx_connection =
+            DatabaseConnectionFactory::<SqlxMySqlConnection>::new(self.target.clone())
+                .create_connection(self.options.target_pool_size())
+                .await?;
+
+        let mut migrator = DatabaseMigrator::new(
+            DatabaseExtractor::new(tiberius_connection.pool)
+                .with_sparse_column_sets(self.options.sparse_column_sets)
+                .with_extraction_consistency(self.options.extraction_consistency),
+            DatabaseInserter::new(sqlx_connection),
+            self.mappings.clone(),
+            self.options.clone(),
+        );
+
+        migrator.run().await.context("Migration failed")
+    }
+}
+
+impl MigratorBuilder {
+    // The MSSQL database to migrate
+    pub fn source(mut self, config: DatabaseConfig) -> Self {
+        self.source = Some(config);
+        self
+    }
+
+    // The MySQL database to migrate into
+    pub fn target(mut self, config: DatabaseConfig) -> Self {
+        self.target = Some(config);
+        self
+    }
+
+    pub fn mappings(mut self, mappings: Mappings) -> Self {
+        self.mappings = Some(mappings);
+        self
+    }
+
+    // Mappings file in the format of `mappings.toml`, read by `build`
+    pub fn mappings_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.mappings_file = Some(path.into());
+        self
+    }
+
+    pub fn options(mut self, options: MigrationOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    // Notified from the migration tasks as tables and batches progress, closures taking a
+    // `&MigrationEvent` are observers too
+    pub fn observer(mut self, observer: impl MigrationObserver + 'static) -> Self {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    pub fn build(self) -> Result<Migrator> {
+        let mappings = match (self.mappings, self.mappings_file) {
+            (Some(mappings), _) => mappings,
+            (None, Some(path)) => Mappings::load(&path)
+                .with_context(|| format!("Failed to load mappings file {}", path.display()))?,
+            (None, None) => return Err(anyhow!("Missing mappings")),
+        };
+
+        let options = self
+            .options
+            .ok_or_else(|| anyhow!("Missing migration options"))?;
+        let options = self
+            .observers
+            .into_iter()
+            .fold(options, |options, observer| options.with_observer(observer));
+
+        Ok(Migrator {
+            source: self
+                .source
+                .ok_or_else(|| anyhow!("Missing source database"))?,
+            target: self
+                .target
+                .ok_or_else(|| anyhow!("Missing target database"))?,
+            mappings,
+            options,
+        })
+    }
+}
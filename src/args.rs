@@ -1,9 +1,19 @@
+use std::iter;
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::thread::available_parallelism;
+use std::time::Duration;
 
+use anyhow::{anyhow, bail, Error};
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeZone};
 use structopt::clap::AppSettings;
 use structopt::lazy_static::lazy_static;
 use structopt::StructOpt;
 
+use crate::migrate::migration_options::{CheckFallback, OnError, Phase, SchedulerMode};
+use crate::migrate::strict_types::GuidFormat;
+use crate::sync::syncer::SchemaDriftAction;
+
 lazy_static! {
     static ref DEFAULT_PARALLELISM: String = get_default_parallelism().to_string();
 }
@@ -11,11 +21,19 @@ lazy_static! {
 #[derive(Debug, StructOpt)]
 #[structopt(
 name = "DBMigrator",
-version = env ! ("CARGO_PKG_VERSION"),
 about = "A Rust project to migrate MSSQL databases to MySQL, including table structures, column data types, constraints and table data rows.\n\nGitHub: https://github.com/bitalizer/db-migrator",
 setting = AppSettings::ColoredHelp,
+global_setting = AppSettings::DisableVersion,
 )]
 pub struct Args {
+    /// Prints version information
+    #[structopt(short = "V", long = "version")]
+    pub version: bool,
+
+    /// List the optional features of this build along with the version
+    #[structopt(long = "features", requires = "version")]
+    pub features: bool,
+
     /// Activate verbose mode
     #[structopt(short = "v", long = "verbose")]
     pub verbose: bool,
@@ -24,21 +42,511 @@ pub struct Args {
     #[structopt(short = "q", long = "quiet")]
     pub quiet: bool,
 
+    /// Path to the config file
+    #[structopt(
+        long = "config",
+        env = "DB_MIGRATOR_CONFIG",
+        default_value = "config.toml",
+        parse(from_os_str)
+    )]
+    pub config: PathBuf,
+
+    /// Path to the mappings file
+    #[structopt(
+        long = "mappings",
+        env = "DB_MIGRATOR_MAPPINGS",
+        default_value = "mappings.toml",
+        parse(from_os_str)
+    )]
+    pub mappings: PathBuf,
+
+    /// Path to a dotenv file with variables for `${NAME}` references in the config [default: .env if present]
+    #[structopt(long = "env-file", env = "DB_MIGRATOR_ENV_FILE", parse(from_os_str))]
+    pub env_file: Option<PathBuf>,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct MigrationArgs {
     /// Drop tables before migration
     #[structopt(short = "d", long = "drop")]
     pub drop: bool,
 
+    /// Rename existing tables to `<name>__backup_<run id>` instead of dropping or truncating them
+    #[structopt(long = "backup", conflicts_with = "drop")]
+    pub backup: bool,
+
+    /// Keep existing rows and update them by primary key instead of failing on non-empty tables
+    #[structopt(long = "upsert", conflicts_with_all = &["drop", "backup"])]
+    pub upsert: bool,
+
+    /// Keep existing rows and only insert rows whose primary key isn't in the target yet, counting the ignored ones
+    #[structopt(long = "append", conflicts_with_all = &["drop", "backup", "upsert"])]
+    pub append: bool,
+
+    /// Load each table into `<name>__new` and swap it with the live table once its row count is verified
+    #[structopt(long = "shadow", conflicts_with_all = &["drop", "backup", "upsert", "append"])]
+    pub shadow: bool,
+
+    /// Alter existing target tables whose columns differ from the mapped schema instead of failing them
+    #[structopt(long = "sync-schema")]
+    pub sync_schema: bool,
+
     /// Create constraints
     #[structopt(short = "c", long = "constraints")]
     pub constraints: bool,
 
+    /// Drop constraints already on the target and add them again instead of leaving them as they are
+    #[structopt(long = "recreate-constraints", requires = "constraints")]
+    pub recreate_constraints: bool,
+
+    /// Run a single phase against the current state of the target instead of the whole migration
+    #[structopt(long = "phase", possible_values = &["schema", "data", "constraints", "verify"], conflicts_with = "shadow")]
+    pub phase: Option<Phase>,
+
+    /// Emulate CHECK constraints with triggers when the target server doesn't enforce them
+    #[structopt(long = "check-triggers", requires = "constraints")]
+    pub check_triggers: bool,
+
+    /// Handling of CHECK constraints without a MySQL translation, `skip` drops them with a warning, `fail` fails the table
+    #[structopt(long = "check-fallback", default_value = "skip", possible_values = &["skip", "fail"])]
+    pub check_fallback: CheckFallback,
+
+    /// Retry failed batches row by row and keep rejected rows in the `_quarantine` database
+    #[structopt(long = "quarantine")]
+    pub quarantine: bool,
+
+    /// Write the rows still quarantined after the final retry pass to this JSON file
+    #[structopt(
+        long = "quarantine-report",
+        parse(from_os_str),
+        requires = "quarantine"
+    )]
+    pub quarantine_report: Option<PathBuf>,
+
+    /// Handling of batches MySQL rejects, `skip` retries them row by row and writes the failing rows to a dead-letter file
+    #[structopt(
+        long = "on-error",
+        default_value = "abort",
+        possible_values = &["abort", "skip"],
+        conflicts_with = "quarantine"
+    )]
+    pub on_error: OnError,
+
+    /// JSON lines file the rows skipped by `--on-error skip` are appended to
+    #[structopt(
+        long = "dead-letter-file",
+        default_value = "dead-letters.jsonl",
+        parse(from_os_str)
+    )]
+    pub dead_letter_file: PathBuf,
+
+    /// Verify each table as soon as it is loaded, while the remaining tables are still being migrated
+    #[structopt(long = "verify")]
+    pub verify: bool,
+
+    /// Also compare checksums of the tables verified by --verify, reading every row on both sides
+    #[structopt(long = "verify-checksums", requires = "verify")]
+    pub verify_checksums: bool,
+
+    /// Send decimals as exact numeric literals and check every value fits its MySQL column type before inserting
+    #[structopt(long = "strict-types")]
+    pub strict_types: bool,
+
+    /// Column type of `uniqueidentifier` columns in strict mode, `char` for CHAR(36) or `binary` for BINARY(16), which requires --strict-types
+    #[structopt(
+        long = "guid-format",
+        default_value = "char",
+        possible_values = &["char", "binary"]
+    )]
+    pub guid_format: GuidFormat,
+
+    /// Also migrate tables referenced by foreign keys of the whitelisted tables
+    #[structopt(long = "include-dependencies")]
+    pub include_dependencies: bool,
+
     /// Format snake case table and column names
     #[structopt(short = "f", long = "format")]
     pub format: bool,
 
+    /// Comment created tables with the source table, version, date and run id they were migrated by
+    #[structopt(long = "table-comments")]
+    pub table_comments: bool,
+
     /// Set parallelism
     #[structopt(short = "p", long = "parallelism", default_value = & DEFAULT_PARALLELISM.as_str())]
     pub parallelism: usize,
+
+    /// Adjust parallelism during the run based on batch latencies and errors
+    #[structopt(short = "a", long = "adaptive")]
+    pub adaptive: bool,
+
+    /// Lower parallelism bound for adaptive mode
+    #[structopt(long = "min-parallelism", default_value = "1")]
+    pub min_parallelism: usize,
+
+    /// Upper parallelism bound for adaptive mode [default: parallelism]
+    #[structopt(long = "max-parallelism")]
+    pub max_parallelism: Option<usize>,
+
+    /// Scheduling of parallelism slots, `fair` interleaves chunks of large tables with small tables
+    #[structopt(long = "scheduler", default_value = "table", possible_values = &["table", "fair"])]
+    pub scheduler: SchedulerMode,
+
+    /// Number of concurrent chunk workers per table, requires a configured chunk size
+    #[structopt(short = "w", long = "table-workers", default_value = "1")]
+    pub table_workers: usize,
+
+    /// Number of insert workers per table worker, writing its batches through connections of their own
+    #[structopt(long = "insert-workers", default_value = "1")]
+    pub insert_workers: usize,
+
+    /// Stop starting new batches once the run took this long, like `4h` or `1h30m`, and checkpoint it for `--resume`
+    #[structopt(long = "max-duration", parse(try_from_str = parse_duration), conflicts_with = "shadow")]
+    pub max_duration: Option<Duration>,
+
+    /// Continue a suspended run from its checkpoint, skipping the tables and chunks it already loaded
+    #[structopt(long = "resume", conflicts_with_all = &["backup", "shadow"])]
+    pub resume: bool,
+
+    /// Serve Prometheus metrics of the running migration at `http://<host>:<port>/metrics`
+    #[structopt(long = "metrics-port")]
+    pub metrics_port: Option<u16>,
+
+    /// Write a Chrome trace event file with spans of every table and batch, viewable in chrome://tracing or Perfetto
+    #[structopt(long = "trace-file", parse(from_os_str))]
+    pub trace_file: Option<PathBuf>,
+
+    /// Wait before starting until this query against the source returns a row, like a maintenance flag
+    #[structopt(long = "wait-for-query")]
+    pub wait_for_query: Option<String>,
+
+    /// Wait before starting until the availability group replicas of the source lag at most this many seconds
+    #[structopt(long = "wait-for-replica-lag")]
+    pub wait_for_replica_lag: Option<u64>,
+
+    /// Wait before starting until this local time, `HH:MM` for the next occurrence or `YYYY-MM-DD HH:MM`
+    #[structopt(long = "wait-until", parse(try_from_str = parse_wait_time))]
+    pub wait_until: Option<DateTime<Local>>,
+
+    /// Seconds between checks of the wait conditions
+    #[structopt(long = "wait-interval", default_value = "30")]
+    pub wait_interval: u64,
+
+    /// Give up waiting for the wait conditions after this long, like `2h` or `30m`
+    #[structopt(long = "wait-timeout", parse(try_from_str = parse_duration))]
+    pub wait_timeout: Option<Duration>,
+
+    /// Checkpoint file written by a suspended run and read by `--resume`
+    #[structopt(
+        long = "checkpoint-file",
+        default_value = "migration-checkpoint.toml",
+        parse(from_os_str)
+    )]
+    pub checkpoint_file: PathBuf,
+
+    /// Number of `[[migration]]` blocks of the config migrated at the same time
+    #[structopt(long = "parallel-migrations", default_value = "1")]
+    pub parallel_migrations: usize,
+
+    /// Migration output, `sql-file` writes per table SQL scripts and `csv` CSV files with LOAD DATA scripts instead of
+    /// loading into MySQL
+    #[structopt(long = "output", default_value = "mysql", possible_values = &["mysql", "sql-file", "csv"])]
+    pub output: OutputMode,
+
+    /// Output directory for the `sql-file` and `csv` outputs
+    #[structopt(
+        long = "out-dir",
+        parse(from_os_str),
+        required_ifs(&[("output", "sql-file"), ("output", "csv")])
+    )]
+    pub out_dir: Option<PathBuf>,
+
+    /// Cache extracted rows in this directory and reuse them while the source table is unchanged
+    #[structopt(long = "cache-dir", parse(from_os_str))]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Stop caching extracted rows once the cache directory holds this many MB
+    #[structopt(long = "max-cache-size", requires("cache-dir"))]
+    pub max_cache_size: Option<u64>,
+
+    /// Memory in MB the batches of all tables may hold at once, batches are flushed early and tables wait when it runs out
+    #[structopt(long = "max-memory")]
+    pub max_memory: Option<usize>,
+
+    /// Write suggested MySQL GRANT statements based on the source table permissions to this file
+    #[structopt(long = "grants-file", parse(from_os_str))]
+    pub grants_file: Option<PathBuf>,
+
+    /// Write a JSON column lineage report of the migrated tables to this file
+    #[structopt(long = "lineage-file", parse(from_os_str))]
+    pub lineage_file: Option<PathBuf>,
+
+    /// Describe the run, e.g. "Q3 refresh for analytics", kept in the run history and included in notifications
+    #[structopt(long = "note")]
+    pub note: Option<String>,
+}
+
+impl Default for MigrationArgs {
+    // Same as if no flags were given
+    fn default() -> Self {
+        MigrationArgs::from_iter(iter::once("db-migrator"))
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Interactively create the config file and default mappings
+    Init,
+
+    /// Migrate the whitelisted tables from MSSQL to MySQL
+    Migrate(MigrationArgs),
+
+    /// Check connectivity, permissions and server settings of both databases before a migration
+    Check,
+
+    /// List the source tables with their approximate row counts
+    ListTables,
+
+    /// List the whitelisted tables with their row counts, data sizes, mapped types and estimated batches
+    Plan {
+        /// Format snake case table and column names
+        #[structopt(short = "f", long = "format")]
+        format: bool,
+    },
+
+    /// Print the target table name of every source table and whether a migration loads it
+    Names {
+        /// Format snake case table and column names
+        #[structopt(short = "f", long = "format")]
+        format: bool,
+    },
+
+    /// Print the mapped MySQL DDL of a source table
+    Schema {
+        /// Source table, as `schema.table` or a bare table name in the default schema
+        table: String,
+
+        /// Format snake case table and column names
+        #[structopt(short = "f", long = "format")]
+        format: bool,
+    },
+
+    /// Explain how the MySQL type of a source column was chosen
+    ExplainMapping {
+        /// Source table, as `schema.table` or a bare table name in the default schema
+        table: String,
+
+        /// Source column name
+        column: String,
+
+        /// Format snake case table and column names
+        #[structopt(short = "f", long = "format")]
+        format: bool,
+    },
+
+    /// Print a few source rows next to the converted values and the INSERT statement, without connecting to MySQL
+    Preview {
+        /// Source table, as `schema.table` or a bare table name in the default schema
+        table: String,
+
+        /// Number of rows to preview
+        #[structopt(long = "rows", default_value = "20")]
+        rows: usize,
+
+        /// Format snake case table and column names
+        #[structopt(short = "f", long = "format")]
+        format: bool,
+    },
+
+    /// Compare row counts and columns of the migrated tables between source and target
+    Verify {
+        /// Format snake case table and column names
+        #[structopt(short = "f", long = "format")]
+        format: bool,
+
+        /// Also compare checksums of the table contents, reading every row on both sides
+        #[structopt(long = "checksums")]
+        checksums: bool,
+
+        /// Hold READ locks on the target tables during verification, so writes wait until it is done
+        #[structopt(long = "lock")]
+        lock: bool,
+    },
+
+    /// Compare the rows of a table between source and target by primary key
+    Diff {
+        /// Source table, as `schema.table` or a bare table name in the default schema
+        table: String,
+
+        /// Number of missing, extra and changed rows to print each
+        #[structopt(long = "limit", default_value = "20")]
+        limit: usize,
+
+        /// Format snake case table and column names
+        #[structopt(short = "f", long = "format")]
+        format: bool,
+    },
+
+    /// Continuously apply source changes made since the migration, using change tracking or rowversion columns
+    Sync {
+        /// Format snake case table and column names
+        #[structopt(short = "f", long = "format")]
+        format: bool,
+
+        /// Seconds to wait between sync passes
+        #[structopt(long = "interval", default_value = "10")]
+        interval: u64,
+
+        /// Run a single sync pass and exit
+        #[structopt(long = "once")]
+        once: bool,
+
+        /// Handling of tables whose source columns changed since they were migrated, `warn` leaves them out of the passes
+        #[structopt(long = "on-schema-drift", default_value = "warn", possible_values = &["warn", "abort"])]
+        on_schema_drift: SchemaDriftAction,
+    },
+
+    /// Restore the tables backed up by a migration run with `--backup`
+    Rollback {
+        /// Run id logged by the migration
+        #[structopt(long = "run")]
+        run: String,
+    },
+
+    /// Drop the table backups of older migration runs and the leftovers of aborted ones
+    Cleanup {
+        /// Number of latest runs to keep the backups of
+        #[structopt(long = "keep", default_value = "1")]
+        keep: usize,
+    },
+
+    /// Print the latest migration runs and compare the throughput of their tables with earlier runs
+    History {
+        /// Number of latest runs to print
+        #[structopt(long = "runs", default_value = "5")]
+        runs: usize,
+
+        /// Flag tables whose rows per second dropped by at least this many percent
+        #[structopt(long = "slowdown", default_value = "25")]
+        slowdown: f64,
+    },
+
+    /// Print the configuration with secrets redacted
+    Config(ConfigCommand),
+
+    /// Extract schema and data into a portable bundle without connecting to MySQL
+    #[cfg_attr(not(feature = "bundle"), allow(dead_code))]
+    Dump {
+        /// Bundle output directory
+        #[structopt(parse(from_os_str))]
+        bundle_dir: PathBuf,
+
+        #[structopt(flatten)]
+        migration: MigrationArgs,
+    },
+
+    /// Load a bundle created by `dump` into MySQL, resuming a previously interrupted apply
+    #[cfg_attr(not(feature = "bundle"), allow(dead_code))]
+    Apply {
+        /// Bundle input directory
+        #[structopt(parse(from_os_str))]
+        bundle_dir: PathBuf,
+
+        #[structopt(flatten)]
+        migration: MigrationArgs,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum ConfigCommand {
+    /// Print the config file, or with `--resolved` the configuration the commands run with
+    Show {
+        /// Merge the [[migration]] blocks and resolve `${NAME}` references, annotating each value with the layer that set it
+        #[structopt(long = "resolved")]
+        resolved: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputMode {
+    Mysql,
+    SqlFile,
+    Csv,
+}
+
+impl FromStr for OutputMode {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "mysql" => Ok(OutputMode::Mysql),
+            "sql-file" => Ok(OutputMode::SqlFile),
+            "csv" => Ok(OutputMode::Csv),
+            _ => bail!("Invalid output: {}", value),
+        }
+    }
+}
+
+// Durations like `4h`, `90m`, `1h30m` or `45s`
+fn parse_duration(value: &str) -> Result<Duration, Error> {
+    let mut total = 0;
+    let mut number = String::new();
+
+    for c in value.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => bail!("Invalid duration unit '{}' in {}", c, value),
+        };
+
+        if number.is_empty() {
+            bail!("Invalid duration: {}", value);
+        }
+
+        total += number.parse::<u64>()? * unit;
+        number.clear();
+    }
+
+    if !number.is_empty() || total == 0 {
+        bail!("Invalid duration, expected a positive number of hours, minutes or seconds like 4h or 90m");
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+// Local times like `22:30`, the next occurrence, or `2024-06-01 22:30`
+fn parse_wait_time(value: &str) -> Result<DateTime<Local>, Error> {
+    let now = Local::now();
+
+    let time = match NaiveDateTime::parse_from_str(value.trim(), "%Y-%m-%d %H:%M") {
+        Ok(time) => time,
+        Err(_) => {
+            let time = NaiveTime::parse_from_str(value.trim(), "%H:%M").map_err(|_| {
+                anyhow!("Invalid time {}, expected HH:MM or YYYY-MM-DD HH:MM", value)
+            })?;
+            let today = now.date_naive().and_time(time);
+
+            if today > now.naive_local() {
+                today
+            } else {
+                today + chrono::Duration::days(1)
+            }
+        }
+    };
+
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .ok_or_else(|| anyhow!("Time {} doesn't exist in the local time zone", value))
 }
 
 fn get_default_parallelism() -> usize {
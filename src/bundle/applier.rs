@@ -0,0 +1,336 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, bail, Context, Result};
+use futures::future::join_all;
+use tokio::spawn;
+use tokio::time::Instant;
+use toml::value::Table;
+use toml::Value;
+
+use crate::bundle::chunk_file::read_chunk_statements;
+use crate::bundle::integrity::verify_checksum;
+use crate::bundle::manifest::{BundleManifest, BundleTable};
+use crate::common::helpers::print_error_chain;
+use crate::common::source_table::SourceTable;
+use crate::insert::inserter::DatabaseInserter;
+use crate::insert::table_action::TableAction;
+use crate::migrate::concurrency_limiter::ConcurrencyLimiter;
+use crate::migrate::migration_options::MigrationOptions;
+
+const PROGRESS_FILE: &str = "applied.toml";
+
+pub struct BundleApplier {
+    inserter: DatabaseInserter,
+    options: MigrationOptions,
+    bundle_dir: PathBuf,
+}
+
+// Tracks which tables were prepared and which chunks were loaded, so an interrupted apply can resume
+#[derive(Debug, Default)]
+struct ApplyProgress {
+    started_tables: BTreeSet<String>,
+    applied_chunks: BTreeSet<String>,
+}
+
+impl BundleApplier {
+    pub fn new(inserter: DatabaseInserter, options: MigrationOptions, bundle_dir: PathBuf) -> Self {
+        BundleApplier {
+            inserter,
+            options,
+            bundle_dir,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        info!("Applying bundle {}", self.bundle_dir.display());
+
+        let start_time = Instant::now();
+
+        let signing_key = self
+            .options
+            .bundle_signing_key
+            .as_deref()
+            .map(str::as_bytes);
+        let manifest = BundleManifest::load(&self.bundle_dir, signing_key)?;
+        let progress = ApplyProgress::load(&self.bundle_dir)?;
+
+        let (tables, incomplete): (Vec<BundleTable>, Vec<BundleTable>) = manifest
+            .tables
+            .into_iter()
+            .partition(|table| table.complete);
+
+        for table in &incomplete {
+            warn!("Table {} was not completely dumped, skipping", table.name);
+        }
+
+        if tables.is_empty() {
+            bail!("No tables to apply");
+        }
+
+        // Refuse the whole bundle before anything is loaded if any pending file was altered
+        verify_bundle_files(&self.bundle_dir, &tables, &progress)?;
+
+        if progress.started_tables.is_empty() {
+            let source_tables = tables
+                .iter()
+                .map(|table| SourceTable::parse(&table.source))
+                .collect::<Vec<_>>();
+
+            let reset_action = if self.options.drop {
+                "dropped by --drop"
+            } else {
+                "truncated"
+            };
+
+            self.options
+                .check_protected_tables(&source_tables, reset_action)?;
+
+            let table_names = tables
+                .iter()
+                .map(|table| table.name.clone())
+                .collect::<Vec<_>>();
+
+            let action = if self.options.drop {
+                TableAction::Drop
+            } else {
+                TableAction::Truncate
+            };
+
+            self.inserter
+                .reset_tables(&table_names, action, self.options.unchecked_reset_fallback)
+                .await?;
+        } else {
+            info!("Resuming previously started apply");
+        }
+
+        let progress = Arc::new(Mutex::new(progress));
+        let limiter = Arc::new(ConcurrencyLimiter::fixed(self.options.table_parallelism()));
+
+        let tasks = tables
+            .into_iter()
+            .map(|table| {
+                let inserter = self.inserter.clone();
+                let bundle_dir = self.bundle_dir.clone();
+                let progress = Arc::clone(&progress);
+                let limiter = Arc::clone(&limiter);
+
+                spawn(async move {
+                    let _permit = limiter
+                        .acquire()
+                        .await
+                        .expect("Failed to acquire limiter permit");
+
+                    let table_name = table.name.clone();
+
+                    if let Err(err) = apply_table(inserter, &bundle_dir, &progress, table)
+                        .await
+                        .with_context(|| format!("Error while applying table: {}", table_name))
+                    {
+                        print_error_chain(&err);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        join_all(tasks).await;
+
+        info!(
+            "Apply finished, total time took: {}s",
+            Instant::now()
+                .saturating_duration_since(start_time)
+                .as_secs_f32()
+        );
+
+        Ok(())
+    }
+}
+
+async fn apply_table(
+    mut inserter: DatabaseInserter,
+    bundle_dir: &Path,
+    progress: &Mutex<ApplyProgress>,
+    table: BundleTable,
+) -> Result<()> {
+    info!("Applying table: {}", table.name);
+
+    let start_time = Instant::now();
+
+    let started = progress
+        .lock()
+        .unwrap()
+        .started_tables
+        .contains(&table.name);
+
+    if !started {
+        if inserter.table_exists(&table.name).await? {
+            if inserter.table_rows_count(&table.name).await? > 0 {
+                bail!("Rows already exists in table {}", table.name);
+            }
+        } else {
+            let create_table_query = fs::read_to_string(bundle_dir.join(&table.schema_file))?;
+            verify_checksum(
+                &table.schema_file,
+                create_table_query.as_bytes(),
+                &table.schema_sha256,
+            )?;
+
+            inserter
+                .execute_transactional_query(&create_table_query)
+                .await
+                .with_context(|| "Failed to create table".to_string())?;
+
+            info!("Table {} created successfully", table.name);
+        }
+
+        let mut progress = progress.lock().unwrap();
+        progress.started_tables.insert(table.name.clone());
+        progress.save(bundle_dir)?;
+    }
+
+    for chunk in &table.chunks {
+        if progress
+            .lock()
+            .unwrap()
+            .applied_chunks
+            .contains(&chunk.file)
+        {
+            debug!("Chunk {} already applied, skipping", chunk.file);
+            continue;
+        }
+
+        let statements = read_chunk_statements(&bundle_dir.join(&chunk.file), &chunk.sha256)?;
+        let rows: usize = statements.iter().map(|(_, rows)| rows).sum();
+
+        if statements.len() != chunk.batches || rows != chunk.rows {
+            bail!("Chunk {} does not match the manifest", chunk.file);
+        }
+
+        let batch_start_time = Instant::now();
+
+        // The whole chunk is loaded in one transaction so a resumed apply never repeats rows
+        let queries = statements
+            .into_iter()
+            .map(|(statement, _)| statement)
+            .collect::<Vec<_>>();
+
+        inserter
+            .execute_transactional_queries(&queries)
+            .await
+            .with_context(|| format!("Failed to apply chunk {}", chunk.file))?;
+
+        debug!(
+            "Applied chunk {} with {} rows, took: {}s",
+            chunk.file,
+            rows,
+            Instant::now()
+                .saturating_duration_since(batch_start_time)
+                .as_secs_f32()
+        );
+
+        let mut progress = progress.lock().unwrap();
+        progress.applied_chunks.insert(chunk.file.clone());
+        progress.save(bundle_dir)?;
+    }
+
+    // Verify the loaded table against the manifest
+    let count = inserter.table_rows_count(&table.name).await? as usize;
+
+    if count != table.rows {
+        return Err(anyhow!(
+            "Row count mismatch in table {}, expected: {}, found: {}",
+            table.name,
+            table.rows,
+            count
+        ));
+    }
+
+    info!(
+        "Table {} applied, rows: {}, took: {}s",
+        table.name,
+        count,
+        Instant::now()
+            .saturating_duration_since(start_time)
+            .as_secs_f32()
+    );
+
+    Ok(())
+}
+
+fn verify_bundle_files(
+    bundle_dir: &Path,
+    tables: &[BundleTable],
+    progress: &ApplyProgress,
+) -> Result<()> {
+    info!("Verifying bundle checksums");
+
+    for table in tables {
+        if !progress.started_tables.contains(&table.name) {
+            let content = fs::read(bundle_dir.join(&table.schema_file))
+                .with_context(|| format!("Missing schema file {}", table.schema_file))?;
+            verify_checksum(&table.schema_file, &content, &table.schema_sha256)?;
+        }
+
+        for chunk in &table.chunks {
+            if progress.applied_chunks.contains(&chunk.file) {
+                continue;
+            }
+
+            let content = fs::read(bundle_dir.join(&chunk.file))
+                .with_context(|| format!("Missing chunk file {}", chunk.file))?;
+            verify_checksum(&chunk.file, &content, &chunk.sha256)?;
+        }
+    }
+
+    Ok(())
+}
+
+impl ApplyProgress {
+    fn load(bundle_dir: &Path) -> Result<Self> {
+        let path = bundle_dir.join(PROGRESS_FILE);
+
+        if !path.exists() {
+            return Ok(ApplyProgress::default());
+        }
+
+        let value = fs::read_to_string(path)?.parse::<Value>()?;
+
+        Ok(ApplyProgress {
+            started_tables: parse_string_set(&value, "started_tables")?,
+            applied_chunks: parse_string_set(&value, "applied_chunks")?,
+        })
+    }
+
+    fn save(&self, bundle_dir: &Path) -> Result<()> {
+        let to_array =
+            |set: &BTreeSet<String>| Value::Array(set.iter().cloned().map(Value::String).collect());
+
+        let mut value = Table::new();
+        value.insert("started_tables".into(), to_array(&self.started_tables));
+        value.insert("applied_chunks".into(), to_array(&self.applied_chunks));
+
+        fs::write(
+            bundle_dir.join(PROGRESS_FILE),
+            toml::to_string(&Value::Table(value))?,
+        )?;
+
+        Ok(())
+    }
+}
+
+fn parse_string_set(value: &Value, key: &str) -> Result<BTreeSet<String>> {
+    value
+        .get(key)
+        .and_then(|value| value.as_array())
+        .ok_or_else(|| anyhow!("Missing or invalid apply progress field '{}'", key))?
+        .iter()
+        .map(|value| {
+            value
+                .as_str()
+                .map(|value| value.to_string())
+                .ok_or_else(|| anyhow!("Invalid apply progress entry"))
+        })
+        .collect()
+}
@@ -0,0 +1,147 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::bundle::integrity::{verify_checksum, HashingWriter};
+use crate::bundle::manifest::BundleChunk;
+use crate::migrate::batch_sink::{Batch, BatchSink};
+
+const BATCHES_PER_CHUNK: usize = 64;
+
+// Writes batches as length-prefixed statements into compressed chunk files
+pub struct ChunkFileWriter {
+    bundle_dir: PathBuf,
+    table_dir: String,
+    current: Option<(GzEncoder<HashingWriter<BufWriter<File>>>, BundleChunk)>,
+    chunks: Vec<BundleChunk>,
+}
+
+impl ChunkFileWriter {
+    pub fn new(bundle_dir: &Path, table_dir: &str) -> Result<Self> {
+        let path = bundle_dir.join(table_dir);
+
+        // Discard leftovers of an interrupted dump of the same table
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::create_dir_all(&path)?;
+
+        Ok(ChunkFileWriter {
+            bundle_dir: bundle_dir.to_path_buf(),
+            table_dir: table_dir.to_string(),
+            current: None,
+            chunks: Vec::new(),
+        })
+    }
+
+    pub fn finish(mut self) -> Result<Vec<BundleChunk>> {
+        self.close_current()?;
+        Ok(self.chunks)
+    }
+
+    fn close_current(&mut self) -> Result<()> {
+        if let Some((encoder, mut chunk)) = self.current.take() {
+            chunk.sha256 = encoder.finish()?.finish()?;
+            self.chunks.push(chunk);
+        }
+
+        Ok(())
+    }
+
+    fn write_statement(&mut self, statement: &str, rows: usize) -> Result<()> {
+        if self.current.is_none() {
+            self.open_next()?;
+        }
+
+        let (encoder, chunk) = self.current.as_mut().unwrap();
+
+        writeln!(encoder, "{} {}", statement.len(), rows)?;
+        encoder.write_all(statement.as_bytes())?;
+        writeln!(encoder)?;
+
+        chunk.rows += rows;
+        chunk.batches += 1;
+
+        if chunk.batches >= BATCHES_PER_CHUNK {
+            self.close_current()?;
+        }
+
+        Ok(())
+    }
+
+    fn open_next(&mut self) -> Result<()> {
+        let file = format!(
+            "{}/chunk-{:06}.sql.gz",
+            self.table_dir,
+            self.chunks.len() + 1
+        );
+        let writer = BufWriter::new(File::create(self.bundle_dir.join(&file))?);
+
+        self.current = Some((
+            GzEncoder::new(HashingWriter::new(writer), Compression::default()),
+            BundleChunk {
+                file,
+                rows: 0,
+                batches: 0,
+                sha256: String::new(),
+            },
+        ));
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BatchSink for ChunkFileWriter {
+    async fn write_batch(&mut self, batch: &Batch<'_>) -> Result<()> {
+        self.write_statement(batch.query, batch.len())
+    }
+
+    async fn execute_statement(&mut self, statement: &str) -> Result<()> {
+        self.write_statement(statement, 0)
+    }
+}
+
+// Returns the chunk's statements along with the number of rows each one inserts
+pub fn read_chunk_statements(path: &Path, sha256: &str) -> Result<Vec<(String, usize)>> {
+    let bytes =
+        fs::read(path).with_context(|| format!("Failed to open chunk {}", path.display()))?;
+
+    // Verify the exact bytes that get decoded, so a file swapped after the pre-flight check is still caught
+    verify_checksum(&path.display().to_string(), &bytes, sha256)?;
+
+    let mut reader = BufReader::new(GzDecoder::new(bytes.as_slice()));
+    let mut statements = Vec::new();
+    let mut header = String::new();
+
+    loop {
+        header.clear();
+
+        if reader.read_line(&mut header)? == 0 {
+            break;
+        }
+
+        let (length, rows): (usize, usize) = header
+            .trim_end()
+            .split_once(' ')
+            .and_then(|(length, rows)| Some((length.parse().ok()?, rows.parse().ok()?)))
+            .ok_or_else(|| anyhow!("Corrupted chunk {}", path.display()))?;
+
+        // Statement bytes followed by the terminating newline
+        let mut statement = vec![0; length + 1];
+        reader
+            .read_exact(&mut statement)
+            .with_context(|| format!("Truncated chunk {}", path.display()))?;
+        statement.pop();
+
+        statements.push((String::from_utf8(statement)?, rows));
+    }
+
+    Ok(statements)
+}
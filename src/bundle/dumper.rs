@@ -0,0 +1,211 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use tokio::time::Instant;
+
+use crate::bundle::chunk_file::ChunkFileWriter;
+use crate::bundle::integrity::sha256_hex;
+use crate::bundle::manifest::{BundleManifest, BundleTable};
+use crate::common::source_table::SourceTable;
+use crate::export::table_runner::{regenerate_keys, run_tables};
+use crate::extract::extractor::DatabaseExtractor;
+use crate::insert::query::build_create_table_query;
+use crate::mappings::Mappings;
+use crate::migrate::batch_sink::{write_table_batches, InsertPlan};
+use crate::migrate::migration_options::MigrationOptions;
+use crate::migrate::migrator::resolve_source_tables;
+use crate::migrate::partitioning::plan_partitioning;
+use crate::migrate::provenance::table_comment;
+use crate::migrate::table_schema_mapper::TableSchemaMapper;
+
+pub struct BundleDumper {
+    extractor: DatabaseExtractor,
+    mappings: Mappings,
+    options: MigrationOptions,
+    bundle_dir: PathBuf,
+}
+
+impl BundleDumper {
+    pub fn new(
+        extractor: DatabaseExtractor,
+        mappings: Mappings,
+        options: MigrationOptions,
+        bundle_dir: PathBuf,
+    ) -> Self {
+        BundleDumper {
+            extractor,
+            mappings,
+            options,
+            bundle_dir,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        info!("Dumping tables to bundle {}", self.bundle_dir.display());
+
+        let start_time = Instant::now();
+
+        fs::create_dir_all(&self.bundle_dir)?;
+
+        let manifest = BundleManifest::load_or_new(&self.bundle_dir, self.signing_key())?;
+        let (tables, _) = resolve_source_tables(&mut self.extractor, &self.options).await?;
+
+        regenerate_keys(&mut self.extractor, &tables, &mut self.options).await?;
+
+        let manifest = Arc::new(Mutex::new(manifest));
+
+        let runs = run_tables(
+            tables,
+            self.options.table_parallelism(),
+            "dumping",
+            |table| {
+                let extractor = self.extractor.clone();
+                let mappings = self.mappings.clone();
+                let options = self.options.clone();
+                let bundle_dir = self.bundle_dir.clone();
+                let manifest = Arc::clone(&manifest);
+
+                async move {
+                    let output_table_name = options.target_table_name(&table);
+
+                    let already_dumped = manifest
+                        .lock()
+                        .unwrap()
+                        .table(&output_table_name)
+                        .is_some_and(|entry| entry.complete);
+
+                    if already_dumped {
+                        info!("Table {} already dumped, skipping", output_table_name);
+                        return Ok(());
+                    }
+
+                    let entry = dump_table(
+                        extractor,
+                        &mappings,
+                        &options,
+                        &bundle_dir,
+                        &table,
+                        output_table_name,
+                    )
+                    .await?;
+
+                    let mut manifest = manifest.lock().unwrap();
+                    manifest.upsert_table(entry);
+                    manifest.save(
+                        &bundle_dir,
+                        options.bundle_signing_key.as_deref().map(str::as_bytes),
+                    )
+                }
+            },
+        )
+        .await;
+
+        // Save once more so an empty run still produces a manifest
+        manifest
+            .lock()
+            .unwrap()
+            .save(&self.bundle_dir, self.signing_key())?;
+
+        info!(
+            "Dump finished, total time took: {}s",
+            Instant::now()
+                .saturating_duration_since(start_time)
+                .as_secs_f32()
+        );
+
+        runs.check()
+    }
+
+    fn signing_key(&self) -> Option<&[u8]> {
+        self.options
+            .bundle_signing_key
+            .as_deref()
+            .map(str::as_bytes)
+    }
+}
+
+async fn dump_table(
+    mut extractor: DatabaseExtractor,
+    mappings: &Mappings,
+    options: &MigrationOptions,
+    bundle_dir: &Path,
+    table: &SourceTable,
+    output_table_name: String,
+) -> Result<BundleTable> {
+    info!("Dumping table: {}", output_table_name);
+
+    let start_time = Instant::now();
+
+    let table_schema = extractor
+        .get_table_schema(table)
+        .await
+        .with_context(|| "Failed to get table schema".to_string())?;
+    let mapped_schema = TableSchemaMapper::map_schema(mappings, table, &table_schema, options);
+
+    let schema_file = format!("{}/schema.sql", output_table_name);
+    let mut writer = ChunkFileWriter::new(bundle_dir, &output_table_name)?;
+
+    let partitioning = plan_partitioning(
+        &mut extractor,
+        table,
+        &table_schema,
+        &mapped_schema,
+        options,
+    )
+    .await?;
+    let comment = table_comment(&mut extractor, table, options).await?;
+    let create_table_query = build_create_table_query(
+        &output_table_name,
+        &mapped_schema,
+        &options.extra_columns,
+        &options.collation,
+        comment.as_deref(),
+        partitioning.as_deref(),
+    );
+    let schema_sha256 = sha256_hex(create_table_query.as_bytes());
+    fs::write(bundle_dir.join(&schema_file), create_table_query)?;
+
+    let plan = InsertPlan::new(
+        table,
+        &output_table_name,
+        &table_schema,
+        &mapped_schema,
+        options,
+    );
+
+    let rows = write_table_batches(
+        &mut extractor,
+        &mut writer,
+        table,
+        &table_schema,
+        &plan,
+        options,
+    )
+    .await?;
+
+    plan.log_row_changes(&output_table_name);
+
+    let chunks = writer.finish()?;
+
+    info!(
+        "Table {} dumped, rows: {}, chunks: {}, took: {}s",
+        output_table_name,
+        rows,
+        chunks.len(),
+        Instant::now()
+            .saturating_duration_since(start_time)
+            .as_secs_f32()
+    );
+
+    Ok(BundleTable {
+        name: output_table_name,
+        source: table.to_string(),
+        schema_file,
+        schema_sha256,
+        rows,
+        complete: true,
+        chunks,
+    })
+}
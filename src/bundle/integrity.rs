@@ -0,0 +1,73 @@
+use std::io::{self, Write};
+
+use anyhow::{bail, Result};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+pub fn sign(key: &[u8], content: &[u8]) -> Result<String> {
+    let mut mac = HmacSha256::new_from_slice(key)?;
+    mac.update(content);
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+pub fn verify_signature(key: &[u8], content: &[u8], signature: &str) -> Result<()> {
+    let signature = hex::decode(signature.trim())?;
+
+    let mut mac = HmacSha256::new_from_slice(key)?;
+    mac.update(content);
+
+    if mac.verify_slice(&signature).is_err() {
+        bail!("Bundle manifest signature does not match, the bundle was modified or signed with another key");
+    }
+
+    Ok(())
+}
+
+pub fn verify_checksum(name: &str, bytes: &[u8], expected: &str) -> Result<()> {
+    if sha256_hex(bytes) != expected {
+        bail!(
+            "Checksum mismatch for {}, the file is corrupted or truncated",
+            name
+        );
+    }
+
+    Ok(())
+}
+
+// Hashes everything written through it, used to checksum chunk files while they are written
+pub struct HashingWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> HashingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        HashingWriter {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<String> {
+        self.inner.flush()?;
+        Ok(hex::encode(self.hasher.finalize()))
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
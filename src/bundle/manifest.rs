@@ -0,0 +1,228 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Local;
+use toml::value::{Array, Table};
+use toml::Value;
+
+use crate::bundle::integrity::{sign, verify_signature};
+
+pub const MANIFEST_FILE: &str = "manifest.toml";
+const SIGNATURE_FILE: &str = "manifest.toml.sig";
+const BUNDLE_VERSION: i64 = 2;
+
+#[derive(Debug, Clone)]
+pub struct BundleManifest {
+    pub created_at: String,
+    pub tables: Vec<BundleTable>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BundleTable {
+    pub name: String,
+    pub source: String,
+    pub schema_file: String,
+    pub schema_sha256: String,
+    pub rows: usize,
+    pub complete: bool,
+    pub chunks: Vec<BundleChunk>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BundleChunk {
+    pub file: String,
+    pub rows: usize,
+    pub batches: usize,
+    pub sha256: String,
+}
+
+impl BundleManifest {
+    pub fn new() -> Self {
+        BundleManifest {
+            created_at: Local::now().to_rfc3339(),
+            tables: Vec::new(),
+        }
+    }
+
+    pub fn load(bundle_dir: &Path, signing_key: Option<&[u8]>) -> Result<Self> {
+        let path = bundle_dir.join(MANIFEST_FILE);
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read bundle manifest {}", path.display()))?;
+
+        match signing_key {
+            Some(key) => {
+                let signature = fs::read_to_string(bundle_dir.join(SIGNATURE_FILE))
+                    .with_context(|| "Bundle manifest is not signed")?;
+                verify_signature(key, content.as_bytes(), &signature)?;
+            }
+            None => warn!("No bundle signing key configured, manifest signature is not verified"),
+        }
+
+        Self::from_toml(content.parse::<Value>()?)
+    }
+
+    pub fn load_or_new(bundle_dir: &Path, signing_key: Option<&[u8]>) -> Result<Self> {
+        if bundle_dir.join(MANIFEST_FILE).exists() {
+            Self::load(bundle_dir, signing_key)
+        } else {
+            Ok(Self::new())
+        }
+    }
+
+    pub fn save(&self, bundle_dir: &Path, signing_key: Option<&[u8]>) -> Result<()> {
+        let content = toml::to_string(&self.to_toml())?;
+
+        // Write through temporary files so an interrupted dump never leaves a truncated manifest
+        write_atomically(&bundle_dir.join(MANIFEST_FILE), &content)?;
+
+        match signing_key {
+            Some(key) => write_atomically(
+                &bundle_dir.join(SIGNATURE_FILE),
+                &sign(key, content.as_bytes())?,
+            )?,
+            None => {
+                let signature_path = bundle_dir.join(SIGNATURE_FILE);
+
+                if signature_path.exists() {
+                    fs::remove_file(signature_path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn table(&self, name: &str) -> Option<&BundleTable> {
+        self.tables.iter().find(|table| table.name == name)
+    }
+
+    pub fn upsert_table(&mut self, table: BundleTable) {
+        match self.tables.iter_mut().find(|t| t.name == table.name) {
+            Some(existing) => *existing = table,
+            None => self.tables.push(table),
+        }
+    }
+
+    fn to_toml(&self) -> Value {
+        let tables = self
+            .tables
+            .iter()
+            .map(|table| {
+                let chunks = table
+                    .chunks
+                    .iter()
+                    .map(|chunk| {
+                        let mut value = Table::new();
+                        value.insert("file".into(), Value::String(chunk.file.clone()));
+                        value.insert("rows".into(), Value::Integer(chunk.rows as i64));
+                        value.insert("batches".into(), Value::Integer(chunk.batches as i64));
+                        value.insert("sha256".into(), Value::String(chunk.sha256.clone()));
+                        Value::Table(value)
+                    })
+                    .collect::<Array>();
+
+                let mut value = Table::new();
+                value.insert("name".into(), Value::String(table.name.clone()));
+                value.insert("source".into(), Value::String(table.source.clone()));
+                value.insert(
+                    "schema_file".into(),
+                    Value::String(table.schema_file.clone()),
+                );
+                value.insert(
+                    "schema_sha256".into(),
+                    Value::String(table.schema_sha256.clone()),
+                );
+                value.insert("rows".into(), Value::Integer(table.rows as i64));
+                value.insert("complete".into(), Value::Boolean(table.complete));
+                value.insert("chunks".into(), Value::Array(chunks));
+                Value::Table(value)
+            })
+            .collect::<Array>();
+
+        let mut manifest = Table::new();
+        manifest.insert("version".into(), Value::Integer(BUNDLE_VERSION));
+        manifest.insert("created_at".into(), Value::String(self.created_at.clone()));
+        manifest.insert("tables".into(), Value::Array(tables));
+        Value::Table(manifest)
+    }
+
+    fn from_toml(value: Value) -> Result<Self> {
+        let version = value
+            .get("version")
+            .and_then(|value| value.as_integer())
+            .ok_or_else(|| anyhow!("Missing or invalid bundle version"))?;
+
+        if version != BUNDLE_VERSION {
+            bail!("Unsupported bundle version {}", version);
+        }
+
+        let created_at = get_str(&value, "created_at")?;
+
+        let tables = value
+            .get("tables")
+            .and_then(|value| value.as_array())
+            .ok_or_else(|| anyhow!("Missing or invalid bundle tables"))?
+            .iter()
+            .map(parse_table)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(BundleManifest { created_at, tables })
+    }
+}
+
+fn parse_table(value: &Value) -> Result<BundleTable> {
+    let chunks = value
+        .get("chunks")
+        .and_then(|value| value.as_array())
+        .ok_or_else(|| anyhow!("Missing or invalid table chunks"))?
+        .iter()
+        .map(|chunk| {
+            Ok(BundleChunk {
+                file: get_str(chunk, "file")?,
+                rows: get_usize(chunk, "rows")?,
+                batches: get_usize(chunk, "batches")?,
+                sha256: get_str(chunk, "sha256")?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(BundleTable {
+        name: get_str(value, "name")?,
+        source: get_str(value, "source")?,
+        schema_file: get_str(value, "schema_file")?,
+        schema_sha256: get_str(value, "schema_sha256")?,
+        rows: get_usize(value, "rows")?,
+        complete: value
+            .get("complete")
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false),
+        chunks,
+    })
+}
+
+fn get_str(value: &Value, key: &str) -> Result<String> {
+    value
+        .get(key)
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_string())
+        .ok_or_else(|| anyhow!("Missing or invalid manifest field '{}'", key))
+}
+
+fn get_usize(value: &Value, key: &str) -> Result<usize> {
+    value
+        .get(key)
+        .and_then(|value| value.as_integer())
+        .map(|value| value as usize)
+        .ok_or_else(|| anyhow!("Missing or invalid manifest field '{}'", key))
+}
+
+fn write_atomically(path: &Path, content: &str) -> Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+
+    fs::write(&temp_path, content)?;
+    fs::rename(&temp_path, path)?;
+
+    Ok(())
+}
@@ -0,0 +1,5 @@
+pub mod applier;
+mod chunk_file;
+pub mod dumper;
+mod integrity;
+pub mod manifest;
@@ -0,0 +1,887 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use futures::stream::{self, StreamExt};
+use structopt::clap::{Error, ErrorKind};
+use structopt::StructOpt;
+
+use crate::args::{Args, Command, ConfigCommand, MigrationArgs, OutputMode};
+#[cfg(feature = "bundle")]
+use crate::bundle::applier::BundleApplier;
+#[cfg(feature = "bundle")]
+use crate::bundle::dumper::BundleDumper;
+use crate::common::collation::Collation;
+use crate::common::helpers::print_error_chain;
+use crate::config::{load_env_file, read_logging_config, Config, SettingsConfig};
+use crate::config_show::show_config;
+use crate::connection::{DatabaseConnectionFactory, SqlxMySqlConnection, TiberiusConnection};
+use crate::export::null_exporter::NullExporter;
+use crate::export::sql_exporter::SqlFileExporter;
+use crate::extract::extractor::DatabaseExtractor;
+use crate::extract::generator::Generator;
+use crate::init_wizard::run_init_wizard;
+use crate::insert::inserter::DatabaseInserter;
+use crate::inspect::{
+    explain_mapping, list_tables, preview_rows, print_names, print_plan, print_schema,
+};
+use crate::logging::initialize_logger;
+use crate::mappings::Mappings;
+use crate::migrate::backup::{cleanup_backups, new_run_id, rollback_run};
+use crate::migrate::memory_budget::MemoryBudget;
+use crate::migrate::migration_options::{MigrationOptions, OnError, ParallelismBounds};
+use crate::migrate::migration_report::MigrationReport;
+use crate::migrate::migrator::DatabaseMigrator;
+use crate::migrate::observer::{LoggingObserver, Observers};
+use crate::migrate::run_history::{cleanup_incomplete_runs, print_history};
+use crate::migrate::strict_types::GuidFormat;
+use crate::migrate::table_diff::diff_table;
+use crate::migrate::verifier::DatabaseVerifier;
+use crate::migrate::wait::WaitConditions;
+#[cfg(feature = "webhook")]
+use crate::migrate::webhook::WebhookNotifier;
+use crate::preflight::run_check;
+use crate::sync::syncer::{ChangeSyncer, SchemaDriftAction};
+
+const DEFAULT_ENV_FILE: &str = ".env";
+
+// Exit code of a migration that used up its --max-duration before it was complete
+const SUSPENDED_EXIT_CODE: u8 = 3;
+
+// Optional features and whether this build includes them
+const FEATURES: &[(&str, bool)] = &[
+    ("bundle", cfg!(feature = "bundle")),
+    ("metrics", cfg!(feature = "metrics")),
+    ("webhook", cfg!(feature = "webhook")),
+];
+
+// Runs the command line interface with the arguments of the process, on a runtime of its own
+pub fn run_cli() -> ExitCode {
+    match start().with_context(|| "Initialization failed") {
+        Ok(exit_code) => exit_code,
+        Err(errors) => report_errors(Err(errors)),
+    }
+}
+
+fn start() -> Result<ExitCode> {
+    let options = Args::from_args();
+
+    if options.version {
+        print_version(options.features);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let Some(command) = &options.command else {
+        Error::with_description("A subcommand is required", ErrorKind::MissingSubcommand).exit()
+    };
+
+    initialize_logger(
+        options.verbose,
+        options.quiet,
+        &read_logging_config(&options.config),
+    );
+
+    // Load the dotenv file before the config so its variables can be interpolated, and before the runtime is started:
+    // setting variables while other threads may read the environment isn't sound
+    let (env_file, env_file_variables) = match &options.env_file {
+        Some(env_file) => (
+            Some(env_file.clone()),
+            load_env_file(env_file)
+                .with_context(|| format!("Failed to load env file {}", env_file.display()))?,
+        ),
+        None if Path::new(DEFAULT_ENV_FILE).exists() => (
+            Some(PathBuf::from(DEFAULT_ENV_FILE)),
+            load_env_file(Path::new(DEFAULT_ENV_FILE)).context("Failed to load .env file")?,
+        ),
+        None => (None, Vec::new()),
+    };
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start the runtime")?
+        .block_on(init(&options, command, env_file, env_file_variables))
+}
+
+async fn init(
+    options: &Args,
+    command: &Command,
+    env_file: Option<PathBuf>,
+    env_file_variables: Vec<String>,
+) -> Result<ExitCode> {
+    if let Command::Init = command {
+        run_init_wizard(&options.config, &options.mappings).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if !options.config.exists() {
+        bail!(
+            "Config file {} not found, run `db-migrator init` to create one",
+            options.config.display()
+        );
+    }
+
+    // Parse config
+    let config = Config::load(&options.config)
+        .with_context(|| format!("Failed to load config file {}", options.config.display()))?;
+
+    // Printed once it is known to be valid, without the mappings or a connection
+    if let Command::Config(ConfigCommand::Show { resolved }) = command {
+        show_config(options, env_file.as_deref(), &env_file_variables, *resolved)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    let mappings = Mappings::load(&options.mappings).with_context(|| {
+        format!(
+            "Failed to load mappings file {}",
+            options.mappings.display()
+        )
+    })?;
+
+    debug!("Total mappings loaded: {}", mappings.len());
+    info!("Initializing connections...");
+
+    let settings = config.settings().clone();
+
+    let exit_code = match command {
+        Command::Init | Command::Config(_) => unreachable!(),
+        Command::Migrate(args) => {
+            let migration_options = build_migration_options(args, settings);
+            let source_pool_size = migration_options.source_pool_size();
+            let target_pool_size = migration_options.target_pool_size();
+
+            #[cfg(not(feature = "metrics"))]
+            if args.metrics_port.is_some() {
+                bail!("--metrics-port is not supported by this build, it was compiled without the `metrics` feature");
+            }
+
+            // Only the default format can go without strict types, a chosen one would be ignored
+            if args.guid_format == GuidFormat::Binary && !args.strict_types {
+                bail!("--guid-format binary requires --strict-types");
+            }
+
+            // Phases, time boxes and waits only make sense when loading into a database
+            let other_output = match (args.output, config.null_target()) {
+                (OutputMode::SqlFile | OutputMode::Csv, true) => {
+                    bail!("target = \"null\" writes nothing, it can't be combined with --output sql-file or csv")
+                }
+                (OutputMode::SqlFile, false) => Some("--output sql-file"),
+                (OutputMode::Csv, false) => Some("--output csv"),
+                (_, true) => Some("target = \"null\""),
+                _ => None,
+            };
+
+            if let Some(output) = other_output {
+                if args.phase.is_some() {
+                    bail!(
+                        "Phases can only be run against a database, not with {}",
+                        output
+                    );
+                }
+
+                if args.max_duration.is_some() || args.resume {
+                    bail!("--max-duration and --resume can only be used against a database, not with {}", output);
+                }
+
+                if args.verify {
+                    bail!(
+                        "--verify compares tables loaded into a database, not with {}",
+                        output
+                    );
+                }
+
+                if migration_options.wait.is_some() {
+                    bail!(
+                        "Wait conditions can only be used against a database, not with {}",
+                        output
+                    );
+                }
+
+                if !config.migrations().is_empty() {
+                    bail!("Configs with [[migration]] blocks can only be migrated into MySQL, not with {}", output);
+                }
+            }
+
+            // Exports leave such rows out, there's no quarantine to keep them in
+            if other_output.is_none()
+                && !args.quarantine
+                && migration_options.quarantines_invalid_rows()
+            {
+                bail!("on_invalid = \"quarantine\" requires --quarantine");
+            }
+
+            if !config.migrations().is_empty() {
+                if args.metrics_port.is_some() && args.parallel_migrations > 1 {
+                    bail!("--metrics-port can only serve one migration at a time, not with --parallel-migrations");
+                }
+
+                run_migrations(config.migrations(), &mappings, args).await?
+            } else if matches!(args.output, OutputMode::SqlFile | OutputMode::Csv) {
+                let extractor = create_extractor(&config, source_pool_size).await?;
+                let out_dir = args.out_dir.clone().unwrap_or_default();
+
+                run_export(extractor, mappings, migration_options, out_dir).await?
+            } else if config.null_target() {
+                let extractor = create_extractor(&config, source_pool_size).await?;
+
+                run_null_export(extractor, mappings, migration_options).await?
+            } else {
+                let extractor = create_extractor(&config, source_pool_size).await?;
+                let sqlx_connection = create_sqlx_connection(&config, target_pool_size).await?;
+
+                run_migration(extractor, sqlx_connection, mappings, migration_options).await?
+            }
+        }
+        Command::Check => {
+            let migration_options = build_migration_options(&MigrationArgs::default(), settings);
+
+            if run_check(&config, &migration_options).await? {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Command::ListTables => {
+            let mut extractor = create_extractor(&config, 1).await?;
+            let migration_options = build_migration_options(&MigrationArgs::default(), settings);
+
+            list_tables(&mut extractor, &migration_options).await?;
+
+            ExitCode::SUCCESS
+        }
+        Command::Plan { format } => {
+            let mut extractor = create_extractor(&config, 1).await?;
+            let args = MigrationArgs {
+                format: *format,
+                ..MigrationArgs::default()
+            };
+            let migration_options = build_migration_options(&args, settings);
+
+            print_plan(&mut extractor, &mappings, &migration_options).await?;
+
+            ExitCode::SUCCESS
+        }
+        Command::Names { format } => {
+            let mut extractor = create_extractor(&config, 1).await?;
+            let args = MigrationArgs {
+                format: *format,
+                ..MigrationArgs::default()
+            };
+            let migration_options = build_migration_options(&args, settings);
+
+            print_names(&mut extractor, &migration_options).await?;
+
+            ExitCode::SUCCESS
+        }
+        Command::Schema { table, format } => {
+            let mut extractor = create_extractor(&config, 1).await?;
+            let args = MigrationArgs {
+                format: *format,
+                ..MigrationArgs::default()
+            };
+            let migration_options = build_migration_options(&args, settings);
+
+            print_schema(&mut extractor, &mappings, &migration_options, table).await?;
+
+            ExitCode::SUCCESS
+        }
+        Command::ExplainMapping {
+            table,
+            column,
+            format,
+        } => {
+            let mut extractor = create_extractor(&config, 1).await?;
+            let args = MigrationArgs {
+                format: *format,
+                ..MigrationArgs::default()
+            };
+            let migration_options = build_migration_options(&args, settings);
+
+            explain_mapping(&mut extractor, &mappings, &migration_options, table, column).await?;
+
+            ExitCode::SUCCESS
+        }
+        Command::Preview {
+            table,
+            rows,
+            format,
+        } => {
+            let mut extractor = create_extractor(&config, 1).await?;
+            let args = MigrationArgs {
+                format: *format,
+                ..MigrationArgs::default()
+            };
+            let migration_options = build_migration_options(&args, settings);
+
+            preview_rows(&mut extractor, &mappings, &migration_options, table, *rows).await?;
+
+            ExitCode::SUCCESS
+        }
+        Command::Verify {
+            format,
+            checksums,
+            lock,
+        } => {
+            let extractor = create_extractor(&config, 1).await?;
+            // The lock occupies a connection of its own
+            let sqlx_connection =
+                create_sqlx_connection(&config, if *lock { 2 } else { 1 }).await?;
+            let args = MigrationArgs {
+                format: *format,
+                ..MigrationArgs::default()
+            };
+            let migration_options = build_migration_options(&args, settings);
+
+            run_verify(
+                extractor,
+                sqlx_connection,
+                mappings,
+                migration_options,
+                *checksums,
+                *lock,
+            )
+            .await?
+        }
+        Command::Diff {
+            table,
+            limit,
+            format,
+        } => {
+            let mut extractor = create_extractor(&config, 1).await?;
+            let sqlx_connection = create_sqlx_connection(&config, 1).await?;
+            let mut inserter = DatabaseInserter::new(sqlx_connection);
+            let args = MigrationArgs {
+                format: *format,
+                ..MigrationArgs::default()
+            };
+            let migration_options = build_migration_options(&args, settings);
+
+            report_errors(
+                diff_table(
+                    &mut extractor,
+                    &mut inserter,
+                    &mappings,
+                    &migration_options,
+                    table,
+                    *limit,
+                )
+                .await
+                .with_context(|| "Diff failed"),
+            )
+        }
+        Command::Sync {
+            format,
+            interval,
+            once,
+            on_schema_drift,
+        } => {
+            let extractor = create_extractor(&config, 1).await?;
+            let sqlx_connection = create_sqlx_connection(&config, 1).await?;
+            let args = MigrationArgs {
+                format: *format,
+                ..MigrationArgs::default()
+            };
+            let migration_options = build_migration_options(&args, settings);
+
+            run_sync(
+                extractor,
+                sqlx_connection,
+                mappings,
+                migration_options,
+                Duration::from_secs(*interval),
+                *once,
+                *on_schema_drift,
+            )
+            .await?
+        }
+        Command::Rollback { run } => {
+            let sqlx_connection = create_sqlx_connection(&config, 1).await?;
+
+            let mut inserter = DatabaseInserter::new(sqlx_connection);
+            rollback_run(&mut inserter, run).await?;
+
+            ExitCode::SUCCESS
+        }
+        Command::Cleanup { keep } => {
+            let sqlx_connection = create_sqlx_connection(&config, 1).await?;
+
+            let mut inserter = DatabaseInserter::new(sqlx_connection);
+            cleanup_backups(&mut inserter, *keep).await?;
+            cleanup_incomplete_runs(&mut inserter).await?;
+
+            ExitCode::SUCCESS
+        }
+        Command::History { runs, slowdown } => {
+            let sqlx_connection = create_sqlx_connection(&config, 1).await?;
+
+            let mut inserter = DatabaseInserter::new(sqlx_connection);
+            print_history(&mut inserter, *runs, *slowdown).await?;
+
+            ExitCode::SUCCESS
+        }
+        #[cfg(feature = "bundle")]
+        Command::Dump {
+            bundle_dir,
+            migration: args,
+        } => {
+            let migration_options = build_migration_options(args, settings);
+            let extractor = create_extractor(&config, migration_options.source_pool_size()).await?;
+
+            run_dump(extractor, mappings, migration_options, bundle_dir.clone()).await?
+        }
+        #[cfg(feature = "bundle")]
+        Command::Apply {
+            bundle_dir,
+            migration: args,
+        } => {
+            let migration_options = build_migration_options(args, settings);
+            let sqlx_connection =
+                create_sqlx_connection(&config, migration_options.target_pool_size()).await?;
+
+            run_apply(sqlx_connection, migration_options, bundle_dir.clone()).await?
+        }
+        #[cfg(not(feature = "bundle"))]
+        Command::Dump { .. } | Command::Apply { .. } => {
+            bail!("Bundles are not supported by this build, it was compiled without the `bundle` feature")
+        }
+    };
+
+    Ok(exit_code)
+}
+
+fn print_version(features: bool) {
+    println!("DBMigrator {}", env!("CARGO_PKG_VERSION"));
+
+    if features {
+        for (name, enabled) in FEATURES {
+            println!("{}{}", if *enabled { "+" } else { "-" }, name);
+        }
+    }
+}
+
+pub(crate) fn build_migration_options(
+    options: &MigrationArgs,
+    settings: SettingsConfig,
+) -> MigrationOptions {
+    let mut observers = Observers::default();
+    observers.add(Arc::new(LoggingObserver));
+
+    if let Some(notifications) = settings.notifications {
+        #[cfg(feature = "webhook")]
+        observers.add(Arc::new(WebhookNotifier::new(
+            notifications,
+            options.note.clone(),
+        )));
+        #[cfg(not(feature = "webhook"))]
+        warn!(
+            "Ignoring the webhook {}, this build was compiled without the `webhook` feature",
+            notifications.webhook_url
+        );
+    }
+
+    MigrationOptions {
+        drop: options.drop,
+        backup: options.backup,
+        upsert: options.upsert,
+        append: options.append,
+        shadow: options.shadow,
+        sync_schema: options.sync_schema,
+        phase: options.phase,
+        constraints: options.constraints,
+        recreate_constraints: options.recreate_constraints,
+        check_triggers: options.check_triggers,
+        check_fallback: options.check_fallback,
+        quarantine: options.quarantine,
+        quarantine_report: options.quarantine_report.clone(),
+        dead_letter_file: (options.on_error == OnError::Skip)
+            .then(|| options.dead_letter_file.clone()),
+        verify: options.verify,
+        verify_checksums: options.verify_checksums,
+        strict_types: options.strict_types,
+        guid_format: options.guid_format,
+        include_dependencies: options.include_dependencies,
+        format_snake_case: options.format,
+        table_comments: options.table_comments,
+        run_id: new_run_id(),
+        note: options.note.clone(),
+        max_concurrent_tasks: options.parallelism,
+        max_concurrent_tables: settings.max_concurrent_tables,
+        source_max_connections: settings.source_max_connections,
+        target_max_connections: settings.target_max_connections,
+        adaptive_parallelism: options.adaptive.then(|| ParallelismBounds {
+            min: options.min_parallelism,
+            max: options.max_parallelism.unwrap_or(options.parallelism),
+        }),
+        scheduler: options.scheduler,
+        table_workers: options.table_workers,
+        insert_workers: options.insert_workers,
+        max_packet_bytes: settings.max_packet_bytes,
+        chunk_size: settings.chunk_size,
+        collation: Collation::resolve(&settings.collation).for_dialect(settings.dialect),
+        column_collations: settings.column_collations,
+        unchecked_reset_fallback: settings.unchecked_reset_fallback,
+        whitelisted_tables: settings.whitelisted_tables,
+        schema_prefixes: settings.schema_prefixes,
+        prefix_schemas: settings.prefix_schemas,
+        foreign_key_on_delete: settings.foreign_key_on_delete,
+        foreign_key_on_update: settings.foreign_key_on_update,
+        bundle_signing_key: settings.bundle_signing_key,
+        table_configs: settings.tables,
+        id_offset: settings.id_offset,
+        regenerate_keys: settings.regenerate_keys,
+        key_mappings: None,
+        time_zone: settings.time_zone,
+        char_padding: settings.char_padding,
+        on_invalid: settings.on_invalid,
+        check_in_lists: settings.check_in_lists,
+        dialect: settings.dialect,
+        long_keys: settings.long_keys,
+        temporal_tables: settings.temporal_tables,
+        sparse_column_sets: settings.sparse_column_sets,
+        extraction_consistency: settings.extraction_consistency,
+        computed_columns: settings.computed_columns,
+        assertions: settings.assertions,
+        extra_columns: settings.extra_columns,
+        identifiers: settings.identifiers,
+        csv: (options.output == OutputMode::Csv).then_some(settings.csv),
+        cache_dir: options.cache_dir.clone(),
+        max_cache_bytes: options.max_cache_size.map(|mb| mb * 1024 * 1024),
+        memory_budget: new_memory_budget(options),
+        grants_file: options.grants_file.clone(),
+        lineage_file: options.lineage_file.clone(),
+        observers,
+        metrics_port: options.metrics_port,
+        trace_file: options.trace_file.clone(),
+        wait: (options.wait_for_query.is_some()
+            || options.wait_for_replica_lag.is_some()
+            || options.wait_until.is_some())
+        .then(|| WaitConditions {
+            query: options.wait_for_query.clone(),
+            max_replica_lag: options.wait_for_replica_lag,
+            until: options.wait_until,
+            interval: Duration::from_secs(options.wait_interval.max(1)),
+            timeout: options.wait_timeout,
+        }),
+        max_duration: options.max_duration,
+        resume: options.resume,
+        checkpoint_file: options.checkpoint_file.clone(),
+        time_box: None,
+    }
+}
+
+// Extractor of the generated tables, or of the MSSQL database
+async fn create_extractor(config: &Config, max_connections: u32) -> Result<DatabaseExtractor> {
+    if let Some(generator) = config.generator() {
+        info!(
+            "Generating {} tables of {} rows",
+            generator.tables, generator.rows
+        );
+        return Ok(DatabaseExtractor::generated(Generator::new(
+            generator.clone(),
+        )));
+    }
+
+    let tiberius_factory =
+        DatabaseConnectionFactory::<TiberiusConnection>::new(config.mssql_database()?.clone());
+    let tiberius_connection = tiberius_factory.create_connection(max_connections).await?;
+    Ok(DatabaseExtractor::new(tiberius_connection.pool)
+        .with_sparse_column_sets(config.settings().sparse_column_sets)
+        .with_extraction_consistency(config.settings().extraction_consistency))
+}
+
+async fn create_sqlx_connection(
+    config: &Config,
+    max_connections: u32,
+) -> Result<SqlxMySqlConnection> {
+    let sqlx_factory =
+        DatabaseConnectionFactory::<SqlxMySqlConnection>::new(config.mysql_database()?.clone());
+    let sqlx_connection = sqlx_factory.create_connection(max_connections).await?;
+    Ok(sqlx_connection)
+}
+
+fn new_memory_budget(options: &MigrationArgs) -> Option<Arc<MemoryBudget>> {
+    options
+        .max_memory
+        .map(|mb| Arc::new(MemoryBudget::new(mb * 1024 * 1024)))
+}
+
+async fn run_migration(
+    extractor: DatabaseExtractor,
+    sqlx_connection: SqlxMySqlConnection,
+    mappings: Mappings,
+    migration_options: MigrationOptions,
+) -> Result<ExitCode> {
+    let inserter = DatabaseInserter::new(sqlx_connection);
+
+    let mut migrator = DatabaseMigrator::new(extractor, inserter, mappings, migration_options);
+
+    match migrator.run().await.with_context(|| "Migration failed") {
+        Ok(report) if report.suspended => Ok(ExitCode::from(SUSPENDED_EXIT_CODE)),
+        result => Ok(report_errors(result.map(|_| ()))),
+    }
+}
+
+// Runs the `[[migration]]` blocks of the config, `--parallel-migrations` at a time, and logs a combined report
+async fn run_migrations(
+    migrations: &[(String, Config)],
+    mappings: &Mappings,
+    args: &MigrationArgs,
+) -> Result<ExitCode> {
+    info!(
+        "Running {} migrations: {}",
+        migrations.len(),
+        migrations
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    // The migrations run in one process, so they share the memory ceiling
+    let memory_budget = new_memory_budget(args);
+    let memory_budget = &memory_budget;
+
+    let outcomes = stream::iter(migrations)
+        .map(|(name, config)| async move {
+            let start_time = Instant::now();
+            info!("Starting migration {}", name);
+
+            let result = run_named_migration(
+                name,
+                config,
+                mappings,
+                args,
+                memory_budget,
+                migrations.len(),
+            )
+            .await
+            .with_context(|| format!("Migration {} failed", name));
+
+            let status = match &result {
+                Ok(report) if report.suspended => "suspended",
+                Ok(_) => "succeeded",
+                Err(err) => {
+                    print_error_chain(err);
+                    "failed"
+                }
+            };
+
+            (name, status, start_time.elapsed())
+        })
+        .buffered(args.parallel_migrations.max(1))
+        .collect::<Vec<_>>()
+        .await;
+
+    let count = |status| {
+        outcomes
+            .iter()
+            .filter(|outcome| outcome.1 == status)
+            .count()
+    };
+    let (failed, suspended) = (count("failed"), count("suspended"));
+    let name_width = outcomes
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or_default();
+
+    info!("Migration report:");
+    for (name, status, duration) in &outcomes {
+        info!(
+            "  {:<width$}  {:<9}  {:.1}s",
+            name,
+            status,
+            duration.as_secs_f32(),
+            width = name_width
+        );
+    }
+    info!(
+        "{} of {} migrations succeeded",
+        outcomes.len() - failed - suspended,
+        outcomes.len()
+    );
+
+    Ok(if failed > 0 {
+        ExitCode::FAILURE
+    } else if suspended > 0 {
+        ExitCode::from(SUSPENDED_EXIT_CODE)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+async fn run_named_migration(
+    name: &str,
+    config: &Config,
+    mappings: &Mappings,
+    args: &MigrationArgs,
+    memory_budget: &Option<Arc<MemoryBudget>>,
+    migration_count: usize,
+) -> Result<MigrationReport> {
+    let mut migration_options = build_migration_options(args, config.settings().clone());
+    migration_options.memory_budget = memory_budget.clone();
+
+    // Files written per run get the migration name, so the migrations don't overwrite each other's
+    if migration_count > 1 {
+        for path in [
+            &mut migration_options.grants_file,
+            &mut migration_options.lineage_file,
+            &mut migration_options.quarantine_report,
+            &mut migration_options.dead_letter_file,
+            &mut migration_options.trace_file,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *path = with_migration_name(path, name);
+        }
+
+        if let Some(cache_dir) = &mut migration_options.cache_dir {
+            *cache_dir = cache_dir.join(name);
+        }
+
+        migration_options.checkpoint_file =
+            with_migration_name(&migration_options.checkpoint_file, name);
+    }
+
+    let extractor = create_extractor(config, migration_options.source_pool_size()).await?;
+    let sqlx_connection =
+        create_sqlx_connection(config, migration_options.target_pool_size()).await?;
+
+    let inserter = DatabaseInserter::new(sqlx_connection);
+
+    DatabaseMigrator::new(extractor, inserter, mappings.clone(), migration_options)
+        .run()
+        .await
+}
+
+// `lineage.json` of migration `shop` becomes `lineage.shop.json`
+fn with_migration_name(path: &Path, name: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, name, extension.to_string_lossy()),
+        None => format!("{}.{}", stem, name),
+    };
+
+    path.with_file_name(file_name)
+}
+
+async fn run_verify(
+    extractor: DatabaseExtractor,
+    sqlx_connection: SqlxMySqlConnection,
+    mappings: Mappings,
+    migration_options: MigrationOptions,
+    checksums: bool,
+    lock: bool,
+) -> Result<ExitCode> {
+    let inserter = DatabaseInserter::new(sqlx_connection);
+
+    let mut verifier = DatabaseVerifier::new(
+        extractor,
+        inserter,
+        mappings,
+        migration_options,
+        checksums,
+        lock,
+    );
+
+    Ok(report_errors(
+        verifier.run().await.with_context(|| "Verification failed"),
+    ))
+}
+
+async fn run_sync(
+    extractor: DatabaseExtractor,
+    sqlx_connection: SqlxMySqlConnection,
+    mappings: Mappings,
+    migration_options: MigrationOptions,
+    interval: Duration,
+    once: bool,
+    on_schema_drift: SchemaDriftAction,
+) -> Result<ExitCode> {
+    let inserter = DatabaseInserter::new(sqlx_connection);
+
+    let mut syncer = ChangeSyncer::new(
+        extractor,
+        inserter,
+        mappings,
+        migration_options,
+        interval,
+        once,
+        on_schema_drift,
+    );
+
+    Ok(report_errors(
+        syncer.run().await.with_context(|| "Sync failed"),
+    ))
+}
+
+#[cfg(feature = "bundle")]
+async fn run_dump(
+    extractor: DatabaseExtractor,
+    mappings: Mappings,
+    migration_options: MigrationOptions,
+    bundle_dir: PathBuf,
+) -> Result<ExitCode> {
+    let mut dumper = BundleDumper::new(extractor, mappings, migration_options, bundle_dir);
+
+    Ok(report_errors(
+        dumper.run().await.with_context(|| "Dump failed"),
+    ))
+}
+
+async fn run_null_export(
+    extractor: DatabaseExtractor,
+    mappings: Mappings,
+    migration_options: MigrationOptions,
+) -> Result<ExitCode> {
+    let mut exporter = NullExporter::new(extractor, mappings, migration_options);
+
+    Ok(report_errors(
+        exporter.run().await.with_context(|| "Export failed"),
+    ))
+}
+
+async fn run_export(
+    extractor: DatabaseExtractor,
+    mappings: Mappings,
+    migration_options: MigrationOptions,
+    out_dir: PathBuf,
+) -> Result<ExitCode> {
+    let mut exporter = SqlFileExporter::new(extractor, mappings, migration_options, out_dir);
+
+    Ok(report_errors(
+        exporter.run().await.with_context(|| "Export failed"),
+    ))
+}
+
+#[cfg(feature = "bundle")]
+async fn run_apply(
+    sqlx_connection: SqlxMySqlConnection,
+    migration_options: MigrationOptions,
+    bundle_dir: PathBuf,
+) -> Result<ExitCode> {
+    let inserter = DatabaseInserter::new(sqlx_connection);
+
+    let mut applier = BundleApplier::new(inserter, migration_options, bundle_dir);
+
+    Ok(report_errors(
+        applier.run().await.with_context(|| "Apply failed"),
+    ))
+}
+
+// Logs the error chain of a failed run, which is reflected in the exit code
+fn report_errors(result: Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(errors) => {
+            for (index, error) in errors.chain().enumerate() {
+                error!("└> {} - {}", index, error);
+            }
+
+            ExitCode::FAILURE
+        }
+    }
+}
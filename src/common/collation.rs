@@ -0,0 +1,63 @@
+use crate::config::Dialect;
+
+const DEFAULT_CHARSET: &str = "utf8mb4";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collation {
+    pub charset: String,
+    pub name: String,
+}
+
+impl Collation {
+    // Accepts either a MySQL collation like `utf8mb4_unicode_ci` or an MSSQL collation like
+    // `Latin1_General_CI_AS`, which is translated to the closest utf8mb4 collation
+    pub fn resolve(collation: &str) -> Self {
+        let is_mysql_collation = collation
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+        if is_mysql_collation {
+            let charset = collation.split('_').next().unwrap_or(DEFAULT_CHARSET);
+
+            return Collation {
+                charset: charset.to_string(),
+                name: collation.to_string(),
+            };
+        }
+
+        Collation {
+            charset: DEFAULT_CHARSET.to_string(),
+            name: map_mssql_collation(collation).to_string(),
+        }
+    }
+
+    // The UCA 9.0.0 collations only exist in MySQL 8, older servers and MariaDB get the UCA 5.2.0 or binary one
+    pub fn for_dialect(self, dialect: Dialect) -> Self {
+        if dialect == Dialect::Mysql8 || !self.name.contains("_0900_") {
+            return self;
+        }
+
+        let name = if self.name.ends_with("_ci") {
+            format!("{}_unicode_520_ci", self.charset)
+        } else {
+            format!("{}_bin", self.charset)
+        };
+
+        Collation { name, ..self }
+    }
+}
+
+fn map_mssql_collation(collation: &str) -> &'static str {
+    let flags = collation.to_uppercase();
+    let has_flag = |flag: &str| flags.split('_').any(|part| part == flag);
+
+    if has_flag("BIN") || has_flag("BIN2") {
+        "utf8mb4_bin"
+    } else if has_flag("CS") {
+        "utf8mb4_0900_as_cs"
+    } else if has_flag("AI") {
+        "utf8mb4_0900_ai_ci"
+    } else {
+        "utf8mb4_0900_as_ci"
+    }
+}
@@ -1,9 +1,17 @@
+use anyhow::{bail, Result};
+
+use crate::common::identifiers::{quote_identifier, quote_literal};
+use crate::common::schema::ColumnSchema;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Constraint {
     PrimaryKey,
     ForeignKey {
         referenced_table: String,
         referenced_column: String,
+        // Referential actions like `NO ACTION` or `CASCADE`
+        on_delete: String,
+        on_update: String,
     },
     Unique,
     Check(String),
@@ -17,13 +25,15 @@ impl Constraint {
             Ok(Some(Constraint::PrimaryKey))
         } else if s.starts_with("FOREIGN KEY") {
             let parts: Vec<&str> = s.split(',').map(|p| p.trim()).collect();
-            if parts.len() == 3 {
+            if parts.len() == 5 {
                 let referenced_table = parts[1].to_string();
                 let referenced_column = parts[2].to_string();
 
                 Ok(Some(Constraint::ForeignKey {
                     referenced_table,
                     referenced_column,
+                    on_delete: parts[3].to_string(),
+                    on_update: parts[4].to_string(),
                 }))
             } else {
                 Err(()) // Return an error if the FOREIGN KEY constraint format is incorrect
@@ -31,7 +41,7 @@ impl Constraint {
         } else if s == "UNIQUE" {
             Ok(Some(Constraint::Unique))
         } else if s.starts_with("CHECK") {
-            let check_clause = s.trim_matches(|c| c == '(' || c == ')').to_string();
+            let check_clause = s.trim_start_matches("CHECK").trim().to_string();
             Ok(Some(Constraint::Check(check_clause)))
         } else if s.starts_with("DEFAULT") {
             let default_value = s.trim_start_matches("DEFAULT ").to_string();
@@ -41,3 +51,511 @@ impl Constraint {
         }
     }
 }
+
+// Foreign key, unique or CHECK constraint found on a target table, the column is known of keys only
+#[derive(Debug, Clone)]
+pub struct TargetConstraint {
+    pub name: String,
+    pub constraint_type: String,
+    pub column_name: Option<String>,
+}
+
+impl TargetConstraint {
+    // Matched by name, or by kind and column when MySQL generated the name. MySQL lists no column of CHECK
+    // constraints, so those are only found by name, which source constraints always have
+    pub fn matches(&self, column: &ColumnSchema, constraint: &Constraint) -> bool {
+        let constraint_type = match constraint {
+            Constraint::ForeignKey { .. } => "FOREIGN KEY",
+            Constraint::Unique => "UNIQUE",
+            Constraint::Check(_) => "CHECK",
+            _ => return false,
+        };
+
+        self.constraint_type == constraint_type
+            && match &column.constraint_name {
+                Some(name) => self.name.eq_ignore_ascii_case(name),
+                None => self.column_name.as_deref() == Some(column.column_name.as_str()),
+            }
+    }
+}
+
+// MSSQL functions of check clauses with a MySQL counterpart of the same arguments
+const CHECK_FUNCTIONS: [(&str, &str); 6] = [
+    ("len", "CHAR_LENGTH"),
+    ("datalength", "LENGTH"),
+    ("isnull", "IFNULL"),
+    ("charindex", "LOCATE"),
+    ("ceiling", "CEILING"),
+    ("substring", "SUBSTRING"),
+];
+
+// MySQL rejects nondeterministic functions in CHECK constraints and generated columns
+const NONDETERMINISTIC_FUNCTIONS: [&str; 8] = [
+    "getdate",
+    "getutcdate",
+    "sysdatetime",
+    "sysutcdatetime",
+    "sysdatetimeoffset",
+    "current_timestamp",
+    "newid",
+    "rand",
+];
+
+// Rewrites an MSSQL check clause into MySQL syntax: bracket quoted column references become backtick quoted
+// target columns, `N'...'` strings plain strings and common functions their MySQL counterparts.
+// Fails for clauses MySQL can't express
+pub fn translate_check_clause<F: Fn(&str) -> String>(
+    clause: &str,
+    column_name: F,
+) -> Result<String> {
+    let chars = clause.chars().collect::<Vec<_>>();
+    let mut result = String::with_capacity(clause.len());
+    let mut last_word = String::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+
+        if c == '[' {
+            let Some(length) = chars[index..].iter().position(|c| *c == ']') else {
+                bail!("unterminated column reference");
+            };
+
+            let name = chars[index + 1..index + length].iter().collect::<String>();
+            result.push_str(&quote_identifier(&column_name(&name)));
+            last_word.clear();
+            index += length + 1;
+        } else if c == '\'' {
+            let mut end = index + 1;
+
+            // Quotes inside the literal are doubled
+            while end < chars.len() {
+                if chars[end] == '\'' && chars.get(end + 1) == Some(&'\'') {
+                    end += 2;
+                } else if chars[end] == '\'' {
+                    break;
+                } else {
+                    end += 1;
+                }
+            }
+
+            let literal = chars[index..(end + 1).min(chars.len())]
+                .iter()
+                .collect::<String>();
+
+            if last_word == "like" && literal.contains('[') {
+                bail!("LIKE pattern {} uses a character class", literal);
+            }
+
+            result.push_str(&literal);
+            last_word.clear();
+            index = end + 1;
+        } else if c.is_alphabetic() || c == '_' || c == '@' {
+            let end = chars[index..]
+                .iter()
+                .position(|c| !(c.is_alphanumeric() || *c == '_' || *c == '@'))
+                .map_or(chars.len(), |length| index + length);
+            let word = chars[index..end].iter().collect::<String>();
+            let lowercase = word.to_lowercase();
+            let next = chars[end..].iter().find(|c| !c.is_whitespace());
+
+            if lowercase == "n" && chars.get(end) == Some(&'\'') {
+                // Unicode string prefix, dropped
+            } else if next == Some(&'.') {
+                bail!("references {}.", word);
+            } else if word.starts_with('@') {
+                bail!("uses variable {}", word);
+            } else if NONDETERMINISTIC_FUNCTIONS.contains(&lowercase.as_str())
+                && (next == Some(&'(') || lowercase == "current_timestamp")
+            {
+                bail!(
+                    "uses {}, which MySQL doesn't allow in CHECK constraints",
+                    word
+                );
+            } else if next == Some(&'(') {
+                match CHECK_FUNCTIONS.iter().find(|(name, _)| *name == lowercase) {
+                    Some((_, function)) => result.push_str(function),
+                    None if lowercase == "convert" => bail!("uses CONVERT"),
+                    None => result.push_str(&word),
+                }
+            } else {
+                result.push_str(&word);
+            }
+
+            last_word = lowercase;
+            index = end;
+        } else {
+            result.push(c);
+            index += 1;
+        }
+    }
+
+    Ok(result)
+}
+
+// MSSQL functions of computed columns that MySQL lacks or evaluates differently, like CONCAT skipping NULL values
+const NONPORTABLE_FUNCTIONS: [&str; 9] = [
+    "cast", "try_cast", "dateadd", "datediff", "datepart", "datename", "iif", "format", "concat",
+];
+
+// Rewrites the definition of an MSSQL computed column of the given source type into the expression of a MySQL
+// generated column, like `([price]*[quantity])`. Fails for expressions that aren't portable
+pub fn translate_computed_expression<F: Fn(&str) -> String>(
+    definition: &str,
+    data_type: &str,
+    column_name: F,
+) -> Result<String> {
+    let code = strip_quoted(definition);
+
+    // `+` concatenates strings and adds days to dates in MSSQL, MySQL adds numbers
+    if ["char", "varchar", "nchar", "nvarchar"].contains(&data_type) && code.contains('+') {
+        bail!("concatenates strings with +");
+    }
+
+    if data_type.contains("date") && (code.contains('+') || code.contains('-')) {
+        bail!("uses date arithmetic");
+    }
+
+    // `/` truncates integers in MSSQL, MySQL returns a decimal. The operand types are unknown, so any division is
+    // rejected, and `%` unless the result is an integer, as MSSQL and MySQL convert mixed operands differently
+    if code.contains('/') {
+        bail!("divides with /");
+    }
+
+    if code.contains('%') && !["tinyint", "smallint", "int", "bigint"].contains(&data_type) {
+        bail!("uses % on {}", data_type);
+    }
+
+    for word in code.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+        let lowercase = word.to_lowercase();
+
+        if NONPORTABLE_FUNCTIONS.contains(&lowercase.as_str()) {
+            bail!("uses {}", word.to_uppercase());
+        }
+
+        if NONDETERMINISTIC_FUNCTIONS.contains(&lowercase.as_str()) {
+            bail!(
+                "uses {}, which MySQL doesn't allow in generated columns",
+                word.to_uppercase()
+            );
+        }
+    }
+
+    translate_check_clause(definition, column_name)
+}
+
+// The expression with string literals and bracket quoted names blanked, leaving operators and function names
+fn strip_quoted(expression: &str) -> String {
+    let mut result = String::with_capacity(expression.len());
+    let mut closing = None;
+
+    for c in expression.chars() {
+        match closing {
+            // Doubled quotes end the literal and start it again, which blanks the same
+            Some(end) if c == end => closing = None,
+            Some(_) => {}
+            None if c == '\'' => closing = Some('\''),
+            None if c == '[' => closing = Some(']'),
+            None => {
+                result.push(c);
+                continue;
+            }
+        }
+
+        result.push(' ');
+    }
+
+    result
+}
+
+// Translates an MSSQL default definition like `((0))`, `(N'abc')` or `(getdate())` into a MySQL default,
+// `None` when there is no MySQL equivalent
+pub fn translate_default_expression(definition: &str) -> Option<String> {
+    let mut expression = definition.trim();
+
+    while let Some(inner) = strip_wrapping_parentheses(expression) {
+        expression = inner.trim();
+    }
+
+    let function = expression.to_lowercase().replace(' ', "");
+
+    let translated = match function.as_str() {
+        "getdate()" | "sysdatetime()" | "current_timestamp" | "sysdatetimeoffset()" => {
+            "CURRENT_TIMESTAMP".to_string()
+        }
+        "getutcdate()" | "sysutcdatetime()" => "(UTC_TIMESTAMP())".to_string(),
+        "newid()" | "newsequentialid()" => "(UUID())".to_string(),
+        "null" => "NULL".to_string(),
+        _ => match parse_string_literal(expression) {
+            // Requoted, as MySQL reads backslashes in string literals as escapes
+            Some((value, tail)) if tail.trim().is_empty() => quote_literal(&value),
+            Some(_) => return None,
+            None if is_decimal_literal(expression) => expression.to_string(),
+            None => return None,
+        },
+    };
+
+    Some(translated)
+}
+
+// Number written like `12`, `-0.5` or `3.`, but not `1e3`, `inf` or `NaN` like other parsable numbers
+fn is_decimal_literal(literal: &str) -> bool {
+    let digits = literal.strip_prefix(['-', '+']).unwrap_or(literal);
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+    !(integer.is_empty() && fraction.is_empty())
+        && integer.chars().all(|c| c.is_ascii_digit())
+        && fraction.chars().all(|c| c.is_ascii_digit())
+}
+
+// Members of a check clause limiting the column to a list of strings, either `[col] IN ('A', 'B')` or the
+// `([col]='B' OR [col]='A')` chain MSSQL stores such a list as, which names the members in reverse order
+pub fn parse_check_in_list(clause: &str, column_name: &str) -> Option<Vec<String>> {
+    let mut clause = clause.trim();
+
+    while let Some(inner) = strip_wrapping_parentheses(clause) {
+        clause = inner.trim();
+    }
+
+    let column = format!("[{}]", column_name);
+
+    if let Some(list) = strip_prefix_ignore_case(clause, &column)
+        .and_then(|rest| strip_keyword(rest.trim_start(), "in"))
+    {
+        let mut rest = list.trim_start().strip_prefix('(')?.strip_suffix(')')?;
+        let mut members = Vec::new();
+
+        loop {
+            let (member, tail) = parse_string_literal(rest.trim_start())?;
+            members.push(member);
+
+            match tail.trim_start().strip_prefix(',') {
+                Some(tail) => rest = tail,
+                None if tail.trim().is_empty() => return Some(members),
+                None => return None,
+            }
+        }
+    }
+
+    let mut members = Vec::new();
+    let mut rest = clause;
+
+    loop {
+        let term = rest.trim_start();
+        let (term, parenthesized) = match term.strip_prefix('(') {
+            Some(term) => (term.trim_start(), true),
+            None => (term, false),
+        };
+
+        let value = strip_prefix_ignore_case(term, &column)?
+            .trim_start()
+            .strip_prefix('=')?;
+        let (member, tail) = parse_string_literal(value.trim_start())?;
+        let mut tail = tail.trim_start();
+
+        if parenthesized {
+            tail = tail.strip_prefix(')')?.trim_start();
+        }
+
+        members.push(member);
+
+        if tail.is_empty() {
+            break;
+        }
+
+        rest = strip_keyword(tail, "or")?;
+    }
+
+    members.reverse();
+    Some(members)
+}
+
+// Content of a leading `'...'` or `N'...'` literal along with the rest of the input
+fn parse_string_literal(input: &str) -> Option<(String, &str)> {
+    let input = input
+        .strip_prefix(['N', 'n'])
+        .filter(|input| input.starts_with('\''))
+        .unwrap_or(input)
+        .strip_prefix('\'')?;
+
+    let mut value = String::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        if c != '\'' {
+            value.push(c);
+        } else if chars.peek().is_some_and(|(_, next)| *next == '\'') {
+            value.push('\'');
+            chars.next();
+        } else {
+            return Some((value, &input[index + 1..]));
+        }
+    }
+
+    None
+}
+
+fn strip_prefix_ignore_case<'a>(input: &'a str, prefix: &str) -> Option<&'a str> {
+    input
+        .get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &input[prefix.len()..])
+}
+
+// Rest of the input after a leading keyword that isn't the start of a longer word
+fn strip_keyword<'a>(input: &'a str, keyword: &str) -> Option<&'a str> {
+    strip_prefix_ignore_case(input, keyword)
+        .filter(|rest| rest.starts_with(|c: char| c.is_whitespace() || c == '('))
+}
+
+// Inner part of an expression enclosed in one pair of matching parentheses
+fn strip_wrapping_parentheses(expression: &str) -> Option<&str> {
+    let inner = expression.strip_prefix('(')?.strip_suffix(')')?;
+    let mut depth = 0;
+
+    // `(1) + (2)` starts and ends with parentheses that don't belong together
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return None,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    Some(inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate(definition: &str, data_type: &str) -> Result<String> {
+        translate_computed_expression(definition, data_type, |name| name.to_lowercase())
+    }
+
+    #[test]
+    fn translates_portable_expressions() {
+        assert_eq!(
+            translate("([Price]*[Quantity])", "decimal").unwrap(),
+            "(`price`*`quantity`)"
+        );
+        assert_eq!(
+            translate("(isnull([Discount],(0))-[Rebate])", "int").unwrap(),
+            "(IFNULL(`discount`,(0))-`rebate`)"
+        );
+        assert_eq!(
+            translate("([Quantity]%(12))", "int").unwrap(),
+            "(`quantity`%(12))"
+        );
+        assert_eq!(
+            translate("(upper([Name]))", "nvarchar").unwrap(),
+            "(upper(`name`))"
+        );
+        // Operators inside literals and names don't count
+        assert_eq!(
+            translate("(([A/B]+'/')+'%')", "int").unwrap(),
+            "((`a/b`+'/')+'%')"
+        );
+    }
+
+    #[test]
+    fn rejects_nonportable_expressions() {
+        let rejected = [
+            (
+                "([First]+[Last])",
+                "nvarchar",
+                "concatenates strings with +",
+            ),
+            ("([Start]+(1))", "datetime", "uses date arithmetic"),
+            ("([Total]/[Count])", "int", "divides with /"),
+            ("([Total]/(2.0))", "numeric", "divides with /"),
+            ("([Amount]%(2.5))", "decimal", "uses % on decimal"),
+            ("(CONVERT([int],[Code]))", "int", "uses CONVERT"),
+            ("(concat([First],[Last]))", "nvarchar", "uses CONCAT"),
+            ("(datediff(day,[Start],getdate()))", "int", "uses DATEDIFF"),
+            (
+                "(newid())",
+                "uniqueidentifier",
+                "uses NEWID, which MySQL doesn't allow in generated columns",
+            ),
+        ];
+
+        for (definition, data_type, reason) in rejected {
+            assert_eq!(
+                translate(definition, data_type).unwrap_err().to_string(),
+                reason,
+                "{}",
+                definition
+            );
+        }
+    }
+
+    #[test]
+    fn translates_default_literals_and_functions() {
+        let translated = [
+            ("((0))", "0"),
+            ("((-1.5))", "-1.5"),
+            ("(N'abc')", "'abc'"),
+            ("('it''s')", "'it''s'"),
+            ("('C:\\temp')", "'C:\\\\temp'"),
+            ("(getdate())", "CURRENT_TIMESTAMP"),
+            ("(getutcdate())", "(UTC_TIMESTAMP())"),
+            ("(newid())", "(UUID())"),
+            ("(NULL)", "NULL"),
+        ];
+
+        for (definition, default) in translated {
+            assert_eq!(
+                translate_default_expression(definition).as_deref(),
+                Some(default),
+                "{}",
+                definition
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_defaults_without_mysql_equivalent() {
+        for definition in [
+            "(inf)",
+            "(NaN)",
+            "(infinity)",
+            "((1e3))",
+            "('a'+'b')",
+            "(suser_sname())",
+            "((.))",
+        ] {
+            assert_eq!(
+                translate_default_expression(definition),
+                None,
+                "{}",
+                definition
+            );
+        }
+    }
+
+    #[test]
+    fn parses_check_in_lists() {
+        assert_eq!(
+            parse_check_in_list("([Status] IN ('open', N'closed'))", "Status"),
+            Some(vec!["open".to_string(), "closed".to_string()])
+        );
+        // MSSQL stores the list as a chain of comparisons in reverse order
+        assert_eq!(
+            parse_check_in_list("([Status]='closed' OR [Status]='it''s open')", "Status"),
+            Some(vec!["it's open".to_string(), "closed".to_string()])
+        );
+        assert_eq!(
+            parse_check_in_list("(([status]='b') OR ([status]='a'))", "Status"),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+
+        assert_eq!(parse_check_in_list("([Status] IN (1, 2))", "Status"), None);
+        assert_eq!(
+            parse_check_in_list("([Status]='a' OR [Other]='b')", "Status"),
+            None
+        );
+        assert_eq!(parse_check_in_list("([Status]<>'a')", "Status"), None);
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::common::constraints::Constraint;
+use crate::common::schema::ColumnSchema;
+
+// Directed graph of tables pointing at the tables their foreign keys reference
+#[derive(Debug, Clone)]
+pub struct DependencyGraph<T: Ord + Clone> {
+    dependencies: BTreeMap<T, BTreeSet<T>>,
+}
+
+impl<T: Ord + Clone> DependencyGraph<T> {
+    pub fn from_references<I: IntoIterator<Item = (T, T)>>(references: I) -> Self {
+        let mut dependencies: BTreeMap<T, BTreeSet<T>> = BTreeMap::new();
+
+        for (table, referenced_table) in references {
+            // Self references don't constrain the order
+            if table != referenced_table {
+                dependencies
+                    .entry(table)
+                    .or_default()
+                    .insert(referenced_table);
+            }
+        }
+
+        DependencyGraph { dependencies }
+    }
+
+    pub fn dependencies(&self, table: &T) -> impl Iterator<Item = &T> {
+        self.dependencies.get(table).into_iter().flatten()
+    }
+
+    // Referenced tables that are not part of the selection, paired with the table referencing them
+    pub fn missing_dependencies(&self, selected: &[T]) -> Vec<(T, T)> {
+        selected
+            .iter()
+            .flat_map(|table| {
+                self.dependencies(table)
+                    .filter(|dependency| !selected.contains(dependency))
+                    .map(move |dependency| (table.clone(), dependency.clone()))
+            })
+            .collect()
+    }
+
+    // Extends the selection with every table it transitively depends on
+    pub fn with_dependencies(&self, selected: &[T]) -> Vec<T> {
+        let mut result = selected.to_vec();
+        let mut pending = selected.to_vec();
+
+        while let Some(table) = pending.pop() {
+            for dependency in self.dependencies(&table) {
+                if !result.contains(dependency) {
+                    result.push(dependency.clone());
+                    pending.push(dependency.clone());
+                }
+            }
+        }
+
+        result
+    }
+
+    // Groups the tables into levels where each level only depends on the previous ones,
+    // tables caught in a reference cycle end up together in the last level
+    pub fn levels(&self, tables: &[T]) -> Vec<Vec<T>> {
+        let mut remaining = tables.iter().cloned().collect::<BTreeSet<_>>();
+        let mut levels = Vec::new();
+
+        while !remaining.is_empty() {
+            let level = remaining
+                .iter()
+                .filter(|table| {
+                    self.dependencies(table)
+                        .all(|dependency| !remaining.contains(dependency))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+
+            if level.is_empty() {
+                levels.push(remaining.into_iter().collect());
+                break;
+            }
+
+            for table in &level {
+                remaining.remove(table);
+            }
+
+            levels.push(level);
+        }
+
+        levels
+    }
+}
+
+impl DependencyGraph<String> {
+    // Builds the graph of target tables from the foreign keys of their mapped schemas
+    pub fn from_schemas<'a, I>(tables: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, &'a [ColumnSchema])>,
+    {
+        Self::from_references(tables.into_iter().flat_map(|(table_name, schema)| {
+            schema
+                .iter()
+                .filter_map(move |column| match &column.constraints {
+                    Some(Constraint::ForeignKey {
+                        referenced_table, ..
+                    }) => Some((table_name.to_string(), referenced_table.clone())),
+                    _ => None,
+                })
+        }))
+    }
+}
@@ -1,3 +1,9 @@
+pub mod collation;
 pub mod constraints;
+pub mod dependency_graph;
 pub mod helpers;
+pub mod identifiers;
+pub mod permission;
+pub mod pool_stats;
 pub mod schema;
+pub mod source_table;
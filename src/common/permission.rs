@@ -0,0 +1,31 @@
+use crate::common::source_table::SourceTable;
+
+#[derive(Debug, Clone)]
+pub struct TablePermission {
+    pub table: SourceTable,
+    pub principal: String,
+    pub principal_type: PrincipalType,
+    pub permission: String,
+    pub state: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrincipalType {
+    User,
+    Role,
+}
+
+impl PrincipalType {
+    pub fn from_type_desc(type_desc: &str) -> Self {
+        match type_desc {
+            "DATABASE_ROLE" | "APPLICATION_ROLE" => PrincipalType::Role,
+            _ => PrincipalType::User,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RoleMembership {
+    pub role: String,
+    pub member: String,
+}
@@ -0,0 +1,50 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// Connections of a pool at one point in time
+#[derive(Debug, Clone, Copy)]
+pub struct PoolUsage {
+    pub in_use: u32,
+    pub idle: u32,
+}
+
+// Time spent waiting for connections of a pool, shared by all clones of an extractor or inserter
+#[derive(Debug, Default)]
+pub struct WaitStats {
+    waits: AtomicU64,
+    total_micros: AtomicU64,
+    max_micros: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WaitSnapshot {
+    pub waits: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl WaitStats {
+    pub fn record(&self, wait: Duration) {
+        let micros = wait.as_micros() as u64;
+
+        self.waits.fetch_add(1, Ordering::Relaxed);
+        self.total_micros.fetch_add(micros, Ordering::Relaxed);
+        self.max_micros.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> WaitSnapshot {
+        WaitSnapshot {
+            waits: self.waits.load(Ordering::Relaxed),
+            total: Duration::from_micros(self.total_micros.load(Ordering::Relaxed)),
+            max: Duration::from_micros(self.max_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl WaitSnapshot {
+    pub fn average(&self) -> Duration {
+        self.total
+            .checked_div(self.waits as u32)
+            .unwrap_or_default()
+    }
+}
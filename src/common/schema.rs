@@ -9,8 +9,14 @@ pub struct ColumnSchema {
     pub character_maximum_length: Option<i32>,
     pub numeric_precision: Option<u8>,
     pub numeric_scale: Option<i32>,
+    // Digits of the fractional seconds of date and time types
+    pub datetime_precision: Option<i16>,
     pub is_nullable: bool,
     pub constraints: Option<Constraint>,
+    pub constraint_name: Option<String>,
+    pub collation: Option<String>,
+    // Definition of a computed column, the MySQL expression of a generated column once mapped
+    pub computed_definition: Option<String>,
 }
 
 impl ColumnSchema {
@@ -20,8 +26,12 @@ impl ColumnSchema {
         let character_maximum_length = Column::get(row, "CHARACTER_MAXIMUM_LENGTH");
         let numeric_precision = Column::get(row, "NUMERIC_PRECISION");
         let numeric_scale = Column::get(row, "NUMERIC_SCALE");
+        let datetime_precision = Column::get(row, "DATETIME_PRECISION");
         let is_nullable = parse_bool_from_string(Column::get(row, "IS_NULLABLE"));
         let constraints = Constraint::from_str(Column::get(row, "CONSTRAINTS")).unwrap();
+        let constraint_name = Column::get(row, "CONSTRAINT_NAME");
+        let collation = Column::get(row, "COLLATION_NAME");
+        let computed_definition = Column::get(row, "COMPUTED_DEFINITION");
 
         Ok(ColumnSchema {
             column_name,
@@ -29,8 +39,12 @@ impl ColumnSchema {
             character_maximum_length,
             numeric_precision,
             numeric_scale,
+            datetime_precision,
             is_nullable,
             constraints,
+            constraint_name,
+            collation,
+            computed_definition,
         })
     }
 }
@@ -60,6 +74,12 @@ impl Column for Option<u8> {
     }
 }
 
+impl Column for Option<i16> {
+    fn get(row: &Row, col_name: &str) -> Option<i16> {
+        row.get::<i16, _>(col_name)
+    }
+}
+
 impl Column for Option<i64> {
     fn get(row: &Row, col_name: &str) -> Option<i64> {
         row.get::<i64, _>(col_name)
@@ -0,0 +1,56 @@
+use std::fmt;
+
+pub const DEFAULT_SCHEMA: &str = "dbo";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SourceTable {
+    pub schema: String,
+    pub name: String,
+    // Period start column of a temporal table flattened with its history, whose rows are read in all versions
+    pub period_start: Option<String>,
+}
+
+impl SourceTable {
+    pub fn new(schema: &str, name: &str) -> Self {
+        SourceTable {
+            schema: schema.to_string(),
+            name: name.to_string(),
+            period_start: None,
+        }
+    }
+
+    /// Parses `schema.table`, falling back to the default schema for bare names
+    pub fn parse(qualified_name: &str) -> Self {
+        match qualified_name.split_once('.') {
+            Some((schema, name)) => SourceTable::new(schema, name),
+            None => SourceTable::new(DEFAULT_SCHEMA, qualified_name),
+        }
+    }
+
+    /// Bracket-quoted name usable in MSSQL queries
+    pub fn quoted(&self) -> String {
+        format!("[{}].[{}]", self.schema, self.name)
+    }
+
+    /// Row source of the table in MSSQL queries, including the history rows of a flattened temporal table
+    pub fn row_source(&self) -> String {
+        match self.period_start {
+            Some(_) => format!("{} FOR SYSTEM_TIME ALL", self.quoted()),
+            None => self.quoted(),
+        }
+    }
+
+    /// Whitelist entries may be either `schema.table` or a bare table name matching any schema
+    pub fn matches(&self, pattern: &str) -> bool {
+        match pattern.split_once('.') {
+            Some((schema, name)) => self.schema == schema && self.name == name,
+            None => self.name == pattern,
+        }
+    }
+}
+
+impl fmt::Display for SourceTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.schema, self.name)
+    }
+}
@@ -1,11 +1,48 @@
-use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{env, fs};
+
+use anyhow::{anyhow, bail, Context, Error, Result};
+use chrono_tz::Tz;
+use log::LevelFilter;
+use regex::Regex;
+use sqlx::mysql::MySqlSslMode;
+use toml::value::Table;
 use toml::Value;
 
-#[derive(Debug)]
-pub(crate) struct Config {
-    mssql_database: DatabaseConfig,
-    mysql_database: DatabaseConfig,
+use crate::common::identifiers::quote_literal;
+
+const DEFAULT_PARTITIONS: usize = 16;
+
+const DEFAULT_MYSQL_PORT: u16 = 3306;
+
+const DEFAULT_BROWSER_PORT: u16 = 1434;
+
+const DEFAULT_GENERATED_TABLES: usize = 3;
+
+const DEFAULT_GENERATED_ROWS: i64 = 100_000;
+
+pub(crate) const MIGRATION_SECTIONS: [&str; 4] =
+    ["mssql_database", "mysql_database", "generator", "settings"];
+
+// Keys of the database sections whose `${NAME}` references are replaced with environment variables
+pub(crate) const INTERPOLATED_KEYS: [&str; 7] = [
+    "socket", "host", "instance", "port", "username", "password", "database",
+];
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    // Left out when the tables come from the generator, or nothing is written with the null target
+    mssql_database: Option<DatabaseConfig>,
+    mysql_database: Option<DatabaseConfig>,
+    // Synthesized tables read instead of an MSSQL database, with `source = "generator"`
+    generator: Option<GeneratorConfig>,
+    // Rows are read and converted but discarded, with `target = "null"`
+    null_target: bool,
     settings: SettingsConfig,
+    // Named `[[migration]]` blocks, each a complete config of its own
+    migrations: Vec<(String, Config)>,
 }
 
 #[derive(Debug, Clone)]
@@ -15,29 +52,452 @@ pub struct DatabaseConfig {
     pub username: String,
     pub password: String,
     pub database: String,
+    // Named instance resolved through the SQL Browser listening on `port`, MSSQL only
+    pub instance: Option<String>,
+    // UNIX socket used instead of host and port, MySQL only
+    pub socket: Option<PathBuf>,
+    pub connect_timeout: Option<Duration>,
+    pub ssl_mode: Option<MySqlSslMode>,
+    pub ssl_ca: Option<PathBuf>,
+    // Statements run on every new connection, like `SET time_zone = '+00:00'`
+    pub init_sql: Vec<String>,
+    // Session variables set before every transaction, like `sql_log_bin = 0`, as name and SQL literal
+    pub session_variables: Vec<(String, String)>,
+    // Health checks of the pooled connections, from `[settings.pool]`
+    pub pool: PoolConfig,
+}
+
+// Keeps pooled connections usable through long extracts, idle ones may be dropped by the server's `wait_timeout`
+#[derive(Debug, Clone, Copy)]
+pub struct PoolConfig {
+    // Ping a connection before handing it out, replacing it when the server hung up
+    pub ping_before_use: bool,
+    // Connections older than this are replaced, the pool's default otherwise
+    pub max_lifetime: Option<Duration>,
+    // Connections idle for longer than this are replaced, the pool's default otherwise
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            ping_before_use: true,
+            max_lifetime: None,
+            idle_timeout: None,
+        }
+    }
+}
+
+// Format of the files written by `--output csv`, also used by the LOAD DATA statements loading them
+#[derive(Debug, Clone)]
+pub struct CsvConfig {
+    pub delimiter: char,
+    pub quote: char,
+    // Escapes the quote, itself and line breaks inside quoted values, quotes are doubled without one
+    pub escape: Option<char>,
+    // Written unquoted for NULL values, so it can't be confused with a string
+    pub null: String,
+    pub line_terminator: String,
+    pub binary_encoding: BinaryEncoding,
+}
+
+impl Default for CsvConfig {
+    fn default() -> Self {
+        CsvConfig {
+            delimiter: ',',
+            quote: '"',
+            escape: Some('\\'),
+            null: "NULL".to_string(),
+            line_terminator: "\n".to_string(),
+            binary_encoding: BinaryEncoding::Hex,
+        }
+    }
+}
+
+// Text form of binary values in CSV files, decoded again by LOAD DATA
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryEncoding {
+    Hex,
+    Base64,
+}
+
+// Naming of the target tables and columns, which are quoted in all generated statements
+#[derive(Debug, Clone)]
+pub struct IdentifierConfig {
+    pub case: IdentifierCase,
+    // Prepended to names that are MySQL reserved words, which are otherwise kept as they are
+    pub reserved_prefix: Option<String>,
+}
+
+impl Default for IdentifierConfig {
+    fn default() -> Self {
+        IdentifierConfig {
+            case: IdentifierCase::Preserve,
+            reserved_prefix: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdentifierCase {
+    Preserve,
+    Lower,
+}
+
+// Fake tables of the `[generator]` section
+#[derive(Debug, Clone)]
+pub struct GeneratorConfig {
+    pub tables: usize,
+    pub rows: i64,
 }
 
 #[derive(Debug, Clone)]
 pub struct SettingsConfig {
     pub max_packet_bytes: usize,
+    pub chunk_size: Option<usize>,
+    // Pool sizes of each database and the tables migrated at once, all derived from `--parallelism` if left out
+    pub source_max_connections: Option<u32>,
+    pub target_max_connections: Option<u32>,
+    pub max_concurrent_tables: Option<usize>,
     pub collation: String,
+    pub column_collations: bool,
+    // Retries a reset failing on foreign keys without checking them
+    pub unchecked_reset_fallback: bool,
     pub whitelisted_tables: Vec<String>,
+    pub schema_prefixes: HashMap<String, String>,
+    // Prefixes tables outside `dbo` without a prefix of their own with `<schema>_`
+    pub prefix_schemas: bool,
+    pub foreign_key_on_delete: Option<String>,
+    pub foreign_key_on_update: Option<String>,
+    pub bundle_signing_key: Option<String>,
+    pub tables: HashMap<String, TableConfig>,
+    pub assertions: Vec<AssertionConfig>,
+    pub notifications: Option<NotificationConfig>,
+    // Added to integer key columns of all tables without an offset of their own
+    pub id_offset: Option<i64>,
+    // Regenerates the integer primary keys of all tables without a setting of their own
+    pub regenerate_keys: bool,
+    pub time_zone: Option<TimeZoneConfig>,
+    pub char_padding: CharPadding,
+    pub pool: PoolConfig,
+    // Handling of rows failing a validation rule, for tables without a policy of their own
+    pub on_invalid: OnInvalid,
+    pub check_in_lists: CheckInLists,
+    pub dialect: Dialect,
+    pub long_keys: LongKeys,
+    pub temporal_tables: TemporalTables,
+    pub sparse_column_sets: SparseColumnSets,
+    pub extraction_consistency: ExtractionConsistency,
+    pub computed_columns: ComputedColumns,
+    pub csv: CsvConfig,
+    pub extra_columns: Vec<ExtraColumn>,
+    pub identifiers: IdentifierConfig,
+}
+
+// The settings of a config holding only the required keys, at the values of `config.example.toml` and with nothing
+// whitelisted yet
+impl Default for SettingsConfig {
+    fn default() -> Self {
+        let mut settings = Table::new();
+        settings.insert("max_packet_bytes".to_string(), Value::Integer(1048576));
+        settings.insert(
+            "collation".to_string(),
+            Value::String("Latin1_General_CI_AS".to_string()),
+        );
+        settings.insert("whitelisted_tables".to_string(), Value::Array(Vec::new()));
+
+        parse_settings_config(Value::Table(settings)).expect("Default settings are valid")
+    }
+}
+
+// Handling of system-versioned temporal tables and their history tables
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TemporalTables {
+    // The history table of each migrated temporal table is migrated as a table of its own
+    Both,
+    // Only the current rows, history tables are left out
+    Current,
+    // The current and the history rows in one table, keyed by the primary key and the period start
+    Flatten,
+}
+
+// Target server flavour, adjusting the generated DDL to what it accepts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    Mysql57,
+    Mysql8,
+    // MariaDB 10.4 and later
+    MariaDb,
+}
+
+// Target of text columns whose CHECK constraint limits them to a list of strings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckInLists {
+    // The mapped type along with the translated CHECK constraint
+    Check,
+    // An ENUM of the listed strings replacing the constraint
+    Enum,
+}
+
+// Handling of primary and unique key columns whose mapped type is too long for an index
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LongKeys {
+    // Index a prefix of the values, keys may then collide on values sharing the prefix
+    Prefix,
+    // Shorten the type to the longest indexable VARCHAR or VARBINARY, longer values don't fit
+    Varchar,
+}
+
+// Handling of the XML column set MSSQL offers over the sparse columns of a table, which `SELECT *` returns in their
+// place
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SparseColumnSets {
+    // Only the sparse columns are migrated, as regular nullable columns
+    Skip,
+    // The column set is migrated as well, as a text column holding the XML of the non-NULL sparse values
+    Materialize,
+}
+
+// Target of MSSQL computed columns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComputedColumns {
+    // Generated columns computing a translated expression, columns with an untranslatable one get the values
+    Generated,
+    // Regular columns holding the values computed by MSSQL
+    Values,
+}
+
+// How source rows are read next to the writers of a busy database
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExtractionConsistency {
+    // The default of MSSQL, readers wait for the locks of writers
+    ReadCommitted,
+    // READ UNCOMMITTED like `WITH (NOLOCK)`, blocks no one but may read uncommitted or skipped rows
+    NoLock,
+    // SNAPSHOT isolation, every query reads the committed state at its start without taking locks
+    Snapshot,
+    // A database snapshot created for the run, all tables are read as of its creation
+    DatabaseSnapshot,
+}
+
+// Handling of the trailing spaces MSSQL pads `char(n)` and `nchar(n)` values with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CharPadding {
+    // CHAR columns, MySQL removes the trailing spaces on read and ignores them in comparisons
+    Pad,
+    // VARCHAR columns keeping the values including their padding
+    Preserve,
+    // CHAR columns with the trailing spaces removed from the values while extracting
+    Trim,
+}
+
+// Handling of rows with a value failing one of the validation rules of its table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnInvalid {
+    // Leave the row out and count it
+    Skip,
+    // Store the row along with the violation in the `_quarantine` database
+    Quarantine,
+    // Fail the table
+    Fail,
+}
+
+// Checks a column's non-NULL values have to pass, any combination of them
+#[derive(Debug, Clone, Default)]
+pub struct ValidationRule {
+    pub pattern: Option<Regex>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub values: Option<Vec<String>>,
+}
+
+// Conversion of datetime values between time zones
+#[derive(Debug, Clone, Copy)]
+pub struct TimeZoneConfig {
+    // Zone of the values without an offset in the source
+    pub source: Tz,
+    pub target: Tz,
+}
+
+// Log filtering from the `[logging]` section
+#[derive(Debug, Clone, Default)]
+pub struct LoggingConfig {
+    pub level: Option<LevelFilter>,
+    pub modules: Vec<(String, LevelFilter)>,
+    // Debug output is limited to messages mentioning these tables
+    pub tables: Vec<String>,
+}
+
+// Scalar query run against the target after the migration
+#[derive(Debug, Clone)]
+pub struct AssertionConfig {
+    pub name: String,
+    pub query: String,
+    pub expectation: Expectation,
+}
+
+// Column added to every migrated table after its mapped columns
+#[derive(Debug, Clone)]
+pub struct ExtraColumn {
+    pub name: String,
+    pub data_type: String,
+    // SQL expression of the column default, e.g. `CURRENT_TIMESTAMP`
+    pub default: Option<String>,
+    // SQL literal inserted into every row, the default applies without one
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expectation {
+    // Equal to the value, numerically when both sides are numbers
+    Equals(String),
+    // Numeric comparison like `> 0`
+    Compare(String, f64),
+}
+
+// Webhook called when a migration finishes
+#[derive(Debug, Clone)]
+pub struct NotificationConfig {
+    pub webhook_url: String,
+    pub format: NotificationFormat,
+    // Only notify about failed migrations
+    pub failures_only: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotificationFormat {
+    // The finished event as a JSON object
+    Json,
+    // A Slack incoming webhook message
+    Slack,
+}
+
+// Settings of the tables matching a whitelist pattern
+#[derive(Debug, Clone, Default)]
+pub struct TableConfig {
+    pub partitioning: Option<PartitioningConfig>,
+    // SQL literals inserted instead of NULL, by source column name
+    pub null_fallbacks: HashMap<String, String>,
+    pub id_offset: Option<i64>,
+    pub regenerate_keys: Option<bool>,
+    // Target time zones by source column name, overriding the target of `[settings.time_zone]`
+    pub time_zones: HashMap<String, Tz>,
+    // Validation rules by source column name
+    pub validations: HashMap<String, ValidationRule>,
+    pub on_invalid: Option<OnInvalid>,
+    // Overrides the global `max_packet_bytes`, e.g. smaller for tables with huge rows
+    pub max_packet_bytes: Option<usize>,
+    // Rows per INSERT at most, batches are only limited by their size otherwise
+    pub batch_rows: Option<usize>,
+    // Long key handling by source column name, overriding `long_keys` in [settings]
+    pub long_keys: HashMap<String, LongKeys>,
+    // Never dropped, truncated or replaced by this tool, whatever the flags
+    pub protected: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PartitioningConfig {
+    pub mode: PartitioningMode,
+    pub column: Option<String>,
+    pub partitions: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PartitioningMode {
+    // Only log the suggested partitioning clause
+    Recommend,
+    // Create the table with the partitioning clause
+    Apply,
 }
 
 impl Config {
     pub(crate) fn from_toml(config: Value) -> Result<Self> {
-        let mssql_database = parse_database_config(
-            config
-                .get("mssql_database")
-                .ok_or(anyhow!("Missing or invalid MSSQL database settings"))?
-                .clone(),
-        )?;
-        let mysql_database = parse_database_config(
-            config
-                .get("mysql_database")
-                .ok_or(anyhow!("Missing or invalid MySQL database settings"))?
-                .clone(),
-        )?;
+        let migrations = match config.get("migration") {
+            Some(Value::Array(blocks)) => blocks
+                .iter()
+                .enumerate()
+                .map(|(index, block)| {
+                    let name = block
+                        .get("name")
+                        .and_then(|name| name.as_str())
+                        .map(|name| name.to_string())
+                        .unwrap_or_else(|| format!("migration {}", index + 1));
+
+                    Config::from_toml(merge_migration_block(&config, block))
+                        .map(|block_config| (name.clone(), block_config))
+                        .with_context(|| format!("Invalid migration block {}", name))
+                })
+                .collect::<Result<Vec<_>>>()?,
+            Some(_) => bail!("Invalid migration blocks, expected [[migration]] tables"),
+            None => Vec::new(),
+        };
+
+        for (index, (name, _)) in migrations.iter().enumerate() {
+            if migrations[..index].iter().any(|(other, _)| other == name) {
+                bail!("Duplicate migration block name {}", name);
+            }
+        }
+
+        // Other commands than `migrate` work on the first block
+        if let Some((_, first)) = migrations.first() {
+            let first = first.clone();
+
+            return Ok(Config {
+                migrations,
+                ..first
+            });
+        }
+
+        let generator = match config.get("source").map(|value| value.as_str()) {
+            None | Some(Some("mssql")) => None,
+            Some(Some("generator")) => Some(
+                parse_generator_config(config.get("generator"))
+                    .context("Invalid generator settings")?,
+            ),
+            Some(_) => bail!("Invalid source, expected \"mssql\" or \"generator\""),
+        };
+
+        let null_target = match config.get("target").map(|value| value.as_str()) {
+            None | Some(Some("mysql")) => false,
+            Some(Some("null")) => true,
+            Some(_) => bail!("Invalid target, expected \"mysql\" or \"null\""),
+        };
+
+        let mssql_database = match config.get("mssql_database") {
+            None if generator.is_some() => None,
+            value => Some(parse_database_config(
+                value
+                    .ok_or(anyhow!("Missing or invalid MSSQL database settings"))?
+                    .clone(),
+            )?),
+        };
+
+        if let Some(mssql_database) = &mssql_database {
+            if mssql_database.socket.is_some()
+                || mssql_database.ssl_mode.is_some()
+                || mssql_database.ssl_ca.is_some()
+                || !mssql_database.init_sql.is_empty()
+                || !mssql_database.session_variables.is_empty()
+            {
+                bail!("socket, ssl_mode, ssl_ca, init_sql and session_variables are only supported by the MySQL database settings");
+            }
+        }
+
+        let mysql_database = match config.get("mysql_database") {
+            None if null_target => None,
+            value => Some(parse_database_config(
+                value
+                    .ok_or(anyhow!("Missing or invalid MySQL database settings"))?
+                    .clone(),
+            )?),
+        };
+
+        if mysql_database
+            .as_ref()
+            .is_some_and(|mysql_database| mysql_database.instance.is_some())
+        {
+            bail!("Named instances are only supported by the MSSQL database settings");
+        }
         let settings = parse_settings_config(
             config
                 .get("settings")
@@ -45,56 +505,235 @@ impl Config {
                 .clone(),
         )?;
 
+        // Both pools share the health settings
+        let with_pool = |database| DatabaseConfig {
+            pool: settings.pool,
+            ..database
+        };
+        let mssql_database = mssql_database.map(with_pool);
+        let mysql_database = mysql_database.map(with_pool);
+
+        // Logging is set up from the raw file before the config is loaded, it is only validated here
+        if let Some(logging) = config.get("logging") {
+            parse_logging_config(logging).context("Invalid logging settings")?;
+        }
+
         Ok(Config {
             mssql_database,
             mysql_database,
+            generator,
+            null_target,
             settings,
+            migrations,
         })
     }
 
-    pub fn mssql_database(&self) -> &DatabaseConfig {
-        &self.mssql_database
+    // Reads a config file like `config.example.toml`, `${NAME}` references are replaced with environment variables
+    pub fn load(config_file: impl AsRef<Path>) -> Result<Self> {
+        let content = fs::read_to_string(config_file)?;
+        Config::from_toml(content.parse::<Value>()?)
+    }
+
+    pub fn mssql_database(&self) -> Result<&DatabaseConfig> {
+        self.mssql_database
+            .as_ref()
+            .ok_or_else(|| anyhow!("Missing MSSQL database settings, the source is the generator"))
+    }
+
+    pub fn mysql_database(&self) -> Result<&DatabaseConfig> {
+        self.mysql_database
+            .as_ref()
+            .ok_or_else(|| anyhow!("Missing MySQL database settings, the target is null"))
+    }
+
+    pub fn generator(&self) -> Option<&GeneratorConfig> {
+        self.generator.as_ref()
     }
 
-    pub fn mysql_database(&self) -> &DatabaseConfig {
-        &self.mysql_database
+    pub fn null_target(&self) -> bool {
+        self.null_target
     }
 
     pub fn settings(&self) -> &SettingsConfig {
         &self.settings
     }
+
+    pub fn migrations(&self) -> &[(String, Config)] {
+        &self.migrations
+    }
+}
+
+// Top level sections with the keys of a `[[migration]]` block's sections taking precedence
+pub(crate) fn merge_migration_block(config: &Value, block: &Value) -> Value {
+    let mut merged = config.clone();
+
+    if let Some(merged) = merged.as_table_mut() {
+        merged.remove("migration");
+
+        for section in MIGRATION_SECTIONS {
+            let Some(Value::Table(overrides)) = block.get(section) else {
+                continue;
+            };
+
+            match merged.get_mut(section) {
+                Some(Value::Table(defaults)) => {
+                    for (key, value) in overrides {
+                        defaults.insert(key.clone(), value.clone());
+                    }
+                }
+                _ => {
+                    merged.insert(section.to_string(), Value::Table(overrides.clone()));
+                }
+            }
+        }
+    }
+
+    merged
+}
+
+fn parse_generator_config(config: Option<&Value>) -> Result<GeneratorConfig> {
+    let tables = match config.and_then(|config| config.get("tables")) {
+        Some(value) => value
+            .as_integer()
+            .filter(|tables| *tables > 0)
+            .ok_or_else(|| anyhow!("Invalid number of tables, expected a positive number"))?
+            as usize,
+        None => DEFAULT_GENERATED_TABLES,
+    };
+
+    let rows = match config.and_then(|config| config.get("rows")) {
+        Some(value) => value
+            .as_integer()
+            .filter(|rows| *rows >= 0)
+            .ok_or_else(|| anyhow!("Invalid number of rows, expected a number"))?,
+        None => DEFAULT_GENERATED_ROWS,
+    };
+
+    Ok(GeneratorConfig { tables, rows })
 }
 
 fn parse_database_config(config: Value) -> Result<DatabaseConfig> {
-    let host = config
-        .get("host")
-        .and_then(|value| value.as_str())
-        .ok_or_else(|| anyhow!("Missing or invalid host"))?
-        .to_string();
+    let socket = config
+        .get("socket")
+        .map(|value| {
+            value
+                .as_str()
+                .ok_or_else(|| anyhow!("Invalid socket"))
+                .and_then(interpolate_env)
+                .map(PathBuf::from)
+        })
+        .transpose()?;
+
+    // Host and port only serve to describe the server when connecting through a socket
+    let host = match config.get("host") {
+        None if socket.is_some() => "localhost".to_string(),
+        value => value
+            .and_then(|value| value.as_str())
+            .ok_or_else(|| anyhow!("Missing or invalid host"))
+            .and_then(interpolate_env)?,
+    };
+
+    // The instance may also be given along with the host, as in `HOST\SQLEXPRESS`
+    let (host, instance) = match host.split_once('\\') {
+        Some((host, instance)) => (host.to_string(), Some(instance.to_string())),
+        None => (host, None),
+    };
 
-    let port = config
-        .get("port")
-        .and_then(|value| value.as_integer())
-        .ok_or_else(|| anyhow!("Missing or invalid port"))?
-        .try_into()?;
+    let instance = match config.get("instance") {
+        Some(value) => Some(
+            value
+                .as_str()
+                .ok_or_else(|| anyhow!("Invalid instance"))
+                .and_then(interpolate_env)?,
+        ),
+        None => instance,
+    };
+
+    // The port may also be given as a string to allow interpolation
+    let port = match config.get("port") {
+        Some(Value::Integer(port)) => (*port).try_into()?,
+        Some(Value::String(port)) => interpolate_env(port)?
+            .parse()
+            .map_err(|_| anyhow!("Invalid port"))?,
+        None if socket.is_some() => DEFAULT_MYSQL_PORT,
+        None if instance.is_some() => DEFAULT_BROWSER_PORT,
+        _ => bail!("Missing or invalid port"),
+    };
 
     let username = config
         .get("username")
         .and_then(|value| value.as_str())
-        .ok_or_else(|| anyhow!("Missing or invalid username"))?
-        .to_string();
+        .ok_or_else(|| anyhow!("Missing or invalid username"))
+        .and_then(interpolate_env)?;
 
     let password = config
         .get("password")
         .and_then(|value| value.as_str())
-        .ok_or_else(|| anyhow!("Missing or invalid password"))?
-        .to_string();
+        .ok_or_else(|| anyhow!("Missing or invalid password"))
+        .and_then(interpolate_env)?;
 
     let database = config
         .get("database")
         .and_then(|value| value.as_str())
-        .ok_or_else(|| anyhow!("Missing or invalid database"))?
-        .to_string();
+        .ok_or_else(|| anyhow!("Missing or invalid database"))
+        .and_then(interpolate_env)?;
+
+    let connect_timeout = config
+        .get("connect_timeout")
+        .map(|value| {
+            value
+                .as_integer()
+                .filter(|seconds| *seconds > 0)
+                .map(|seconds| Duration::from_secs(seconds as u64))
+                .ok_or_else(|| anyhow!("Invalid connect timeout, expected a number of seconds"))
+        })
+        .transpose()?;
+
+    let ssl_mode = config
+        .get("ssl_mode")
+        .map(|value| {
+            value
+                .as_str()
+                .and_then(|mode| mode.parse::<MySqlSslMode>().ok())
+                .ok_or_else(|| {
+                    anyhow!("Invalid SSL mode, expected \"disabled\", \"preferred\", \"required\", \"verify_ca\" or \"verify_identity\"")
+                })
+        })
+        .transpose()?;
+
+    let ssl_ca = config
+        .get("ssl_ca")
+        .map(|value| {
+            value
+                .as_str()
+                .map(PathBuf::from)
+                .ok_or_else(|| anyhow!("Invalid SSL CA file"))
+        })
+        .transpose()?;
+
+    let init_sql = match config.get("init_sql") {
+        Some(Value::String(statement)) => vec![statement.clone()],
+        Some(Value::Array(statements)) => statements
+            .iter()
+            .map(|statement| {
+                statement
+                    .as_str()
+                    .map(|statement| statement.to_string())
+                    .ok_or_else(|| anyhow!("Invalid init SQL statement"))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        Some(_) => bail!("Invalid init SQL, expected a statement or a list of statements"),
+        None => Vec::new(),
+    };
+
+    let session_variables = match config.get("session_variables") {
+        Some(Value::Table(variables)) => variables
+            .iter()
+            .map(|(name, value)| parse_session_variable(name, value))
+            .collect::<Result<Vec<_>>>()?,
+        Some(_) => bail!("Invalid session variables, expected a table of names and values"),
+        None => Vec::new(),
+    };
 
     Ok(DatabaseConfig {
         host,
@@ -102,21 +741,225 @@ fn parse_database_config(config: Value) -> Result<DatabaseConfig> {
         username,
         password,
         database,
+        instance,
+        socket,
+        connect_timeout,
+        ssl_mode,
+        ssl_ca,
+        init_sql,
+        session_variables,
+        pool: PoolConfig::default(),
     })
 }
 
+fn parse_session_variable(name: &str, value: &Value) -> Result<(String, String)> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        bail!("Invalid session variable name: {}", name);
+    }
+
+    let value = match value {
+        Value::Boolean(value) => (*value as u8).to_string(),
+        Value::Integer(value) => value.to_string(),
+        Value::String(value) => quote_literal(value),
+        _ => bail!(
+            "Invalid value of session variable {}, expected a boolean, number or string",
+            name
+        ),
+    };
+
+    Ok((name.to_lowercase(), value))
+}
+
+// Replaces `${NAME}` references with the value of the environment variable, `$${` escapes a literal `${`
+pub(crate) fn interpolate_env(value: &str) -> Result<String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(escaped) = rest.strip_prefix("$${") {
+            result.push_str("${");
+            rest = escaped;
+        } else if let Some(reference) = rest.strip_prefix("${") {
+            let end = reference
+                .find('}')
+                .ok_or_else(|| anyhow!("Unterminated environment variable reference"))?;
+            let name = &reference[..end];
+
+            let variable = env::var(name)
+                .with_context(|| format!("Environment variable {} is not set", name))?;
+
+            result.push_str(&variable);
+            rest = &reference[end + 1..];
+        } else {
+            result.push('$');
+            rest = &rest[1..];
+        }
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+// Exports `KEY=VALUE` lines of a dotenv file, variables already set in the environment take precedence.
+// Returns the names of the variables it set.
+pub fn load_env_file(path: &Path) -> Result<Vec<String>> {
+    let content = fs::read_to_string(path)?;
+    let mut variables = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid line {} in {}", index + 1, path.display()))?;
+
+        let key = key.trim();
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .or_else(|| {
+                value
+                    .strip_prefix('\'')
+                    .and_then(|value| value.strip_suffix('\''))
+            })
+            .unwrap_or(value);
+
+        if env::var_os(key).is_none() {
+            env::set_var(key, value);
+            variables.push(key.to_string());
+        }
+    }
+
+    Ok(variables)
+}
+
+// Logging settings of the config file, defaults when it is missing or invalid
+pub fn read_logging_config(config_file: &Path) -> LoggingConfig {
+    fs::read_to_string(config_file)
+        .ok()
+        .and_then(|content| content.parse::<Value>().ok())
+        .and_then(|config| {
+            config
+                .get("logging")
+                .and_then(|logging| parse_logging_config(logging).ok())
+        })
+        .unwrap_or_default()
+}
+
+fn parse_logging_config(config: &Value) -> Result<LoggingConfig> {
+    let level = config.get("level").map(parse_level).transpose()?;
+
+    let modules = match config.get("modules") {
+        Some(Value::Table(modules)) => modules
+            .iter()
+            .map(|(module, level)| Ok((module.clone(), parse_level(level)?)))
+            .collect::<Result<Vec<_>>>()?,
+        Some(_) => bail!("Invalid module levels, expected a table"),
+        None => Vec::new(),
+    };
+
+    let tables = match config.get("tables") {
+        Some(Value::Array(tables)) => tables
+            .iter()
+            .map(|table| {
+                table
+                    .as_str()
+                    .map(|table| table.to_string())
+                    .ok_or_else(|| anyhow!("Invalid table name"))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        Some(_) => bail!("Invalid tables, expected an array"),
+        None => Vec::new(),
+    };
+
+    Ok(LoggingConfig {
+        level,
+        modules,
+        tables,
+    })
+}
+
+fn parse_level(value: &Value) -> Result<LevelFilter> {
+    value
+        .as_str()
+        .and_then(|level| level.parse().ok())
+        .ok_or_else(|| {
+            anyhow!(
+                "Invalid log level {}, expected off, error, warn, info, debug or trace",
+                value
+            )
+        })
+}
+
 fn parse_settings_config(config: Value) -> Result<SettingsConfig> {
     let max_packet_bytes = config
         .get("max_packet_bytes")
         .and_then(|v| v.as_integer().map(|v| v as usize))
         .ok_or_else(|| anyhow!("Missing or invalid max send packet value"))?;
 
+    let chunk_size = config
+        .get("chunk_size")
+        .map(|value| {
+            value
+                .as_integer()
+                .filter(|size| *size > 0)
+                .map(|size| size as usize)
+                .ok_or_else(|| anyhow!("Invalid chunk size"))
+        })
+        .transpose()?;
+
+    let count = |key: &str| {
+        config
+            .get(key)
+            .map(|value| {
+                value
+                    .as_integer()
+                    .filter(|count| *count > 0)
+                    .map(|count| count as usize)
+                    .ok_or_else(|| anyhow!("Invalid {}, expected a positive number", key))
+            })
+            .transpose()
+    };
+
+    let source_max_connections = count("source_max_connections")?.map(|count| count as u32);
+    let target_max_connections = count("target_max_connections")?.map(|count| count as u32);
+    let max_concurrent_tables = count("max_concurrent_tables")?;
+
     let collation = config
         .get("collation")
         .and_then(|value| value.as_str())
         .ok_or_else(|| anyhow!("Missing or invalid collation"))?
         .to_string();
 
+    let column_collations = config
+        .get("column_collations")
+        .map(|value| {
+            value
+                .as_bool()
+                .ok_or_else(|| anyhow!("Invalid column collations flag"))
+        })
+        .transpose()?
+        .unwrap_or(false);
+
+    let unchecked_reset_fallback = config
+        .get("unchecked_reset_fallback")
+        .map(|value| {
+            value
+                .as_bool()
+                .ok_or_else(|| anyhow!("Invalid unchecked reset fallback flag"))
+        })
+        .transpose()?
+        .unwrap_or(false);
+
     let whitelisted_tables = config
         .get("whitelisted_tables")
         .and_then(|value| value.as_array())
@@ -125,9 +968,780 @@ fn parse_settings_config(config: Value) -> Result<SettingsConfig> {
         .filter_map(|value| value.as_str().map(|s| s.to_string()))
         .collect::<Vec<String>>();
 
+    let schema_prefixes = match config.get("schema_prefixes") {
+        Some(value) => value
+            .as_table()
+            .ok_or_else(|| anyhow!("Invalid schema prefixes"))?
+            .iter()
+            .map(|(schema, prefix)| {
+                prefix
+                    .as_str()
+                    .map(|prefix| (schema.clone(), prefix.to_string()))
+                    .ok_or_else(|| anyhow!("Invalid prefix for schema {}", schema))
+            })
+            .collect::<Result<HashMap<String, String>>>()?,
+        None => HashMap::new(),
+    };
+
+    let prefix_schemas = config
+        .get("prefix_schemas")
+        .map(|value| {
+            value
+                .as_bool()
+                .ok_or_else(|| anyhow!("Invalid prefix schemas flag"))
+        })
+        .transpose()?
+        .unwrap_or(false);
+
+    let foreign_keys = config.get("foreign_keys");
+    let foreign_key_on_delete = parse_referential_action(foreign_keys, "on_delete")?;
+    let foreign_key_on_update = parse_referential_action(foreign_keys, "on_update")?;
+
+    let bundle_signing_key = config
+        .get("bundle_signing_key")
+        .map(|value| {
+            value
+                .as_str()
+                .filter(|key| !key.is_empty())
+                .map(|key| key.to_string())
+                .ok_or_else(|| anyhow!("Invalid bundle signing key"))
+        })
+        .transpose()?;
+
+    let tables = match config.get("tables") {
+        Some(value) => value
+            .as_table()
+            .ok_or_else(|| anyhow!("Invalid tables settings"))?
+            .iter()
+            .map(|(pattern, table)| {
+                parse_table_config(table)
+                    .map(|table| (pattern.clone(), table))
+                    .with_context(|| format!("Invalid settings of table {}", pattern))
+            })
+            .collect::<Result<HashMap<String, TableConfig>>>()?,
+        None => HashMap::new(),
+    };
+
+    let assertions = match config.get("assertions") {
+        Some(value) => value
+            .as_array()
+            .ok_or_else(|| anyhow!("Invalid assertions"))?
+            .iter()
+            .enumerate()
+            .map(|(index, assertion)| {
+                parse_assertion_config(assertion)
+                    .with_context(|| format!("Invalid assertion {}", index + 1))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => Vec::new(),
+    };
+
+    let extra_columns = match config.get("extra_columns") {
+        Some(value) => value
+            .as_array()
+            .ok_or_else(|| anyhow!("Invalid extra_columns"))?
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                parse_extra_column(column)
+                    .with_context(|| format!("Invalid extra column {}", index + 1))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => Vec::new(),
+    };
+
+    let notifications = config
+        .get("notifications")
+        .map(|value| parse_notification_config(value).with_context(|| "Invalid notifications"))
+        .transpose()?;
+
+    let id_offset = parse_id_offset(&config)?;
+    let regenerate_keys = parse_regenerate_keys(&config)?.unwrap_or_default();
+
+    if id_offset.is_some() && regenerate_keys {
+        bail!("Keys can either be shifted by an ID offset or regenerated, not both");
+    }
+
+    let time_zone = config
+        .get("time_zone")
+        .map(|value| {
+            Ok::<_, Error>(TimeZoneConfig {
+                source: parse_time_zone(value.get("source")).context("Invalid source time zone")?,
+                target: parse_time_zone(value.get("target")).context("Invalid target time zone")?,
+            })
+        })
+        .transpose()?;
+
+    let char_padding = match config.get("char_padding").map(|value| value.as_str()) {
+        None | Some(Some("pad")) => CharPadding::Pad,
+        Some(Some("preserve")) => CharPadding::Preserve,
+        Some(Some("trim")) => CharPadding::Trim,
+        Some(_) => bail!("Invalid char padding, expected \"pad\", \"preserve\" or \"trim\""),
+    };
+
+    let on_invalid = parse_on_invalid(&config)?.unwrap_or(OnInvalid::Fail);
+
+    let check_in_lists = match config.get("check_in_lists").map(|value| value.as_str()) {
+        None | Some(Some("check")) => CheckInLists::Check,
+        Some(Some("enum")) => CheckInLists::Enum,
+        Some(_) => bail!("Invalid check_in_lists, expected \"check\" or \"enum\""),
+    };
+
+    let dialect = match config.get("dialect").map(|value| value.as_str()) {
+        None | Some(Some("mysql8")) => Dialect::Mysql8,
+        Some(Some("mysql5.7")) => Dialect::Mysql57,
+        Some(Some("mariadb")) => Dialect::MariaDb,
+        Some(_) => bail!("Invalid dialect, expected \"mysql8\", \"mysql5.7\" or \"mariadb\""),
+    };
+
+    let long_keys = config
+        .get("long_keys")
+        .map(parse_long_keys)
+        .transpose()?
+        .unwrap_or(LongKeys::Prefix);
+
+    let temporal_tables = match config.get("temporal_tables").map(|value| value.as_str()) {
+        None | Some(Some("both")) => TemporalTables::Both,
+        Some(Some("current")) => TemporalTables::Current,
+        Some(Some("flatten")) => TemporalTables::Flatten,
+        Some(_) => {
+            bail!("Invalid temporal_tables, expected \"both\", \"current\" or \"flatten\"")
+        }
+    };
+
+    let sparse_column_sets = match config.get("sparse_column_sets").map(|value| value.as_str()) {
+        None | Some(Some("skip")) => SparseColumnSets::Skip,
+        Some(Some("materialize")) => SparseColumnSets::Materialize,
+        Some(_) => bail!("Invalid sparse_column_sets, expected \"skip\" or \"materialize\""),
+    };
+
+    let extraction_consistency = match config
+        .get("extraction_consistency")
+        .map(|value| value.as_str())
+    {
+        None | Some(Some("read_committed")) => ExtractionConsistency::ReadCommitted,
+        Some(Some("nolock")) => ExtractionConsistency::NoLock,
+        Some(Some("snapshot")) => ExtractionConsistency::Snapshot,
+        Some(Some("database_snapshot")) => ExtractionConsistency::DatabaseSnapshot,
+        Some(_) => bail!(
+            "Invalid extraction_consistency, expected \"read_committed\", \"nolock\", \"snapshot\" or \"database_snapshot\""
+        ),
+    };
+
+    let computed_columns = match config.get("computed_columns").map(|value| value.as_str()) {
+        None | Some(Some("generated")) => ComputedColumns::Generated,
+        Some(Some("values")) => ComputedColumns::Values,
+        Some(_) => bail!("Invalid computed_columns, expected \"generated\" or \"values\""),
+    };
+
+    let pool = config
+        .get("pool")
+        .map(|value| parse_pool_config(value).context("Invalid pool settings"))
+        .transpose()?
+        .unwrap_or_default();
+
+    let csv = config
+        .get("csv")
+        .map(|value| parse_csv_config(value).context("Invalid csv settings"))
+        .transpose()?
+        .unwrap_or_default();
+
+    let identifiers = config
+        .get("identifiers")
+        .map(|value| parse_identifier_config(value).context("Invalid identifier settings"))
+        .transpose()?
+        .unwrap_or_default();
+
+    if time_zone.is_none() {
+        if let Some((pattern, _)) = tables
+            .iter()
+            .find(|(_, table)| !table.time_zones.is_empty())
+        {
+            bail!(
+                "Time zones of the columns of table {} require a [settings.time_zone] section",
+                pattern
+            );
+        }
+    }
+
     Ok(SettingsConfig {
         max_packet_bytes,
+        chunk_size,
+        source_max_connections,
+        target_max_connections,
+        max_concurrent_tables,
         collation,
+        column_collations,
+        unchecked_reset_fallback,
         whitelisted_tables,
+        schema_prefixes,
+        prefix_schemas,
+        foreign_key_on_delete,
+        foreign_key_on_update,
+        bundle_signing_key,
+        tables,
+        assertions,
+        notifications,
+        id_offset,
+        regenerate_keys,
+        time_zone,
+        char_padding,
+        pool,
+        on_invalid,
+        check_in_lists,
+        dialect,
+        long_keys,
+        temporal_tables,
+        sparse_column_sets,
+        extraction_consistency,
+        computed_columns,
+        csv,
+        extra_columns,
+        identifiers,
     })
 }
+
+fn parse_long_keys(value: &Value) -> Result<LongKeys> {
+    match value.as_str() {
+        Some("prefix") => Ok(LongKeys::Prefix),
+        Some("varchar") => Ok(LongKeys::Varchar),
+        _ => bail!("Invalid long_keys, expected \"prefix\" or \"varchar\""),
+    }
+}
+
+fn parse_pool_config(config: &Value) -> Result<PoolConfig> {
+    let ping_before_use = match config.get("ping_before_use") {
+        Some(value) => value
+            .as_bool()
+            .ok_or_else(|| anyhow!("Invalid ping_before_use, expected true or false"))?,
+        None => true,
+    };
+
+    let seconds = |key: &str| {
+        config
+            .get(key)
+            .map(|value| {
+                value
+                    .as_integer()
+                    .filter(|seconds| *seconds > 0)
+                    .map(|seconds| Duration::from_secs(seconds as u64))
+                    .ok_or_else(|| anyhow!("Invalid {}, expected a number of seconds", key))
+            })
+            .transpose()
+    };
+
+    Ok(PoolConfig {
+        ping_before_use,
+        max_lifetime: seconds("max_lifetime")?,
+        idle_timeout: seconds("idle_timeout")?,
+    })
+}
+
+fn parse_csv_config(config: &Value) -> Result<CsvConfig> {
+    let defaults = CsvConfig::default();
+
+    let text = |key: &str| {
+        config
+            .get(key)
+            .map(|value| {
+                value
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow!("Invalid {}, expected a string", key))
+            })
+            .transpose()
+    };
+    let character = |key: &str| {
+        text(key)?
+            .map(|value| {
+                let mut chars = value.chars();
+
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Ok(c),
+                    _ => Err(anyhow!("Invalid {}, expected a single character", key)),
+                }
+            })
+            .transpose()
+    };
+
+    let delimiter = character("delimiter")?.unwrap_or(defaults.delimiter);
+    let quote = character("quote")?.unwrap_or(defaults.quote);
+
+    // An empty escape doubles the quotes instead
+    let escape = match text("escape")?.as_deref() {
+        Some("") => None,
+        Some(_) => character("escape")?,
+        None => defaults.escape,
+    };
+
+    let null = text("null")?.unwrap_or(defaults.null);
+    let line_terminator = text("line_terminator")?.unwrap_or(defaults.line_terminator);
+
+    let binary_encoding = match config.get("binary_encoding").map(|value| value.as_str()) {
+        None | Some(Some("hex")) => BinaryEncoding::Hex,
+        Some(Some("base64")) => BinaryEncoding::Base64,
+        Some(_) => bail!("Invalid binary_encoding, expected \"hex\" or \"base64\""),
+    };
+
+    if delimiter == quote || escape.is_some_and(|escape| escape == delimiter || escape == quote) {
+        bail!("The delimiter, quote and escape characters must differ");
+    }
+
+    if line_terminator.is_empty() {
+        bail!("Invalid line_terminator, expected a non-empty string");
+    }
+
+    if null.is_empty() || null.contains([delimiter, quote]) || null.contains(&line_terminator) {
+        bail!("Invalid null, expected a non-empty string without the delimiter, quote or line terminator");
+    }
+
+    Ok(CsvConfig {
+        delimiter,
+        quote,
+        escape,
+        null,
+        line_terminator,
+        binary_encoding,
+    })
+}
+
+fn parse_identifier_config(config: &Value) -> Result<IdentifierConfig> {
+    let case = match config.get("case").map(|value| value.as_str()) {
+        None | Some(Some("preserve")) => IdentifierCase::Preserve,
+        Some(Some("lower")) => IdentifierCase::Lower,
+        Some(_) => bail!("Invalid case, expected \"preserve\" or \"lower\""),
+    };
+
+    let reserved_prefix = config
+        .get("reserved_prefix")
+        .map(|value| {
+            value
+                .as_str()
+                .filter(|prefix| !prefix.is_empty())
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("Invalid reserved_prefix, expected a non-empty string"))
+        })
+        .transpose()?;
+
+    Ok(IdentifierConfig {
+        case,
+        reserved_prefix,
+    })
+}
+
+fn parse_notification_config(config: &Value) -> Result<NotificationConfig> {
+    let webhook_url = config
+        .get("webhook_url")
+        .and_then(|value| value.as_str())
+        .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+        .ok_or_else(|| anyhow!("Missing or invalid webhook url"))?
+        .to_string();
+
+    let format = match config.get("format").map(|value| value.as_str()) {
+        None | Some(Some("json")) => NotificationFormat::Json,
+        Some(Some("slack")) => NotificationFormat::Slack,
+        _ => bail!("Invalid notification format, expected \"json\" or \"slack\""),
+    };
+
+    let failures_only = match config.get("notify_on").map(|value| value.as_str()) {
+        None | Some(Some("always")) => false,
+        Some(Some("failure")) => true,
+        _ => bail!("Invalid notify_on, expected \"always\" or \"failure\""),
+    };
+
+    Ok(NotificationConfig {
+        webhook_url,
+        format,
+        failures_only,
+    })
+}
+
+fn parse_referential_action(config: Option<&Value>, key: &str) -> Result<Option<String>> {
+    config
+        .and_then(|config| config.get(key))
+        .map(|value| {
+            value
+                .as_str()
+                .map(|action| action.trim().to_uppercase())
+                .filter(|action| ["CASCADE", "RESTRICT", "NO ACTION", "SET NULL"].contains(&action.as_str()))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Invalid foreign key {}, expected \"cascade\", \"restrict\", \"no action\" or \"set null\"",
+                        key
+                    )
+                })
+        })
+        .transpose()
+}
+
+fn parse_assertion_config(config: &Value) -> Result<AssertionConfig> {
+    let query = config
+        .get("query")
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| anyhow!("Missing or invalid query"))?
+        .trim()
+        .trim_end_matches(';')
+        .to_string();
+
+    let name = config
+        .get("name")
+        .and_then(|value| value.as_str())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| query.clone());
+
+    let expectation = match (config.get("expect"), config.get("predicate")) {
+        (Some(value), None) => Expectation::Equals(match value {
+            Value::String(value) => value.clone(),
+            Value::Integer(value) => value.to_string(),
+            Value::Float(value) => value.to_string(),
+            Value::Boolean(value) => (*value as u8).to_string(),
+            _ => bail!("Invalid expected value"),
+        }),
+        (None, Some(predicate)) => {
+            let predicate = predicate
+                .as_str()
+                .ok_or_else(|| anyhow!("Invalid predicate"))?
+                .trim();
+
+            let operator = ["<=", ">=", "!=", "<", ">", "="]
+                .into_iter()
+                .find(|operator| predicate.starts_with(operator))
+                .ok_or_else(|| anyhow!("Invalid predicate operator in \"{}\"", predicate))?;
+
+            let value = predicate[operator.len()..]
+                .trim()
+                .parse::<f64>()
+                .with_context(|| format!("Invalid predicate value in \"{}\"", predicate))?;
+
+            Expectation::Compare(operator.to_string(), value)
+        }
+        _ => bail!("Exactly one of expect or predicate is required"),
+    };
+
+    Ok(AssertionConfig {
+        name,
+        query,
+        expectation,
+    })
+}
+
+fn parse_extra_column(config: &Value) -> Result<ExtraColumn> {
+    let name = config
+        .get("name")
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| anyhow!("Missing or invalid name"))?
+        .to_string();
+
+    let data_type = config
+        .get("type")
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| anyhow!("Missing or invalid type"))?
+        .to_string();
+
+    let default = config
+        .get("default")
+        .map(|value| {
+            value
+                .as_str()
+                .map(|default| default.to_string())
+                .ok_or_else(|| anyhow!("Invalid default, expected an SQL expression"))
+        })
+        .transpose()?;
+
+    let value = config
+        .get("value")
+        .map(|value| match value {
+            Value::String(text) => Ok(quote_literal(text)),
+            Value::Integer(number) => Ok(number.to_string()),
+            Value::Float(number) => Ok(number.to_string()),
+            Value::Boolean(flag) => Ok((*flag as u8).to_string()),
+            _ => Err(anyhow!(
+                "Invalid value, expected a string, number or boolean"
+            )),
+        })
+        .transpose()?;
+
+    Ok(ExtraColumn {
+        name,
+        data_type,
+        default,
+        value,
+    })
+}
+
+fn parse_table_config(config: &Value) -> Result<TableConfig> {
+    let partitioning = config
+        .get("partitioning")
+        .map(|value| {
+            let mode = match value.as_str() {
+                Some("recommend") => PartitioningMode::Recommend,
+                Some("apply") => PartitioningMode::Apply,
+                _ => bail!("Invalid partitioning mode, expected \"recommend\" or \"apply\""),
+            };
+
+            let column = config
+                .get("partition_column")
+                .map(|value| {
+                    value
+                        .as_str()
+                        .map(|column| column.to_string())
+                        .ok_or_else(|| anyhow!("Invalid partition column"))
+                })
+                .transpose()?;
+
+            let partitions = config
+                .get("partitions")
+                .map(|value| {
+                    value
+                        .as_integer()
+                        .filter(|partitions| (2..=1024).contains(partitions))
+                        .map(|partitions| partitions as usize)
+                        .ok_or_else(|| anyhow!("Invalid number of partitions"))
+                })
+                .transpose()?
+                .unwrap_or(DEFAULT_PARTITIONS);
+
+            Ok(PartitioningConfig {
+                mode,
+                column,
+                partitions,
+            })
+        })
+        .transpose()?;
+
+    let null_fallbacks = match config.get("null_fallback") {
+        Some(value) => value
+            .as_table()
+            .ok_or_else(|| anyhow!("Invalid NULL fallbacks"))?
+            .iter()
+            .map(|(column, fallback)| {
+                let literal = match fallback {
+                    Value::String(text) => quote_literal(text),
+                    Value::Integer(number) => number.to_string(),
+                    Value::Float(number) => number.to_string(),
+                    Value::Boolean(flag) => flag.to_string(),
+                    _ => bail!("Invalid NULL fallback for column {}", column),
+                };
+
+                Ok((column.clone(), literal))
+            })
+            .collect::<Result<HashMap<String, String>>>()?,
+        None => HashMap::new(),
+    };
+
+    let time_zones = match config.get("time_zone") {
+        Some(value) => value
+            .as_table()
+            .ok_or_else(|| anyhow!("Invalid time zones"))?
+            .iter()
+            .map(|(column, zone)| {
+                parse_time_zone(Some(zone))
+                    .map(|zone| (column.clone(), zone))
+                    .with_context(|| format!("Invalid time zone of column {}", column))
+            })
+            .collect::<Result<HashMap<String, Tz>>>()?,
+        None => HashMap::new(),
+    };
+
+    let id_offset = parse_id_offset(config)?;
+    let regenerate_keys = parse_regenerate_keys(config)?;
+
+    if id_offset.is_some() && regenerate_keys == Some(true) {
+        bail!("Keys can either be shifted by an ID offset or regenerated, not both");
+    }
+
+    let validations = match config.get("validate") {
+        Some(value) => value
+            .as_table()
+            .ok_or_else(|| anyhow!("Invalid validation rules"))?
+            .iter()
+            .map(|(column, rule)| {
+                parse_validation_rule(rule)
+                    .map(|rule| (column.clone(), rule))
+                    .with_context(|| format!("Invalid validation rule of column {}", column))
+            })
+            .collect::<Result<HashMap<String, ValidationRule>>>()?,
+        None => HashMap::new(),
+    };
+
+    let long_keys = match config.get("long_keys") {
+        Some(value) => value
+            .as_table()
+            .ok_or_else(|| anyhow!("Invalid long key handling, expected a table of columns"))?
+            .iter()
+            .map(|(column, policy)| {
+                parse_long_keys(policy)
+                    .map(|policy| (column.clone(), policy))
+                    .with_context(|| format!("Invalid long key handling of column {}", column))
+            })
+            .collect::<Result<HashMap<String, LongKeys>>>()?,
+        None => HashMap::new(),
+    };
+
+    let max_packet_bytes = config
+        .get("max_packet_bytes")
+        .map(|value| {
+            value
+                .as_integer()
+                .filter(|bytes| *bytes > 0)
+                .map(|bytes| bytes as usize)
+                .ok_or_else(|| anyhow!("Invalid max packet bytes, expected a number of bytes"))
+        })
+        .transpose()?;
+
+    let batch_rows = config
+        .get("batch_rows")
+        .map(|value| {
+            value
+                .as_integer()
+                .filter(|rows| *rows > 0)
+                .map(|rows| rows as usize)
+                .ok_or_else(|| anyhow!("Invalid batch rows, expected a number of rows"))
+        })
+        .transpose()?;
+
+    let protected = config
+        .get("protected")
+        .map(|value| {
+            value
+                .as_bool()
+                .ok_or_else(|| anyhow!("Invalid protected, expected true or false"))
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    Ok(TableConfig {
+        partitioning,
+        null_fallbacks,
+        id_offset,
+        regenerate_keys,
+        time_zones,
+        validations,
+        on_invalid: parse_on_invalid(config)?,
+        max_packet_bytes,
+        batch_rows,
+        long_keys,
+        protected,
+    })
+}
+
+fn parse_validation_rule(config: &Value) -> Result<ValidationRule> {
+    let config = config
+        .as_table()
+        .ok_or_else(|| anyhow!("Expected a table with regex, min, max or values"))?;
+
+    if let Some(key) = config
+        .keys()
+        .find(|key| !["regex", "min", "max", "values"].contains(&key.as_str()))
+    {
+        bail!("Unknown key {}, expected regex, min, max or values", key);
+    }
+
+    let pattern = config
+        .get("regex")
+        .map(|value| {
+            let pattern = value
+                .as_str()
+                .ok_or_else(|| anyhow!("Invalid regex, expected a string"))?;
+
+            Regex::new(pattern).with_context(|| format!("Invalid regex {}", pattern))
+        })
+        .transpose()?;
+
+    let bound = |key: &str| {
+        config
+            .get(key)
+            .map(|value| match value {
+                Value::Integer(number) => Ok(*number as f64),
+                Value::Float(number) => Ok(*number),
+                _ => bail!("Invalid {}, expected a number", key),
+            })
+            .transpose()
+    };
+
+    let min = bound("min")?;
+    let max = bound("max")?;
+
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            bail!("The minimum {} exceeds the maximum {}", min, max);
+        }
+    }
+
+    // Numbers are compared by their text, as the values are
+    let values = config
+        .get("values")
+        .map(|value| {
+            value
+                .as_array()
+                .ok_or_else(|| anyhow!("Invalid values, expected a list"))?
+                .iter()
+                .map(|value| match value {
+                    Value::String(text) => Ok(text.clone()),
+                    Value::Integer(number) => Ok(number.to_string()),
+                    Value::Float(number) => Ok(number.to_string()),
+                    Value::Boolean(flag) => Ok((*flag as u8).to_string()),
+                    _ => bail!("Invalid value {}, expected a string or a number", value),
+                })
+                .collect::<Result<Vec<_>>>()
+        })
+        .transpose()?;
+
+    if pattern.is_none() && min.is_none() && max.is_none() && values.is_none() {
+        bail!("Expected regex, min, max or values");
+    }
+
+    Ok(ValidationRule {
+        pattern,
+        min,
+        max,
+        values,
+    })
+}
+
+fn parse_on_invalid(config: &Value) -> Result<Option<OnInvalid>> {
+    config
+        .get("on_invalid")
+        .map(|value| match value.as_str() {
+            Some("skip") => Ok(OnInvalid::Skip),
+            Some("quarantine") => Ok(OnInvalid::Quarantine),
+            Some("fail") => Ok(OnInvalid::Fail),
+            _ => bail!("Invalid on_invalid, expected \"skip\", \"quarantine\" or \"fail\""),
+        })
+        .transpose()
+}
+
+fn parse_time_zone(value: Option<&Value>) -> Result<Tz> {
+    let zone = value
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| anyhow!("Missing or invalid time zone"))?;
+
+    zone.parse::<Tz>().map_err(|_| {
+        anyhow!(
+            "Unknown time zone {}, expected a name like \"UTC\" or \"Europe/Berlin\"",
+            zone
+        )
+    })
+}
+
+fn parse_regenerate_keys(config: &Value) -> Result<Option<bool>> {
+    config
+        .get("regenerate_keys")
+        .map(|value| {
+            value
+                .as_bool()
+                .ok_or_else(|| anyhow!("Invalid regenerate_keys, expected true or false"))
+        })
+        .transpose()
+}
+
+fn parse_id_offset(config: &Value) -> Result<Option<i64>> {
+    config
+        .get("id_offset")
+        .map(|value| {
+            value
+                .as_integer()
+                .ok_or_else(|| anyhow!("Invalid ID offset"))
+        })
+        .transpose()
+}
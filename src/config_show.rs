@@ -0,0 +1,264 @@
+use std::path::Path;
+use std::{env, fs};
+
+use anyhow::Result;
+use toml::value::Table;
+use toml::Value;
+
+use crate::args::Args;
+use crate::config::{
+    interpolate_env, merge_migration_block, INTERPOLATED_KEYS, MIGRATION_SECTIONS,
+};
+
+// Values of these keys are never printed
+const SECRET_KEYS: [&str; 3] = ["password", "bundle_signing_key", "webhook_url"];
+
+const REDACTED: &str = "<redacted>";
+
+type LeafRenderer<'a> = dyn Fn(&[String], &Value) -> Result<(Value, Option<String>)> + 'a;
+
+// Prints the config file with secrets redacted. Resolved, the [[migration]] blocks are merged into the sections they
+// override and `${NAME}` references replaced, with every value annotated by the layer it comes from.
+pub fn show_config(
+    options: &Args,
+    env_file: Option<&Path>,
+    env_file_variables: &[String],
+    resolved: bool,
+) -> Result<()> {
+    let config = fs::read_to_string(&options.config)?.parse::<Value>()?;
+    let mut output = String::new();
+
+    if !resolved {
+        if let Value::Table(table) = &config {
+            render_table(&mut output, &mut Vec::new(), table, &|path, value| {
+                Ok((redact(path, value.clone()), None))
+            })?;
+        }
+
+        println!("{}", output.trim());
+        return Ok(());
+    }
+
+    output.push_str(&format!(
+        "# config = {}  # {}\n",
+        Value::String(options.config.display().to_string()),
+        option_layer("--config", "DB_MIGRATOR_CONFIG")
+    ));
+    output.push_str(&format!(
+        "# mappings = {}  # {}\n",
+        Value::String(options.mappings.display().to_string()),
+        option_layer("--mappings", "DB_MIGRATOR_MAPPINGS")
+    ));
+
+    if let Some(env_file) = env_file {
+        output.push_str(&format!(
+            "# env_file = {}  # {}\n",
+            Value::String(env_file.display().to_string()),
+            option_layer("--env-file", "DB_MIGRATOR_ENV_FILE")
+        ));
+    }
+
+    let blocks = match config.get("migration") {
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .enumerate()
+            .map(|(index, block)| {
+                let name = block
+                    .get("name")
+                    .and_then(|name| name.as_str())
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| format!("migration {}", index + 1));
+
+                (Some((name, block)), merge_migration_block(&config, block))
+            })
+            .collect::<Vec<_>>(),
+        _ => vec![(None, config.clone())],
+    };
+
+    for (block, merged) in &blocks {
+        let Value::Table(table) = merged else {
+            continue;
+        };
+
+        if let Some((name, _)) = block {
+            output.push_str(&format!("\n# Migration \"{}\"\n", name));
+        }
+
+        render_table(&mut output, &mut Vec::new(), table, &|path, value| {
+            resolve_value(path, value, block.as_ref(), env_file_variables)
+        })?;
+    }
+
+    println!("{}", output.trim());
+
+    Ok(())
+}
+
+// Value as the commands see it, along with the layers that set it
+fn resolve_value(
+    path: &[String],
+    value: &Value,
+    block: Option<&(String, &Value)>,
+    env_file_variables: &[String],
+) -> Result<(Value, Option<String>)> {
+    let overridden_by = block.filter(|(_, block)| {
+        path.len() >= 2
+            && MIGRATION_SECTIONS.contains(&path[0].as_str())
+            && block
+                .get(&path[0])
+                .and_then(|section| section.get(&path[1]))
+                .is_some()
+    });
+
+    let mut layers = vec![match overridden_by {
+        Some((name, _)) => format!("migration \"{}\"", name),
+        None => "file".to_string(),
+    }];
+    let mut value = value.clone();
+
+    let interpolated = path.len() == 2
+        && matches!(path[0].as_str(), "mssql_database" | "mysql_database")
+        && INTERPOLATED_KEYS.contains(&path[1].as_str());
+
+    if let (true, Value::String(text)) = (interpolated, &value) {
+        for name in env_references(text) {
+            if env_file_variables.iter().any(|variable| variable == name) {
+                layers.push(format!("env file {}", name));
+            } else {
+                layers.push(format!("env {}", name));
+            }
+        }
+
+        value = Value::String(interpolate_env(text)?);
+    }
+
+    Ok((redact(path, value), Some(layers.join(", "))))
+}
+
+// Renders the values of a table before its sub-tables, like a TOML file is written
+fn render_table(
+    output: &mut String,
+    path: &mut Vec<String>,
+    table: &Table,
+    leaf: &LeafRenderer,
+) -> Result<()> {
+    for (key, value) in table {
+        if value.is_table() || is_array_of_tables(value) {
+            continue;
+        }
+
+        path.push(key.clone());
+        let (value, annotation) = leaf(path, value)?;
+        path.pop();
+
+        output.push_str(&format!("{} = {}", format_key(key), value));
+
+        if let Some(annotation) = annotation {
+            output.push_str(&format!("  # {}", annotation));
+        }
+
+        output.push('\n');
+    }
+
+    for (key, value) in table {
+        path.push(key.clone());
+
+        match value {
+            Value::Table(child) => {
+                // Like `[settings.tables]`, tables holding only tables are implied by their sub-tables
+                if child.is_empty() || child.values().any(|value| !value.is_table()) {
+                    output.push_str(&format!("\n[{}]\n", format_path(path)));
+                }
+
+                render_table(output, path, child, leaf)?;
+            }
+            Value::Array(items) if is_array_of_tables(value) => {
+                for item in items.iter().filter_map(|item| item.as_table()) {
+                    output.push_str(&format!("\n[[{}]]\n", format_path(path)));
+                    render_table(output, path, item, leaf)?;
+                }
+            }
+            _ => {}
+        }
+
+        path.pop();
+    }
+
+    Ok(())
+}
+
+fn is_array_of_tables(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => !items.is_empty() && items.iter().all(Value::is_table),
+        _ => false,
+    }
+}
+
+fn format_path(path: &[String]) -> String {
+    path.iter()
+        .map(|key| format_key(key))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+// Bare keys as they are, others quoted like `"dbo.orders"`
+fn format_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        key.to_string()
+    } else {
+        Value::String(key.to_string()).to_string()
+    }
+}
+
+fn redact(path: &[String], value: Value) -> Value {
+    let secret = path
+        .last()
+        .is_some_and(|key| SECRET_KEYS.contains(&key.as_str()));
+
+    match value {
+        Value::String(text) if secret && !text.is_empty() => Value::String(REDACTED.to_string()),
+        value => value,
+    }
+}
+
+// Names referenced as `${NAME}`, skipping escaped `$${` like the interpolation does
+fn env_references(value: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find('$') {
+        rest = &rest[start..];
+
+        if let Some(escaped) = rest.strip_prefix("$${") {
+            rest = escaped;
+        } else if let Some(reference) = rest.strip_prefix("${") {
+            let Some(end) = reference.find('}') else {
+                break;
+            };
+
+            names.push(&reference[..end]);
+            rest = &reference[end + 1..];
+        } else {
+            rest = &rest[1..];
+        }
+    }
+
+    names
+}
+
+// Whether a global option was given on the command line, through its environment variable or left to its default
+fn option_layer(flag: &str, variable: &str) -> String {
+    let prefix = format!("{}=", flag);
+
+    if env::args().any(|arg| arg == flag || arg.starts_with(&prefix)) {
+        "cli".to_string()
+    } else if env::var_os(variable).is_some() {
+        format!("env {}", variable)
+    } else {
+        "default".to_string()
+    }
+}
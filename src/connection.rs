@@ -1,9 +1,9 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use bb8::Pool;
 use bb8_tiberius::ConnectionManager;
 use sqlx::mysql::{MySqlConnectOptions, MySqlPool, MySqlPoolOptions};
-use sqlx::ConnectOptions;
+use sqlx::{ConnectOptions, Executor};
 use tiberius::{AuthMethod, Config, EncryptionLevel};
 
 use crate::config::DatabaseConfig;
@@ -14,6 +14,7 @@ pub struct TiberiusConnection {
 
 pub struct SqlxMySqlConnection {
     pub pool: MySqlPool,
+    pub session_variables: Vec<(String, String)>,
 }
 
 #[async_trait]
@@ -25,16 +26,54 @@ pub trait DatabaseConnection: Sized {
 impl DatabaseConnection for TiberiusConnection {
     async fn new(config: &DatabaseConfig, max_connections: u32) -> Result<Self> {
         let mut tiberius_config = Config::new();
+        tiberius_config.host(&config.host);
+        tiberius_config.port(config.port);
         tiberius_config.encryption(EncryptionLevel::NotSupported);
         tiberius_config.authentication(AuthMethod::sql_server(&config.username, &config.password));
         tiberius_config.database(&config.database);
 
-        let mgr = ConnectionManager::new(tiberius_config);
-        let pool = Pool::builder()
+        // The port of a named instance is looked up through the SQL Browser
+        let mgr = match &config.instance {
+            Some(instance) => {
+                tiberius_config.instance_name(instance);
+                ConnectionManager::new(tiberius_config).using_named_connection()
+            }
+            None => ConnectionManager::new(tiberius_config),
+        };
+        // Opening every connection up front surfaces login and network errors before any table is touched
+        let mut builder = Pool::builder()
             .max_size(max_connections)
+            .min_idle(Some(max_connections))
+            .test_on_check_out(config.pool.ping_before_use);
+
+        // Connections replaced by the reaper are reopened to keep `min_idle`
+        if let Some(max_lifetime) = config.pool.max_lifetime {
+            builder = builder.max_lifetime(Some(max_lifetime));
+        }
+
+        if let Some(idle_timeout) = config.pool.idle_timeout {
+            builder = builder.idle_timeout(Some(idle_timeout));
+        }
+
+        if let Some(connect_timeout) = config.connect_timeout {
+            builder = builder.connection_timeout(connect_timeout);
+        }
+
+        let pool = builder
             .build(mgr)
             .await
-            .context("Failed to connect to MSSQL server")?;
+            .with_context(|| format!("Failed to connect to MSSQL server {}", endpoint(config)))?;
+
+        let version = validate_tiberius_pool(&pool)
+            .await
+            .with_context(|| format!("Failed to query MSSQL server {}", endpoint(config)))?;
+
+        info!(
+            "Connected to MSSQL server {} with {} connections, {}",
+            endpoint(config),
+            max_connections,
+            version
+        );
 
         Ok(TiberiusConnection { pool })
     }
@@ -43,22 +82,149 @@ impl DatabaseConnection for TiberiusConnection {
 #[async_trait]
 impl DatabaseConnection for SqlxMySqlConnection {
     async fn new(config: &DatabaseConfig, max_connections: u32) -> Result<Self> {
-        let options = MySqlConnectOptions::new()
+        let mut options = MySqlConnectOptions::new()
             .host(&config.host)
             .port(config.port)
             .username(&config.username)
             .password(&config.password)
-            .database(&config.database)
-            .disable_statement_logging()
-            .clone();
+            .database(&config.database);
+
+        if let Some(socket) = &config.socket {
+            options = options.socket(socket);
+        }
+
+        if let Some(ssl_mode) = config.ssl_mode {
+            options = options.ssl_mode(ssl_mode);
+        }
 
-        let pool = MySqlPoolOptions::new()
+        if let Some(ssl_ca) = &config.ssl_ca {
+            options = options.ssl_ca(ssl_ca);
+        }
+
+        options.disable_statement_logging();
+
+        let mut pool_options = MySqlPoolOptions::new()
             .max_connections(max_connections)
+            .min_connections(max_connections)
+            .test_before_acquire(config.pool.ping_before_use);
+
+        if let Some(max_lifetime) = config.pool.max_lifetime {
+            pool_options = pool_options.max_lifetime(max_lifetime);
+        }
+
+        // The reaper never closes connections below `min_connections`, so idle ones are replaced when checked out
+        if let Some(idle_timeout) = config.pool.idle_timeout {
+            pool_options =
+                pool_options
+                    .idle_timeout(idle_timeout)
+                    .before_acquire(move |_, meta| {
+                        Box::pin(async move { Ok(meta.idle_for <= idle_timeout) })
+                    });
+        }
+
+        if let Some(connect_timeout) = config.connect_timeout {
+            pool_options = pool_options.acquire_timeout(connect_timeout);
+        }
+
+        if !config.init_sql.is_empty() {
+            let init_sql = config.init_sql.clone();
+
+            pool_options = pool_options.after_connect(move |conn, _| {
+                let init_sql = init_sql.clone();
+
+                Box::pin(async move {
+                    for statement in &init_sql {
+                        conn.execute(statement.as_str()).await?;
+                    }
+
+                    Ok(())
+                })
+            });
+        }
+
+        let pool = pool_options
             .connect_with(options)
-            .await?;
+            .await
+            .with_context(|| format!("Failed to connect to MySQL server {}", endpoint(config)))?;
+
+        let version = validate_mysql_pool(&pool, max_connections)
+            .await
+            .with_context(|| format!("Failed to query MySQL server {}", endpoint(config)))?;
+
+        info!(
+            "Connected to MySQL server {} with {} connections, version {}",
+            endpoint(config),
+            max_connections,
+            version
+        );
+
+        Ok(SqlxMySqlConnection {
+            pool,
+            session_variables: config.session_variables.clone(),
+        })
+    }
+}
+
+fn endpoint(config: &DatabaseConfig) -> String {
+    if let Some(instance) = &config.instance {
+        return format!(
+            "{}\\{} (database {}, user {})",
+            config.host, instance, config.database, config.username
+        );
+    }
+
+    if let Some(socket) = &config.socket {
+        return format!(
+            "{} (database {}, user {})",
+            socket.display(),
+            config.database,
+            config.username
+        );
+    }
+
+    format!(
+        "{}:{} (database {}, user {})",
+        config.host, config.port, config.database, config.username
+    )
+}
+
+// First line of `@@VERSION` as read through a pooled connection
+async fn validate_tiberius_pool(pool: &Pool<ConnectionManager>) -> Result<String> {
+    let mut conn = pool.get().await?;
+    let row = conn
+        .simple_query("SELECT @@VERSION")
+        .await?
+        .into_row()
+        .await?
+        .ok_or_else(|| anyhow!("No version returned"))?;
+
+    let version = row.get::<&str, _>(0).unwrap_or_default();
+
+    Ok(version
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string())
+}
 
-        Ok(SqlxMySqlConnection { pool })
+// Checks out all connections at once so each of them is established and answers a query
+async fn validate_mysql_pool(pool: &MySqlPool, max_connections: u32) -> Result<String> {
+    let mut connections = Vec::new();
+
+    for _ in 0..max_connections {
+        connections.push(pool.acquire().await?);
+    }
+
+    let mut version = String::new();
+
+    for conn in &mut connections {
+        version = sqlx::query_scalar("SELECT @@version")
+            .fetch_one(&mut **conn)
+            .await?;
     }
+
+    Ok(version)
 }
 
 pub struct DatabaseConnectionFactory<C: DatabaseConnection> {
@@ -0,0 +1,378 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::common::identifiers::quote_identifier;
+use crate::common::schema::ColumnSchema;
+use crate::config::{BinaryEncoding, CsvConfig, ExtraColumn};
+use crate::migrate::batch_sink::{Batch, BatchSink};
+
+// Spatial values are read in MySQL's geometry format, which loads from the decoded bytes like binary values
+const SPATIAL_TYPES: [&str; 8] = [
+    "geometry",
+    "point",
+    "linestring",
+    "polygon",
+    "multipoint",
+    "multilinestring",
+    "multipolygon",
+    "geometrycollection",
+];
+
+// Writes the rows of a table into a CSV file, in a format LOAD DATA reads back without loss
+pub struct CsvFileWriter {
+    writer: BufWriter<File>,
+    config: CsvConfig,
+    binary_columns: Vec<bool>,
+}
+
+impl CsvFileWriter {
+    pub fn create(path: &Path, config: &CsvConfig, mapped_schema: &[ColumnSchema]) -> Result<Self> {
+        Ok(CsvFileWriter {
+            writer: BufWriter::new(File::create(path)?),
+            config: config.clone(),
+            binary_columns: mapped_schema.iter().map(is_binary_column).collect(),
+        })
+    }
+
+    fn write_row(&mut self, value_set: &str) -> Result<()> {
+        let fields = split_value_set(value_set)
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let binary = self.binary_columns.get(index).copied().unwrap_or(false);
+                format_field(&self.config, value, binary)
+            })
+            .collect::<Vec<_>>();
+
+        self.writer.write_all(
+            fields
+                .join(self.config.delimiter.encode_utf8(&mut [0; 4]))
+                .as_bytes(),
+        )?;
+        self.writer
+            .write_all(self.config.line_terminator.as_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BatchSink for CsvFileWriter {
+    async fn write_batch(&mut self, batch: &Batch<'_>) -> Result<()> {
+        for value_set in batch.rows {
+            self.write_row(value_set)?;
+        }
+
+        Ok(())
+    }
+
+    async fn execute_statement(&mut self, _statement: &str) -> Result<()> {
+        Err(anyhow!("Statements can't be written into a CSV file"))
+    }
+}
+
+// `LOAD DATA` statement reading a CSV file written with the config, binary columns are decoded while loading
+// and the extra columns are set to their values. Rows with a duplicate key replace the existing ones or are ignored.
+pub fn build_load_data_statement(
+    file_name: &str,
+    table_name: &str,
+    mapped_schema: &[ColumnSchema],
+    extra_columns: &[ExtraColumn],
+    config: &CsvConfig,
+    replace: bool,
+    ignore: bool,
+) -> String {
+    let columns = mapped_schema
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            if is_binary_column(column) {
+                format!("@column{}", index)
+            } else {
+                quote_identifier(&column.column_name)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let decode = match config.binary_encoding {
+        BinaryEncoding::Hex => "UNHEX",
+        BinaryEncoding::Base64 => "FROM_BASE64",
+    };
+    let assignments = mapped_schema
+        .iter()
+        .enumerate()
+        .filter(|(_, column)| is_binary_column(column))
+        .map(|(index, column)| {
+            format!(
+                "{} = {}(@column{})",
+                quote_identifier(&column.column_name),
+                decode,
+                index
+            )
+        })
+        .chain(extra_columns.iter().filter_map(|column| {
+            column
+                .value
+                .as_ref()
+                .map(|value| format!("{} = {}", quote_identifier(&column.name), value))
+        }))
+        .collect::<Vec<_>>();
+
+    format!(
+        "LOAD DATA LOCAL INFILE {} {}INTO TABLE {} CHARACTER SET utf8mb4 \
+         FIELDS TERMINATED BY {} OPTIONALLY ENCLOSED BY {} ESCAPED BY {} LINES TERMINATED BY {} ({}){}",
+        quote_literal(file_name),
+        if replace {
+            "REPLACE "
+        } else if ignore {
+            "IGNORE "
+        } else {
+            ""
+        },
+        quote_identifier(table_name),
+        quote_literal(&config.delimiter.to_string()),
+        quote_literal(&config.quote.to_string()),
+        quote_literal(&config.escape.map(String::from).unwrap_or_default()),
+        quote_literal(&config.line_terminator),
+        columns,
+        if assignments.is_empty() {
+            String::new()
+        } else {
+            format!(" SET {}", assignments.join(", "))
+        }
+    )
+}
+
+fn is_binary_column(column: &ColumnSchema) -> bool {
+    let data_type = column.data_type.to_lowercase();
+    data_type.contains("binary")
+        || data_type.ends_with("blob")
+        || SPATIAL_TYPES.contains(&data_type.as_str())
+}
+
+// Values of a row as formatted for the INSERT statement, `(1, 'a, b', NULL) ` gives `1`, `'a, b'` and `NULL`
+fn split_value_set(value_set: &str) -> Vec<&str> {
+    let values = value_set.trim_end();
+    let values = values
+        .strip_prefix('(')
+        .and_then(|values| values.strip_suffix(')'))
+        .unwrap_or(values);
+
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+
+    // Quotes inside strings are doubled, so they toggle twice
+    for (index, c) in values.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            ',' if !quoted => {
+                fields.push(values[start..index].trim_start());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    fields.push(values[start..].trim_start());
+    fields
+}
+
+// CSV field of a value formatted as an SQL literal: strings are always quoted, so they are told apart from NULL
+fn format_field(config: &CsvConfig, value: &str, binary: bool) -> String {
+    if value == "NULL" {
+        return config.null.clone();
+    }
+
+    if let Some(text) = value
+        .strip_prefix('\'')
+        .and_then(|value| value.strip_suffix('\''))
+    {
+        return quote_text(config, &text.replace("''", "'"));
+    }
+
+    let hex = value
+        .strip_prefix("0x")
+        .or_else(|| (value == "X''").then_some(""));
+
+    if let Some(hex) = hex {
+        let bytes = hex::decode(hex).unwrap_or_default();
+
+        return match (binary, config.binary_encoding) {
+            (true, BinaryEncoding::Hex) => hex.to_uppercase(),
+            (true, BinaryEncoding::Base64) => STANDARD.encode(bytes),
+            // Like the INSERT, the bytes become the text of a non-binary column
+            (false, _) => quote_text(config, &String::from_utf8_lossy(&bytes)),
+        };
+    }
+
+    match value {
+        "true" => "1".to_string(),
+        "false" => "0".to_string(),
+        value
+            if value.contains([config.delimiter, config.quote])
+                || value.contains(&config.line_terminator) =>
+        {
+            quote_text(config, value)
+        }
+        value => value.to_string(),
+    }
+}
+
+fn quote_text(config: &CsvConfig, text: &str) -> String {
+    let mut field = String::with_capacity(text.len() + 2);
+    field.push(config.quote);
+
+    for c in text.chars() {
+        match config.escape {
+            Some(escape) if c == config.quote || c == escape => {
+                field.push(escape);
+                field.push(c);
+            }
+            // LOAD DATA reads `\n`, `\r` and `\0` after the escape character as the control characters
+            Some(escape) if matches!(c, '\n' | '\r' | '\0') => {
+                field.push(escape);
+                field.push(match c {
+                    '\n' => 'n',
+                    '\r' => 'r',
+                    _ => '0',
+                });
+            }
+            None if c == config.quote => {
+                field.push(c);
+                field.push(c);
+            }
+            _ => field.push(c),
+        }
+    }
+
+    field.push(config.quote);
+    field
+}
+
+// MySQL string literal, escaped for the default `sql_mode`
+fn quote_literal(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('\'');
+
+    for c in value.chars() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '\'' => literal.push_str("\\'"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c => literal.push(c),
+        }
+    }
+
+    literal.push('\'');
+    literal
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::extract::format::format_string_value;
+
+    // SQL value set of a row like the INSERT planner formats it
+    fn value_set(values: &[Option<String>]) -> String {
+        let values = values
+            .iter()
+            .map(|value| format_string_value(value.as_deref()))
+            .collect::<Vec<_>>();
+
+        format!("({})", values.join(", "))
+    }
+
+    fn write_rows(config: &CsvConfig, rows: &[Vec<Option<String>>]) -> Vec<u8> {
+        let path = std::env::temp_dir().join(format!(
+            "db-migrator-csv-{}-{:?}.csv",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+
+        let mut writer = CsvFileWriter::create(&path, config, &[]).unwrap();
+        for row in rows {
+            writer.write_row(&value_set(row)).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let contents = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        contents
+    }
+
+    fn text() -> impl Strategy<Value = String> {
+        prop_oneof![any::<String>(), "[a-z ,;|\t\"'\\\\\r\n\0]{0,16}"]
+    }
+
+    proptest! {
+        #[test]
+        fn rows_read_back_unchanged(
+            delimiter in prop::sample::select(vec![',', ';', '\t', '|']),
+            rows in prop::collection::vec(prop::collection::vec(prop::option::of(text()), 1..6), 1..8),
+        ) {
+            let config = CsvConfig {
+                delimiter,
+                escape: None,
+                ..CsvConfig::default()
+            };
+
+            let contents = write_rows(&config, &rows);
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .delimiter(delimiter as u8)
+                .quote(b'"')
+                .double_quote(true)
+                .terminator(csv::Terminator::Any(b'\n'))
+                .from_reader(contents.as_slice());
+
+            let records = reader
+                .records()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+
+            prop_assert_eq!(records.len(), rows.len());
+
+            for (record, row) in records.iter().zip(&rows) {
+                let expected = row
+                    .iter()
+                    .map(|value| value.as_deref().unwrap_or(&config.null))
+                    .collect::<Vec<_>>();
+
+                prop_assert_eq!(record.iter().collect::<Vec<_>>(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn escapes_control_characters_for_load_data() {
+        let config = CsvConfig::default();
+        let rows = vec![vec![
+            Some("a\"b\\c\r\n\0".to_string()),
+            None,
+            Some("x, y".to_string()),
+        ]];
+
+        assert_eq!(
+            String::from_utf8(write_rows(&config, &rows)).unwrap(),
+            "\"a\\\"b\\\\c\\r\\n\\0\",NULL,\"x, y\"\n"
+        );
+    }
+}
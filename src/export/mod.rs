@@ -0,0 +1,5 @@
+mod csv_file_writer;
+pub mod null_exporter;
+pub mod sql_exporter;
+mod sql_file_writer;
+pub mod table_runner;
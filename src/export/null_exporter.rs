@@ -0,0 +1,200 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use tokio::time::Instant;
+
+use crate::common::source_table::SourceTable;
+use crate::export::table_runner::{regenerate_keys, run_tables};
+use crate::extract::extractor::DatabaseExtractor;
+use crate::mappings::Mappings;
+use crate::migrate::batch_sink::{write_table_batches, Batch, BatchSink, InsertPlan};
+use crate::migrate::migration_options::MigrationOptions;
+use crate::migrate::migrator::resolve_source_tables;
+use crate::migrate::table_schema_mapper::TableSchemaMapper;
+
+const BYTES_PER_MB: f64 = 1024.0 * 1024.0;
+
+// Reads and converts the tables like a migration but discards the batches, measuring the extraction speed
+pub struct NullExporter {
+    extractor: DatabaseExtractor,
+    mappings: Mappings,
+    options: MigrationOptions,
+}
+
+// Counts the batches it is handed instead of writing them
+#[derive(Default)]
+struct NullSink {
+    rows: usize,
+    bytes: usize,
+}
+
+#[async_trait]
+impl BatchSink for NullSink {
+    async fn write_batch(&mut self, batch: &Batch<'_>) -> Result<()> {
+        self.rows += batch.len();
+        self.bytes += batch.query.len();
+        Ok(())
+    }
+
+    async fn execute_statement(&mut self, statement: &str) -> Result<()> {
+        self.bytes += statement.len();
+        Ok(())
+    }
+}
+
+impl NullExporter {
+    pub fn new(
+        extractor: DatabaseExtractor,
+        mappings: Mappings,
+        options: MigrationOptions,
+    ) -> Self {
+        NullExporter {
+            extractor,
+            mappings,
+            options,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        info!("Reading tables into the null target");
+
+        let start_time = Instant::now();
+
+        let (tables, _) = resolve_source_tables(&mut self.extractor, &self.options).await?;
+
+        regenerate_keys(&mut self.extractor, &tables, &mut self.options).await?;
+
+        let runs = run_tables(
+            tables,
+            self.options.table_parallelism(),
+            "reading",
+            |table| {
+                let extractor = self.extractor.clone();
+                let mappings = self.mappings.clone();
+                let options = self.options.clone();
+
+                async move { read_table(extractor, &mappings, &options, &table).await }
+            },
+        )
+        .await;
+        let sinks = &runs.completed;
+
+        let seconds = Instant::now()
+            .saturating_duration_since(start_time)
+            .as_secs_f64();
+        let rows = sinks.iter().map(|sink| sink.rows).sum::<usize>();
+        let bytes = sinks.iter().map(|sink| sink.bytes).sum::<usize>();
+
+        info!(
+            "Null target finished, tables: {}, rows: {}, {:.1} MB, total time took: {:.1}s, {:.0} rows/s, {:.1} MB/s",
+            sinks.len(),
+            rows,
+            bytes as f64 / BYTES_PER_MB,
+            seconds,
+            rows as f64 / seconds.max(f64::EPSILON),
+            bytes as f64 / BYTES_PER_MB / seconds.max(f64::EPSILON)
+        );
+
+        runs.check()
+    }
+}
+
+async fn read_table(
+    mut extractor: DatabaseExtractor,
+    mappings: &Mappings,
+    options: &MigrationOptions,
+    table: &SourceTable,
+) -> Result<NullSink> {
+    let output_table_name = options.target_table_name(table);
+
+    let start_time = Instant::now();
+
+    let table_schema = extractor
+        .get_table_schema(table)
+        .await
+        .with_context(|| "Failed to get table schema".to_string())?;
+    let mapped_schema = TableSchemaMapper::map_schema(mappings, table, &table_schema, options);
+
+    let plan = InsertPlan::new(
+        table,
+        &output_table_name,
+        &table_schema,
+        &mapped_schema,
+        options,
+    );
+
+    let mut sink = NullSink::default();
+
+    write_table_batches(
+        &mut extractor,
+        &mut sink,
+        table,
+        &table_schema,
+        &plan,
+        options,
+    )
+    .await?;
+
+    plan.log_row_changes(&output_table_name);
+
+    info!(
+        "Table {} read, rows: {}, {:.1} MB, took: {}s",
+        output_table_name,
+        sink.rows,
+        sink.bytes as f64 / BYTES_PER_MB,
+        Instant::now()
+            .saturating_duration_since(start_time)
+            .as_secs_f32()
+    );
+
+    Ok(sink)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{GeneratorConfig, SettingsConfig};
+    use crate::extract::generator::Generator;
+
+    fn generated_tables() -> (DatabaseExtractor, Vec<SourceTable>) {
+        let generator = Generator::new(GeneratorConfig {
+            tables: 2,
+            rows: 250,
+        });
+        let tables = generator.tables();
+
+        (DatabaseExtractor::generated(generator), tables)
+    }
+
+    fn mappings() -> Mappings {
+        Mappings::load(concat!(env!("CARGO_MANIFEST_DIR"), "/mappings.toml")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn reads_every_generated_row() -> Result<()> {
+        let (extractor, tables) = generated_tables();
+        let options = MigrationOptions::new(SettingsConfig::default());
+
+        for table in &tables {
+            let sink = read_table(extractor.clone(), &mappings(), &options, table).await?;
+
+            assert_eq!(sink.rows, 250);
+            assert!(sink.bytes > 0);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn runs_all_generated_tables() -> Result<()> {
+        let (extractor, tables) = generated_tables();
+        let settings = SettingsConfig {
+            whitelisted_tables: tables.iter().map(ToString::to_string).collect(),
+            ..SettingsConfig::default()
+        };
+        let options = MigrationOptions::new(settings).with_parallelism(2);
+
+        NullExporter::new(extractor, mappings(), options)
+            .run()
+            .await
+    }
+}
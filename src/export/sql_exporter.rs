@@ -0,0 +1,244 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tokio::time::Instant;
+
+use crate::common::dependency_graph::DependencyGraph;
+use crate::common::identifiers::quote_identifier;
+use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::export::csv_file_writer::{build_load_data_statement, CsvFileWriter};
+use crate::export::sql_file_writer::SqlFileWriter;
+use crate::export::table_runner::{regenerate_keys, run_tables};
+use crate::extract::extractor::DatabaseExtractor;
+use crate::insert::query::{build_create_constraints, build_create_table_query};
+use crate::mappings::Mappings;
+use crate::migrate::batch_sink::{write_table_batches, InsertPlan};
+use crate::migrate::migration_options::MigrationOptions;
+use crate::migrate::migrator::resolve_source_tables;
+use crate::migrate::partitioning::plan_partitioning;
+use crate::migrate::provenance::table_comment;
+use crate::migrate::table_schema_mapper::{loaded_columns, TableSchemaMapper};
+
+const CONSTRAINTS_FILE: &str = "constraints.sql";
+
+pub struct SqlFileExporter {
+    extractor: DatabaseExtractor,
+    mappings: Mappings,
+    options: MigrationOptions,
+    out_dir: PathBuf,
+}
+
+impl SqlFileExporter {
+    pub fn new(
+        extractor: DatabaseExtractor,
+        mappings: Mappings,
+        options: MigrationOptions,
+        out_dir: PathBuf,
+    ) -> Self {
+        SqlFileExporter {
+            extractor,
+            mappings,
+            options,
+            out_dir,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        info!("Exporting tables to {}", self.out_dir.display());
+
+        let start_time = Instant::now();
+
+        fs::create_dir_all(&self.out_dir)?;
+
+        let (tables, names) = resolve_source_tables(&mut self.extractor, &self.options).await?;
+        let formatted_tables = names.target_names();
+
+        if self.options.drop {
+            self.options
+                .check_protected_tables(&tables, "dropped by --drop")?;
+        }
+
+        regenerate_keys(&mut self.extractor, &tables, &mut self.options).await?;
+
+        let runs = run_tables(
+            tables,
+            self.options.table_parallelism(),
+            "exporting",
+            |table| {
+                let extractor = self.extractor.clone();
+                let mappings = self.mappings.clone();
+                let options = self.options.clone();
+                let out_dir = self.out_dir.clone();
+
+                async move { export_table(extractor, &mappings, &options, &out_dir, &table).await }
+            },
+        )
+        .await;
+        let exported_tables = &runs.completed;
+
+        if self.options.constraints {
+            // Constraints go into a separate script, to be run once every table file was loaded
+            let mut writer = SqlFileWriter::create(&self.out_dir.join(CONSTRAINTS_FILE))?;
+
+            let graph = DependencyGraph::from_schemas(
+                exported_tables
+                    .iter()
+                    .map(|(table_name, schema)| (table_name.as_str(), schema.as_slice())),
+            );
+            let table_names = exported_tables
+                .iter()
+                .map(|(table_name, _)| table_name.clone())
+                .collect::<Vec<_>>();
+
+            for table_name in graph.levels(&table_names).into_iter().flatten() {
+                let (_, schema) = exported_tables
+                    .iter()
+                    .find(|(name, _)| *name == table_name)
+                    .unwrap();
+
+                if let Some(query) = build_create_constraints(
+                    &table_name,
+                    schema,
+                    &formatted_tables,
+                    self.options.dialect,
+                    &[],
+                    false,
+                ) {
+                    writer.write_statement(&query)?;
+                }
+            }
+
+            writer.finish()?;
+        }
+
+        info!(
+            "Export finished, tables: {}, total time took: {}s",
+            exported_tables.len(),
+            Instant::now()
+                .saturating_duration_since(start_time)
+                .as_secs_f32()
+        );
+
+        runs.check()
+    }
+}
+
+async fn export_table(
+    mut extractor: DatabaseExtractor,
+    mappings: &Mappings,
+    options: &MigrationOptions,
+    out_dir: &Path,
+    table: &SourceTable,
+) -> Result<(String, Vec<ColumnSchema>)> {
+    let output_table_name = options.target_table_name(table);
+
+    info!("Exporting table: {}", output_table_name);
+
+    let start_time = Instant::now();
+
+    let table_schema = extractor
+        .get_table_schema(table)
+        .await
+        .with_context(|| "Failed to get table schema".to_string())?;
+    let mapped_schema = TableSchemaMapper::map_schema(mappings, table, &table_schema, options);
+
+    let mut writer = SqlFileWriter::create(&out_dir.join(format!("{}.sql", output_table_name)))?;
+
+    writer.write_comment(&format!("Exported from {}", table))?;
+    writer.write_statement("SET FOREIGN_KEY_CHECKS=0")?;
+
+    if options.drop {
+        writer.write_statement(&format!(
+            "DROP TABLE IF EXISTS {}",
+            quote_identifier(&output_table_name)
+        ))?;
+    }
+
+    let partitioning = plan_partitioning(
+        &mut extractor,
+        table,
+        &table_schema,
+        &mapped_schema,
+        options,
+    )
+    .await?;
+    let comment = table_comment(&mut extractor, table, options).await?;
+
+    writer.write_statement(&build_create_table_query(
+        &output_table_name,
+        &mapped_schema,
+        &options.extra_columns,
+        &options.collation,
+        comment.as_deref(),
+        partitioning.as_deref(),
+    ))?;
+
+    let plan = InsertPlan::new(
+        table,
+        &output_table_name,
+        &table_schema,
+        &mapped_schema,
+        options,
+    );
+
+    let rows = match &options.csv {
+        // The script loads the rows from a CSV file next to it
+        Some(csv) => {
+            let (_, loaded_schema) = loaded_columns(&table_schema, &mapped_schema);
+            let file_name = format!("{}.csv", output_table_name);
+            let mut csv_writer =
+                CsvFileWriter::create(&out_dir.join(&file_name), csv, &loaded_schema)?;
+
+            let rows = write_table_batches(
+                &mut extractor,
+                &mut csv_writer,
+                table,
+                &table_schema,
+                &plan,
+                options,
+            )
+            .await?;
+
+            csv_writer.finish()?;
+            writer.write_statement(&build_load_data_statement(
+                &file_name,
+                &output_table_name,
+                &loaded_schema,
+                &options.extra_columns,
+                csv,
+                options.upsert,
+                options.append,
+            ))?;
+
+            rows
+        }
+        None => {
+            write_table_batches(
+                &mut extractor,
+                &mut writer,
+                table,
+                &table_schema,
+                &plan,
+                options,
+            )
+            .await?
+        }
+    };
+
+    plan.log_row_changes(&output_table_name);
+
+    writer.finish()?;
+
+    info!(
+        "Table {} exported, rows: {}, took: {}s",
+        output_table_name,
+        rows,
+        Instant::now()
+            .saturating_duration_since(start_time)
+            .as_secs_f32()
+    );
+
+    Ok((output_table_name, mapped_schema))
+}
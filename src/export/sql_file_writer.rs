@@ -0,0 +1,48 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::migrate::batch_sink::{Batch, BatchSink};
+
+// Writes statements into a plain SQL script that can be piped into the mysql client
+pub struct SqlFileWriter {
+    writer: BufWriter<File>,
+}
+
+impl SqlFileWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        Ok(SqlFileWriter {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    pub fn write_statement(&mut self, statement: &str) -> Result<()> {
+        self.writer.write_all(statement.as_bytes())?;
+        self.writer.write_all(b";\n")?;
+        Ok(())
+    }
+
+    pub fn write_comment(&mut self, comment: &str) -> Result<()> {
+        writeln!(self.writer, "-- {}", comment)?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BatchSink for SqlFileWriter {
+    async fn write_batch(&mut self, batch: &Batch<'_>) -> Result<()> {
+        self.write_statement(batch.query.trim_end())
+    }
+
+    async fn execute_statement(&mut self, statement: &str) -> Result<()> {
+        self.write_statement(statement)
+    }
+}
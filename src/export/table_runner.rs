@@ -0,0 +1,97 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use futures::future::join_all;
+use tokio::spawn;
+
+use crate::common::helpers::print_error_chain;
+use crate::common::source_table::SourceTable;
+use crate::extract::extractor::DatabaseExtractor;
+use crate::migrate::concurrency_limiter::ConcurrencyLimiter;
+use crate::migrate::key_mapping::build_key_mappings;
+use crate::migrate::migration_options::MigrationOptions;
+
+// Results of running a task per table, the failed tables already printed
+pub struct TableRuns<T> {
+    pub completed: Vec<T>,
+    pub failed: usize,
+}
+
+impl<T> TableRuns<T> {
+    pub fn check(&self) -> Result<()> {
+        if self.failed > 0 {
+            bail!(
+                "{} of {} tables failed",
+                self.failed,
+                self.failed + self.completed.len()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+// Regenerates keys for exports without a target to continue from, so the new keys start at 1
+pub async fn regenerate_keys(
+    extractor: &mut DatabaseExtractor,
+    tables: &[SourceTable],
+    options: &mut MigrationOptions,
+) -> Result<()> {
+    let key_mappings = build_key_mappings(extractor, None, tables, options)
+        .await
+        .with_context(|| "Failed to regenerate keys")?;
+
+    if !key_mappings.is_empty() {
+        options.key_mappings = Some(Arc::new(key_mappings));
+    }
+
+    Ok(())
+}
+
+// Runs `task` for every table, at most `parallelism` at once, printing the tables that failed
+pub async fn run_tables<T, F, Fut>(
+    tables: Vec<SourceTable>,
+    parallelism: usize,
+    action: &'static str,
+    task: F,
+) -> TableRuns<T>
+where
+    T: Send + 'static,
+    F: Fn(SourceTable) -> Fut,
+    Fut: Future<Output = Result<T>> + Send + 'static,
+{
+    let limiter = Arc::new(ConcurrencyLimiter::fixed(parallelism));
+
+    let tasks = tables
+        .into_iter()
+        .map(|table| {
+            let run = task(table.clone());
+            let limiter = Arc::clone(&limiter);
+
+            spawn(async move {
+                let _permit = limiter.acquire().await?;
+
+                run.await
+                    .with_context(|| format!("Error while {} table: {}", action, table))
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut runs = TableRuns {
+        completed: Vec::new(),
+        failed: 0,
+    };
+
+    for result in join_all(tasks).await {
+        match result.map_err(|err| anyhow!(err)).and_then(|result| result) {
+            Ok(completed) => runs.completed.push(completed),
+            Err(err) => {
+                print_error_chain(&err);
+                runs.failed += 1;
+            }
+        }
+    }
+
+    runs
+}
@@ -0,0 +1,256 @@
+use crate::common::source_table::SourceTable;
+
+const KEYSET_TYPES: [&str; 4] = ["tinyint", "smallint", "int", "bigint"];
+
+// Upper limit of chunks a table is split into, larger tables get wider chunks
+pub const MAX_CHUNKS: usize = 100_000;
+
+#[derive(Debug, Clone)]
+pub enum ChunkPlan {
+    // Ranges over a single integer primary key, each chunk covers `key_width` key values
+    Keyset {
+        key_column: String,
+        min_key: i64,
+        max_key: i64,
+        key_width: u64,
+    },
+    // Pages ordered by the primary key columns, each chunk covers `chunk_size` rows. The pages only keep their rows
+    // while the table doesn't change, so they should be read from a snapshot.
+    Offset {
+        key_columns: Vec<String>,
+        total_rows: i64,
+        chunk_size: usize,
+    },
+}
+
+impl ChunkPlan {
+    pub fn supports_keyset(data_type: &str) -> bool {
+        KEYSET_TYPES.contains(&data_type)
+    }
+
+    // Splits the keys from `min_key` to `max_key` into chunks of about `chunk_size` rows. Sparse keys get wider
+    // chunks, so gaps in the keys don't turn into runs of empty chunks.
+    pub fn keyset(
+        key_column: String,
+        min_key: i64,
+        max_key: i64,
+        total_rows: i64,
+        chunk_size: usize,
+    ) -> Self {
+        let span = key_span(min_key, max_key);
+        let rows = total_rows.max(1) as u128;
+
+        let key_width = (span * chunk_size.max(1) as u128)
+            .div_ceil(rows)
+            .max(span.div_ceil(MAX_CHUNKS as u128))
+            .clamp(1, span);
+
+        ChunkPlan::Keyset {
+            key_column,
+            min_key,
+            max_key,
+            key_width: key_width as u64,
+        }
+    }
+
+    pub fn offset(key_columns: Vec<String>, total_rows: i64, chunk_size: usize) -> Self {
+        let chunk_size = chunk_size
+            .max(1)
+            .max((total_rows.max(0) as usize).div_ceil(MAX_CHUNKS));
+
+        ChunkPlan::Offset {
+            key_columns,
+            total_rows,
+            chunk_size,
+        }
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        match self {
+            ChunkPlan::Keyset {
+                min_key,
+                max_key,
+                key_width,
+                ..
+            } => key_span(*min_key, *max_key).div_ceil(*key_width as u128) as usize,
+            ChunkPlan::Offset {
+                total_rows,
+                chunk_size,
+                ..
+            } => ((*total_rows).max(0) as usize).div_ceil(*chunk_size),
+        }
+    }
+
+    // Strategy and bounds the chunks are cut from, chunk N covers the same rows only while these stay the same
+    pub fn bounds(&self) -> String {
+        match self {
+            ChunkPlan::Keyset {
+                key_column,
+                min_key,
+                max_key,
+                key_width,
+            } => format!(
+                "keyset on [{}] from {} to {} by {}",
+                key_column, min_key, max_key, key_width
+            ),
+            ChunkPlan::Offset {
+                key_columns,
+                total_rows,
+                chunk_size,
+            } => format!(
+                "offset on [{}] over {} rows by {}",
+                key_columns.join("], ["),
+                total_rows,
+                chunk_size
+            ),
+        }
+    }
+
+    // Lowest and highest key value of a keyset chunk, both included
+    pub fn key_range(&self, index: usize) -> Option<(i64, i64)> {
+        match self {
+            ChunkPlan::Keyset {
+                min_key,
+                max_key,
+                key_width,
+                ..
+            } => {
+                let lower = *min_key as i128 + index as i128 * *key_width as i128;
+                let upper = (lower + *key_width as i128 - 1).min(*max_key as i128);
+
+                (lower <= upper).then_some((lower as i64, upper as i64))
+            }
+            ChunkPlan::Offset { .. } => None,
+        }
+    }
+
+    pub fn chunk_query(&self, table: &SourceTable, columns: &str, index: usize) -> String {
+        match self {
+            ChunkPlan::Keyset { key_column, .. } => {
+                let (lower, upper) = self.key_range(index).unwrap();
+
+                format!(
+                    "SELECT {} FROM {} WHERE [{}] >= {} AND [{}] <= {} ORDER BY [{}]",
+                    columns,
+                    table.row_source(),
+                    key_column,
+                    lower,
+                    key_column,
+                    upper,
+                    key_column
+                )
+            }
+            ChunkPlan::Offset {
+                key_columns,
+                chunk_size,
+                ..
+            } => {
+                let order_by = key_columns
+                    .iter()
+                    .map(|column| format!("[{}]", column))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                format!(
+                    "SELECT {} FROM {} ORDER BY {} OFFSET {} ROWS FETCH NEXT {} ROWS ONLY",
+                    columns,
+                    table.row_source(),
+                    order_by,
+                    index * chunk_size,
+                    chunk_size
+                )
+            }
+        }
+    }
+}
+
+// Number of key values from `min_key` to `max_key`, which can exceed the range of i64
+fn key_span(min_key: i64, max_key: i64) -> u128 {
+    (max_key as i128 - min_key as i128 + 1).max(1) as u128
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyset(min_key: i64, max_key: i64, total_rows: i64, chunk_size: usize) -> ChunkPlan {
+        ChunkPlan::keyset("Id".into(), min_key, max_key, total_rows, chunk_size)
+    }
+
+    // Key ranges of all chunks, which must follow each other without gaps or overlaps
+    fn key_ranges(plan: &ChunkPlan) -> Vec<(i64, i64)> {
+        let ranges = (0..plan.chunk_count())
+            .map(|index| plan.key_range(index).unwrap())
+            .collect::<Vec<_>>();
+
+        for pair in ranges.windows(2) {
+            assert_eq!(pair[0].1 as i128 + 1, pair[1].0 as i128, "{:?}", ranges);
+        }
+
+        ranges
+    }
+
+    #[test]
+    fn splits_dense_keys_into_chunks_of_chunk_size() {
+        let plan = keyset(1, 1000, 1000, 300);
+
+        assert_eq!(
+            key_ranges(&plan),
+            [(1, 300), (301, 600), (601, 900), (901, 1000)]
+        );
+        assert_eq!(plan.key_range(4), None);
+        assert_eq!(keyset(5, 5, 1, 300).chunk_count(), 1);
+    }
+
+    #[test]
+    fn widens_chunks_over_sparse_keys() {
+        // 1000 rows spread over a billion key values
+        let plan = keyset(1, 1_000_000_000, 1000, 100);
+
+        assert_eq!(plan.chunk_count(), 10);
+        assert_eq!(key_ranges(&plan).last().unwrap().1, 1_000_000_000);
+    }
+
+    #[test]
+    fn covers_the_whole_key_range_without_overflow() {
+        for (min_key, max_key) in [(i64::MIN, i64::MAX), (i64::MAX - 10, i64::MAX), (-1, 1)] {
+            let plan = keyset(min_key, max_key, 3, 1);
+            let ranges = key_ranges(&plan);
+
+            assert!(plan.chunk_count() <= MAX_CHUNKS);
+            assert_eq!(ranges.first().unwrap().0, min_key);
+            assert_eq!(ranges.last().unwrap().1, max_key);
+        }
+    }
+
+    #[test]
+    fn caps_the_chunk_count() {
+        assert_eq!(
+            keyset(1, 10_000_000, 10_000_000, 1).chunk_count(),
+            MAX_CHUNKS
+        );
+
+        let plan = ChunkPlan::offset(vec!["A".into(), "B".into()], 10_000_000, 1);
+
+        assert_eq!(plan.chunk_count(), MAX_CHUNKS);
+        assert_eq!(ChunkPlan::offset(vec!["A".into()], 0, 100).chunk_count(), 0);
+    }
+
+    #[test]
+    fn builds_chunk_queries() {
+        let table = SourceTable::new("dbo", "Orders");
+
+        assert_eq!(
+            keyset(1, 1000, 1000, 300).chunk_query(&table, "[Id]", 1),
+            "SELECT [Id] FROM [dbo].[Orders] WHERE [Id] >= 301 AND [Id] <= 600 ORDER BY [Id]"
+        );
+        assert_eq!(
+            ChunkPlan::offset(vec!["A".into(), "B".into()], 1000, 300).chunk_query(
+                &table,
+                "[A], [B]",
+                2
+            ),
+            "SELECT [A], [B] FROM [dbo].[Orders] ORDER BY [A], [B] OFFSET 600 ROWS FETCH NEXT 300 ROWS ONLY"
+        );
+    }
+}
@@ -1,28 +1,294 @@
-use anyhow::{anyhow, Result};
-use bb8::{Pool, PooledConnection};
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::{anyhow, bail, Context, Result};
+use bb8::{ManageConnection, Pool, PooledConnection};
 use bb8_tiberius::ConnectionManager;
 use futures::stream::{BoxStream, StreamExt};
 use futures::TryStreamExt;
 
+use crate::common::constraints::Constraint;
+use crate::common::permission::{PrincipalType, RoleMembership, TablePermission};
+use crate::common::pool_stats::{PoolUsage, WaitSnapshot, WaitStats};
 use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::config::{ExtractionConsistency, SparseColumnSets};
+use crate::extract::chunk::ChunkPlan;
 use crate::extract::format::format_row_values;
+use crate::extract::generator::Generator;
+use crate::extract::row_cache::RowCache;
+use crate::migrate::temporal::TemporalTable;
 
 #[derive(Clone)]
 pub struct DatabaseExtractor {
-    pub pool: Pool<ConnectionManager>,
+    source: Source,
+    waits: Arc<WaitStats>,
+    sparse_column_sets: SparseColumnSets,
+    consistency: ExtractionConsistency,
+    // Database snapshot rows are read from
+    snapshot: Option<String>,
+}
+
+#[derive(Clone)]
+enum Source {
+    Mssql(Pool<ConnectionManager>),
+    Generator(Arc<Generator>),
+}
+
+type SourceClient = <ConnectionManager as ManageConnection>::Connection;
+
+// Connection rows are streamed through, the generator needs none
+pub enum SourceConnection<'a> {
+    Pooled(Box<PooledConnection<'a, ConnectionManager>>),
+    // Reads at the isolation level or from the database snapshot of the run. It is opened apart from the pool and
+    // closed once dropped, so no pooled connection is left with either, whether the read completed or not.
+    Scoped(Box<SourceClient>),
+    Generator(Arc<Generator>),
+}
+
+impl SourceConnection<'_> {
+    fn client(&mut self) -> Result<&mut SourceClient> {
+        match self {
+            SourceConnection::Pooled(conn) => Ok(conn),
+            SourceConnection::Scoped(conn) => Ok(conn),
+            SourceConnection::Generator(_) => bail!("Not supported by the generator source"),
+        }
+    }
 }
 
 impl DatabaseExtractor {
     pub fn new(pool: Pool<ConnectionManager>) -> Self {
-        DatabaseExtractor { pool }
+        DatabaseExtractor {
+            source: Source::Mssql(pool),
+            waits: Arc::new(WaitStats::default()),
+            sparse_column_sets: SparseColumnSets::Skip,
+            consistency: ExtractionConsistency::ReadCommitted,
+            snapshot: None,
+        }
+    }
+
+    pub fn with_sparse_column_sets(mut self, sparse_column_sets: SparseColumnSets) -> Self {
+        self.sparse_column_sets = sparse_column_sets;
+        self
+    }
+
+    pub fn with_extraction_consistency(mut self, consistency: ExtractionConsistency) -> Self {
+        self.consistency = consistency;
+        self
+    }
+
+    // Reads the fake tables of the generator instead of a database
+    pub fn generated(generator: Generator) -> Self {
+        DatabaseExtractor {
+            source: Source::Generator(Arc::new(generator)),
+            waits: Arc::new(WaitStats::default()),
+            sparse_column_sets: SparseColumnSets::Skip,
+            consistency: ExtractionConsistency::ReadCommitted,
+            snapshot: None,
+        }
+    }
+
+    fn pool(&self) -> Result<&Pool<ConnectionManager>> {
+        match &self.source {
+            Source::Mssql(pool) => Ok(pool),
+            Source::Generator(_) => bail!("Not supported by the generator source"),
+        }
+    }
+
+    fn generator(&self) -> Option<&Generator> {
+        match &self.source {
+            Source::Mssql(_) => None,
+            Source::Generator(generator) => Some(generator),
+        }
+    }
+
+    // Connection for streaming rows, with the time spent waiting for it recorded
+    pub async fn connection(&self) -> Result<SourceConnection<'_>> {
+        let pool = match &self.source {
+            Source::Mssql(pool) => pool,
+            Source::Generator(generator) => {
+                return Ok(SourceConnection::Generator(Arc::clone(generator)))
+            }
+        };
+
+        let Some(setup) = self.read_setup() else {
+            let start = Instant::now();
+            let conn = pool.get().await?;
+            self.waits.record(start.elapsed());
+
+            return Ok(SourceConnection::Pooled(Box::new(conn)));
+        };
+
+        let start = Instant::now();
+        let mut conn = pool.dedicated_connection().await?;
+        self.waits.record(start.elapsed());
+
+        conn.simple_query(setup.as_str())
+            .await?
+            .into_results()
+            .await
+            .with_context(|| format!("Failed to prepare the read connection: {}", setup))?;
+
+        Ok(SourceConnection::Scoped(Box::new(conn)))
+    }
+
+    // Statements setting the isolation level and database rows are read with, if they differ from the pool's
+    fn read_setup(&self) -> Option<String> {
+        let isolation_level = match self.consistency {
+            ExtractionConsistency::ReadCommitted | ExtractionConsistency::DatabaseSnapshot => None,
+            ExtractionConsistency::NoLock => Some("READ UNCOMMITTED"),
+            ExtractionConsistency::Snapshot => Some("SNAPSHOT"),
+        };
+
+        let statements = isolation_level
+            .map(|isolation_level| format!("SET TRANSACTION ISOLATION LEVEL {}", isolation_level))
+            .into_iter()
+            .chain(
+                self.snapshot
+                    .as_ref()
+                    .map(|snapshot| format!("USE [{}]", snapshot)),
+            )
+            .collect::<Vec<_>>();
+
+        (!statements.is_empty()).then(|| statements.join("; "))
     }
 
-    pub async fn fetch_tables(&mut self) -> Result<Vec<String>> {
-        let mut conn = self.pool.get().await?;
+    // Prepares the reads of a run: checks that SNAPSHOT isolation is allowed, or creates the database snapshot all
+    // tables are read from until `end_consistent_reads`
+    pub async fn begin_consistent_reads(&mut self, run_id: &str) -> Result<()> {
+        if self.generator().is_some() {
+            return Ok(());
+        }
+
+        match self.consistency {
+            ExtractionConsistency::ReadCommitted | ExtractionConsistency::NoLock => Ok(()),
+            ExtractionConsistency::Snapshot => {
+                let mut conn = self.pool()?.get().await?;
+
+                let allowed = conn
+                    .simple_query(
+                        "SELECT CAST(snapshot_isolation_state AS INT) FROM sys.databases WHERE name = DB_NAME()",
+                    )
+                    .await?
+                    .into_row()
+                    .await?
+                    .and_then(|row| row.get::<i32, _>(0))
+                    == Some(1);
+
+                if !allowed {
+                    bail!(
+                        "SNAPSHOT isolation is not allowed by the source database, enable it with \
+                         ALTER DATABASE ... SET ALLOW_SNAPSHOT_ISOLATION ON"
+                    );
+                }
+
+                Ok(())
+            }
+            ExtractionConsistency::DatabaseSnapshot => {
+                let database = self.fetch_database_name().await?;
+                let snapshot = format!("{}_snapshot_{}", database, run_id);
+
+                let mut conn = self.pool()?.get().await?;
+
+                // A sparse file is needed next to every data file of the database
+                let files = conn
+                    .simple_query(
+                        "SELECT name, physical_name FROM sys.database_files WHERE type = 0",
+                    )
+                    .await?
+                    .into_first_result()
+                    .await?
+                    .into_iter()
+                    .filter_map(|row| {
+                        let name = row.get::<&str, _>(0)?;
+                        let path = row.get::<&str, _>(1)?;
+
+                        Some(format!(
+                            "(NAME = [{}], FILENAME = '{}')",
+                            name,
+                            format!("{}_{}.ss", path, run_id).replace('\'', "''")
+                        ))
+                    })
+                    .collect::<Vec<_>>();
+
+                conn.simple_query(format!(
+                    "CREATE DATABASE [{}] ON {} AS SNAPSHOT OF [{}]",
+                    snapshot,
+                    files.join(", "),
+                    database
+                ))
+                .await?
+                .into_results()
+                .await
+                .with_context(|| format!("Failed to create database snapshot {}", snapshot))?;
+
+                drop(conn);
+
+                info!("Reading from database snapshot {}", snapshot);
+                self.snapshot = Some(snapshot);
+
+                Ok(())
+            }
+        }
+    }
+
+    // Drops the database snapshot of the run, if any
+    pub async fn end_consistent_reads(&mut self) -> Result<()> {
+        let Some(snapshot) = self.snapshot.take() else {
+            return Ok(());
+        };
+
+        let mut conn = self.pool()?.get().await?;
+
+        conn.simple_query(format!("DROP DATABASE [{}]", snapshot))
+            .await?
+            .into_results()
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to drop database snapshot {}, drop it manually",
+                    snapshot
+                )
+            })?;
+
+        Ok(())
+    }
+
+    pub fn pool_usage(&self) -> PoolUsage {
+        let Source::Mssql(pool) = &self.source else {
+            return PoolUsage { in_use: 0, idle: 0 };
+        };
+
+        let state = pool.state();
+
+        PoolUsage {
+            in_use: state.connections - state.idle_connections,
+            idle: state.idle_connections,
+        }
+    }
+
+    pub fn wait_stats(&self) -> WaitSnapshot {
+        self.waits.snapshot()
+    }
+
+    // Connections held by the pool, which opens all of them up front, the generator serves any number of tasks
+    pub fn pool_size(&self) -> u32 {
+        match &self.source {
+            Source::Mssql(pool) => pool.state().connections,
+            Source::Generator(_) => u32::MAX,
+        }
+    }
+
+    pub async fn fetch_tables(&mut self) -> Result<Vec<SourceTable>> {
+        if let Some(generator) = self.generator() {
+            return Ok(generator.tables());
+        }
+
+        let mut conn = self.pool()?.get().await?;
 
         let rows = conn
             .simple_query(
-                "SELECT TABLE_NAME FROM INFORMATION_SCHEMA.TABLES WHERE TABLE_TYPE = 'BASE TABLE'",
+                "SELECT TABLE_SCHEMA, TABLE_NAME FROM INFORMATION_SCHEMA.TABLES WHERE TABLE_TYPE = 'BASE TABLE'",
             )
             .await?
             .into_results()
@@ -32,20 +298,191 @@ impl DatabaseExtractor {
             .iter()
             .flatten()
             .map(|row| {
-                let table_name: Option<&str> = row.get(0);
-                match table_name {
-                    Some(name) => Ok(name.to_owned()),
-                    None => Err(anyhow!("Failed to retrieve table name")),
+                let schema_name: Option<&str> = row.get(0);
+                let table_name: Option<&str> = row.get(1);
+                match (schema_name, table_name) {
+                    (Some(schema), Some(name)) => Ok(SourceTable::new(schema, name)),
+                    _ => Err(anyhow!("Failed to retrieve table name")),
                 }
             })
-            .collect::<Result<Vec<String>, _>>()?;
+            .collect::<Result<Vec<SourceTable>, _>>()?;
 
         Ok(tables)
     }
 
-    pub async fn get_table_schema(&mut self, table: &str) -> Result<Vec<ColumnSchema>> {
-        let mut conn = self.pool.get().await?;
+    // System-versioned temporal tables with their history tables, none on servers before SQL Server 2016
+    pub async fn fetch_temporal_tables(&mut self) -> Result<Vec<TemporalTable>> {
+        if self.generator().is_some() {
+            return Ok(Vec::new());
+        }
 
+        let mut conn = self.pool()?.get().await?;
+
+        let rows = conn
+            .simple_query(
+                "IF COL_LENGTH('sys.tables', 'temporal_type') IS NOT NULL
+                EXEC('SELECT SCHEMA_NAME(t.schema_id), t.name, SCHEMA_NAME(h.schema_id), h.name, c.name, c.is_hidden
+                FROM sys.tables t
+                INNER JOIN sys.tables h ON h.object_id = t.history_table_id
+                INNER JOIN sys.periods p ON p.object_id = t.object_id
+                INNER JOIN sys.columns c ON c.object_id = t.object_id AND c.column_id = p.start_column_id
+                WHERE t.temporal_type = 2')",
+            )
+            .await?
+            .into_first_result()
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                match (
+                    row.get::<&str, _>(0),
+                    row.get::<&str, _>(1),
+                    row.get::<&str, _>(2),
+                    row.get::<&str, _>(3),
+                    row.get::<&str, _>(4),
+                    row.get::<bool, _>(5),
+                ) {
+                    (
+                        Some(schema),
+                        Some(name),
+                        Some(history_schema),
+                        Some(history_name),
+                        Some(period_start),
+                        Some(period_hidden),
+                    ) => Ok(TemporalTable {
+                        table: SourceTable::new(schema, name),
+                        history_table: SourceTable::new(history_schema, history_name),
+                        period_start: period_start.to_string(),
+                        period_hidden,
+                    }),
+                    _ => Err(anyhow!("Failed to retrieve temporal table")),
+                }
+            })
+            .collect()
+    }
+
+    // Pairs of tables with the tables their foreign keys reference
+    pub async fn fetch_foreign_key_references(
+        &mut self,
+    ) -> Result<Vec<(SourceTable, SourceTable)>> {
+        if self.generator().is_some() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.pool()?.get().await?;
+
+        let rows = conn
+            .simple_query(
+                "SELECT DISTINCT fk.TABLE_SCHEMA, fk.TABLE_NAME, pk.TABLE_SCHEMA, pk.TABLE_NAME
+                FROM INFORMATION_SCHEMA.REFERENTIAL_CONSTRAINTS rc
+                INNER JOIN INFORMATION_SCHEMA.TABLE_CONSTRAINTS fk
+                    ON rc.CONSTRAINT_SCHEMA = fk.CONSTRAINT_SCHEMA AND rc.CONSTRAINT_NAME = fk.CONSTRAINT_NAME
+                INNER JOIN INFORMATION_SCHEMA.TABLE_CONSTRAINTS pk
+                    ON rc.UNIQUE_CONSTRAINT_SCHEMA = pk.CONSTRAINT_SCHEMA AND rc.UNIQUE_CONSTRAINT_NAME = pk.CONSTRAINT_NAME",
+            )
+            .await?
+            .into_first_result()
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                match (
+                    row.get::<&str, _>(0),
+                    row.get::<&str, _>(1),
+                    row.get::<&str, _>(2),
+                    row.get::<&str, _>(3),
+                ) {
+                    (Some(schema), Some(name), Some(referenced_schema), Some(referenced_name)) => {
+                        Ok((
+                            SourceTable::new(schema, name),
+                            SourceTable::new(referenced_schema, referenced_name),
+                        ))
+                    }
+                    _ => Err(anyhow!("Failed to retrieve foreign key reference")),
+                }
+            })
+            .collect()
+    }
+
+    pub async fn fetch_table_permissions(&mut self) -> Result<Vec<TablePermission>> {
+        let mut conn = self.pool()?.get().await?;
+
+        let rows = conn
+            .simple_query(
+                "SELECT s.name, o.name, pr.name, pr.type_desc, p.permission_name, p.state_desc
+                FROM sys.database_permissions p
+                INNER JOIN sys.objects o ON p.major_id = o.object_id
+                INNER JOIN sys.schemas s ON o.schema_id = s.schema_id
+                INNER JOIN sys.database_principals pr ON p.grantee_principal_id = pr.principal_id
+                WHERE p.class = 1 AND p.minor_id = 0 AND o.type = 'U'
+                ORDER BY s.name, o.name, pr.name, p.permission_name",
+            )
+            .await?
+            .into_first_result()
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                let value = |index: usize| {
+                    row.get::<&str, _>(index)
+                        .map(|value| value.to_string())
+                        .ok_or_else(|| anyhow!("Failed to retrieve table permission"))
+                };
+
+                Ok(TablePermission {
+                    table: SourceTable::new(&value(0)?, &value(1)?),
+                    principal: value(2)?,
+                    principal_type: PrincipalType::from_type_desc(&value(3)?),
+                    permission: value(4)?,
+                    state: value(5)?,
+                })
+            })
+            .collect()
+    }
+
+    pub async fn fetch_role_memberships(&mut self) -> Result<Vec<RoleMembership>> {
+        let mut conn = self.pool()?.get().await?;
+
+        let rows = conn
+            .simple_query(
+                "SELECT r.name, m.name
+                FROM sys.database_role_members rm
+                INNER JOIN sys.database_principals r ON rm.role_principal_id = r.principal_id
+                INNER JOIN sys.database_principals m ON rm.member_principal_id = m.principal_id
+                WHERE m.name <> 'dbo'
+                ORDER BY r.name, m.name",
+            )
+            .await?
+            .into_first_result()
+            .await?;
+
+        rows.iter()
+            .map(|row| match (row.get::<&str, _>(0), row.get::<&str, _>(1)) {
+                (Some(role), Some(member)) => Ok(RoleMembership {
+                    role: role.to_string(),
+                    member: member.to_string(),
+                }),
+                _ => Err(anyhow!("Failed to retrieve role membership")),
+            })
+            .collect()
+    }
+
+    pub async fn get_table_schema(&mut self, table: &SourceTable) -> Result<Vec<ColumnSchema>> {
+        if let Some(generator) = self.generator() {
+            if !generator.contains(table) {
+                bail!("Table {} is not generated", table);
+            }
+
+            return Ok(generator.table_schema(table));
+        }
+
+        let mut conn = self.pool()?.get().await?;
+
+        // Hidden columns are left out, like `SELECT *` does, and column sets unless they are materialized
+        let column_set_filter = match self.sparse_column_sets {
+            SparseColumnSets::Skip => "AND ISNULL(COLUMNPROPERTY(OBJECT_ID(QUOTENAME(c.TABLE_SCHEMA) + '.' + QUOTENAME(c.TABLE_NAME)), c.COLUMN_NAME, 'IsColumnSet'), 0) = 0",
+            SparseColumnSets::Materialize => "",
+        };
         let query = format !(
             "SELECT 
                 c.COLUMN_NAME,
@@ -53,47 +490,608 @@ impl DatabaseExtractor {
                 c.CHARACTER_MAXIMUM_LENGTH,
                 c.NUMERIC_PRECISION,
                 c.NUMERIC_SCALE,
+                c.DATETIME_PRECISION,
                 c.IS_NULLABLE,
+                c.COLLATION_NAME,
                 (
-                    SELECT CASE 
+                    SELECT scc.definition
+                    FROM sys.computed_columns scc
+                    WHERE scc.object_id = OBJECT_ID(QUOTENAME(c.TABLE_SCHEMA) + '.' + QUOTENAME(c.TABLE_NAME)) AND scc.name = c.COLUMN_NAME
+                ) AS COMPUTED_DEFINITION,
+                con.CONSTRAINTS,
+                con.CONSTRAINT_NAME
+            FROM 
+                INFORMATION_SCHEMA.COLUMNS c
+            -- A column in several constraints keeps one, its primary key before a foreign key, unique or CHECK
+            -- constraint, along with the name of that constraint
+            OUTER APPLY (
+                    SELECT TOP 1 CASE 
                         WHEN tc.CONSTRAINT_TYPE = 'PRIMARY KEY' THEN 'PRIMARY KEY'
-                        WHEN tc.CONSTRAINT_TYPE = 'FOREIGN KEY' THEN 'FOREIGN KEY,' + rcf.TABLE_NAME + ',' + rcf.COLUMN_NAME   
+                        WHEN tc.CONSTRAINT_TYPE = 'FOREIGN KEY' THEN 'FOREIGN KEY,' + rcf.TABLE_SCHEMA + '.' + rcf.TABLE_NAME + ',' + rcf.COLUMN_NAME + ',' + rc.DELETE_RULE + ',' + rc.UPDATE_RULE
                         WHEN tc.CONSTRAINT_TYPE = 'UNIQUE' THEN 'UNIQUE'
                         WHEN cc.CHECK_CLAUSE IS NOT NULL THEN 'CHECK (' + cc.CHECK_CLAUSE + ')'
                         WHEN c.COLUMN_DEFAULT IS NOT NULL THEN 'DEFAULT ' + c.COLUMN_DEFAULT
                         ELSE ''
-                    END
+                    END AS CONSTRAINTS,
+                    ccu.CONSTRAINT_NAME
                     FROM INFORMATION_SCHEMA.CONSTRAINT_COLUMN_USAGE ccu 
                     LEFT JOIN INFORMATION_SCHEMA.TABLE_CONSTRAINTS tc ON ccu.CONSTRAINT_CATALOG = tc.CONSTRAINT_CATALOG AND ccu.CONSTRAINT_SCHEMA = tc.CONSTRAINT_SCHEMA AND ccu.CONSTRAINT_NAME = tc.CONSTRAINT_NAME
                     LEFT JOIN INFORMATION_SCHEMA.CHECK_CONSTRAINTS cc ON tc.CONSTRAINT_CATALOG = cc.CONSTRAINT_CATALOG AND tc.CONSTRAINT_SCHEMA = cc.CONSTRAINT_SCHEMA AND tc.CONSTRAINT_NAME = cc.CONSTRAINT_NAME
                     LEFT JOIN INFORMATION_SCHEMA.REFERENTIAL_CONSTRAINTS rc ON tc.CONSTRAINT_CATALOG = rc.CONSTRAINT_CATALOG AND tc.CONSTRAINT_SCHEMA = rc.CONSTRAINT_SCHEMA AND tc.CONSTRAINT_NAME = rc.CONSTRAINT_NAME
                     LEFT JOIN INFORMATION_SCHEMA.CONSTRAINT_COLUMN_USAGE ccu_ref ON rc.UNIQUE_CONSTRAINT_CATALOG = ccu_ref.CONSTRAINT_CATALOG AND rc.UNIQUE_CONSTRAINT_SCHEMA = ccu_ref.CONSTRAINT_SCHEMA AND rc.UNIQUE_CONSTRAINT_NAME = ccu_ref.CONSTRAINT_NAME
                     LEFT JOIN INFORMATION_SCHEMA.COLUMNS rcf ON ccu_ref.TABLE_CATALOG = rcf.TABLE_CATALOG AND ccu_ref.TABLE_SCHEMA = rcf.TABLE_SCHEMA AND ccu_ref.TABLE_NAME = rcf.TABLE_NAME AND ccu_ref.COLUMN_NAME = rcf.COLUMN_NAME
-                    WHERE ccu.TABLE_NAME = c.TABLE_NAME AND ccu.COLUMN_NAME = c.COLUMN_NAME
-                ) AS CONSTRAINTS
-            FROM 
-                INFORMATION_SCHEMA.COLUMNS c       
-            WHERE c.TABLE_NAME = '{}';",
-            table
+                    WHERE ccu.TABLE_SCHEMA = c.TABLE_SCHEMA AND ccu.TABLE_NAME = c.TABLE_NAME AND ccu.COLUMN_NAME = c.COLUMN_NAME
+                    ORDER BY CASE tc.CONSTRAINT_TYPE
+                        WHEN 'PRIMARY KEY' THEN 1
+                        WHEN 'FOREIGN KEY' THEN 2
+                        WHEN 'UNIQUE' THEN 3
+                        ELSE 4
+                    END, ccu.CONSTRAINT_NAME
+            ) con
+            WHERE c.TABLE_SCHEMA = '{}' AND c.TABLE_NAME = '{}'
+                AND ISNULL(COLUMNPROPERTY(OBJECT_ID(QUOTENAME(c.TABLE_SCHEMA) + '.' + QUOTENAME(c.TABLE_NAME)), c.COLUMN_NAME, 'IsHidden'), 0) = 0
+                {}
+            ORDER BY c.ORDINAL_POSITION;",
+            table.schema, table.name, column_set_filter
         );
 
         let rows = conn.simple_query(query).await?.into_first_result().await?;
 
-        let schema = rows
+        let mut schema = rows
             .into_iter()
             .map(|r| ColumnSchema::from_row(&r))
             .collect::<Result<Vec<_>, _>>()
             .unwrap();
 
+        // The versions of a row in a flattened temporal table are told apart by their period start
+        if let Some(period_start) = &table.period_start {
+            for column in schema
+                .iter_mut()
+                .filter(|column| &column.column_name == period_start)
+            {
+                column.constraints = Some(Constraint::PrimaryKey);
+            }
+        }
+
         Ok(schema)
     }
+
+    pub async fn plan_chunks(
+        &mut self,
+        table: &SourceTable,
+        schema: &[ColumnSchema],
+        chunk_size: usize,
+    ) -> Result<Option<ChunkPlan>> {
+        let key_columns: Vec<&ColumnSchema> = schema
+            .iter()
+            .filter(|column| column.constraints == Some(Constraint::PrimaryKey))
+            .collect();
+
+        if key_columns.is_empty() {
+            return Ok(None);
+        }
+
+        // Generated keys run from 1 to the number of rows
+        if let (Some(generator), [key_column]) = (self.generator(), key_columns.as_slice()) {
+            return Ok((generator.rows() > 0).then(|| {
+                ChunkPlan::keyset(
+                    key_column.column_name.clone(),
+                    1,
+                    generator.rows(),
+                    generator.rows(),
+                    chunk_size,
+                )
+            }));
+        }
+
+        // Bounds are read like the rows, so they cover the same snapshot
+        let mut conn = self.connection().await?;
+        let client = conn.client()?;
+
+        if let [key_column] = key_columns.as_slice() {
+            if ChunkPlan::supports_keyset(&key_column.data_type) {
+                let query = format!(
+                    "SELECT CAST(MIN([{}]) AS BIGINT), CAST(MAX([{}]) AS BIGINT), COUNT_BIG(*) FROM {}",
+                    key_column.column_name,
+                    key_column.column_name,
+                    table.row_source()
+                );
+
+                let row = client
+                    .simple_query(query)
+                    .await?
+                    .into_row()
+                    .await?
+                    .ok_or_else(|| anyhow!("Failed to retrieve key bounds"))?;
+
+                let plan = match (row.get::<i64, _>(0), row.get::<i64, _>(1)) {
+                    (Some(min_key), Some(max_key)) => Some(ChunkPlan::keyset(
+                        key_column.column_name.clone(),
+                        min_key,
+                        max_key,
+                        row.get::<i64, _>(2).unwrap_or_default(),
+                        chunk_size,
+                    )),
+                    _ => None,
+                };
+
+                return Ok(plan);
+            }
+        }
+
+        let query = format!("SELECT COUNT_BIG(*) FROM {}", table.row_source());
+
+        let total_rows = client
+            .simple_query(query)
+            .await?
+            .into_row()
+            .await?
+            .and_then(|row| row.get::<i64, _>(0))
+            .ok_or_else(|| anyhow!("Failed to retrieve row count"))?;
+
+        Ok(Some(ChunkPlan::offset(
+            key_columns
+                .iter()
+                .map(|column| column.column_name.clone())
+                .collect(),
+            total_rows,
+            chunk_size,
+        )))
+    }
+
+    // Approximate row counts of all tables taken from the partition statistics, without scanning the tables
+    pub async fn fetch_table_row_counts(&mut self) -> Result<Vec<(SourceTable, i64)>> {
+        if let Some(generator) = self.generator() {
+            return Ok(generator
+                .tables()
+                .into_iter()
+                .map(|table| (table, generator.rows()))
+                .collect());
+        }
+
+        let mut conn = self.pool()?.get().await?;
+
+        let rows = conn
+            .simple_query(
+                "SELECT s.name, t.name, SUM(p.rows)
+                FROM sys.tables t
+                JOIN sys.schemas s ON s.schema_id = t.schema_id
+                JOIN sys.partitions p ON p.object_id = t.object_id AND p.index_id IN (0, 1)
+                GROUP BY s.name, t.name",
+            )
+            .await?
+            .into_first_result()
+            .await?;
+
+        rows.iter()
+            .map(|row| match (row.get::<&str, _>(0), row.get::<&str, _>(1)) {
+                (Some(schema), Some(name)) => Ok((
+                    SourceTable::new(schema, name),
+                    row.get::<i64, _>(2).unwrap_or_default(),
+                )),
+                _ => Err(anyhow!("Failed to retrieve table row count")),
+            })
+            .collect()
+    }
+
+    // Row counts and data sizes in bytes of all tables, from the pages used by their heap or clustered index
+    pub async fn fetch_table_sizes(&mut self) -> Result<Vec<(SourceTable, i64, i64)>> {
+        if let Some(generator) = self.generator() {
+            // Generated rows take no space in the source
+            return Ok(generator
+                .tables()
+                .into_iter()
+                .map(|table| (table, generator.rows(), 0))
+                .collect());
+        }
+
+        let mut conn = self.pool()?.get().await?;
+
+        let rows = conn
+            .simple_query(
+                "SELECT s.name, t.name, SUM(ps.row_count), SUM(ps.used_page_count) * 8192
+                FROM sys.tables t
+                JOIN sys.schemas s ON s.schema_id = t.schema_id
+                JOIN sys.dm_db_partition_stats ps ON ps.object_id = t.object_id AND ps.index_id IN (0, 1)
+                GROUP BY s.name, t.name",
+            )
+            .await?
+            .into_first_result()
+            .await?;
+
+        rows.iter()
+            .map(|row| match (row.get::<&str, _>(0), row.get::<&str, _>(1)) {
+                (Some(schema), Some(name)) => Ok((
+                    SourceTable::new(schema, name),
+                    row.get::<i64, _>(2).unwrap_or_default(),
+                    row.get::<i64, _>(3).unwrap_or_default(),
+                )),
+                _ => Err(anyhow!("Failed to retrieve table size")),
+            })
+            .collect()
+    }
+
+    pub async fn table_rows_count(&mut self, table: &SourceTable) -> Result<i64> {
+        if let Some(generator) = self.generator() {
+            return Ok(generator.rows());
+        }
+
+        let mut conn = self.connection().await?;
+
+        let query = format!("SELECT COUNT_BIG(*) FROM {}", table.row_source());
+
+        let count = conn
+            .client()?
+            .simple_query(query)
+            .await?
+            .into_row()
+            .await?
+            .and_then(|row| row.get::<i64, _>(0))
+            .ok_or_else(|| anyhow!("Failed to retrieve row count"))?;
+
+        Ok(count)
+    }
+
+    // Minimum and maximum of an integer expression over the table, `None` for an empty table
+    pub async fn fetch_bounds(
+        &mut self,
+        table: &SourceTable,
+        expression: &str,
+    ) -> Result<Option<(i64, i64)>> {
+        let mut conn = self.connection().await?;
+
+        let query = format!(
+            "SELECT CAST(MIN({}) AS BIGINT), CAST(MAX({}) AS BIGINT) FROM {}",
+            expression,
+            expression,
+            table.row_source()
+        );
+
+        let row = conn
+            .client()?
+            .simple_query(query)
+            .await?
+            .into_row()
+            .await?
+            .ok_or_else(|| anyhow!("Failed to retrieve bounds"))?;
+
+        let bounds = match (row.get::<i64, _>(0), row.get::<i64, _>(1)) {
+            (Some(min), Some(max)) => Some((min, max)),
+            _ => None,
+        };
+
+        Ok(bounds)
+    }
+
+    // Change tracking version of the table when it is tracked, and the highest committed rowversion of the database
+    pub async fn fetch_sync_versions(
+        &mut self,
+        table: &SourceTable,
+    ) -> Result<(Option<i64>, Option<i64>)> {
+        let mut conn = self.pool()?.get().await?;
+
+        let query = format!(
+            "SELECT
+                CASE WHEN EXISTS (SELECT 1 FROM sys.change_tracking_tables WHERE object_id = OBJECT_ID('{}'))
+                    THEN CHANGE_TRACKING_CURRENT_VERSION() END,
+                CAST(MIN_ACTIVE_ROWVERSION() AS BIGINT) - 1",
+            table.quoted()
+        );
+
+        let row = conn
+            .simple_query(query)
+            .await?
+            .into_row()
+            .await?
+            .ok_or_else(|| anyhow!("Failed to retrieve sync versions"))?;
+
+        Ok((row.get::<i64, _>(0), row.get::<i64, _>(1)))
+    }
+
+    // Oldest version change tracking still has the changes of, older versions require a full migration
+    pub async fn fetch_change_tracking_min_version(&mut self, table: &SourceTable) -> Result<i64> {
+        let mut conn = self.pool()?.get().await?;
+
+        let query = format!(
+            "SELECT CHANGE_TRACKING_MIN_VALID_VERSION(OBJECT_ID('{}'))",
+            table.quoted()
+        );
+
+        let version = conn
+            .simple_query(query)
+            .await?
+            .into_row()
+            .await?
+            .and_then(|row| row.get::<i64, _>(0))
+            .ok_or_else(|| anyhow!("Change tracking is not enabled for table {}", table))?;
+
+        Ok(version)
+    }
+
+    pub async fn fetch_database_name(&mut self) -> Result<String> {
+        if self.generator().is_some() {
+            return Ok("generator".to_string());
+        }
+
+        let mut conn = self.pool()?.get().await?;
+
+        let name = conn
+            .simple_query("SELECT DB_NAME()")
+            .await?
+            .into_row()
+            .await?
+            .and_then(|row| row.get::<&str, _>(0).map(|name| name.to_string()))
+            .ok_or_else(|| anyhow!("Failed to retrieve database name"))?;
+
+        Ok(name)
+    }
+
+    // Server and database, telling apart the sources that sync into the same target
+    pub async fn fetch_source_name(&mut self) -> Result<String> {
+        if self.generator().is_some() {
+            return Ok("generator".to_string());
+        }
+
+        let mut conn = self.pool()?.get().await?;
+
+        let name = conn
+            .simple_query("SELECT CONCAT(@@SERVERNAME, '/', DB_NAME())")
+            .await?
+            .into_row()
+            .await?
+            .and_then(|row| row.get::<&str, _>(0).map(|name| name.to_string()))
+            .ok_or_else(|| anyhow!("Failed to retrieve source name"))?;
+
+        Ok(name)
+    }
+
+    // Logical CPUs of the source server, requires the VIEW SERVER STATE permission
+    pub async fn fetch_cpu_count(&mut self) -> Result<usize> {
+        let mut conn = self.pool()?.get().await?;
+
+        let cpu_count = conn
+            .simple_query("SELECT cpu_count FROM sys.dm_os_sys_info")
+            .await?
+            .into_row()
+            .await?
+            .and_then(|row| row.get::<i32, _>(0))
+            .ok_or_else(|| anyhow!("Failed to retrieve CPU count"))?;
+
+        Ok(cpu_count.max(1) as usize)
+    }
+
+    // Whether the query returns at least one row
+    pub async fn query_returns_rows(&mut self, query: &str) -> Result<bool> {
+        let mut conn = self.pool()?.get().await?;
+
+        let query = format!(
+            "SELECT CASE WHEN EXISTS ({}) THEN 1 ELSE 0 END",
+            query.trim().trim_end_matches(';')
+        );
+
+        let exists = conn
+            .simple_query(query)
+            .await?
+            .into_row()
+            .await?
+            .and_then(|row| row.get::<i32, _>(0))
+            .ok_or_else(|| anyhow!("Failed to evaluate query"))?;
+
+        Ok(exists == 1)
+    }
+
+    // Highest lag of the availability group replicas of the database, `None` outside of availability groups
+    pub async fn fetch_replica_lag(&mut self) -> Result<Option<i64>> {
+        let mut conn = self.pool()?.get().await?;
+
+        let lag = conn
+            .simple_query(
+                "SELECT CAST(MAX(secondary_lag_seconds) AS BIGINT)
+                FROM sys.dm_hadr_database_replica_states
+                WHERE database_id = DB_ID()",
+            )
+            .await?
+            .into_row()
+            .await?
+            .and_then(|row| row.get::<i64, _>(0));
+
+        Ok(lag)
+    }
+
+    // Rows of a query over the table data, read like the rows that are migrated
+    pub async fn fetch_formatted_rows(&mut self, query: String) -> Result<Vec<Vec<String>>> {
+        let mut conn = self.connection().await?;
+
+        let rows = open_query_stream(conn.client()?, query)
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        Ok(rows)
+    }
+
+    // Cheap fingerprint of the table contents, used to tell whether cached rows are still current
+    pub async fn table_watermark(
+        &mut self,
+        table: &SourceTable,
+        schema: &[ColumnSchema],
+    ) -> Result<String> {
+        let mut conn = self.connection().await?;
+
+        let version_column = schema
+            .iter()
+            .find(|column| matches!(column.data_type.as_str(), "timestamp" | "rowversion"));
+
+        let query = match version_column {
+            Some(column) => format!(
+                "SELECT COUNT_BIG(*), CAST(MAX([{}]) AS BIGINT) FROM {}",
+                column.column_name,
+                table.row_source()
+            ),
+            None => format!(
+                "SELECT COUNT_BIG(*), CAST(CHECKSUM_AGG(BINARY_CHECKSUM(*)) AS BIGINT) FROM {}",
+                table.row_source()
+            ),
+        };
+
+        let row = conn
+            .client()?
+            .simple_query(query)
+            .await?
+            .into_row()
+            .await?
+            .ok_or_else(|| anyhow!("Failed to retrieve table watermark"))?;
+
+        Ok(format!(
+            "{}:{}",
+            row.get::<i64, _>(0).unwrap_or_default(),
+            row.get::<i64, _>(1).unwrap_or_default()
+        ))
+    }
+}
+
+// Columns of the rows read from a table, listed since `SELECT *` returns the column set of a table with sparse columns
+// in their place. Values tiberius can't read are converted: spatial values to MySQL's own geometry format, the SRID as
+// 4 little endian bytes followed by the WKB, or WKT for text columns, hierarchyid values to their `/1/2/` path, or
+// their bytes for binary columns, and sql_variant values to text.
+pub fn select_list(
+    source_schema: &[ColumnSchema],
+    mapped_schema: &[ColumnSchema],
+    qualifier: &str,
+) -> String {
+    if source_schema.is_empty() {
+        return format!("{}*", qualifier);
+    }
+
+    source_schema
+        .iter()
+        .zip(mapped_schema)
+        .map(|(source, mapped)| {
+            let column = format!("{}[{}]", qualifier, source.column_name);
+            let alias = format!("[{}]", source.column_name);
+            let target_type = mapped.data_type.to_lowercase();
+            let text_target = target_type.contains("char") || target_type.ends_with("text");
+
+            match source.data_type.as_str() {
+                "geometry" | "geography" if text_target => {
+                    format!("{}.STAsText() AS {}", column, alias)
+                }
+                "geometry" | "geography" => {
+                    format!("{} AS {}", mysql_geometry_expression(&column), alias)
+                }
+                "hierarchyid" if text_target => format!("{}.ToString() AS {}", column, alias),
+                "hierarchyid" => format!("CAST({} AS VARBINARY(892)) AS {}", column, alias),
+                "sql_variant" => format!("{} AS {}", variant_text_expression(&column), alias),
+                _ => column,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Spatial value in the format MySQL stores geometries in, which MySQL takes as the value of a GEOMETRY column
+pub fn mysql_geometry_expression(column: &str) -> String {
+    let srid = format!("CAST({}.STSrid AS BINARY(4))", column);
+
+    format!(
+        "SUBSTRING({0}, 4, 1) + SUBSTRING({0}, 3, 1) + SUBSTRING({0}, 2, 1) + SUBSTRING({0}, 1, 1) + {1}.STAsBinary()",
+        srid, column
+    )
+}
+
+// sql_variant value as text, dates in ISO format and binary values in hex like the values of those types
+pub fn variant_text_expression(column: &str) -> String {
+    format!(
+        "CASE WHEN CAST(SQL_VARIANT_PROPERTY({0}, 'BaseType') AS NVARCHAR(128)) IN \
+         ('date', 'datetime', 'datetime2', 'smalldatetime', 'datetimeoffset', 'time') \
+         THEN CONVERT(NVARCHAR(4000), {0}, 121) \
+         WHEN CAST(SQL_VARIANT_PROPERTY({0}, 'BaseType') AS NVARCHAR(128)) IN ('binary', 'varbinary') \
+         THEN CONVERT(NVARCHAR(4000), {0}, 1) \
+         ELSE CONVERT(NVARCHAR(4000), {0}) END",
+        column
+    )
 }
 
 pub async fn open_row_stream<'a>(
-    conn: &'a mut PooledConnection<'_, ConnectionManager>,
-    table: &'a str,
+    conn: &'a mut SourceConnection<'_>,
+    table: &'a SourceTable,
+    columns: &str,
+) -> Result<BoxStream<'a, Result<Vec<String>, tiberius::error::Error>>> {
+    if let SourceConnection::Generator(generator) = conn {
+        return Ok(generator.row_stream(table, 1, generator.rows()));
+    }
+
+    let query = format!("SELECT {} FROM {}", columns, table.row_source());
+    open_query_stream(conn.client()?, query).await
+}
+
+// Values of an integer column in ascending order
+pub async fn open_key_stream<'a>(
+    conn: &'a mut SourceConnection<'_>,
+    table: &'a SourceTable,
+    column: &str,
+) -> Result<BoxStream<'a, Result<Vec<String>, tiberius::error::Error>>> {
+    if let SourceConnection::Generator(generator) = conn {
+        return Ok(generator.key_stream());
+    }
+
+    let query = format!(
+        "SELECT CAST([{}] AS BIGINT) FROM {} ORDER BY [{}]",
+        column,
+        table.row_source(),
+        column
+    );
+    open_query_stream(conn.client()?, query).await
+}
+
+pub async fn open_chunk_stream<'a>(
+    conn: &'a mut SourceConnection<'_>,
+    table: &'a SourceTable,
+    columns: &str,
+    plan: &ChunkPlan,
+    index: usize,
+) -> Result<BoxStream<'a, Result<Vec<String>, tiberius::error::Error>>> {
+    if let SourceConnection::Generator(generator) = conn {
+        let (lower, upper) = plan
+            .key_range(index)
+            .ok_or_else(|| anyhow!("Generated tables are only read in key ranges"))?;
+
+        return Ok(generator.row_stream(table, lower, upper));
+    }
+
+    let query = plan.chunk_query(table, columns, index);
+    open_query_stream(conn.client()?, query).await
+}
+
+// Opens the whole table when there is no plan, serving and filling the row cache when one is given
+pub async fn open_source_stream<'a>(
+    conn: &'a mut SourceConnection<'_>,
+    table: &'a SourceTable,
+    columns: &str,
+    plan: Option<&ChunkPlan>,
+    index: usize,
+    cache: Option<&RowCache>,
+) -> Result<BoxStream<'a, Result<Vec<String>, tiberius::error::Error>>> {
+    if let Some(rows) = cache.map(|cache| cache.read(index)).transpose()?.flatten() {
+        debug!("Reading table {} chunk {} from row cache", table, index);
+        return Ok(rows);
+    }
+
+    let rows = match plan {
+        Some(plan) => open_chunk_stream(conn, table, columns, plan, index).await?,
+        None => open_row_stream(conn, table, columns).await?,
+    };
+
+    match cache {
+        Some(cache) => cache.record(index, rows),
+        None => Ok(rows),
+    }
+}
+
+async fn open_query_stream<'a>(
+    conn: &'a mut SourceClient,
+    query: String,
 ) -> Result<BoxStream<'a, Result<Vec<String>, tiberius::error::Error>>> {
-    let query = format!("SELECT * FROM [{}]", table);
     let stream = conn
         .simple_query(query)
         .await?
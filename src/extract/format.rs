@@ -1,19 +1,23 @@
 use chrono::DateTime as ChronosDateTime;
-use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
 use hex::encode;
 use tiberius::numeric::Numeric;
 use tiberius::time::{Date, DateTime, DateTime2, DateTimeOffset, SmallDateTime, Time};
 use tiberius::{ColumnData, Row};
 
+// MySQL keeps fractional seconds down to microseconds
+const MAX_FRACTION_DIGITS: u8 = 6;
+
 pub fn format_row_values(row: Row) -> Vec<String> {
     row.into_iter().map(format_column_value).collect()
 }
 
 pub fn format_column_value(item: ColumnData) -> String {
     match item {
-        ColumnData::Binary(Some(val)) => format!("'0x{}'", encode(val)),
+        ColumnData::Binary(Some(val)) if val.is_empty() => "X''".to_string(),
+        ColumnData::Binary(Some(val)) => format!("0x{}", encode(val)),
         ColumnData::Binary(None) => "NULL".to_string(),
-        ColumnData::Bit(val) => val.unwrap_or_default().to_string(),
+        ColumnData::Bit(val) => format_number_value(val),
         ColumnData::I16(val) => format_number_value(val),
         ColumnData::I32(val) => format_number_value(val),
         ColumnData::I64(val) => format_number_value(val),
@@ -28,21 +32,30 @@ pub fn format_column_value(item: ColumnData) -> String {
         ColumnData::DateTime(ref val) => format_datetime(val),
         ColumnData::DateTime2(ref val) => format_datetime2(val),
         ColumnData::DateTimeOffset(ref val) => format_datetime_offset(val),
-        ColumnData::U8(val) => val.unwrap_or_default().to_string(),
-        ColumnData::Xml(val) => val.unwrap().as_ref().to_string(),
+        ColumnData::U8(val) => format_number_value(val),
+        ColumnData::Xml(val) => format_string_value(val.map(|xml| xml.as_ref().to_string())),
     }
 }
 
 pub fn format_numeric_value(value: Option<Numeric>) -> String {
     match value {
         Some(numeric) => {
-            let int_part = numeric.int_part();
-            let dec_part = numeric.dec_part().abs();
             let scale = numeric.scale() as usize;
-
-            let formatted_value = format!("{}.{:0<scale$}", int_part, dec_part, scale = scale);
-
-            format!("'{}'", formatted_value)
+            let sign = if numeric.value() < 0 { "-" } else { "" };
+
+            // Built from the unscaled digits to keep the exact scale
+            let digits = format!(
+                "{:0>width$}",
+                numeric.value().unsigned_abs(),
+                width = scale + 1
+            );
+            let (int_part, dec_part) = digits.split_at(digits.len() - scale);
+
+            if dec_part.is_empty() {
+                format!("'{}{}'", sign, int_part)
+            } else {
+                format!("'{}{}.{}'", sign, int_part, dec_part)
+            }
         }
         None => "NULL".to_string(),
     }
@@ -65,9 +78,14 @@ where
 
 pub fn format_time(val: &Option<Time>) -> String {
     val.map(|time| {
-        let ns = time.increments() as i64 * 10i64.pow(9 - time.scale() as u32);
+        let scale = time.scale();
+        let ns = time.increments() as i64 * 10i64.pow(9 - scale as u32);
         let time = NaiveTime::from_hms_opt(0, 0, 0).unwrap() + Duration::nanoseconds(ns);
-        format!("{}", time.format("'%H:%M:%S'"))
+        format!(
+            "'{}{}'",
+            time.format("%H:%M:%S"),
+            format_fraction(time.nanosecond(), scale)
+        )
     })
     .unwrap_or_else(|| "NULL".to_string())
 }
@@ -86,7 +104,7 @@ pub fn format_datetime(val: &Option<DateTime>) -> String {
             from_days(dt.days() as i64, 1900),
             from_sec_fragments(dt.seconds_fragments() as i64),
         );
-        datetime.format("'%Y-%m-%d %H:%M:%S'").to_string()
+        format_datetime_value(datetime, 3)
     })
     .unwrap_or_else(|| "NULL".to_string())
 }
@@ -100,7 +118,7 @@ pub fn format_datetime2(val: &Option<DateTime2>) -> String {
                     dt.time().increments() as i64 * 10i64.pow(9 - dt.time().scale() as u32),
                 ),
         );
-        datetime.format("'%Y-%m-%d %H:%M:%S'").to_string()
+        format_datetime_value(datetime, dt.time().scale())
     })
     .unwrap_or_else(|| "NULL".to_string())
 }
@@ -119,6 +137,7 @@ pub fn format_small_datetime(val: &Option<SmallDateTime>) -> String {
 pub fn format_datetime_offset(val: &Option<DateTimeOffset>) -> String {
     val.map(|dto| {
         let date = from_days(dto.datetime2().date().days() as i64, 1);
+        let scale = dto.datetime2().time().scale();
         let ns = dto.datetime2().time().increments() as i64
             * 10i64.pow(9 - dto.datetime2().time().scale() as u32);
 
@@ -127,11 +146,35 @@ pub fn format_datetime_offset(val: &Option<DateTimeOffset>) -> String {
         let naive = NaiveDateTime::new(date, time);
 
         let dto: ChronosDateTime<Utc> = ChronosDateTime::from_utc(naive, Utc);
-        dto.format("'%Y-%m-%d %H:%M:%S %z'").to_string()
+        format!(
+            "'{}{} {}'",
+            dto.format("%Y-%m-%d %H:%M:%S"),
+            format_fraction(dto.nanosecond(), scale),
+            dto.format("%z")
+        )
     })
     .unwrap_or_else(|| "NULL".to_string())
 }
 
+pub fn format_datetime_value(datetime: NaiveDateTime, scale: u8) -> String {
+    format!(
+        "'{}{}'",
+        datetime.format("%Y-%m-%d %H:%M:%S"),
+        format_fraction(datetime.nanosecond(), scale)
+    )
+}
+
+// Fractional seconds with as many digits as the source scale, truncated to the ones MySQL keeps
+fn format_fraction(nanoseconds: u32, scale: u8) -> String {
+    let digits = scale.min(MAX_FRACTION_DIGITS) as usize;
+
+    if digits == 0 {
+        String::new()
+    } else {
+        format!(".{}", &format!("{:09}", nanoseconds)[..digits])
+    }
+}
+
 pub fn from_days(days: i64, base_year: i32) -> NaiveDate {
     NaiveDate::from_ymd_opt(base_year, 1, 1).expect("Invalid date components")
         + Duration::days(days)
@@ -145,7 +188,8 @@ pub fn from_minutes(minutes: u32) -> NaiveTime {
 }
 
 pub fn from_sec_fragments(seconds_fragments: i64) -> NaiveTime {
-    let milliseconds = seconds_fragments * 1000 / 300;
+    // Fragments are 1/300 seconds, rounded to the milliseconds MSSQL displays them as
+    let milliseconds = (seconds_fragments * 1000 + 150) / 300;
     let seconds = milliseconds / 1000;
     let milliseconds_remainder = milliseconds % 1000;
     let minutes = seconds / 60;
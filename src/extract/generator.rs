@@ -0,0 +1,145 @@
+use chrono::{Duration, NaiveDate};
+use futures::stream::{self, BoxStream, StreamExt};
+
+use crate::common::constraints::Constraint;
+use crate::common::schema::ColumnSchema;
+use crate::common::source_table::{SourceTable, DEFAULT_SCHEMA};
+use crate::config::GeneratorConfig;
+use crate::extract::format::{format_datetime_value, format_number_value, format_string_value};
+
+const TABLE_PREFIX: &str = "generated_";
+
+const NOTES: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut \
+    labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip.";
+
+// Seconds of the span the generated timestamps are spread over
+const TIMESTAMP_SPAN: u64 = 4 * 365 * 24 * 60 * 60;
+
+// Fake source tables with keys 1 to `rows`, the values of a row only depend on its table and key
+#[derive(Debug)]
+pub struct Generator {
+    config: GeneratorConfig,
+}
+
+impl Generator {
+    pub fn new(config: GeneratorConfig) -> Self {
+        Generator { config }
+    }
+
+    pub fn rows(&self) -> i64 {
+        self.config.rows
+    }
+
+    pub fn tables(&self) -> Vec<SourceTable> {
+        (1..=self.config.tables)
+            .map(|index| SourceTable::new(DEFAULT_SCHEMA, &format!("{}{}", TABLE_PREFIX, index)))
+            .collect()
+    }
+
+    pub fn contains(&self, table: &SourceTable) -> bool {
+        self.tables().contains(table)
+    }
+
+    pub fn table_schema(&self, table: &SourceTable) -> Vec<ColumnSchema> {
+        let column = |name: &str, data_type: &str| ColumnSchema {
+            column_name: name.to_string(),
+            data_type: data_type.to_string(),
+            character_maximum_length: None,
+            numeric_precision: None,
+            numeric_scale: None,
+            datetime_precision: None,
+            is_nullable: false,
+            constraints: None,
+            constraint_name: None,
+            collation: None,
+            computed_definition: None,
+        };
+
+        vec![
+            ColumnSchema {
+                numeric_precision: Some(10),
+                numeric_scale: Some(0),
+                constraints: Some(Constraint::PrimaryKey),
+                constraint_name: Some(format!("PK_{}", table.name)),
+                ..column("Id", "int")
+            },
+            ColumnSchema {
+                character_maximum_length: Some(50),
+                ..column("Name", "nvarchar")
+            },
+            ColumnSchema {
+                numeric_precision: Some(12),
+                numeric_scale: Some(2),
+                ..column("Amount", "decimal")
+            },
+            ColumnSchema {
+                datetime_precision: Some(3),
+                ..column("CreatedAt", "datetime2")
+            },
+            column("Active", "bit"),
+            ColumnSchema {
+                character_maximum_length: Some(-1),
+                is_nullable: true,
+                ..column("Notes", "nvarchar")
+            },
+        ]
+    }
+
+    // Rows of the keys from `first_key` up to and including `last_key`
+    pub fn row_stream<E: Send + 'static>(
+        &self,
+        table: &SourceTable,
+        first_key: i64,
+        last_key: i64,
+    ) -> BoxStream<'static, Result<Vec<String>, E>> {
+        let seed = table_seed(table);
+
+        stream::iter(first_key.max(1)..=last_key.min(self.config.rows))
+            .map(move |key| Ok(generate_row(seed, key)))
+            .boxed()
+    }
+
+    pub fn key_stream<E: Send + 'static>(&self) -> BoxStream<'static, Result<Vec<String>, E>> {
+        stream::iter(1..=self.config.rows)
+            .map(|key| Ok(vec![key.to_string()]))
+            .boxed()
+    }
+}
+
+fn generate_row(seed: u64, key: i64) -> Vec<String> {
+    let hash = mix(seed ^ key as u64);
+    let created_at = NaiveDate::from_ymd_opt(2020, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        + Duration::milliseconds((hash % (TIMESTAMP_SPAN * 1000)) as i64);
+
+    // Every fifth row has no notes, the others a prefix of varying length
+    let notes = (key % 5 != 0).then(|| &NOTES[..(hash >> 40) as usize % NOTES.len() + 1]);
+
+    vec![
+        format_number_value(Some(key)),
+        format_string_value(Some(format!("Item {:06}", hash % 1_000_000))),
+        format!("'{}.{:02}'", (hash >> 8) % 1_000_000, (hash >> 32) % 100),
+        format_datetime_value(created_at, 3),
+        format_number_value(Some((hash >> 16) & 1 == 1)),
+        format_string_value(notes),
+    ]
+}
+
+fn table_seed(table: &SourceTable) -> u64 {
+    table
+        .to_string()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+// SplitMix64 finalizer, spreads consecutive keys over the whole range
+fn mix(value: u64) -> u64 {
+    let mut value = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
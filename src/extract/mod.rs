@@ -1,2 +1,5 @@
+pub mod chunk;
 pub mod extractor;
-mod format;
+pub(crate) mod format;
+pub mod generator;
+pub mod row_cache;
@@ -0,0 +1,261 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::stream::{self, StreamExt};
+use sha2::{Digest, Sha256};
+
+use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::extract::extractor::DatabaseExtractor;
+use crate::migrate::batch_sink::RowStream;
+use crate::migrate::migration_options::MigrationOptions;
+
+// Bumped whenever value formatting changes, so entries written by older versions are not reused
+const FORMAT_VERSION: u32 = 3;
+
+// Local copy of extracted rows, keyed by table and a watermark of its contents
+#[derive(Debug, Clone)]
+pub struct RowCache {
+    dir: PathBuf,
+    cache_dir: PathBuf,
+    max_bytes: Option<u64>,
+}
+
+impl RowCache {
+    pub async fn from_options(
+        options: &MigrationOptions,
+        extractor: &mut DatabaseExtractor,
+        table: &SourceTable,
+        schema: &[ColumnSchema],
+    ) -> Result<Option<Self>> {
+        match &options.cache_dir {
+            Some(cache_dir) => {
+                let cache =
+                    Self::open(cache_dir, extractor, table, schema, options.chunk_size).await?;
+                Ok(Some(RowCache {
+                    max_bytes: options.max_cache_bytes,
+                    ..cache
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub async fn open(
+        cache_dir: &Path,
+        extractor: &mut DatabaseExtractor,
+        table: &SourceTable,
+        schema: &[ColumnSchema],
+        chunk_size: Option<usize>,
+    ) -> Result<Self> {
+        let watermark = extractor.table_watermark(table, schema).await?;
+
+        // Column types decide how values are formatted, so a schema change invalidates the cache as well
+        let mut hasher = Sha256::new();
+        hasher.update(FORMAT_VERSION.to_le_bytes());
+        hasher.update(watermark.as_bytes());
+        hasher.update(format!("{:?}", chunk_size).as_bytes());
+        for column in schema {
+            hasher.update(format!("{}:{};", column.column_name, column.data_type).as_bytes());
+        }
+        let key = hex::encode(&hasher.finalize()[..8]);
+
+        let table_dir = cache_dir.join(table.to_string());
+        let dir = table_dir.join(&key);
+
+        // Drop entries of previous versions of the table
+        if table_dir.exists() {
+            for entry in fs::read_dir(&table_dir)? {
+                let entry = entry?;
+
+                if entry.file_name() != key.as_str() {
+                    fs::remove_dir_all(entry.path())?;
+                }
+            }
+        }
+        fs::create_dir_all(&dir)?;
+
+        debug!("Using row cache {} for table {}", dir.display(), table);
+
+        Ok(RowCache {
+            dir,
+            cache_dir: cache_dir.to_path_buf(),
+            max_bytes: None,
+        })
+    }
+
+    pub fn read(&self, index: usize) -> Result<Option<RowStream<'static>>> {
+        let path = self.chunk_path(index);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let rows = CachedRows {
+            reader: BufReader::new(GzDecoder::new(File::open(path)?)),
+        };
+
+        Ok(Some(stream::iter(rows).boxed()))
+    }
+
+    // Passes the rows through while copying them into the cache, the entry only becomes visible once the stream ends
+    pub fn record<'a>(&self, index: usize, rows: RowStream<'a>) -> Result<RowStream<'a>> {
+        if let Some(max_bytes) = self.max_bytes {
+            let used_bytes = directory_size(&self.cache_dir)?;
+
+            if used_bytes >= max_bytes {
+                warn!(
+                    "Row cache {} holds {} MB, not caching chunk {} of {}",
+                    self.cache_dir.display(),
+                    used_bytes / 1024 / 1024,
+                    index,
+                    self.dir.display()
+                );
+                return Ok(rows);
+            }
+        }
+
+        let writer = CacheWriter::create(self.chunk_path(index))?;
+
+        let stream = stream::unfold((rows, Some(writer)), |(mut rows, mut writer)| async move {
+            match rows.next().await {
+                Some(Ok(row)) => {
+                    if let Some(err) = writer.as_mut().and_then(|w| w.write_row(&row).err()) {
+                        warn!("Failed to write row cache, disabling it: {}", err);
+                        writer = None;
+                    }
+
+                    Some((Ok(row), (rows, writer)))
+                }
+                Some(Err(err)) => Some((Err(err), (rows, None))),
+                None => {
+                    if let Some(Err(err)) = writer.map(CacheWriter::finish) {
+                        warn!("Failed to finish row cache: {}", err);
+                    }
+
+                    None
+                }
+            }
+        });
+
+        Ok(stream.boxed())
+    }
+
+    fn chunk_path(&self, index: usize) -> PathBuf {
+        self.dir.join(format!("chunk-{:06}.rows.gz", index))
+    }
+}
+
+fn directory_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        size += if metadata.is_dir() {
+            directory_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+
+    Ok(size)
+}
+
+struct CacheWriter {
+    path: PathBuf,
+    temp_path: PathBuf,
+    encoder: Option<GzEncoder<BufWriter<File>>>,
+}
+
+impl CacheWriter {
+    fn create(path: PathBuf) -> io::Result<Self> {
+        let temp_path = path.with_extension("tmp");
+        let file = File::create(&temp_path)?;
+
+        Ok(CacheWriter {
+            path,
+            temp_path,
+            encoder: Some(GzEncoder::new(BufWriter::new(file), Compression::fast())),
+        })
+    }
+
+    // Each row is a header line with the value lengths followed by the concatenated values
+    fn write_row(&mut self, row: &[String]) -> io::Result<()> {
+        let encoder = self.encoder.as_mut().unwrap();
+
+        let lengths = row
+            .iter()
+            .map(|value| value.len().to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        writeln!(encoder, "{}", lengths)?;
+
+        for value in row {
+            encoder.write_all(value.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        let encoder = self.encoder.take().unwrap();
+        encoder.finish()?.flush()?;
+        fs::rename(&self.temp_path, &self.path)
+    }
+}
+
+impl Drop for CacheWriter {
+    fn drop(&mut self) {
+        // An unfinished entry is incomplete and must never be read back
+        if self.encoder.take().is_some() {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+struct CachedRows {
+    reader: BufReader<GzDecoder<File>>,
+}
+
+impl CachedRows {
+    fn read_row(&mut self) -> io::Result<Option<Vec<String>>> {
+        let mut header = String::new();
+
+        if self.reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let lengths = header
+            .split_whitespace()
+            .map(|length| length.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        lengths
+            .into_iter()
+            .map(|length| {
+                let mut value = vec![0; length];
+                self.reader.read_exact(&mut value)?;
+                String::from_utf8(value)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            })
+            .collect::<io::Result<Vec<_>>>()
+            .map(Some)
+    }
+}
+
+impl Iterator for CachedRows {
+    type Item = Result<Vec<String>, tiberius::error::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_row().map_err(Into::into).transpose()
+    }
+}
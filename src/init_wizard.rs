@@ -0,0 +1,279 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use toml::Value;
+
+use crate::common::source_table::SourceTable;
+use crate::config::{DatabaseConfig, PoolConfig};
+use crate::connection::{DatabaseConnectionFactory, SqlxMySqlConnection, TiberiusConnection};
+use crate::extract::extractor::DatabaseExtractor;
+use crate::insert::inserter::DatabaseInserter;
+
+const DEFAULT_MAPPINGS: &str = include_str!("../mappings.toml");
+const DEFAULT_COLLATION: &str = "Latin1_General_CI_AS";
+const DEFAULT_MAX_PACKET_BYTES: usize = 1_048_576;
+
+// Interactively creates the config file, and the mappings file when it doesn't exist yet
+pub async fn run_init_wizard(config_path: &Path, mappings_path: &Path) -> Result<()> {
+    println!("This wizard creates {}", config_path.display());
+
+    if config_path.exists()
+        && !confirm(
+            &format!("{} already exists, overwrite it?", config_path.display()),
+            false,
+        )?
+    {
+        bail!("Aborted, existing config left unchanged");
+    }
+
+    let (mssql_database, tables) = loop {
+        println!("\nMSSQL source database");
+        let config = prompt_database_config(1433)?;
+
+        match test_mssql_connection(&config).await {
+            Ok(tables) => {
+                println!("Connected, found {} tables", tables.len());
+                break (config, tables);
+            }
+            Err(err) => retry_or_abort("MSSQL", &err)?,
+        }
+    };
+
+    let (mysql_database, max_allowed_packet) = loop {
+        println!("\nMySQL target database");
+        let config = prompt_database_config(3306)?;
+
+        match test_mysql_connection(&config).await {
+            Ok((version, max_allowed_packet)) => {
+                println!("Connected to MySQL {}", version);
+                break (config, max_allowed_packet);
+            }
+            Err(err) => retry_or_abort("MySQL", &err)?,
+        }
+    };
+
+    let whitelisted_tables = prompt_tables(&tables)?;
+    let collation = prompt("\nSource collation", Some(DEFAULT_COLLATION))?;
+
+    let config = render_config(
+        &mssql_database,
+        &mysql_database,
+        DEFAULT_MAX_PACKET_BYTES.min(max_allowed_packet),
+        &collation,
+        &whitelisted_tables,
+    );
+
+    fs::write(config_path, config)
+        .with_context(|| format!("Failed to write {}", config_path.display()))?;
+    println!("\nWrote {}", config_path.display());
+
+    if mappings_path.exists() {
+        println!("Kept existing {}", mappings_path.display());
+    } else {
+        fs::write(mappings_path, DEFAULT_MAPPINGS)
+            .with_context(|| format!("Failed to write {}", mappings_path.display()))?;
+        println!("Wrote default {}", mappings_path.display());
+    }
+
+    Ok(())
+}
+
+async fn test_mssql_connection(config: &DatabaseConfig) -> Result<Vec<SourceTable>> {
+    let connection = DatabaseConnectionFactory::<TiberiusConnection>::new(config.clone())
+        .create_connection(1)
+        .await?;
+
+    let mut tables = DatabaseExtractor::new(connection.pool)
+        .fetch_tables()
+        .await?;
+    tables.sort();
+
+    Ok(tables)
+}
+
+async fn test_mysql_connection(config: &DatabaseConfig) -> Result<(String, usize)> {
+    let connection = DatabaseConnectionFactory::<SqlxMySqlConnection>::new(config.clone())
+        .create_connection(1)
+        .await?;
+
+    let mut inserter = DatabaseInserter::new(connection);
+
+    Ok((
+        inserter.get_server_version().await?,
+        inserter.get_max_allowed_packet().await?,
+    ))
+}
+
+fn retry_or_abort(database: &str, err: &anyhow::Error) -> Result<()> {
+    println!("Connection to {} failed: {:#}", database, err);
+
+    if confirm("Enter the settings again?", true)? {
+        Ok(())
+    } else {
+        bail!("Aborted, no config written")
+    }
+}
+
+fn prompt_database_config(default_port: u16) -> Result<DatabaseConfig> {
+    let host = prompt("Host", Some("localhost"))?;
+    let port = loop {
+        match prompt("Port", Some(&default_port.to_string()))?.parse() {
+            Ok(port) => break port,
+            Err(_) => println!("Invalid port"),
+        }
+    };
+
+    Ok(DatabaseConfig {
+        host,
+        port,
+        username: prompt("Username", None)?,
+        password: prompt("Password", None)?,
+        database: prompt("Database", None)?,
+        instance: None,
+        socket: None,
+        connect_timeout: None,
+        ssl_mode: None,
+        ssl_ca: None,
+        init_sql: Vec::new(),
+        session_variables: Vec::new(),
+        pool: PoolConfig::default(),
+    })
+}
+
+fn prompt_tables(tables: &[SourceTable]) -> Result<Vec<String>> {
+    println!("\nSource tables:");
+    for (index, table) in tables.iter().enumerate() {
+        println!("{:>4}. {}", index + 1, table);
+    }
+
+    loop {
+        let selection = prompt(
+            "Tables to migrate, as numbers and ranges like 1-3,7 (empty for all)",
+            Some(""),
+        )?;
+
+        match parse_selection(&selection, tables.len()) {
+            Some(indexes) => {
+                return Ok(indexes
+                    .into_iter()
+                    .map(|index| tables[index].to_string())
+                    .collect())
+            }
+            None => println!("Invalid selection"),
+        }
+    }
+}
+
+// Zero based indexes of a selection like `1-3,7`, all tables for an empty selection
+fn parse_selection(selection: &str, count: usize) -> Option<Vec<usize>> {
+    if selection.trim().is_empty() {
+        return Some((0..count).collect());
+    }
+
+    let mut indexes = Vec::new();
+
+    for part in selection.split(',').map(str::trim) {
+        let (start, end): (usize, usize) = match part.split_once('-') {
+            Some((start, end)) => (start.trim().parse().ok()?, end.trim().parse().ok()?),
+            None => {
+                let index = part.parse().ok()?;
+                (index, index)
+            }
+        };
+
+        if start == 0 || start > end || end > count {
+            return None;
+        }
+
+        indexes.extend((start - 1)..end);
+    }
+
+    indexes.sort_unstable();
+    indexes.dedup();
+
+    Some(indexes)
+}
+
+fn render_config(
+    mssql_database: &DatabaseConfig,
+    mysql_database: &DatabaseConfig,
+    max_packet_bytes: usize,
+    collation: &str,
+    whitelisted_tables: &[String],
+) -> String {
+    let whitelisted_tables = whitelisted_tables
+        .iter()
+        .map(|table| toml_string(table))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "{}\n{}\n[settings]\nmax_packet_bytes = {}\n\
+         # Optional: read tables with a primary key in chunks of this many rows (or key values)\n\
+         # chunk_size = 100000\n\
+         collation = {}\n\
+         whitelisted_tables = [{}]\n",
+        render_database_config("mssql_database", mssql_database),
+        render_database_config("mysql_database", mysql_database),
+        max_packet_bytes,
+        toml_string(collation),
+        whitelisted_tables
+    )
+}
+
+fn render_database_config(section: &str, config: &DatabaseConfig) -> String {
+    format!(
+        "[{}]\nhost = {}\nport = {}\nusername = {}\npassword = {}\ndatabase = {}\n",
+        section,
+        toml_string(&config.host),
+        config.port,
+        toml_string(&config.username),
+        toml_string(&config.password),
+        toml_string(&config.database)
+    )
+}
+
+// Quoted TOML string, with `${` escaped so values aren't taken for environment references
+fn toml_string(value: &str) -> String {
+    Value::String(value.replace("${", "$${")).to_string()
+}
+
+fn prompt(label: &str, default: Option<&str>) -> Result<String> {
+    loop {
+        match default {
+            Some(default) if !default.is_empty() => print!("{} [{}]: ", label, default),
+            _ => print!("{}: ", label),
+        }
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            bail!("Input closed");
+        }
+
+        let value = line.trim();
+
+        match default {
+            Some(default) if value.is_empty() => return Ok(default.to_string()),
+            _ if value.is_empty() => println!("A value is required"),
+            _ => return Ok(value.to_string()),
+        }
+    }
+}
+
+fn confirm(question: &str, default: bool) -> Result<bool> {
+    let hint = if default { "Y/n" } else { "y/N" };
+
+    loop {
+        let answer = prompt(&format!("{} [{}]", question, hint), Some(""))?;
+
+        match answer.to_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("Please answer y or n"),
+        }
+    }
+}
@@ -1,22 +1,106 @@
-use anyhow::{anyhow, Context, Result};
-use sqlx::{Acquire, Executor, MySqlPool, Row};
-
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use sqlx::pool::PoolConnection;
+use sqlx::{Acquire, Executor, MySql, MySqlPool, Row, Transaction};
+
+use crate::common::collation::Collation;
+use crate::common::constraints::TargetConstraint;
+use crate::common::dependency_graph::DependencyGraph;
+use crate::common::identifiers::{quote_identifier, quote_literal};
+use crate::common::pool_stats::{PoolUsage, WaitSnapshot, WaitStats};
 use crate::common::schema::ColumnSchema;
-use crate::insert::query::{build_create_constraints, build_create_table_query, build_reset_query};
+use crate::config::{Dialect, ExtraColumn};
+use crate::connection::SqlxMySqlConnection;
+use crate::insert::query::{
+    build_create_constraints, build_create_table_query, build_reset_queries,
+};
 use crate::insert::table_action::TableAction;
+use crate::migrate::backup::BACKUP_RECORDS_TABLE;
+use crate::migrate::quarantine::QUARANTINE_SCHEMA;
+use crate::migrate::run_history::{RunRecord, RUN_HISTORY_TABLE};
+use crate::sync::state::SYNC_STATE_TABLE;
+
+type RunRecordRow = (
+    String,
+    String,
+    String,
+    Option<i64>,
+    Option<i64>,
+    i64,
+    Option<i64>,
+    Option<String>,
+);
 
 #[derive(Clone)]
 pub struct DatabaseInserter {
     pool: MySqlPool,
+    waits: Arc<WaitStats>,
+    // `SET SESSION` of the configured session variables, run before every transaction
+    session_statement: Option<Arc<str>>,
+    // Configured `foreign_key_checks` replace the switching around loads
+    session_foreign_key_checks: bool,
 }
 
 impl DatabaseInserter {
-    pub fn new(pool: MySqlPool) -> Self {
-        DatabaseInserter { pool }
+    pub fn new(connection: SqlxMySqlConnection) -> Self {
+        let variables = &connection.session_variables;
+
+        DatabaseInserter {
+            pool: connection.pool,
+            waits: Arc::new(WaitStats::default()),
+            session_statement: (!variables.is_empty()).then(|| {
+                let assignments = variables
+                    .iter()
+                    .map(|(name, value)| format!("{} = {}", name, value))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                format!("SET SESSION {}", assignments).into()
+            }),
+            session_foreign_key_checks: variables
+                .iter()
+                .any(|(name, _)| name == "foreign_key_checks"),
+        }
+    }
+
+    pub fn pool_usage(&self) -> PoolUsage {
+        let idle = self.pool.num_idle() as u32;
+
+        PoolUsage {
+            in_use: self.pool.size().saturating_sub(idle),
+            idle,
+        }
+    }
+
+    pub fn wait_stats(&self) -> WaitSnapshot {
+        self.waits.snapshot()
     }
 
-    pub async fn create_table(&mut self, table_name: &str, schema: &[ColumnSchema]) -> Result<()> {
-        let create_table_query = build_create_table_query(table_name, schema);
+    // Connections held by the pool, which opens all of them up front
+    pub fn pool_size(&self) -> u32 {
+        self.pool.size()
+    }
+
+    pub async fn create_table(
+        &mut self,
+        table_name: &str,
+        schema: &[ColumnSchema],
+        extra_columns: &[ExtraColumn],
+        collation: &Collation,
+        comment: Option<&str>,
+        partitioning: Option<&str>,
+    ) -> Result<()> {
+        let create_table_query = build_create_table_query(
+            table_name,
+            schema,
+            extra_columns,
+            collation,
+            comment,
+            partitioning,
+        );
 
         debug!("Creating table {}", table_name);
 
@@ -34,23 +118,33 @@ impl DatabaseInserter {
         table_name: &str,
         schema: &[ColumnSchema],
         formatted_tables: &[String],
+        dialect: Dialect,
+        existing: &[TargetConstraint],
+        recreate: bool,
     ) -> Result<()> {
-        let alter_table_query = build_create_constraints(table_name, schema, formatted_tables);
+        let alter_table_query = build_create_constraints(
+            table_name,
+            schema,
+            formatted_tables,
+            dialect,
+            existing,
+            recreate,
+        );
 
         if let Some(query) = &alter_table_query {
             debug!("Creating constraints for table {}", table_name);
 
             let mut connection = self.pool.acquire().await?;
-            let mut transaction = connection.begin().await?;
+            let mut transaction = self.begin(&mut connection).await?;
 
-            transaction.execute("SET FOREIGN_KEY_CHECKS=0".to_string().as_str());
+            transaction.execute("SET FOREIGN_KEY_CHECKS=0").await?;
 
             if let Err(err) = transaction.execute(query.as_str()).await {
                 warn!(
                     "Constraints creation failed for table: {}, query: '{}'. Error: {}",
                     table_name, query, err
                 );
-                transaction.execute("SET FOREIGN_KEY_CHECKS=1".to_string().as_str());
+                transaction.execute("SET FOREIGN_KEY_CHECKS=1").await?;
                 transaction.rollback().await?; // Rollback if the transaction fails
             } else {
                 transaction.commit().await?;
@@ -62,26 +156,75 @@ impl DatabaseInserter {
     }
 
     pub async fn execute_transactional_query(&mut self, query: &str) -> Result<()> {
+        self.execute_transactional_queries(&[query]).await
+    }
+
+    pub async fn execute_transactional_queries<Q: AsRef<str>>(
+        &mut self,
+        queries: &[Q],
+    ) -> Result<()> {
+        self.run_transaction(queries, false).await
+    }
+
+    // Same as `execute_transactional_queries`, but with foreign keys enforced
+    pub async fn execute_checked_queries<Q: AsRef<str>>(&mut self, queries: &[Q]) -> Result<()> {
+        self.run_transaction(queries, true).await
+    }
+
+    async fn run_transaction<Q: AsRef<str>>(
+        &mut self,
+        queries: &[Q],
+        foreign_key_checks: bool,
+    ) -> Result<()> {
+        let start = Instant::now();
         let mut connection = self.pool.acquire().await?;
-        let mut transaction = connection.begin().await?;
+        self.waits.record(start.elapsed());
 
-        transaction.execute("SET FOREIGN_KEY_CHECKS=0").await?;
+        let mut transaction = self.begin(&mut connection).await?;
 
-        if let Err(_err) = transaction.execute(query).await {
-            transaction.rollback().await?;
-            let preview = if query.is_empty() {
-                "EMPTY QUERY".to_string()
-            } else {
-                query.chars().take(100).collect()
-            };
-            return Err(anyhow!("Cannot execute transaction query: {}", preview));
+        let switch_foreign_key_checks = !foreign_key_checks && !self.session_foreign_key_checks;
+
+        if switch_foreign_key_checks {
+            transaction.execute("SET FOREIGN_KEY_CHECKS=0").await?;
+        }
+
+        for query in queries {
+            let query = query.as_ref();
+
+            if let Err(err) = transaction.execute(query).await {
+                transaction.rollback().await?;
+                let preview = if query.is_empty() {
+                    "EMPTY QUERY".to_string()
+                } else {
+                    query.chars().take(100).collect()
+                };
+                return Err(anyhow::Error::new(err)
+                    .context(format!("Cannot execute transaction query: {}", preview)));
+            }
         }
 
-        transaction.execute("SET FOREIGN_KEY_CHECKS=1").await?;
+        if switch_foreign_key_checks {
+            transaction.execute("SET FOREIGN_KEY_CHECKS=1").await?;
+        }
         transaction.commit().await?;
         Ok(())
     }
 
+    // Variables like `sql_log_bin` can't be changed inside a transaction, they are set right before it
+    async fn begin<'c>(
+        &self,
+        connection: &'c mut PoolConnection<MySql>,
+    ) -> Result<Transaction<'c, MySql>> {
+        if let Some(statement) = &self.session_statement {
+            connection
+                .execute(&**statement)
+                .await
+                .with_context(|| format!("Failed to set session variables: {}", statement))?;
+        }
+
+        Ok(connection.begin().await?)
+    }
+
     pub async fn get_max_allowed_packet(&mut self) -> Result<usize> {
         let query = "SELECT @@max_allowed_packet";
 
@@ -90,7 +233,23 @@ impl DatabaseInserter {
         Ok(max_allowed_packet as usize)
     }
 
-    pub async fn reset_tables(&mut self, tables: &[String], action: TableAction) -> Result<()> {
+    pub async fn get_server_version(&mut self) -> Result<String> {
+        let version: String = sqlx::query_scalar("SELECT VERSION()")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(version)
+    }
+
+    // Children are reset before the tables they reference, with foreign key checks on. Disabling them is only a
+    // fallback, as it hides references that a reset would break. The reset isn't atomic: MySQL commits each DROP and
+    // TRUNCATE on its own, so a failing reset leaves the tables before the failing one reset.
+    pub async fn reset_tables(
+        &mut self,
+        tables: &[String],
+        action: TableAction,
+        unchecked_fallback: bool,
+    ) -> Result<()> {
         let mut all_tables = self.get_all_tables().await.with_context(|| {
             "Resetting tables encountered an error, cannot obtain existing tables"
         })?;
@@ -105,12 +264,59 @@ impl DatabaseInserter {
         if all_tables.is_empty() {
             debug!("No tables to reset");
         } else {
-            debug!("Resetting tables");
-            let reset_tables_query = build_reset_query(&all_tables, &action);
+            let references = self.fetch_foreign_key_references().await.with_context(|| {
+                "Resetting tables encountered an error, cannot obtain foreign keys"
+            })?;
+            // Self references count as well, MySQL won't truncate those tables either
+            let referenced_tables = references
+                .iter()
+                .map(|(_, referenced_table)| referenced_table.clone())
+                .collect::<BTreeSet<_>>();
+            // Only tables that no table outside the reset references, whose references are checked
+            let self_referencing_tables = references
+                .iter()
+                .filter(|(table, referenced_table)| table == referenced_table)
+                .map(|(table, _)| table.clone())
+                .filter(|table| {
+                    references
+                        .iter()
+                        .filter(|(_, referenced_table)| referenced_table == table)
+                        .all(|(referencing_table, _)| all_tables.contains(referencing_table))
+                })
+                .collect::<BTreeSet<_>>();
+
+            let ordered_tables = DependencyGraph::from_references(references)
+                .levels(&all_tables)
+                .into_iter()
+                .rev()
+                .flatten()
+                .collect::<Vec<_>>();
+
+            debug!("Resetting tables in order: {}", ordered_tables.join(", "));
+            let queries = build_reset_queries(
+                &ordered_tables,
+                &action,
+                &referenced_tables,
+                &self_referencing_tables,
+            );
+
+            if let Err(err) = self.execute_checked_queries(&queries).await {
+                if !unchecked_fallback {
+                    return Err(err.context(
+                        "Resetting tables encountered an error, set `unchecked_reset_fallback = true` in [settings] \
+                         to retry without foreign key checks",
+                    ));
+                }
 
-            self.execute_transactional_query(reset_tables_query.as_str())
-                .await
-                .with_context(|| "Resetting tables encountered an error")?;
+                warn!(
+                    "Resetting tables with foreign key checks failed, retrying without them: {:#}",
+                    err
+                );
+
+                self.execute_transactional_queries(&queries)
+                    .await
+                    .with_context(|| "Resetting tables encountered an error")?;
+            }
 
             match action {
                 TableAction::Drop => info!("Tables dropped successfully"),
@@ -121,7 +327,28 @@ impl DatabaseInserter {
         Ok(())
     }
 
-    async fn get_all_tables(&mut self) -> Result<Vec<String>> {
+    // READ locks held on a dedicated connection, other sessions can still read but writes wait
+    pub async fn lock_tables_for_read(&mut self, tables: &[String]) -> Result<TableLock> {
+        let mut connection = self.pool.acquire().await?;
+
+        let query = format!(
+            "LOCK TABLES {}",
+            tables
+                .iter()
+                .map(|table| format!("{} READ", quote_identifier(table)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        connection
+            .execute(query.as_str())
+            .await
+            .with_context(|| "Failed to lock tables")?;
+
+        Ok(TableLock { connection })
+    }
+
+    pub async fn get_all_tables(&mut self) -> Result<Vec<String>> {
         let rows = sqlx::query("SHOW TABLES").fetch_all(&self.pool).await?;
 
         let table_names: Vec<String> = rows
@@ -134,8 +361,8 @@ impl DatabaseInserter {
 
     pub async fn table_exists(&mut self, table_name: &str) -> Result<bool> {
         let query = format!(
-            "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = DATABASE() AND table_name = '{}'",
-            table_name
+            "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = DATABASE() AND table_name = {}",
+            quote_literal(table_name)
         );
 
         let count: i64 = sqlx::query_scalar(&query).fetch_one(&self.pool).await?;
@@ -143,11 +370,343 @@ impl DatabaseInserter {
         Ok(count > 0)
     }
 
+    // Highest value of an integer column, 0 for a missing or empty table
+    pub async fn fetch_max_key(&mut self, table_name: &str, column_name: &str) -> Result<i64> {
+        if !self.table_exists(table_name).await? {
+            return Ok(0);
+        }
+
+        let query = format!(
+            "SELECT CAST(COALESCE(MAX({}), 0) AS SIGNED) FROM {}",
+            quote_identifier(column_name),
+            quote_identifier(table_name)
+        );
+
+        let max_key: i64 = sqlx::query_scalar(&query).fetch_one(&self.pool).await?;
+
+        Ok(max_key)
+    }
+
+    // Ids of the rows quarantined for the table, empty when nothing was quarantined
+    // Rows that failed validation are only included with `include_invalid`
+    pub async fn fetch_quarantined_ids(
+        &mut self,
+        table_name: &str,
+        include_invalid: bool,
+    ) -> Result<Vec<i64>> {
+        let query = format!(
+            "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = {} AND table_name = {}",
+            quote_literal(QUARANTINE_SCHEMA),
+            quote_literal(table_name)
+        );
+
+        let count: i64 = sqlx::query_scalar(&query).fetch_one(&self.pool).await?;
+
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let query = format!(
+            "SELECT `_quarantine_id` FROM `{}`.{}{} ORDER BY `_quarantine_id`",
+            QUARANTINE_SCHEMA,
+            quote_identifier(table_name),
+            if include_invalid {
+                ""
+            } else {
+                " WHERE NOT `_invalid`"
+            }
+        );
+
+        let ids: Vec<i64> = sqlx::query_scalar(&query).fetch_all(&self.pool).await?;
+
+        Ok(ids)
+    }
+
+    // Rows of a source table quarantined for the table, including the ones that failed validation
+    pub async fn count_quarantined_rows(
+        &mut self,
+        table_name: &str,
+        source_table: &str,
+    ) -> Result<usize> {
+        let query = format!(
+            "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = {} AND table_name = {}",
+            quote_literal(QUARANTINE_SCHEMA),
+            quote_literal(table_name)
+        );
+
+        let count: i64 = sqlx::query_scalar(&query).fetch_one(&self.pool).await?;
+
+        if count == 0 {
+            return Ok(0);
+        }
+
+        let query = format!(
+            "SELECT COUNT(*) FROM `{}`.{} WHERE `_source_table` = {}",
+            QUARANTINE_SCHEMA,
+            quote_identifier(table_name),
+            quote_literal(source_table)
+        );
+
+        let count: i64 = sqlx::query_scalar(&query).fetch_one(&self.pool).await?;
+
+        Ok(count as usize)
+    }
+
+    pub async fn get_column_names(&mut self, table_name: &str) -> Result<Vec<String>> {
+        let query = format!(
+            "SELECT COLUMN_NAME FROM information_schema.columns WHERE table_schema = DATABASE() AND table_name = {} ORDER BY ORDINAL_POSITION",
+            quote_literal(table_name)
+        );
+
+        let columns: Vec<String> = sqlx::query_scalar(&query).fetch_all(&self.pool).await?;
+
+        Ok(columns)
+    }
+
+    // Columns of the table as name, full column type and nullability
+    pub async fn get_column_definitions(
+        &mut self,
+        table_name: &str,
+    ) -> Result<Vec<(String, String, bool)>> {
+        let query = format!(
+            "SELECT COLUMN_NAME, CAST(COLUMN_TYPE AS CHAR), IS_NULLABLE FROM information_schema.columns WHERE table_schema = DATABASE() AND table_name = {} ORDER BY ORDINAL_POSITION",
+            quote_literal(table_name)
+        );
+
+        let columns = sqlx::query_as::<_, (String, String, String)>(&query)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|(name, column_type, nullable)| (name, column_type, nullable == "YES"))
+            .collect();
+
+        Ok(columns)
+    }
+
+    // Backed up tables as run id, table name and backup name, empty before the first backup
+    pub async fn fetch_backup_records(&mut self) -> Result<Vec<(String, String, String)>> {
+        if !self.table_exists(BACKUP_RECORDS_TABLE).await? {
+            return Ok(Vec::new());
+        }
+
+        let query = format!(
+            "SELECT `run_id`, `table_name`, `backup_name` FROM `{}` ORDER BY `run_id`, `table_name`",
+            BACKUP_RECORDS_TABLE
+        );
+
+        let records = sqlx::query_as(&query).fetch_all(&self.pool).await?;
+
+        Ok(records)
+    }
+
+    // Tables of the run history as run id, table name and status, empty before the first migration
+    pub async fn fetch_run_records(&mut self) -> Result<Vec<RunRecord>> {
+        if !self.table_exists(RUN_HISTORY_TABLE).await? {
+            return Ok(Vec::new());
+        }
+
+        // Tables created before runs had notes lack the column until the next migration
+        let note = if self
+            .get_column_names(RUN_HISTORY_TABLE)
+            .await?
+            .iter()
+            .any(|column| column == "note")
+        {
+            "`note`"
+        } else {
+            "CAST(NULL AS CHAR)"
+        };
+
+        let query = format!(
+            "SELECT `run_id`, `table_name`, `status`, `rows`, `duration_ms`, \
+             CAST(UNIX_TIMESTAMP(`started_at`) AS SIGNED), CAST(UNIX_TIMESTAMP(`finished_at`) AS SIGNED), \
+             {} FROM `{}` ORDER BY `run_id`, `table_name`",
+            note, RUN_HISTORY_TABLE
+        );
+
+        let records = sqlx::query_as::<_, RunRecordRow>(&query)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(
+                |(run_id, table_name, status, rows, duration_ms, started_at, finished_at, note)| {
+                    RunRecord {
+                        run_id,
+                        table_name,
+                        status,
+                        rows,
+                        duration_ms,
+                        started_at,
+                        finished_at,
+                        note,
+                    }
+                },
+            )
+            .collect();
+
+        Ok(records)
+    }
+
+    // Foreign key, unique and CHECK constraints of the table, with the column of the keys
+    pub async fn fetch_constraints(&mut self, table_name: &str) -> Result<Vec<TargetConstraint>> {
+        let query = "SELECT tc.CONSTRAINT_NAME, tc.CONSTRAINT_TYPE, kcu.COLUMN_NAME FROM information_schema.TABLE_CONSTRAINTS tc LEFT JOIN information_schema.KEY_COLUMN_USAGE kcu ON kcu.CONSTRAINT_SCHEMA = tc.CONSTRAINT_SCHEMA AND kcu.TABLE_NAME = tc.TABLE_NAME AND kcu.CONSTRAINT_NAME = tc.CONSTRAINT_NAME WHERE tc.TABLE_SCHEMA = DATABASE() AND tc.TABLE_NAME = ? AND tc.CONSTRAINT_TYPE <> 'PRIMARY KEY'";
+
+        let constraints = sqlx::query_as::<_, (String, String, Option<String>)>(query)
+            .bind(table_name)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|(name, constraint_type, column_name)| TargetConstraint {
+                name,
+                constraint_type,
+                column_name,
+            })
+            .collect();
+
+        Ok(constraints)
+    }
+
+    // Pairs of a table and a table its foreign keys reference, within the target database
+    pub async fn fetch_foreign_key_references(&mut self) -> Result<Vec<(String, String)>> {
+        let references = sqlx::query_as(
+            "SELECT TABLE_NAME, REFERENCED_TABLE_NAME FROM information_schema.REFERENTIAL_CONSTRAINTS WHERE CONSTRAINT_SCHEMA = DATABASE() AND UNIQUE_CONSTRAINT_SCHEMA = DATABASE()",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(references)
+    }
+
+    pub async fn fetch_quarantine_tables(&mut self) -> Result<Vec<String>> {
+        let tables = sqlx::query_scalar(
+            "SELECT TABLE_NAME FROM information_schema.tables WHERE table_schema = ?",
+        )
+        .bind(QUARANTINE_SCHEMA)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tables)
+    }
+
+    // Tables with a sync baseline of any source
+    pub async fn fetch_synced_tables(&mut self) -> Result<Vec<String>> {
+        if !self.table_exists(SYNC_STATE_TABLE).await? {
+            return Ok(Vec::new());
+        }
+
+        let query = format!("SELECT DISTINCT `table_name` FROM `{}`", SYNC_STATE_TABLE);
+
+        let tables = sqlx::query_scalar(&query).fetch_all(&self.pool).await?;
+
+        Ok(tables)
+    }
+
+    // Synced versions of the tables, empty before the first migration with a sync baseline
+    pub async fn fetch_sync_states(&mut self, source: &str) -> Result<Vec<(String, String, i64)>> {
+        if !self.table_exists(SYNC_STATE_TABLE).await? {
+            return Ok(Vec::new());
+        }
+
+        let query = format!(
+            "SELECT `table_name`, `method`, `version` FROM `{}` WHERE `source` = ?",
+            SYNC_STATE_TABLE
+        );
+
+        let states = sqlx::query_as(&query)
+            .bind(source)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(states)
+    }
+
+    // Value combinations of the columns held by more than one row, as text with their row count, most frequent first
+    pub async fn fetch_duplicates(
+        &mut self,
+        table_name: &str,
+        columns: &[&str],
+        limit: usize,
+    ) -> Result<(i64, Vec<(String, i64)>)> {
+        let column_list = columns
+            .iter()
+            .map(|column| quote_identifier(column))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        // Rows with a NULL in the columns never collide in a MySQL unique index
+        let condition = columns
+            .iter()
+            .map(|column| format!("{} IS NOT NULL", quote_identifier(column)))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+
+        let duplicates = format!(
+            "SELECT CAST(CONCAT_WS(', ', {}) AS CHAR) AS `value`, COUNT(*) AS `rows` FROM {} WHERE {} GROUP BY {} HAVING COUNT(*) > 1",
+            column_list, quote_identifier(table_name), condition, column_list
+        );
+
+        let count: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM ({}) AS `duplicates`",
+            duplicates
+        ))
+        .fetch_one(&self.pool)
+        .await?;
+
+        let examples = sqlx::query_as(&format!(
+            "{} ORDER BY COUNT(*) DESC LIMIT {}",
+            duplicates, limit
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok((count, examples))
+    }
+
+    // Single value of the query as text, `None` for NULL
+    pub async fn fetch_scalar(&mut self, query: &str) -> Result<Option<String>> {
+        let query = format!("SELECT CAST(({}) AS CHAR)", query);
+
+        let value: Option<String> = sqlx::query_scalar(&query).fetch_one(&self.pool).await?;
+
+        Ok(value)
+    }
+
+    // Rows of the query with all values as text, `None` for NULL
+    pub async fn fetch_text_rows(&mut self, query: &str) -> Result<Vec<Vec<Option<String>>>> {
+        let rows = sqlx::query(query).fetch_all(&self.pool).await?;
+
+        let values = rows
+            .iter()
+            .map(|row| {
+                (0..row.len())
+                    .map(|index| row.try_get::<Option<String>, _>(index))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(values)
+    }
+
     pub async fn table_rows_count(&mut self, table_name: &str) -> Result<i64> {
-        let query = format!("SELECT COUNT(*) FROM `{}`", table_name);
+        let query = format!("SELECT COUNT(*) FROM {}", quote_identifier(table_name));
 
         let count: i64 = sqlx::query_scalar(&query).fetch_one(&self.pool).await?;
 
         Ok(count)
     }
 }
+
+pub struct TableLock {
+    connection: PoolConnection<MySql>,
+}
+
+impl TableLock {
+    pub async fn release(mut self) -> Result<()> {
+        self.connection
+            .execute("UNLOCK TABLES")
+            .await
+            .with_context(|| "Failed to unlock tables")?;
+
+        Ok(())
+    }
+}
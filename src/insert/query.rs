@@ -1,40 +1,158 @@
-use crate::common::constraints::Constraint;
+use std::collections::BTreeSet;
+
+use crate::common::collation::Collation;
+use crate::common::constraints::{Constraint, TargetConstraint};
+use crate::common::identifiers::{quote_identifier, quote_literal};
 use crate::common::schema::ColumnSchema;
+use crate::config::{Dialect, ExtraColumn};
 use crate::insert::table_action::TableAction;
 
-pub fn build_insert_statement(table_name: &str, schema: &[ColumnSchema]) -> String {
+// InnoDB index entries hold up to 3072 bytes per column
+const MAX_KEY_BYTES: i32 = 3072;
+
+// Extra columns without a value are left to their default
+pub fn build_insert_statement(
+    table_name: &str,
+    schema: &[ColumnSchema],
+    extra_columns: &[ExtraColumn],
+) -> String {
     let column_names_string = schema
         .iter()
-        .map(|column| column.column_name.as_str())
+        .map(|column| quote_identifier(&column.column_name))
+        .chain(
+            extra_columns
+                .iter()
+                .filter(|column| column.value.is_some())
+                .map(|column| quote_identifier(&column.name)),
+        )
         .collect::<Vec<_>>()
         .join(", ");
 
     format!(
-        "INSERT INTO `{}` ({}) VALUES",
-        table_name, column_names_string
+        "INSERT INTO {} ({}) VALUES",
+        quote_identifier(table_name),
+        column_names_string
     )
 }
 
-pub fn build_reset_query(tables: &[String], action: &TableAction) -> String {
-    tables
+// Turns an insert into an upsert, overwriting every column of rows with a duplicate key
+pub fn build_upsert_clause(schema: &[ColumnSchema]) -> String {
+    let assignments = schema
+        .iter()
+        .map(|column| format!("{0} = VALUES({0})", quote_identifier(&column.column_name)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(" ON DUPLICATE KEY UPDATE {}", assignments)
+}
+
+// Leaves rows with a duplicate key as they are, so only new rows are inserted
+pub fn build_append_clause(schema: &[ColumnSchema]) -> String {
+    schema
         .iter()
-        .map(|table_name| {
+        .find(|column| column.constraints == Some(Constraint::PrimaryKey))
+        .map(|column| {
             format!(
-                "{} TABLE `{}`;",
-                action.to_string().to_uppercase(),
-                table_name
+                " ON DUPLICATE KEY UPDATE {0} = {0}",
+                quote_identifier(&column.column_name)
             )
         })
-        .collect::<Vec<_>>()
-        .join("\n")
+        .unwrap_or_default()
+}
+
+// Values appended to every row, in the order of `build_insert_statement`
+pub fn extra_column_values(extra_columns: &[ExtraColumn]) -> Vec<String> {
+    extra_columns
+        .iter()
+        .filter_map(|column| column.value.clone())
+        .collect()
 }
 
+// Statements resetting the tables in the given order. MySQL refuses to truncate a table other tables reference, those
+// are emptied with DELETE instead, and a retry skips tables that were already dropped. The rows of a table referencing
+// itself may reference each other in any order, so checks are off while it is emptied as a whole.
+pub fn build_reset_queries(
+    tables: &[String],
+    action: &TableAction,
+    referenced_tables: &BTreeSet<String>,
+    self_referencing_tables: &BTreeSet<String>,
+) -> Vec<String> {
+    tables
+        .iter()
+        .flat_map(|table_name| match action {
+            TableAction::Drop => vec![format!(
+                "DROP TABLE IF EXISTS {}",
+                quote_identifier(table_name)
+            )],
+            TableAction::Truncate if self_referencing_tables.contains(table_name) => vec![
+                "SET @reset_foreign_key_checks = @@FOREIGN_KEY_CHECKS, FOREIGN_KEY_CHECKS = 0"
+                    .to_string(),
+                format!("DELETE FROM {}", quote_identifier(table_name)),
+                "SET FOREIGN_KEY_CHECKS = @reset_foreign_key_checks".to_string(),
+            ],
+            TableAction::Truncate if referenced_tables.contains(table_name) => {
+                vec![format!("DELETE FROM {}", quote_identifier(table_name))]
+            }
+            TableAction::Truncate => {
+                vec![format!("TRUNCATE TABLE {}", quote_identifier(table_name))]
+            }
+        })
+        .collect()
+}
+
+// `CONSTRAINT` clause keeping the source constraint name
+fn format_constraint_name(column: &ColumnSchema) -> String {
+    column
+        .constraint_name
+        .as_ref()
+        .map(|name| format!("CONSTRAINT {} ", quote_identifier(name)))
+        .unwrap_or_default()
+}
+
+// Longest indexable length of a key column whose values may exceed the index limit
+pub fn key_length_limit(column: &ColumnSchema) -> Option<i32> {
+    let data_type = column.data_type.to_lowercase();
+    let is_blob = data_type.ends_with("text") || data_type.ends_with("blob");
+
+    // Binary types count bytes, text types up to 4 bytes per utf8mb4 character
+    let max_length = if data_type.contains("binary") || data_type.ends_with("blob") {
+        MAX_KEY_BYTES
+    } else {
+        MAX_KEY_BYTES / 4
+    };
+
+    let too_long = is_blob
+        || column
+            .character_maximum_length
+            .is_some_and(|length| length > max_length);
+
+    too_long.then_some(max_length)
+}
+
+// Column of a key, only a prefix of long columns is indexed
+fn format_key_column(table_name: &str, column: &ColumnSchema, key: &str) -> String {
+    let Some(max_length) = key_length_limit(column) else {
+        return quote_identifier(&column.column_name);
+    };
+
+    warn!(
+        "{} of column {} in table {} only covers the first {} characters of its values",
+        key, column.column_name, table_name, max_length
+    );
+
+    format!("{}({})", quote_identifier(&column.column_name), max_length)
+}
+
+// Constraints already on the target are left as they are, or dropped and added again with `recreate`
 pub fn build_create_constraints(
     table_name: &str,
     schema: &[ColumnSchema],
     formatted_tables: &[String],
+    dialect: Dialect,
+    existing: &[TargetConstraint],
+    recreate: bool,
 ) -> Option<String> {
-    let constraints: Vec<String> = schema
+    let constraints: Vec<(&ColumnSchema, &Constraint)> = schema
         .iter()
         .filter_map(|column| {
             column
@@ -61,79 +179,214 @@ pub fn build_create_constraints(
                         _ => true,
                     }
                 })
-                .map(|constraints| match constraints {
-                    //Constraint::PrimaryKey => format!("ADD PRIMARY KEY(`{}`)", column.column_name),
-                    Constraint::ForeignKey {
-                        referenced_table,
-                        referenced_column,
-                    } => format!(
-                        "ADD FOREIGN KEY(`{}`) REFERENCES `{}`(`{}`) ON DELETE CASCADE",
-                        column.column_name, referenced_table, referenced_column
-                    ),
-                    Constraint::Unique => format!("ADD UNIQUE(`{}`)", column.column_name),
-                    Constraint::Check(check_clause) => format!("ADD CHECK ({})", check_clause),
-                    Constraint::Default(default_value) => format!("ADD DEFAULT {}", default_value),
-                    _ => String::new(),
-                })
+                .map(|constraint| (column, constraint))
         })
-        .filter(|constraint| !constraint.is_empty())
         .collect();
 
-    if constraints.is_empty() {
+    let mut drops = Vec::new();
+    let mut adds = Vec::new();
+
+    for (column, constraint) in constraints {
+        let add = match constraint {
+            //Constraint::PrimaryKey => format!("ADD PRIMARY KEY(`{}`)", column.column_name),
+            Constraint::ForeignKey {
+                referenced_table,
+                referenced_column,
+                on_delete,
+                on_update,
+            } => format!(
+                "ADD {}FOREIGN KEY({}) REFERENCES {}({}) ON DELETE {} ON UPDATE {}",
+                format_constraint_name(column),
+                quote_identifier(&column.column_name),
+                quote_identifier(referenced_table),
+                quote_identifier(referenced_column),
+                on_delete,
+                on_update
+            ),
+            // MariaDB hashes the whole value of long unique columns
+            Constraint::Unique if dialect == Dialect::MariaDb => format!(
+                "ADD {}UNIQUE({})",
+                format_constraint_name(column),
+                quote_identifier(&column.column_name)
+            ),
+            Constraint::Unique => format!(
+                "ADD {}UNIQUE({})",
+                format_constraint_name(column),
+                format_key_column(table_name, column, "Unique constraint")
+            ),
+            Constraint::Check(check_clause) => format!(
+                "ADD {}CHECK ({})",
+                format_constraint_name(column),
+                check_clause
+            ),
+            _ => continue,
+        };
+
+        match existing
+            .iter()
+            .find(|target| target.matches(column, constraint))
+        {
+            Some(target) if recreate => {
+                drops.push(format_drop_constraint(target, dialect));
+                adds.push(add);
+            }
+            Some(target) => debug!(
+                "Constraint {} of table {} already exists",
+                target.name, table_name
+            ),
+            None => adds.push(add),
+        }
+    }
+
+    if adds.is_empty() {
         return None;
     }
 
-    let alter_table_query = format!(
-        "SET FOREIGN_KEY_CHECKS=0; ALTER TABLE `{}` {}",
-        table_name,
-        constraints.join(", ")
-    );
+    // A name can't be dropped and added again within one statement
+    let mut alter_table_query = "SET FOREIGN_KEY_CHECKS=0; ".to_string();
+
+    if !drops.is_empty() {
+        alter_table_query.push_str(&format!(
+            "ALTER TABLE {} {}; ",
+            quote_identifier(table_name),
+            drops.join(", ")
+        ));
+    }
+
+    alter_table_query.push_str(&format!(
+        "ALTER TABLE {} {}",
+        quote_identifier(table_name),
+        adds.join(", ")
+    ));
 
     Some(alter_table_query)
 }
 
-pub fn build_create_table_query(table_name: &str, schema: &[ColumnSchema]) -> String {
-    let columns: Vec<String> = schema
+fn format_drop_constraint(target: &TargetConstraint, dialect: Dialect) -> String {
+    let name = quote_identifier(&target.name);
+
+    match (target.constraint_type.as_str(), dialect) {
+        ("FOREIGN KEY", _) => format!("DROP FOREIGN KEY {}", name),
+        ("UNIQUE", _) => format!("DROP INDEX {}", name),
+        (_, Dialect::MariaDb) => format!("DROP CONSTRAINT {}", name),
+        _ => format!("DROP CHECK {}", name),
+    }
+}
+
+pub fn build_create_table_query(
+    table_name: &str,
+    schema: &[ColumnSchema],
+    extra_columns: &[ExtraColumn],
+    collation: &Collation,
+    comment: Option<&str>,
+    partitioning: Option<&str>,
+) -> String {
+    let primary_key: Vec<&ColumnSchema> = schema
         .iter()
-        .map(|column| {
-            let mut result_str = String::new();
-
-            result_str.push_str(&column.column_name);
-            result_str.push(' '); // Add a space after column_name
-
-            result_str.push_str(&column.data_type);
-            if let Some(max_length) = column.character_maximum_length {
-                result_str.push_str(&format!("({})", max_length));
-            } else if let Some(precision) = column.numeric_precision {
-                if let Some(scale) = column.numeric_scale {
-                    result_str.push_str(&format!("({}, {})", precision, scale));
-                } else {
-                    result_str.push_str(&format!("({})", precision));
-                }
-            }
+        .filter(|column| column.constraints == Some(Constraint::PrimaryKey))
+        .collect();
 
-            // Add constraints if it contains Constraint::PrimaryKey
-            if let Some(constraint) = &column.constraints {
-                if *constraint == Constraint::PrimaryKey {
-                    result_str.push_str(" PRIMARY KEY");
-                }
-                // You can add more checks for other constraint types if needed
-            }
+    // Composite keys and keys indexing a prefix are added after the columns
+    let inline_key = primary_key.len() == 1 && key_length_limit(primary_key[0]).is_none();
 
-            result_str.push(' '); // Add a space after data_type and type_properties
-            let nullable_property = if column.is_nullable {
-                "NULL"
-            } else {
-                "NOT NULL"
-            };
-            result_str.push_str(nullable_property);
+    let mut columns: Vec<String> = schema
+        .iter()
+        .map(|column| {
+            let definition = format_column_definition(column);
 
-            result_str
+            if column.constraints == Some(Constraint::PrimaryKey) && inline_key {
+                format!("{} PRIMARY KEY", definition)
+            } else {
+                definition
+            }
         })
         .collect();
 
+    columns.extend(extra_columns.iter().map(|column| {
+        let name = quote_identifier(&column.name);
+
+        match &column.default {
+            Some(default) => format!("{} {} DEFAULT {}", name, column.data_type, default),
+            None => format!("{} {}", name, column.data_type),
+        }
+    }));
+
+    if !primary_key.is_empty() && !inline_key {
+        let key_columns = primary_key
+            .iter()
+            .map(|column| format_key_column(table_name, column, "Primary key"))
+            .collect::<Vec<_>>();
+
+        columns.push(format!("PRIMARY KEY ({})", key_columns.join(", ")));
+    }
+
     let columns = columns.join(", ");
-    let create_table_query = format!("CREATE TABLE `{}` ({})", table_name, columns);
+    let mut create_table_query = format!(
+        "CREATE TABLE {} ({}) DEFAULT CHARSET={} COLLATE={}",
+        quote_identifier(table_name),
+        columns,
+        collation.charset,
+        collation.name
+    );
+
+    if let Some(comment) = comment {
+        create_table_query.push_str(&format!(" COMMENT={}", quote_literal(comment)));
+    }
+
+    if let Some(partitioning) = partitioning {
+        create_table_query.push(' ');
+        create_table_query.push_str(partitioning);
+    }
 
     create_table_query
 }
+
+// Column name, type, character set and nullability as used in `CREATE TABLE` and `ALTER TABLE`
+pub fn format_column_definition(column: &ColumnSchema) -> String {
+    let mut definition = format!(
+        "{} {}",
+        quote_identifier(&column.column_name),
+        format_column_type(column)
+    );
+
+    if let Some(column_collation) = &column.collation {
+        definition.push_str(&format!(
+            " CHARACTER SET {} COLLATE {}",
+            Collation::resolve(column_collation).charset,
+            column_collation
+        ));
+    }
+
+    if let Some(expression) = &column.computed_definition {
+        definition.push_str(&format!(" GENERATED ALWAYS AS ({}) STORED", expression));
+    }
+
+    definition.push_str(if column.is_nullable {
+        " NULL"
+    } else {
+        " NOT NULL"
+    });
+
+    if let Some(Constraint::Default(default)) = &column.constraints {
+        definition.push_str(&format!(" DEFAULT {}", default));
+    }
+
+    definition
+}
+
+// Data type along with its length or precision parameters, e.g. `varchar(255)` or `decimal(10, 2)`
+pub fn format_column_type(column: &ColumnSchema) -> String {
+    let mut column_type = column.data_type.clone();
+
+    if let Some(max_length) = column.character_maximum_length {
+        column_type.push_str(&format!("({})", max_length));
+    } else if let Some(precision) = column.numeric_precision {
+        if let Some(scale) = column.numeric_scale {
+            column_type.push_str(&format!("({}, {})", precision, scale));
+        } else {
+            column_type.push_str(&format!("({})", precision));
+        }
+    }
+
+    column_type
+}
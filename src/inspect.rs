@@ -0,0 +1,792 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+
+use crate::common::constraints::Constraint;
+use crate::common::helpers::format_snake_case;
+use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::config::{CharPadding, Dialect, LongKeys};
+use crate::extract::extractor::{select_list, DatabaseExtractor};
+use crate::insert::query::{
+    build_create_constraints, build_create_table_query, build_insert_statement,
+    extra_column_values, format_column_type, key_length_limit,
+};
+use crate::mappings::{Mapping, Mappings};
+use crate::migrate::char_padding::is_char_column;
+use crate::migrate::migration_options::MigrationOptions;
+use crate::migrate::migrator::resolve_source_tables;
+use crate::migrate::partitioning::plan_partitioning;
+use crate::migrate::provenance::table_comment;
+use crate::migrate::table_schema_mapper::{loaded_columns, TableSchemaMapper};
+use crate::migrate::temporal::resolve_temporal_table;
+
+// Prints all source tables with their row counts and the target names of the whitelisted ones
+pub async fn list_tables(
+    extractor: &mut DatabaseExtractor,
+    options: &MigrationOptions,
+) -> Result<()> {
+    let mut tables = extractor.fetch_table_row_counts().await?;
+    tables.sort();
+
+    let width = tables
+        .iter()
+        .map(|(table, _)| table.to_string().len())
+        .max()
+        .unwrap_or_default()
+        .max("TABLE".len());
+
+    println!("{:<width$} {:>15}  TARGET", "TABLE", "ROWS", width = width);
+
+    for (table, rows) in &tables {
+        let target = if options
+            .whitelisted_tables
+            .iter()
+            .any(|pattern| table.matches(pattern))
+        {
+            options.target_table_name(table)
+        } else {
+            String::new()
+        };
+
+        println!(
+            "{:<width$} {:>15}  {}",
+            table.to_string(),
+            rows,
+            target,
+            width = width
+        );
+    }
+
+    Ok(())
+}
+
+// Prints the name map of the run, every source table with its target name and why it's loaded
+pub async fn print_names(
+    extractor: &mut DatabaseExtractor,
+    options: &MigrationOptions,
+) -> Result<()> {
+    let (_, names) = resolve_source_tables(extractor, options).await?;
+    let mut entries = names.entries().to_vec();
+    entries.sort_by(|a, b| a.source.cmp(&b.source));
+
+    let source_width = entries
+        .iter()
+        .map(|entry| entry.source.to_string().len())
+        .max()
+        .unwrap_or_default()
+        .max("SOURCE".len());
+    let target_width = entries
+        .iter()
+        .map(|entry| entry.target.len())
+        .max()
+        .unwrap_or_default()
+        .max("TARGET".len());
+
+    println!(
+        "{:<source_width$}  {:<target_width$}  MIGRATED",
+        "SOURCE",
+        "TARGET",
+        source_width = source_width,
+        target_width = target_width
+    );
+
+    for entry in &entries {
+        let migrated = match (entry.migrated, entry.whitelisted) {
+            (true, true) => "whitelisted",
+            (true, false) => "included",
+            (false, _) => "no",
+        };
+
+        println!(
+            "{:<source_width$}  {:<target_width$}  {}",
+            entry.source.to_string(),
+            entry.target,
+            migrated,
+            source_width = source_width,
+            target_width = target_width
+        );
+    }
+
+    Ok(())
+}
+
+// Prints the tables a migration would load with their size, mapped column types and estimated number of batches
+pub async fn print_plan(
+    extractor: &mut DatabaseExtractor,
+    mappings: &Mappings,
+    options: &MigrationOptions,
+) -> Result<()> {
+    let (tables, _) = resolve_source_tables(extractor, options).await?;
+    let sizes = extractor
+        .fetch_table_sizes()
+        .await?
+        .into_iter()
+        .map(|(table, rows, bytes)| (table, (rows, bytes)))
+        .collect::<HashMap<_, _>>();
+
+    let (mut total_rows, mut total_bytes, mut total_batches) = (0, 0, 0);
+
+    for table in &tables {
+        let schema = extractor.get_table_schema(table).await?;
+        let mapped_schema = TableSchemaMapper::map_schema(mappings, table, &schema, options);
+
+        // The statistics don't know about flattened temporal tables, only the current rows are counted
+        let (rows, bytes) = sizes
+            .get(&SourceTable::new(&table.schema, &table.name))
+            .copied()
+            .unwrap_or_default();
+        let batches = estimate_batches(table, rows, bytes, options);
+
+        println!("{} -> {}", table, options.target_table_name(table));
+        println!(
+            "  rows: {}, size: {}, batches: {}",
+            rows,
+            format_size(bytes),
+            batches
+        );
+
+        let source_types = schema.iter().map(format_source_type).collect::<Vec<_>>();
+        let name_width = schema
+            .iter()
+            .map(|column| column.column_name.len())
+            .max()
+            .unwrap_or_default();
+        let type_width = source_types
+            .iter()
+            .map(|source_type| source_type.len())
+            .max()
+            .unwrap_or_default();
+
+        for ((column, source_type), mapped) in schema.iter().zip(&source_types).zip(&mapped_schema)
+        {
+            println!(
+                "  {:<name_width$}  {:<type_width$}  -> {} {}",
+                column.column_name,
+                source_type,
+                mapped.column_name,
+                format_column_type(mapped).to_uppercase(),
+                name_width = name_width,
+                type_width = type_width
+            );
+        }
+
+        println!();
+
+        total_rows += rows;
+        total_bytes += bytes;
+        total_batches += batches;
+    }
+
+    println!(
+        "Total: {} tables, rows: {}, size: {}, batches: {}",
+        tables.len(),
+        total_rows,
+        format_size(total_bytes),
+        total_batches
+    );
+
+    Ok(())
+}
+
+// Batches are filled up to the packet size, taking the data size of the source as the size of the INSERT statements
+fn estimate_batches(
+    table: &SourceTable,
+    rows: i64,
+    bytes: i64,
+    options: &MigrationOptions,
+) -> usize {
+    if rows <= 0 {
+        return 0;
+    }
+
+    let rows = rows as usize;
+    let by_size = (bytes.max(0) as usize).div_ceil(options.packet_bytes(table));
+    let by_rows = options
+        .batch_rows(table)
+        .map_or(1, |batch_rows| rows.div_ceil(batch_rows));
+    // Every chunk ends with a batch of its own
+    let by_chunks = options
+        .chunk_size
+        .map_or(1, |chunk_size| rows.div_ceil(chunk_size));
+
+    by_size.max(by_rows).max(by_chunks)
+}
+
+fn format_size(bytes: i64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1_048_576.0)
+}
+
+// Prints the CREATE TABLE and constraint statements the migration would run for the table
+pub async fn print_schema(
+    extractor: &mut DatabaseExtractor,
+    mappings: &Mappings,
+    options: &MigrationOptions,
+    table_name: &str,
+) -> Result<()> {
+    let mut table = SourceTable::parse(table_name);
+    resolve_temporal_table(extractor, &mut table, options.temporal_tables).await?;
+    let schema = extractor.get_table_schema(&table).await?;
+
+    if schema.is_empty() {
+        bail!("Table {} not found", table);
+    }
+
+    let (_, names) = resolve_source_tables(extractor, options).await?;
+    let formatted_tables = names.target_names();
+
+    let target_table_name = options.target_table_name(&table);
+    let mapped_schema = TableSchemaMapper::map_schema(mappings, &table, &schema, options);
+
+    let partitioning =
+        plan_partitioning(extractor, &table, &schema, &mapped_schema, options).await?;
+    let comment = table_comment(extractor, &table, options).await?;
+
+    println!(
+        "{};",
+        build_create_table_query(
+            &target_table_name,
+            &mapped_schema,
+            &options.extra_columns,
+            &options.collation,
+            comment.as_deref(),
+            partitioning.as_deref()
+        )
+    );
+
+    if let Some(query) = build_create_constraints(
+        &target_table_name,
+        &mapped_schema,
+        &formatted_tables,
+        options.dialect,
+        &[],
+        false,
+    ) {
+        println!("{};", query);
+    }
+
+    Ok(())
+}
+
+// Prints how the mapper arrived at the MySQL type of a single column
+pub async fn explain_mapping(
+    extractor: &mut DatabaseExtractor,
+    mappings: &Mappings,
+    options: &MigrationOptions,
+    table_name: &str,
+    column_name: &str,
+) -> Result<()> {
+    let table = SourceTable::parse(table_name);
+    let schema = extractor.get_table_schema(&table).await?;
+
+    if schema.is_empty() {
+        bail!("Table {} not found", table);
+    }
+
+    let Some(column) = schema
+        .iter()
+        .find(|column| column.column_name.eq_ignore_ascii_case(column_name))
+    else {
+        bail!("Column {} not found in table {}", column_name, table);
+    };
+
+    println!("Source column   {}.{}", table, column.column_name);
+    println!(
+        "Source type     {}{}",
+        format_source_type(column),
+        if column.is_nullable {
+            " NULL"
+        } else {
+            " NOT NULL"
+        }
+    );
+
+    if let Some(collation) = &column.collation {
+        println!("Collation       {}", collation);
+    }
+
+    if column.data_type == "sql_variant" {
+        let query = format!(
+            "SELECT CAST(SQL_VARIANT_PROPERTY([{0}], 'BaseType') AS NVARCHAR(128)), COUNT_BIG(*) FROM {1} \
+             WHERE [{0}] IS NOT NULL GROUP BY SQL_VARIANT_PROPERTY([{0}], 'BaseType') ORDER BY COUNT_BIG(*) DESC",
+            column.column_name,
+            table.row_source()
+        );
+        let base_types = extractor
+            .fetch_formatted_rows(query)
+            .await?
+            .iter()
+            .map(|row| format!("{} ({} rows)", unquote(&row[0]), row[1]))
+            .collect::<Vec<_>>();
+
+        println!(
+            "Base types      {}",
+            if base_types.is_empty() {
+                "none, all values are NULL".to_string()
+            } else {
+                base_types.join(", ")
+            }
+        );
+    }
+
+    let Some(mapping) = mappings.get(&column.data_type) else {
+        bail!(
+            "No mapping for data type {}, add a `from_type = \"{}\"` entry to the mappings file",
+            column.data_type,
+            column.data_type
+        );
+    };
+
+    let mut mapping_fields = vec![format!("to_type = {}", mapping.to_type)];
+    mapping_fields.push(format!("type_parameters = {}", mapping.type_parameters));
+
+    if let Some(length) = mapping.max_characters_length {
+        mapping_fields.push(format!("max_characters_length = {}", length));
+    }
+    if let Some(precision) = mapping.numeric_precision {
+        mapping_fields.push(format!("numeric_precision = {}", precision));
+    }
+    if let Some(scale) = mapping.numeric_scale {
+        mapping_fields.push(format!("numeric_scale = {}", scale));
+    }
+    if let Some(max_type) = &mapping.max_type {
+        mapping_fields.push(format!("max_type = {}", max_type));
+    }
+
+    println!(
+        "Mapping entry   from_type = {}, {}",
+        column.data_type,
+        mapping_fields.join(", ")
+    );
+
+    let mapped =
+        TableSchemaMapper::map_schema(mappings, &table, std::slice::from_ref(column), options)
+            .remove(0);
+    let (decisions, warnings) = explain_decisions(&table, column, &mapped, mapping, options);
+
+    println!("Decisions");
+    for decision in &decisions {
+        println!("  - {}", decision);
+    }
+
+    println!(
+        "Final type      {}",
+        format_column_type(&mapped).to_uppercase()
+    );
+    println!("Column name     {}", mapped.column_name);
+    println!(
+        "Constraints     {}",
+        mapped
+            .constraints
+            .as_ref()
+            .map(describe_constraint)
+            .unwrap_or_else(|| "none".to_string())
+    );
+
+    if !warnings.is_empty() {
+        println!("Warnings");
+        for warning in &warnings {
+            println!("  - {}", warning);
+        }
+    }
+
+    Ok(())
+}
+
+// Mirrors the steps of `TableSchemaMapper::map_schema` for a single column
+fn explain_decisions(
+    table: &SourceTable,
+    column: &ColumnSchema,
+    mapped: &ColumnSchema,
+    mapping: &Mapping,
+    options: &MigrationOptions,
+) -> (Vec<String>, Vec<String>) {
+    let mut decisions = Vec::new();
+    let mut warnings = Vec::new();
+
+    let is_max = column.character_maximum_length == Some(-1);
+
+    if options.strict_types && column.data_type == "uniqueidentifier" {
+        decisions.push(format!(
+            "strict types store GUIDs as {}",
+            format_column_type(mapped)
+        ));
+    } else if is_max && mapping.max_type.is_some() {
+        decisions
+            .push("(max) column, `max_type` replaces `to_type` without parameters".to_string());
+    } else if !mapping.type_parameters {
+        decisions.push(
+            "`type_parameters` is off, the source length and precision are dropped".to_string(),
+        );
+    } else {
+        match column.character_maximum_length {
+            Some(-1) => {
+                decisions.push("(max) column without `max_type`, length set to 65535".to_string())
+            }
+            Some(length) if (1..=65535).contains(&length) => {
+                decisions.push(format!("source length {} kept", length))
+            }
+            length => {
+                if let Some(length) = length.filter(|length| *length != 0) {
+                    warnings.push(format!("Source length {} doesn't fit MySQL", length));
+                }
+
+                if let Some(length) = mapping.max_characters_length {
+                    decisions.push(format!("length {} from `max_characters_length`", length));
+                }
+            }
+        }
+
+        match (column.numeric_precision, mapping.numeric_precision) {
+            (Some(precision), _) => decisions.push(format!("source precision {} kept", precision)),
+            (None, Some(precision)) => {
+                decisions.push(format!("precision {} from `numeric_precision`", precision))
+            }
+            (None, None) => {}
+        }
+
+        match column.numeric_scale {
+            Some(0) => decisions.push("scale 0 omitted".to_string()),
+            Some(scale) => decisions.push(format!("source scale {} kept", scale)),
+            None => {
+                if let Some(scale) = mapping.numeric_scale {
+                    decisions.push(format!("scale {} from `numeric_scale`", scale));
+                }
+            }
+        }
+    }
+
+    if let (Some(source), Some(precision)) = (
+        column.datetime_precision.filter(|precision| *precision > 0),
+        mapped
+            .numeric_precision
+            .filter(|_| !mapping.type_parameters),
+    ) {
+        if source as u8 > precision {
+            decisions.push(format!(
+                "source fractional seconds precision {} truncated to MySQL's {}",
+                source, precision
+            ));
+        } else {
+            decisions.push(format!(
+                "source fractional seconds precision {} kept",
+                source
+            ));
+        }
+    }
+
+    if is_char_column(column) {
+        match options.char_padding {
+            CharPadding::Pad => warnings.push(format!(
+                "MySQL removes the trailing spaces of {} values on read, set `char_padding` to \"preserve\" \
+                 to keep them or to \"trim\" to remove them from the migrated values as well",
+                format_column_type(mapped).to_uppercase()
+            )),
+            CharPadding::Preserve => decisions.push(
+                "`char_padding = \"preserve\"` keeps the trailing spaces of the values".to_string(),
+            ),
+            CharPadding::Trim => decisions.push(
+                "`char_padding = \"trim\"` removes the trailing spaces of the values".to_string(),
+            ),
+        }
+    }
+
+    if mapped.data_type.starts_with("enum(") {
+        decisions.push(
+            "`check_in_lists = \"enum\"` turns the CHECK IN list into the ENUM members".to_string(),
+        );
+    }
+
+    if column.data_type == "sql_variant" {
+        decisions.push(
+            "sql_variant values are read as text, dates in ISO format and binary values in hex"
+                .to_string(),
+        );
+    }
+
+    if column.data_type == "bit" && column.is_nullable {
+        decisions.push("NULL bits are kept as NULL, not as 0".to_string());
+    }
+
+    let formatted_name = if options.format_snake_case {
+        format_snake_case(&column.column_name)
+    } else {
+        column.column_name.clone()
+    };
+
+    if formatted_name != column.column_name {
+        decisions.push(format!(
+            "name formatted to snake case as {}",
+            formatted_name
+        ));
+    }
+
+    if mapped.column_name != formatted_name {
+        decisions.push(format!(
+            "name renamed to {} by the identifier settings",
+            mapped.column_name
+        ));
+    }
+
+    match (&column.collation, &mapped.collation) {
+        (_, Some(collation)) => decisions.push(format!("column collation {}", collation)),
+        (Some(_), None) => decisions.push(format!(
+            "table collation {} applies",
+            options.collation.name
+        )),
+        _ => {}
+    }
+
+    // utf8mb4 needs up to 4 bytes per character within the 65535 byte row limit
+    if ["char", "varchar"].contains(&mapped.data_type.to_lowercase().as_str())
+        && mapped.character_maximum_length.unwrap_or_default() > 16383
+    {
+        warnings.push(format!(
+            "{} exceeds the 16383 characters a utf8mb4 VARCHAR can hold, set a `max_type` such as `longtext` for {}",
+            format_column_type(mapped).to_uppercase(),
+            column.data_type
+        ));
+    }
+
+    let (is_key, prefixed) = match mapped.constraints {
+        Some(Constraint::PrimaryKey) => (true, true),
+        Some(Constraint::Unique) => (true, options.dialect != Dialect::MariaDb),
+        _ => (false, false),
+    };
+
+    if let Some(max_length) = key_length_limit(mapped).filter(|_| prefixed) {
+        warnings.push(format!(
+            "The key only indexes the first {} characters of the values, set `long_keys` to \"varchar\" \
+             to shorten the column instead",
+            max_length
+        ));
+    } else if is_key
+        && options.long_keys(table, &column.column_name) == LongKeys::Varchar
+        && ["varchar", "varbinary"].contains(&mapped.data_type.as_str())
+        && column
+            .character_maximum_length
+            .is_none_or(|length| length == -1 || Some(length) > mapped.character_maximum_length)
+    {
+        decisions.push(format!(
+            "`long_keys = \"varchar\"` shortens the key column to {}",
+            format_column_type(mapped)
+        ));
+    }
+
+    (decisions, warnings)
+}
+
+fn describe_constraint(constraint: &Constraint) -> String {
+    match constraint {
+        Constraint::PrimaryKey => "PRIMARY KEY".to_string(),
+        Constraint::ForeignKey {
+            referenced_table,
+            referenced_column,
+            on_delete,
+            on_update,
+        } => format!(
+            "FOREIGN KEY REFERENCES {}({}) ON DELETE {} ON UPDATE {}",
+            referenced_table, referenced_column, on_delete, on_update
+        ),
+        Constraint::Unique => "UNIQUE".to_string(),
+        Constraint::Check(clause) => format!("CHECK {}", clause),
+        Constraint::Default(value) => format!("DEFAULT {}", value),
+    }
+}
+
+const PREVIEW_VALUE_WIDTH: usize = 60;
+
+// Prints a few rows of the table as read from MSSQL next to the values the migration would insert
+pub async fn preview_rows(
+    extractor: &mut DatabaseExtractor,
+    mappings: &Mappings,
+    options: &MigrationOptions,
+    table_name: &str,
+    rows: usize,
+) -> Result<()> {
+    let table = SourceTable::parse(table_name);
+    let schema = extractor.get_table_schema(&table).await?;
+
+    if schema.is_empty() {
+        bail!("Table {} not found", table);
+    }
+
+    let target_table_name = options.target_table_name(&table);
+    let mapped_schema = TableSchemaMapper::map_schema(mappings, &table, &schema, options);
+
+    // Generated columns are computed by MySQL, they are neither read nor inserted
+    let (schema, mapped_schema) = loaded_columns(&schema, &mapped_schema);
+
+    // Both renderings come from the same query, so they always describe the same rows
+    let query = format!(
+        "SELECT TOP ({}) {}, {} FROM {}",
+        rows,
+        schema
+            .iter()
+            .map(source_text_expression)
+            .collect::<Vec<_>>()
+            .join(", "),
+        select_list(&schema, &mapped_schema, ""),
+        table.row_source()
+    );
+
+    let extra_values = extra_column_values(&options.extra_columns);
+    let mut value_sets = Vec::new();
+
+    for (index, mut row) in extractor
+        .fetch_formatted_rows(query)
+        .await?
+        .into_iter()
+        .enumerate()
+    {
+        let target_values = row.split_off(schema.len());
+
+        println!("Row {}", index + 1);
+        print_value_table(&schema, &mapped_schema, &row, &target_values);
+        println!();
+
+        value_sets.push(format!(
+            "({})",
+            target_values
+                .iter()
+                .chain(&extra_values)
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    if value_sets.is_empty() {
+        println!("Table {} has no rows", table);
+        return Ok(());
+    }
+
+    println!(
+        "{}{};",
+        build_insert_statement(&target_table_name, &mapped_schema, &options.extra_columns),
+        value_sets.join(",\n")
+    );
+
+    Ok(())
+}
+
+fn print_value_table(
+    schema: &[ColumnSchema],
+    mapped_schema: &[ColumnSchema],
+    source_values: &[String],
+    target_values: &[String],
+) {
+    let lines = schema
+        .iter()
+        .zip(mapped_schema)
+        .zip(source_values.iter().zip(target_values))
+        .map(|((source, target), (source_value, target_value))| {
+            [
+                source.column_name.clone(),
+                format_source_type(source),
+                truncate(&unquote(source_value)),
+                format_column_type(target),
+                truncate(target_value),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let header = [
+        "COLUMN",
+        "SOURCE TYPE",
+        "SOURCE VALUE",
+        "TARGET TYPE",
+        "TARGET VALUE",
+    ];
+
+    let widths = (0..header.len())
+        .map(|index| {
+            lines
+                .iter()
+                .map(|line| line[index].chars().count())
+                .max()
+                .unwrap_or_default()
+                .max(header[index].len())
+        })
+        .collect::<Vec<_>>();
+
+    let print_line = |cells: &[&str]| {
+        let line = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+
+        println!("  {}", line.trim_end());
+    };
+
+    print_line(&header);
+
+    for line in &lines {
+        print_line(&line.iter().map(String::as_str).collect::<Vec<_>>());
+    }
+}
+
+// Source value as text, binary values in hex and dates in ISO format
+fn source_text_expression(column: &ColumnSchema) -> String {
+    let style = match column.data_type.as_str() {
+        "binary" | "varbinary" | "image" | "timestamp" | "rowversion" => ", 1",
+        "date" | "datetime" | "datetime2" | "smalldatetime" | "datetimeoffset" | "time" => ", 121",
+        _ => "",
+    };
+
+    // `image`, `text`, `ntext` and the CLR types can't be converted directly
+    let column_name = match column.data_type.as_str() {
+        "image" => format!("CAST([{}] AS VARBINARY(MAX))", column.column_name),
+        "geometry" | "geography" => format!("[{}].STAsText()", column.column_name),
+        "hierarchyid" => format!("[{}].ToString()", column.column_name),
+        "text" | "ntext" => format!("CAST([{}] AS NVARCHAR(MAX))", column.column_name),
+        _ => format!("[{}]", column.column_name),
+    };
+
+    format!("CONVERT(NVARCHAR(MAX), {}{})", column_name, style)
+}
+
+fn format_source_type(column: &ColumnSchema) -> String {
+    match (column.character_maximum_length, column.data_type.as_str()) {
+        (Some(-1), data_type) => format!("{}(max)", data_type),
+        (Some(length), data_type) if !["text", "ntext", "image", "xml"].contains(&data_type) => {
+            format!("{}({})", data_type, length)
+        }
+        (_, "decimal" | "numeric") => format!(
+            "{}({}, {})",
+            column.data_type,
+            column.numeric_precision.unwrap_or_default(),
+            column.numeric_scale.unwrap_or_default()
+        ),
+        _ => column.data_type.clone(),
+    }
+}
+
+// Strips the quotes of a formatted string literal
+pub fn unquote(value: &str) -> String {
+    match value
+        .strip_prefix('\'')
+        .and_then(|value| value.strip_suffix('\''))
+    {
+        Some(value) => value.replace("''", "'"),
+        None => value.to_string(),
+    }
+}
+
+fn truncate(value: &str) -> String {
+    let value = value.replace(['\r', '\n'], " ");
+
+    if value.chars().count() > PREVIEW_VALUE_WIDTH {
+        format!(
+            "{}...",
+            value
+                .chars()
+                .take(PREVIEW_VALUE_WIDTH - 3)
+                .collect::<String>()
+        )
+    } else {
+        value
+    }
+}
@@ -0,0 +1,36 @@
+#[macro_use]
+extern crate log;
+
+pub use crate::api::{Migrator, MigratorBuilder};
+pub use crate::cli::run_cli;
+pub use crate::config::{
+    AssertionConfig, BinaryEncoding, CharPadding, CheckInLists, ComputedColumns, Config, CsvConfig,
+    DatabaseConfig, Dialect, Expectation, ExtraColumn, ExtractionConsistency, GeneratorConfig,
+    IdentifierCase, IdentifierConfig, LongKeys, NotificationConfig, NotificationFormat, OnInvalid,
+    PartitioningConfig, PartitioningMode, PoolConfig, SettingsConfig, SparseColumnSets,
+    TableConfig, TemporalTables, TimeZoneConfig, ValidationRule,
+};
+pub use crate::mappings::{Mapping, Mappings};
+pub use crate::migrate::migration_options::MigrationOptions;
+pub use crate::migrate::migration_report::{MigrationReport, TableReport};
+pub use crate::migrate::observer::{MigrationEvent, MigrationObserver};
+
+mod api;
+mod args;
+#[cfg(feature = "bundle")]
+mod bundle;
+mod cli;
+mod common;
+mod config;
+mod config_show;
+mod connection;
+mod export;
+mod extract;
+mod init_wizard;
+mod insert;
+mod inspect;
+mod logging;
+mod mappings;
+mod migrate;
+mod preflight;
+mod sync;
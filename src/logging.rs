@@ -0,0 +1,82 @@
+use std::io::Write;
+use std::thread;
+
+use chrono::Local;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::config::LoggingConfig;
+
+// Drops debug and trace records that don't mention any of the configured tables
+struct TableFilter {
+    inner: env_logger::Logger,
+    tables: Vec<String>,
+}
+
+impl Log for TableFilter {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() >= Level::Debug && !self.tables.is_empty() {
+            let message = record.args().to_string();
+
+            if !self.tables.iter().any(|table| message.contains(table)) {
+                return;
+            }
+        }
+
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+pub fn initialize_logger(verbose: bool, quiet: bool, config: &LoggingConfig) {
+    let level = if quiet {
+        LevelFilter::Warn
+    } else if verbose {
+        LevelFilter::Debug
+    } else {
+        config.level.unwrap_or(LevelFilter::Info)
+    };
+
+    let mut builder = env_logger::Builder::new();
+    builder
+        .filter_level(level)
+        .filter_module("tiberius", LevelFilter::Error)
+        .filter_module("sqlx", LevelFilter::Error);
+
+    // Configured module levels replace the defaults above
+    for (module, level) in &config.modules {
+        builder.filter_module(module, *level);
+    }
+
+    let logger = builder
+        .format(|buf, record| {
+            let timestamp = Local::now().format("%H:%M:%S");
+
+            writeln!(
+                buf,
+                "{} {:<5} [{}] - {}",
+                timestamp,
+                record.level(),
+                thread::current().name().unwrap_or("<unnamed>"),
+                record.args()
+            )
+        })
+        .build();
+
+    log::set_max_level(logger.filter());
+
+    let filter = TableFilter {
+        inner: logger,
+        tables: config.tables.clone(),
+    };
+
+    if log::set_boxed_logger(Box::new(filter)).is_err() {
+        eprintln!("Logger was already initialized");
+    }
+}
@@ -1,7 +1,9 @@
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct Mappings {
     mappings: HashMap<String, Mapping>,
 }
@@ -13,9 +15,22 @@ pub struct Mapping {
     pub numeric_precision: Option<u8>,
     pub numeric_scale: Option<u32>,
     pub max_characters_length: Option<u32>,
+    pub max_type: Option<String>,
 }
 
 impl Mappings {
+    // Reads a mappings file in the format of `mappings.toml`
+    pub fn load(mappings_file: impl AsRef<Path>) -> Result<Mappings> {
+        let content = fs::read_to_string(mappings_file)?;
+        Mappings::from_toml(content.parse::<toml::Value>()?)
+    }
+
+    // Maps the MSSQL type `from_type`, replacing an earlier mapping of it
+    pub fn with_mapping(mut self, from_type: &str, mapping: Mapping) -> Self {
+        self.mappings.insert(from_type.to_string(), mapping);
+        self
+    }
+
     pub fn get(&self, name: &str) -> Option<&Mapping> {
         self.mappings.get(name)
     }
@@ -24,6 +39,10 @@ impl Mappings {
         self.mappings.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
     pub(crate) fn from_toml(value: toml::Value) -> Result<Mappings> {
         let mappings_table = value
             .get("mappings")
@@ -63,6 +82,11 @@ impl Mappings {
                 .get("max_characters_length")
                 .and_then(|v| v.as_integer())
                 .map(|v| v as u32);
+            // Replaces `to_type` for `(max)` columns, which don't fit a length parameter
+            let max_type = mapping_table
+                .get("max_type")
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_string());
 
             let mapping = Mapping {
                 to_type,
@@ -70,6 +94,7 @@ impl Mappings {
                 numeric_precision,
                 numeric_scale,
                 max_characters_length,
+                max_type,
             };
 
             mappings.insert(from_type, mapping);
@@ -0,0 +1,86 @@
+use anyhow::{bail, Result};
+
+use crate::config::{AssertionConfig, Expectation};
+use crate::insert::inserter::DatabaseInserter;
+
+// Runs the configured assertions against the target, failing when any of them doesn't hold
+pub async fn run_assertions(
+    inserter: &mut DatabaseInserter,
+    assertions: &[AssertionConfig],
+) -> Result<()> {
+    if assertions.is_empty() {
+        return Ok(());
+    }
+
+    let mut failed = 0;
+
+    for assertion in assertions {
+        match inserter.fetch_scalar(&assertion.query).await {
+            Ok(value) if holds(&assertion.expectation, value.as_deref()) => {
+                info!("Assertion passed: {}", assertion.name);
+            }
+            Ok(value) => {
+                failed += 1;
+                warn!(
+                    "Assertion failed: {}, expected {}, got {}",
+                    assertion.name,
+                    describe(&assertion.expectation),
+                    value.as_deref().unwrap_or("NULL")
+                );
+            }
+            Err(err) => {
+                failed += 1;
+                warn!(
+                    "Assertion failed: {}, query error: {:#}",
+                    assertion.name, err
+                );
+            }
+        }
+    }
+
+    info!(
+        "Assertions passed: {}, failed: {}",
+        assertions.len() - failed,
+        failed
+    );
+
+    if failed > 0 {
+        bail!("{} of {} assertions failed", failed, assertions.len());
+    }
+
+    Ok(())
+}
+
+fn holds(expectation: &Expectation, value: Option<&str>) -> bool {
+    let Some(value) = value else {
+        return false;
+    };
+
+    match expectation {
+        Expectation::Equals(expected) => match (value.parse::<f64>(), expected.parse::<f64>()) {
+            (Ok(value), Ok(expected)) => value == expected,
+            _ => value == expected,
+        },
+        Expectation::Compare(operator, expected) => {
+            let Ok(value) = value.parse::<f64>() else {
+                return false;
+            };
+
+            match operator.as_str() {
+                "<" => value < *expected,
+                "<=" => value <= *expected,
+                ">" => value > *expected,
+                ">=" => value >= *expected,
+                "!=" => value != *expected,
+                _ => value == *expected,
+            }
+        }
+    }
+}
+
+fn describe(expectation: &Expectation) -> String {
+    match expectation {
+        Expectation::Equals(expected) => expected.clone(),
+        Expectation::Compare(operator, expected) => format!("{} {}", operator, expected),
+    }
+}
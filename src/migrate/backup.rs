@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use chrono::Local;
+
+use crate::common::identifiers::{quote_identifier, quote_literal};
+use crate::insert::inserter::DatabaseInserter;
+
+pub const BACKUP_RECORDS_TABLE: &str = "_db_migrator_backups";
+
+const BACKUP_INFIX: &str = "__backup_";
+pub const MAX_IDENTIFIER_LENGTH: usize = 64;
+
+pub fn new_run_id() -> String {
+    Local::now().format("%Y%m%d%H%M%S").to_string()
+}
+
+// Renames the existing tables out of the way and records them under the run id
+pub async fn backup_tables(
+    inserter: &mut DatabaseInserter,
+    tables: &[String],
+    run_id: &str,
+) -> Result<()> {
+    let existing_tables = inserter.get_all_tables().await?;
+
+    let tables = existing_tables
+        .iter()
+        .filter(|table| {
+            tables
+                .iter()
+                .any(|t| t.to_lowercase() == table.to_lowercase())
+        })
+        .collect::<Vec<_>>();
+
+    if tables.is_empty() {
+        debug!("No tables to back up");
+        return Ok(());
+    }
+
+    let mut queries = vec![format!(
+        "CREATE TABLE IF NOT EXISTS `{}` (\
+            `run_id` VARCHAR(32) NOT NULL, \
+            `table_name` VARCHAR(64) NOT NULL, \
+            `backup_name` VARCHAR(64) NOT NULL, \
+            `created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP, \
+            PRIMARY KEY (`run_id`, `table_name`)\
+        )",
+        BACKUP_RECORDS_TABLE
+    )];
+
+    for table in &tables {
+        let backup_name = backup_name(table, run_id);
+
+        queries.push(format!(
+            "RENAME TABLE {} TO {}",
+            quote_identifier(table),
+            quote_identifier(&backup_name)
+        ));
+        queries.push(format!(
+            "INSERT INTO `{}` (`run_id`, `table_name`, `backup_name`) VALUES ({}, {}, {})",
+            BACKUP_RECORDS_TABLE,
+            quote_literal(run_id),
+            quote_literal(table),
+            quote_literal(&backup_name)
+        ));
+    }
+
+    inserter.execute_transactional_queries(&queries).await?;
+
+    info!(
+        "Backed up {} tables as run {}, restore them with `rollback --run {}`",
+        tables.len(),
+        run_id,
+        run_id
+    );
+
+    Ok(())
+}
+
+// Replaces the tables with their backups of the run
+pub async fn rollback_run(inserter: &mut DatabaseInserter, run_id: &str) -> Result<()> {
+    let backups = fetch_runs(inserter)
+        .await?
+        .remove(run_id)
+        .unwrap_or_default();
+
+    if backups.is_empty() {
+        bail!("No backups found for run {}", run_id);
+    }
+
+    let mut queries = backups
+        .iter()
+        .map(|(table, _)| format!("DROP TABLE IF EXISTS {}", quote_identifier(table)))
+        .collect::<Vec<_>>();
+
+    // A single statement renames all tables atomically
+    queries.push(format!(
+        "RENAME TABLE {}",
+        backups
+            .iter()
+            .map(|(table, backup)| {
+                format!(
+                    "{} TO {}",
+                    quote_identifier(backup),
+                    quote_identifier(table)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    queries.push(format!(
+        "DELETE FROM `{}` WHERE `run_id` = {}",
+        BACKUP_RECORDS_TABLE,
+        quote_literal(run_id)
+    ));
+
+    inserter.execute_transactional_queries(&queries).await?;
+
+    info!("Restored {} tables of run {}", backups.len(), run_id);
+
+    Ok(())
+}
+
+// Drops the backups of all but the latest runs
+pub async fn cleanup_backups(inserter: &mut DatabaseInserter, keep: usize) -> Result<()> {
+    let runs = fetch_runs(inserter).await?;
+    let expired = runs.len().saturating_sub(keep);
+
+    if expired == 0 {
+        info!("No backups to clean up");
+        return Ok(());
+    }
+
+    // Run ids are timestamps, so the first ones are the oldest
+    for (run_id, backups) in runs.into_iter().take(expired) {
+        let mut queries = backups
+            .iter()
+            .map(|(_, backup)| format!("DROP TABLE IF EXISTS {}", quote_identifier(backup)))
+            .collect::<Vec<_>>();
+        queries.push(format!(
+            "DELETE FROM `{}` WHERE `run_id` = {}",
+            BACKUP_RECORDS_TABLE,
+            quote_literal(&run_id)
+        ));
+
+        inserter.execute_transactional_queries(&queries).await?;
+
+        info!("Dropped {} backup tables of run {}", backups.len(), run_id);
+    }
+
+    Ok(())
+}
+
+// Table and backup names by run id
+async fn fetch_runs(
+    inserter: &mut DatabaseInserter,
+) -> Result<BTreeMap<String, Vec<(String, String)>>> {
+    let mut runs: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+
+    for (run_id, table, backup) in inserter.fetch_backup_records().await? {
+        runs.entry(run_id).or_default().push((table, backup));
+    }
+
+    Ok(runs)
+}
+
+fn backup_name(table_name: &str, run_id: &str) -> String {
+    let suffix = format!("{}{}", BACKUP_INFIX, run_id);
+    let table_part = table_name
+        .chars()
+        .take(MAX_IDENTIFIER_LENGTH - suffix.len())
+        .collect::<String>();
+
+    format!("{}{}", table_part, suffix)
+}
@@ -0,0 +1,525 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::TryStreamExt;
+use tokio::time::Instant;
+
+use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::config::OnInvalid;
+use crate::extract::extractor::{open_source_stream, select_list, DatabaseExtractor};
+use crate::extract::row_cache::RowCache;
+use crate::insert::inserter::DatabaseInserter;
+use crate::insert::query::{
+    build_append_clause, build_insert_statement, build_upsert_clause, extra_column_values,
+};
+use crate::migrate::char_padding::CharTrim;
+use crate::migrate::checkpoint::TimeBox;
+use crate::migrate::concurrency_limiter::ConcurrencyLimiter;
+use crate::migrate::dead_letters::DeadLetters;
+use crate::migrate::id_offsets::IdOffsets;
+use crate::migrate::lob_writer::LobWriter;
+use crate::migrate::memory_budget::{MemoryBudget, MemoryReservation};
+use crate::migrate::migration_options::MigrationOptions;
+use crate::migrate::null_fallback::NullFallbacks;
+use crate::migrate::observer::{MigrationEvent, Observers};
+use crate::migrate::quarantine::Quarantine;
+use crate::migrate::strict_types::StrictTypes;
+use crate::migrate::table_schema_mapper::loaded_columns;
+use crate::migrate::time_zones::TimeZoneConversion;
+use crate::migrate::validation::Validation;
+
+const RESERVED_BYTES: usize = 10;
+
+pub type RowStream<'a> = BoxStream<'a, Result<Vec<String>, tiberius::error::Error>>;
+
+// One multi-row INSERT along with the value tuples it was built from
+pub struct Batch<'a> {
+    pub insert_statement: &'a str,
+    pub rows: &'a [String],
+    pub suffix: &'a str,
+    pub query: &'a str,
+}
+
+impl Batch<'_> {
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+}
+
+// Statement parts and row processing shared by all batches of a table
+pub struct InsertPlan {
+    // Columns the source rows are read with
+    pub select_list: String,
+    pub insert_statement: String,
+    pub suffix: String,
+    extra_values: Vec<String>,
+    lob_writer: Option<LobWriter>,
+    strict_types: Option<StrictTypes>,
+    null_fallbacks: Option<NullFallbacks>,
+    id_offsets: Option<IdOffsets>,
+    time_zones: Option<TimeZoneConversion>,
+    char_trim: Option<CharTrim>,
+    validation: Option<Validation>,
+    batch_rows: Option<usize>,
+    memory_budget: Option<Arc<MemoryBudget>>,
+    // Lowered when the server rejects a batch as too large, for the remaining batches of the table
+    packet_bytes: AtomicUsize,
+}
+
+impl InsertPlan {
+    pub fn new(
+        table: &SourceTable,
+        table_name: &str,
+        source_schema: &[ColumnSchema],
+        mapped_schema: &[ColumnSchema],
+        options: &MigrationOptions,
+    ) -> Self {
+        let (source_schema, mapped_schema) = &loaded_columns(source_schema, mapped_schema);
+
+        // The LOAD DATA statement of a CSV file sets the extra columns itself
+        let extra_columns = match options.csv {
+            Some(_) => &[][..],
+            None => options.extra_columns.as_slice(),
+        };
+
+        InsertPlan {
+            select_list: select_list(source_schema, mapped_schema, ""),
+            insert_statement: build_insert_statement(table_name, mapped_schema, extra_columns),
+            suffix: if options.upsert {
+                build_upsert_clause(mapped_schema)
+            } else if options.append {
+                build_append_clause(mapped_schema)
+            } else {
+                String::new()
+            },
+            extra_values: extra_column_values(extra_columns),
+            // CSV files hold large values inline, there are no statements to append them
+            lob_writer: match options.csv {
+                Some(_) => None,
+                None => LobWriter::new(table_name, source_schema, mapped_schema),
+            },
+            strict_types: options
+                .strict_types
+                .then(|| StrictTypes::new(source_schema, mapped_schema, options.guid_format)),
+            null_fallbacks: options.table_config(table).and_then(|config| {
+                NullFallbacks::new(
+                    table_name,
+                    &config.null_fallbacks,
+                    source_schema,
+                    mapped_schema,
+                )
+            }),
+            id_offsets: IdOffsets::new(table, source_schema, options),
+            time_zones: TimeZoneConversion::new(table, source_schema, options),
+            char_trim: CharTrim::new(source_schema, options),
+            validation: Validation::new(table, table_name, source_schema, options),
+            batch_rows: options.batch_rows(table),
+            memory_budget: options.memory_budget.clone(),
+            packet_bytes: AtomicUsize::new(options.packet_bytes(table)),
+        }
+    }
+
+    // Memory of rows held for the table, counted against `--max-memory` when set
+    pub fn memory_reservation(&self) -> MemoryReservation {
+        MemoryReservation::new(self.memory_budget.clone())
+    }
+
+    pub fn packet_bytes(&self) -> usize {
+        self.packet_bytes.load(Ordering::Relaxed)
+    }
+
+    fn reduce_packet_bytes(&self, packet_bytes: usize) -> usize {
+        self.packet_bytes.fetch_min(packet_bytes, Ordering::Relaxed);
+        self.packet_bytes()
+    }
+
+    pub fn skipped_rows(&self) -> usize {
+        self.validation
+            .as_ref()
+            .map(|validation| validation.skipped_rows())
+            .unwrap_or_default()
+    }
+
+    pub fn log_row_changes(&self, table_name: &str) {
+        if let Some(null_fallbacks) = &self.null_fallbacks {
+            null_fallbacks.log_substitutions(table_name);
+        }
+
+        if let Some(validation) = &self.validation {
+            validation.log_violations(table_name);
+        }
+    }
+
+    // Checks and adjusts the row values, returning the statements to run once the row is written
+    fn prepare_row(&self, row: &mut [String]) -> Result<Vec<String>> {
+        if let Some(null_fallbacks) = &self.null_fallbacks {
+            null_fallbacks.apply(row);
+        }
+
+        if let Some(id_offsets) = &self.id_offsets {
+            id_offsets.apply(row)?;
+        }
+
+        if let Some(time_zones) = &self.time_zones {
+            time_zones.apply(row)?;
+        }
+
+        if let Some(char_trim) = &self.char_trim {
+            char_trim.apply(row);
+        }
+
+        if let Some(strict_types) = &self.strict_types {
+            strict_types.apply(row)?;
+        }
+
+        Ok(self
+            .lob_writer
+            .as_ref()
+            .map(|lob_writer| lob_writer.detach(row, self.packet_bytes()))
+            .unwrap_or_default())
+    }
+}
+
+#[async_trait]
+pub trait BatchSink: Send {
+    async fn write_batch(&mut self, batch: &Batch<'_>) -> Result<()>;
+
+    // Statements completing rows of the preceding batch, like the pieces of large binary values
+    async fn execute_statement(&mut self, statement: &str) -> Result<()>;
+
+    // Rows failing validation with `on_invalid = "quarantine"`, sinks without a quarantine leave them out
+    async fn quarantine_row(&mut self, _row: &str, _error: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Executes batches against the target database, reporting latencies to the limiter
+pub struct InserterSink<'a> {
+    pub inserter: &'a mut DatabaseInserter,
+    pub limiter: &'a ConcurrencyLimiter,
+    pub quarantine: Option<&'a Quarantine>,
+    pub dead_letters: Option<&'a DeadLetters>,
+    pub table_name: &'a str,
+    pub observers: &'a Observers,
+    // Checked before every batch, no batch is started once the time budget is used up
+    pub time_box: Option<&'a TimeBox>,
+}
+
+#[async_trait]
+impl BatchSink for InserterSink<'_> {
+    async fn write_batch(&mut self, batch: &Batch<'_>) -> Result<()> {
+        if let Some(time_box) = self.time_box {
+            time_box.check()?;
+        }
+
+        let start_time = Instant::now();
+
+        let query_str = batch.query;
+        let transaction_count = batch.len();
+
+        debug!(
+            "Sending {} bytes batch with {} transactions",
+            query_str.len(),
+            transaction_count
+        );
+
+        let result = self
+            .inserter
+            .execute_transactional_query(query_str)
+            .await
+            .with_context(|| "Failed to execute transactional query batch".to_string());
+
+        let end_time = Instant::now();
+
+        self.limiter.record_batch(
+            end_time.saturating_duration_since(start_time),
+            result.is_ok(),
+        );
+
+        match (&result, self.quarantine, self.dead_letters) {
+            // Batches too large for the server are split by the caller until single rows remain
+            (Err(err), Some(quarantine), _) if batch.len() == 1 || !is_packet_error(err) => {
+                debug!("Batch failed, retrying rows one by one: {:#}", err);
+
+                let quarantined = quarantine.salvage(self.inserter, batch).await?;
+
+                warn!(
+                    "{} of {} rows were quarantined into {}",
+                    quarantined,
+                    transaction_count,
+                    quarantine.qualified_name()
+                );
+
+                return Ok(());
+            }
+            (Err(err), None, Some(dead_letters)) if batch.len() == 1 || !is_packet_error(err) => {
+                debug!("Batch failed, retrying rows one by one: {:#}", err);
+
+                let failed = dead_letters.salvage(self.inserter, batch).await?;
+
+                warn!(
+                    "{} of {} rows were skipped and written to {}",
+                    failed,
+                    transaction_count,
+                    dead_letters.path().display()
+                );
+
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        result?;
+
+        self.observers
+            .emit(MigrationEvent::BatchExecuted {
+                table: self.table_name.to_string(),
+                rows: transaction_count,
+                bytes: query_str.len(),
+                duration: end_time.saturating_duration_since(start_time),
+            })
+            .await;
+
+        Ok(())
+    }
+
+    async fn execute_statement(&mut self, statement: &str) -> Result<()> {
+        self.inserter
+            .execute_transactional_query(statement)
+            .await
+            .with_context(|| "Failed to append large value".to_string())
+    }
+
+    async fn quarantine_row(&mut self, row: &str, error: &str) -> Result<()> {
+        let quarantine = self
+            .quarantine
+            .ok_or_else(|| anyhow!("Quarantining invalid rows requires --quarantine"))?;
+
+        quarantine.store_invalid(self.inserter, row, error).await
+    }
+}
+
+// Groups streamed rows into multi-row INSERT statements that fit within the packet size
+pub async fn write_batches<S: BatchSink + ?Sized>(
+    sink: &mut S,
+    mut stream: RowStream<'_>,
+    plan: &InsertPlan,
+) -> Result<usize> {
+    let insert_statement = plan.insert_statement.as_str();
+    let suffix = plan.suffix.as_str();
+    let mut rows: Vec<String> = Vec::new();
+    let mut total_bytes = insert_statement.len() + suffix.len();
+    let mut total_transaction_count = 0;
+    let mut reservation = plan.memory_reservation();
+
+    while let Some(mut row_values) = stream.try_next().await? {
+        // Rules apply to the values as extracted, before any of them is adjusted
+        if let Some(validation) = &plan.validation {
+            if let Some(violation) = validation.check(&row_values) {
+                match validation.on_invalid {
+                    OnInvalid::Fail => return Err(anyhow!(violation)),
+                    OnInvalid::Skip => {}
+                    OnInvalid::Quarantine => {
+                        sink.quarantine_row(&format!("({})", row_values.join(", ")), &violation)
+                            .await?
+                    }
+                }
+
+                continue;
+            }
+        }
+
+        let lob_statements = plan.prepare_row(&mut row_values)?;
+        row_values.extend(plan.extra_values.iter().cloned());
+
+        let values = row_values.join(", ");
+        let value_set = format!("({}) ", values);
+        let value_set_bytes = value_set.len();
+
+        let batch_full = plan
+            .batch_rows
+            .is_some_and(|batch_rows| rows.len() >= batch_rows);
+
+        if (batch_full || RESERVED_BYTES + total_bytes + value_set_bytes > plan.packet_bytes())
+            && !rows.is_empty()
+        {
+            total_transaction_count += flush_batch(sink, plan, &mut rows).await?;
+            total_bytes = insert_statement.len() + suffix.len();
+            reservation.release();
+        }
+
+        // The rows are held twice while the query is built from them
+        if !reservation.try_grow(value_set_bytes * 2) {
+            // Close to the memory ceiling the batch is written early, or waits for room without holding any rows
+            if !rows.is_empty() {
+                debug!(
+                    "Writing a batch of {} rows early, the memory ceiling is reached",
+                    rows.len()
+                );
+
+                total_transaction_count += flush_batch(sink, plan, &mut rows).await?;
+                total_bytes = insert_statement.len() + suffix.len();
+                reservation.release();
+            }
+
+            reservation.grow(value_set_bytes * 2).await;
+        }
+
+        if !rows.is_empty() {
+            // Separating comma
+            total_bytes += 1;
+        }
+
+        total_bytes += value_set_bytes;
+        rows.push(value_set);
+
+        // The row has to exist before its large values can be appended
+        if !lob_statements.is_empty() {
+            total_transaction_count += flush_batch(sink, plan, &mut rows).await?;
+            total_bytes = insert_statement.len() + suffix.len();
+            reservation.release();
+
+            for statement in &lob_statements {
+                sink.execute_statement(statement).await?;
+            }
+        }
+    }
+
+    if !rows.is_empty() {
+        // If there are remaining rows, execute them
+        total_transaction_count += flush_batch(sink, plan, &mut rows).await?;
+    }
+
+    Ok(total_transaction_count)
+}
+
+// Writes the rows as one batch, splitting it in halves as long as the server rejects it as too large
+pub async fn flush_batch<S: BatchSink + ?Sized>(
+    sink: &mut S,
+    plan: &InsertPlan,
+    rows: &mut Vec<String>,
+) -> Result<usize> {
+    let mut pending = vec![std::mem::take(rows)];
+    let mut transaction_count = 0;
+
+    while let Some(mut batch_rows) = pending.pop() {
+        let query = format!(
+            "{}{}{}",
+            plan.insert_statement,
+            batch_rows.join(","),
+            plan.suffix
+        );
+
+        let result = sink
+            .write_batch(&Batch {
+                insert_statement: &plan.insert_statement,
+                rows: &batch_rows,
+                suffix: &plan.suffix,
+                query: &query,
+            })
+            .await;
+
+        match result {
+            Ok(()) => transaction_count += batch_rows.len(),
+            Err(err) if batch_rows.len() > 1 && is_packet_error(&err) => {
+                let packet_bytes = plan.reduce_packet_bytes(query.len() / 2);
+
+                warn!(
+                    "Batch of {} bytes was rejected as too large, retrying it in halves and limiting batches to {} bytes: {:#}",
+                    query.len(),
+                    packet_bytes,
+                    err
+                );
+
+                // Popped in order, the first half is written first
+                let second_half = batch_rows.split_off(batch_rows.len() / 2);
+                pending.push(second_half);
+                pending.push(batch_rows);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(transaction_count)
+}
+
+// Server rejections of oversized packets, which may also surface as a dropped connection
+fn is_packet_error(err: &anyhow::Error) -> bool {
+    let message = format!("{:#}", err).to_lowercase();
+
+    [
+        "max_allowed_packet",
+        "packet too large",
+        "packet bigger",
+        "connection reset",
+        "broken pipe",
+    ]
+    .iter()
+    .any(|pattern| message.contains(pattern))
+}
+
+// Streams a whole table into the sink, chunk after chunk when a chunk size is configured
+pub async fn write_table_batches<S: BatchSink + ?Sized>(
+    extractor: &mut DatabaseExtractor,
+    sink: &mut S,
+    table: &SourceTable,
+    table_schema: &[ColumnSchema],
+    insert_plan: &InsertPlan,
+    options: &MigrationOptions,
+) -> Result<usize> {
+    let plan = match options.chunk_size {
+        Some(chunk_size) => {
+            extractor
+                .plan_chunks(table, table_schema, chunk_size)
+                .await?
+        }
+        None => None,
+    };
+
+    let cache = RowCache::from_options(options, extractor, table, table_schema).await?;
+
+    match plan {
+        Some(plan) => {
+            let mut rows = 0;
+
+            for index in 0..plan.chunk_count() {
+                let mut conn = extractor.connection().await?;
+                let stream = open_source_stream(
+                    &mut conn,
+                    table,
+                    &insert_plan.select_list,
+                    Some(&plan),
+                    index,
+                    cache.as_ref(),
+                )
+                .await?;
+
+                rows += write_batches(sink, stream, insert_plan)
+                    .await
+                    .with_context(|| format!("Failed to write chunk {}", index))?;
+            }
+
+            Ok(rows)
+        }
+        None => {
+            let mut conn = extractor.connection().await?;
+            let stream = open_source_stream(
+                &mut conn,
+                table,
+                &insert_plan.select_list,
+                None,
+                0,
+                cache.as_ref(),
+            )
+            .await?;
+
+            write_batches(sink, stream, insert_plan)
+                .await
+                .with_context(|| "Failed to write rows".to_string())
+        }
+    }
+}
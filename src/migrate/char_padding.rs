@@ -0,0 +1,47 @@
+use crate::common::schema::ColumnSchema;
+use crate::config::CharPadding;
+use crate::migrate::migration_options::MigrationOptions;
+
+// Fixed length types MSSQL pads with trailing spaces
+pub const CHAR_TYPES: [&str; 2] = ["char", "nchar"];
+
+pub fn is_char_column(column: &ColumnSchema) -> bool {
+    CHAR_TYPES.contains(&column.data_type.as_str())
+}
+
+// Removes the padding of fixed length text values, for `char_padding = "trim"`
+pub struct CharTrim {
+    columns: Vec<usize>,
+}
+
+impl CharTrim {
+    pub fn new(source_schema: &[ColumnSchema], options: &MigrationOptions) -> Option<Self> {
+        if options.char_padding != CharPadding::Trim {
+            return None;
+        }
+
+        let columns = source_schema
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| is_char_column(column))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        (!columns.is_empty()).then_some(CharTrim { columns })
+    }
+
+    pub fn apply(&self, row: &mut [String]) {
+        for index in &self.columns {
+            let value = &mut row[*index];
+
+            // Quoted literals only, NULL stays as it is
+            if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+                let trimmed = value[1..value.len() - 1].trim_end_matches(' ').len() + 1;
+
+                if trimmed < value.len() - 1 {
+                    value.replace_range(trimmed..value.len() - 1, "");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::{Context, Result};
+
+use crate::common::constraints::Constraint;
+use crate::common::identifiers::{quote_identifier, quote_literal};
+use crate::insert::inserter::DatabaseInserter;
+use crate::migrate::migration_result::MigrationResult;
+
+const TRIGGER_SUFFIX: &str = "_check_emulation";
+const MAX_IDENTIFIER_LENGTH: usize = 64;
+
+// MySQL parses CHECK constraints but only enforces them from 8.0.16 on, MariaDB from 10.2.1 on
+pub fn enforces_check_constraints(server_version: &str) -> bool {
+    let numbers = server_version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|part| !part.is_empty())
+        .take(3)
+        .map(|part| part.parse::<u32>().unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    let version = (
+        numbers.first().copied().unwrap_or_default(),
+        numbers.get(1).copied().unwrap_or_default(),
+        numbers.get(2).copied().unwrap_or_default(),
+    );
+
+    if server_version.to_lowercase().contains("mariadb") {
+        version >= (10, 2, 1)
+    } else {
+        version >= (8, 0, 16)
+    }
+}
+
+// Creates BEFORE INSERT and BEFORE UPDATE triggers enforcing the check clauses of the tables,
+// returns the names of the created triggers
+pub async fn create_check_triggers(
+    inserter: &mut DatabaseInserter,
+    results: &[MigrationResult],
+) -> Result<Vec<String>> {
+    let mut created = Vec::new();
+
+    for result in results.iter().filter(|result| result.created) {
+        let conditions = result
+            .schema
+            .iter()
+            .filter_map(|column| match &column.constraints {
+                Some(Constraint::Check(check_clause)) => Some(check_clause.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        if conditions.is_empty() {
+            continue;
+        }
+
+        for (event, event_suffix) in [("INSERT", "_bi"), ("UPDATE", "_bu")] {
+            let trigger_name = trigger_name(&result.table_name, event_suffix);
+
+            let checks = conditions
+                .iter()
+                .map(|condition| {
+                    format!(
+                        "IF NOT ({}) THEN SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = {}; END IF;",
+                        reference_new_row(condition),
+                        quote_literal(
+                            &format!(
+                                "CHECK constraint {} violated on {}",
+                                condition, result.table_name
+                            )
+                            .chars()
+                            .take(128)
+                            .collect::<String>()
+                        )
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            let queries = [
+                format!("DROP TRIGGER IF EXISTS {}", quote_identifier(&trigger_name)),
+                format!(
+                    "CREATE TRIGGER {} BEFORE {} ON {} FOR EACH ROW BEGIN {} END",
+                    quote_identifier(&trigger_name),
+                    event,
+                    quote_identifier(&result.table_name),
+                    checks
+                ),
+            ];
+
+            inserter
+                .execute_transactional_queries(&queries)
+                .await
+                .with_context(|| format!("Failed to create trigger {}", trigger_name))?;
+
+            created.push(trigger_name);
+        }
+    }
+
+    Ok(created)
+}
+
+fn trigger_name(table_name: &str, event_suffix: &str) -> String {
+    let suffix = format!("{}{}", TRIGGER_SUFFIX, event_suffix);
+    let table_part = table_name
+        .chars()
+        .take(MAX_IDENTIFIER_LENGTH - suffix.len())
+        .collect::<String>();
+
+    format!("{}{}", table_part, suffix)
+}
+
+// Translated clauses quote every column in backticks, which become references to the new row
+fn reference_new_row(condition: &str) -> String {
+    let mut result = String::with_capacity(condition.len());
+    let mut in_identifier = false;
+    let mut chars = condition.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '`' && !in_identifier {
+            result.push_str("NEW.");
+        }
+        result.push(c);
+
+        // Backticks inside a quoted identifier are doubled
+        if c == '`' && in_identifier && chars.peek() == Some(&'`') {
+            result.push('`');
+            chars.next();
+        } else if c == '`' {
+            in_identifier = !in_identifier;
+        }
+    }
+
+    result
+}
@@ -0,0 +1,346 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Error, Result};
+use tokio::time::Instant;
+use toml::value::{Table, Value};
+
+use crate::extract::chunk::ChunkPlan;
+
+// Error of tables stopped because the time budget of the run was used up
+#[derive(Debug)]
+pub struct Suspended;
+
+impl fmt::Display for Suspended {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Time budget of the run is used up")
+    }
+}
+
+impl std::error::Error for Suspended {}
+
+pub fn is_suspended(err: &Error) -> bool {
+    err.chain().any(|cause| cause.is::<Suspended>())
+}
+
+// Tables and chunks loaded by a suspended run, by target table name
+#[derive(Debug, Default, Clone)]
+pub struct Checkpoint {
+    completed_tables: BTreeSet<String>,
+    chunks: BTreeMap<String, TableChunks>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableChunks {
+    // Chunk count and bounds of the plan the chunks belong to, a different plan can't reuse them
+    pub chunk_count: usize,
+    pub bounds: String,
+    pub completed: BTreeSet<usize>,
+}
+
+impl TableChunks {
+    fn new(plan: &ChunkPlan) -> Self {
+        TableChunks {
+            chunk_count: plan.chunk_count(),
+            bounds: plan.bounds(),
+            completed: BTreeSet::new(),
+        }
+    }
+
+    pub fn belong_to(&self, plan: &ChunkPlan) -> bool {
+        self.chunk_count == plan.chunk_count() && self.bounds == plan.bounds()
+    }
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> Result<Self> {
+        let value = fs::read_to_string(path)?.parse::<Value>()?;
+
+        let completed_tables = value
+            .get("completed_tables")
+            .and_then(|value| value.as_array())
+            .ok_or_else(|| anyhow!("Missing or invalid completed tables"))?
+            .iter()
+            .map(|table| {
+                table
+                    .as_str()
+                    .map(|table| table.to_string())
+                    .ok_or_else(|| anyhow!("Invalid completed table"))
+            })
+            .collect::<Result<BTreeSet<_>>>()?;
+
+        let chunks = match value.get("chunks") {
+            Some(chunks) => chunks
+                .as_table()
+                .ok_or_else(|| anyhow!("Invalid chunks"))?
+                .iter()
+                .map(|(table, chunks)| {
+                    parse_table_chunks(chunks)
+                        .map(|chunks| (table.clone(), chunks))
+                        .with_context(|| format!("Invalid chunks of table {}", table))
+                })
+                .collect::<Result<BTreeMap<_, _>>>()?,
+            None => BTreeMap::new(),
+        };
+
+        Ok(Checkpoint {
+            completed_tables,
+            chunks,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let chunks = self
+            .chunks
+            .iter()
+            .map(|(table, chunks)| {
+                let mut value = Table::new();
+                value.insert(
+                    "chunk_count".into(),
+                    Value::Integer(chunks.chunk_count as i64),
+                );
+                value.insert("bounds".into(), Value::String(chunks.bounds.clone()));
+                value.insert(
+                    "completed".into(),
+                    Value::Array(
+                        chunks
+                            .completed
+                            .iter()
+                            .map(|index| Value::Integer(*index as i64))
+                            .collect(),
+                    ),
+                );
+
+                (table.clone(), Value::Table(value))
+            })
+            .collect::<Table>();
+
+        let mut value = Table::new();
+        value.insert(
+            "completed_tables".into(),
+            Value::Array(
+                self.completed_tables
+                    .iter()
+                    .cloned()
+                    .map(Value::String)
+                    .collect(),
+            ),
+        );
+        value.insert("chunks".into(), Value::Table(chunks));
+
+        fs::write(path, toml::to_string(&Value::Table(value))?)?;
+
+        Ok(())
+    }
+}
+
+fn parse_table_chunks(value: &Value) -> Result<TableChunks> {
+    let chunk_count = value
+        .get("chunk_count")
+        .and_then(|value| value.as_integer())
+        .filter(|count| *count > 0)
+        .ok_or_else(|| anyhow!("Missing or invalid chunk count"))? as usize;
+
+    // Checkpoints of earlier versions only kept the count, their chunks are loaded again
+    let bounds = value
+        .get("bounds")
+        .map(|value| {
+            value
+                .as_str()
+                .map(|bounds| bounds.to_string())
+                .ok_or_else(|| anyhow!("Invalid chunk bounds"))
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    let completed = value
+        .get("completed")
+        .and_then(|value| value.as_array())
+        .ok_or_else(|| anyhow!("Missing or invalid completed chunks"))?
+        .iter()
+        .map(|index| {
+            index
+                .as_integer()
+                .filter(|index| (0..chunk_count as i64).contains(index))
+                .map(|index| index as usize)
+                .ok_or_else(|| anyhow!("Invalid chunk index"))
+        })
+        .collect::<Result<BTreeSet<_>>>()?;
+
+    Ok(TableChunks {
+        chunk_count,
+        bounds,
+        completed,
+    })
+}
+
+// Deadline of a time-boxed run and the progress it checkpoints, shared by all table tasks
+#[derive(Debug)]
+pub struct TimeBox {
+    deadline: Option<Instant>,
+    checkpoint: Mutex<Checkpoint>,
+}
+
+impl TimeBox {
+    pub fn new(max_duration: Option<Duration>, checkpoint: Checkpoint) -> Self {
+        TimeBox {
+            deadline: max_duration.map(|max_duration| Instant::now() + max_duration),
+            checkpoint: Mutex::new(checkpoint),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    // Called before anything new is started, like a batch or a chunk
+    pub fn check(&self) -> Result<()> {
+        if self.is_expired() {
+            return Err(Error::new(Suspended));
+        }
+
+        Ok(())
+    }
+
+    pub fn is_completed(&self, table: &str) -> bool {
+        self.checkpoint
+            .lock()
+            .unwrap()
+            .completed_tables
+            .contains(table)
+    }
+
+    pub fn table_chunks(&self, table: &str) -> Option<TableChunks> {
+        self.checkpoint.lock().unwrap().chunks.get(table).cloned()
+    }
+
+    pub fn complete_table(&self, table: &str) {
+        let mut checkpoint = self.checkpoint.lock().unwrap();
+        checkpoint.chunks.remove(table);
+        checkpoint.completed_tables.insert(table.to_string());
+    }
+
+    pub fn complete_chunk(&self, table: &str, plan: &ChunkPlan, index: usize) {
+        let mut checkpoint = self.checkpoint.lock().unwrap();
+        let chunks = checkpoint
+            .chunks
+            .entry(table.to_string())
+            .or_insert_with(|| TableChunks::new(plan));
+
+        // Chunks of an outdated plan were discarded along with the rows of the table
+        if !chunks.belong_to(plan) {
+            *chunks = TableChunks::new(plan);
+        }
+
+        chunks.completed.insert(index);
+    }
+
+    pub fn discard_chunks(&self, table: &str) {
+        self.checkpoint.lock().unwrap().chunks.remove(table);
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.checkpoint.lock().unwrap().save(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn plan(max_key: i64) -> ChunkPlan {
+        ChunkPlan::keyset("Id".into(), 1, max_key, max_key, 10)
+    }
+
+    fn checkpoint_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "db-migrator-checkpoint-{}-{}.toml",
+            std::process::id(),
+            name
+        ))
+    }
+
+    #[test]
+    fn saves_and_loads_progress() {
+        let time_box = TimeBox::new(None, Checkpoint::default());
+        time_box.complete_table("orders");
+        time_box.complete_chunk("items", &plan(100), 3);
+        time_box.complete_chunk("items", &plan(100), 7);
+
+        let path = checkpoint_path("roundtrip");
+        time_box.save(&path).unwrap();
+        let checkpoint = Checkpoint::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let time_box = TimeBox::new(None, checkpoint);
+        let chunks = time_box.table_chunks("items").unwrap();
+
+        assert!(time_box.is_completed("orders"));
+        assert!(!time_box.is_completed("items"));
+        assert!(chunks.belong_to(&plan(100)));
+        assert_eq!(chunks.completed, BTreeSet::from([3, 7]));
+    }
+
+    #[test]
+    fn rejects_chunks_outside_the_plan() {
+        let path = checkpoint_path("invalid");
+        fs::write(
+            &path,
+            "completed_tables = []\n[chunks.items]\nchunk_count = 2\ncompleted = [2]\n",
+        )
+        .unwrap();
+        let result = Checkpoint::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            format!("{:#}", result.unwrap_err()),
+            "Invalid chunks of table items: Invalid chunk index"
+        );
+    }
+
+    #[test]
+    fn starts_over_when_the_plan_changes() {
+        let time_box = TimeBox::new(None, Checkpoint::default());
+        time_box.complete_chunk("items", &plan(100), 3);
+        time_box.complete_chunk("items", &plan(200), 5);
+
+        let chunks = time_box.table_chunks("items").unwrap();
+        assert!(!chunks.belong_to(&plan(100)));
+        assert_eq!(chunks.completed, BTreeSet::from([5]));
+
+        // Checkpoints without bounds can't tell which plan they were cut from
+        let legacy = TableChunks {
+            bounds: String::new(),
+            ..chunks
+        };
+        assert!(!legacy.belong_to(&plan(200)));
+
+        time_box.complete_table("items");
+        assert_eq!(time_box.table_chunks("items"), None);
+    }
+
+    #[test]
+    fn suspends_once_the_time_is_up() {
+        assert!(TimeBox::new(None, Checkpoint::default()).check().is_ok());
+        assert!(
+            TimeBox::new(Some(Duration::from_secs(3600)), Checkpoint::default())
+                .check()
+                .is_ok()
+        );
+
+        let err = TimeBox::new(Some(Duration::ZERO), Checkpoint::default())
+            .check()
+            .unwrap_err()
+            .context("Failed to migrate table items");
+
+        assert!(is_suspended(&err));
+        assert!(!is_suspended(&anyhow!("Connection reset")));
+    }
+}
@@ -0,0 +1,183 @@
+use anyhow::{Context, Result};
+
+use crate::common::identifiers::quote_identifier;
+use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::extract::extractor::{
+    mysql_geometry_expression, variant_text_expression, DatabaseExtractor,
+};
+use crate::insert::inserter::DatabaseInserter;
+
+// Per-table checksums computed by both databases over the same textual form of every row.
+// Each row is hashed with SHA-256 over its UTF-16LE text and the first four bytes of the
+// hashes are summed, so the result doesn't depend on the row order.
+pub async fn compare_checksums(
+    extractor: &mut DatabaseExtractor,
+    inserter: &mut DatabaseInserter,
+    table: &SourceTable,
+    target_table_name: &str,
+    source_schema: &[ColumnSchema],
+    mapped_schema: &[ColumnSchema],
+) -> Result<Option<String>> {
+    let columns = source_schema
+        .iter()
+        .zip(mapped_schema)
+        .filter_map(|(source, target)| normalize_column(source, &target.column_name))
+        .collect::<Vec<_>>();
+
+    if columns.is_empty() {
+        return Ok(None);
+    }
+
+    let (source_columns, target_columns): (Vec<_>, Vec<_>) = columns.into_iter().unzip();
+
+    let source_query = format!(
+        "SELECT ISNULL(SUM(CAST(SUBSTRING(HASHBYTES('SHA2_256', {}), 1, 4) AS BIGINT)), 0) FROM {}",
+        source_row_text(&source_columns),
+        table.row_source()
+    );
+
+    let target_query = format!(
+        "SELECT COALESCE(SUM(CAST(CONV(LEFT(SHA2(CONVERT({} USING utf16le), 256), 8), 16, 10) AS UNSIGNED)), 0) FROM {}",
+        target_row_text(&target_columns),
+        quote_identifier(target_table_name)
+    );
+
+    let source_checksum = extractor
+        .fetch_formatted_rows(source_query)
+        .await
+        .with_context(|| "Failed to compute source checksum")?
+        .into_iter()
+        .next()
+        .and_then(|row| row.into_iter().next())
+        .unwrap_or_default();
+
+    let target_checksum = inserter
+        .fetch_scalar(&target_query)
+        .await
+        .with_context(|| "Failed to compute target checksum")?
+        .unwrap_or_default();
+
+    if source_checksum == target_checksum {
+        Ok(None)
+    } else {
+        Ok(Some(format!(
+            "checksum mismatch, source: {}, target: {}",
+            source_checksum, target_checksum
+        )))
+    }
+}
+
+// Textual form of a row in MSSQL, from the source expressions of its columns
+pub fn source_row_text(columns: &[String]) -> String {
+    format!(
+        "CONCAT(CAST(N'' AS NVARCHAR(MAX)){})",
+        columns
+            .iter()
+            .enumerate()
+            .map(|(index, source)| format!(
+                ", {}ISNULL(N'+' + {}, N'-')",
+                if index > 0 { "N'|', " } else { "" },
+                source
+            ))
+            .collect::<String>()
+    )
+}
+
+// Textual form of a row in MySQL, matching `source_row_text`
+pub fn target_row_text(columns: &[String]) -> String {
+    format!(
+        "CONCAT({})",
+        columns
+            .iter()
+            .map(|target| format!("COALESCE(CONCAT('+', {}), '-')", target))
+            .collect::<Vec<_>>()
+            .join(", '|', ")
+    )
+}
+
+// Expressions rendering a column the same way on both sides, `None` for columns left out
+pub fn normalize_column(column: &ColumnSchema, target_name: &str) -> Option<(String, String)> {
+    let source = format!("[{}]", column.column_name);
+    let target = quote_identifier(target_name);
+
+    let expressions = match column.data_type.as_str() {
+        // Row versions change with every write and are not copied meaningfully
+        "timestamp" | "rowversion" => return None,
+        "bit" => (
+            format!("CAST(CAST({} AS TINYINT) AS NVARCHAR(3))", source),
+            format!("CAST({} + 0 AS CHAR)", target),
+        ),
+        "tinyint" | "smallint" | "int" | "bigint" => (
+            format!("CAST({} AS NVARCHAR(20))", source),
+            format!("CAST({} AS CHAR)", target),
+        ),
+        "decimal" | "numeric" | "money" | "smallmoney" | "float" | "real" => {
+            let scale = match column.data_type.as_str() {
+                "decimal" | "numeric" => column.numeric_scale.unwrap_or(0),
+                _ => 4,
+            };
+
+            (
+                format!(
+                    "CAST(CAST({} AS DECIMAL(38, {})) AS NVARCHAR(40))",
+                    source, scale
+                ),
+                format!("CAST(CAST({} AS DECIMAL(38, {})) AS CHAR)", target, scale),
+            )
+        }
+        "date" => (
+            format!("CONVERT(NVARCHAR(10), {}, 23)", source),
+            format!("DATE_FORMAT({}, '%Y-%m-%d')", target),
+        ),
+        "time" => (
+            format!("CONVERT(NVARCHAR(8), {}, 108)", source),
+            format!("TIME_FORMAT({}, '%H:%i:%s')", target),
+        ),
+        "datetimeoffset" => (
+            format!(
+                "CONVERT(NVARCHAR(19), SWITCHOFFSET({}, '+00:00'), 120)",
+                source
+            ),
+            format!("DATE_FORMAT({}, '%Y-%m-%d %H:%i:%s')", target),
+        ),
+        "datetime" | "datetime2" | "smalldatetime" => (
+            format!("CONVERT(NVARCHAR(19), {}, 120)", source),
+            format!("DATE_FORMAT({}, '%Y-%m-%d %H:%i:%s')", target),
+        ),
+        "binary" | "varbinary" | "image" => (
+            format!(
+                "LOWER(CONVERT(NVARCHAR(MAX), CAST({} AS VARBINARY(MAX)), 2))",
+                source
+            ),
+            format!("LOWER(HEX({}))", target),
+        ),
+        // Compared in MySQL's geometry format, as they are loaded
+        "geometry" | "geography" => (
+            format!(
+                "LOWER(CONVERT(NVARCHAR(MAX), {}, 2))",
+                mysql_geometry_expression(&source)
+            ),
+            format!("LOWER(HEX({}))", target),
+        ),
+        "sql_variant" => (
+            format!("RTRIM({})", variant_text_expression(&source)),
+            format!("RTRIM(CAST({} AS CHAR))", target),
+        ),
+        "hierarchyid" => (
+            format!("{}.ToString()", source),
+            format!("CAST({} AS CHAR)", target),
+        ),
+        "uniqueidentifier" => (
+            format!("LOWER(CAST({} AS NCHAR(36)))", source),
+            format!("LOWER({})", target),
+        ),
+        // Trailing spaces are dropped, MySQL doesn't keep the padding of CHAR columns
+        _ => (
+            format!("RTRIM(CAST({} AS NVARCHAR(MAX)))", source),
+            format!("RTRIM(CAST({} AS CHAR))", target),
+        ),
+    };
+
+    Some(expressions)
+}
@@ -0,0 +1,282 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+// Latency above this multiple of the best observed average counts as congestion
+const CONGESTION_FACTOR: f64 = 2.0;
+const LATENCY_SMOOTHING: f64 = 0.2;
+// Lets the baseline follow table mix changes instead of pinning the fastest batch ever seen
+const BASELINE_DRIFT: f64 = 0.01;
+
+pub struct ConcurrencyLimiter {
+    semaphore: Arc<Semaphore>,
+    controller: Option<Mutex<AimdController>>,
+}
+
+pub struct LimiterPermit {
+    permit: Option<OwnedSemaphorePermit>,
+    limiter: Arc<ConcurrencyLimiter>,
+}
+
+struct AimdController {
+    limit: usize,
+    min_limit: usize,
+    max_limit: usize,
+    pending_reduction: usize,
+    batches_since_change: usize,
+    successes_since_change: usize,
+    average_latency: Option<f64>,
+    best_latency: Option<f64>,
+}
+
+impl ConcurrencyLimiter {
+    pub fn fixed(limit: usize) -> Self {
+        ConcurrencyLimiter {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            controller: None,
+        }
+    }
+
+    pub fn adaptive(initial: usize, min_limit: usize, max_limit: usize) -> Self {
+        let min_limit = min_limit.max(1);
+        let max_limit = max_limit.max(min_limit);
+        let limit = initial.clamp(min_limit, max_limit);
+
+        ConcurrencyLimiter {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            controller: Some(Mutex::new(AimdController {
+                limit,
+                min_limit,
+                max_limit,
+                pending_reduction: 0,
+                batches_since_change: 0,
+                successes_since_change: 0,
+                average_latency: None,
+                best_latency: None,
+            })),
+        }
+    }
+
+    pub async fn acquire(self: &Arc<Self>) -> Result<LimiterPermit> {
+        let permit = self.semaphore.clone().acquire_owned().await?;
+
+        Ok(LimiterPermit {
+            permit: Some(permit),
+            limiter: Arc::clone(self),
+        })
+    }
+
+    pub fn current_limit(&self) -> Option<usize> {
+        self.controller
+            .as_ref()
+            .map(|controller| controller.lock().unwrap().limit)
+    }
+
+    // Additive increase on fast successful batches, multiplicative decrease on errors or slowdowns
+    pub fn record_batch(&self, latency: Duration, success: bool) {
+        let Some(controller) = &self.controller else {
+            return;
+        };
+
+        let mut controller = controller.lock().unwrap();
+        let latency = latency.as_secs_f64();
+
+        let average = match controller.average_latency {
+            Some(average) => average + LATENCY_SMOOTHING * (latency - average),
+            None => latency,
+        };
+        controller.average_latency = Some(average);
+
+        let best = match controller.best_latency {
+            Some(best) if best <= average => best + BASELINE_DRIFT * (average - best),
+            _ => average,
+        };
+        controller.best_latency = Some(best);
+
+        let congested = average > best * CONGESTION_FACTOR;
+        controller.batches_since_change += 1;
+
+        if !success || congested {
+            // Only back off once per window so a burst of bad batches doesn't collapse the limit
+            if controller.batches_since_change < controller.limit {
+                return;
+            }
+
+            let new_limit = (controller.limit / 2).max(controller.min_limit);
+            let reduction = controller.limit - new_limit;
+
+            if reduction > 0 {
+                let forgotten = self.forget_available(reduction);
+                controller.pending_reduction += reduction - forgotten;
+                controller.limit = new_limit;
+
+                debug!(
+                    "Reducing parallelism to {} ({})",
+                    new_limit,
+                    if success {
+                        "slow batches"
+                    } else {
+                        "batch error"
+                    }
+                );
+            }
+
+            controller.batches_since_change = 0;
+            controller.successes_since_change = 0;
+            // Forget the congested average so recovery is judged against fresh measurements
+            controller.average_latency = None;
+            return;
+        }
+
+        controller.successes_since_change += 1;
+
+        if controller.successes_since_change >= controller.limit
+            && controller.limit < controller.max_limit
+        {
+            controller.limit += 1;
+            controller.batches_since_change = 0;
+            controller.successes_since_change = 0;
+
+            if controller.pending_reduction > 0 {
+                controller.pending_reduction -= 1;
+            } else {
+                self.semaphore.add_permits(1);
+            }
+
+            debug!("Raising parallelism to {}", controller.limit);
+        }
+    }
+
+    fn forget_available(&self, count: usize) -> usize {
+        let mut forgotten = 0;
+
+        while forgotten < count {
+            match self.semaphore.try_acquire() {
+                Ok(permit) => {
+                    permit.forget();
+                    forgotten += 1;
+                }
+                Err(_) => break,
+            }
+        }
+
+        forgotten
+    }
+
+    fn absorb_released_permit(&self) -> bool {
+        let Some(controller) = &self.controller else {
+            return false;
+        };
+
+        let mut controller = controller.lock().unwrap();
+
+        if controller.pending_reduction > 0 {
+            controller.pending_reduction -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Drop for LimiterPermit {
+    fn drop(&mut self) {
+        if let Some(permit) = self.permit.take() {
+            // Permits released while a reduction is pending are retired instead of returned
+            if self.limiter.absorb_released_permit() {
+                permit.forget();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST: Duration = Duration::from_millis(10);
+
+    fn record(limiter: &ConcurrencyLimiter, batches: usize, latency: Duration, success: bool) {
+        for _ in 0..batches {
+            limiter.record_batch(latency, success);
+        }
+    }
+
+    #[test]
+    fn clamps_the_initial_limit() {
+        assert_eq!(
+            ConcurrencyLimiter::adaptive(10, 2, 4).current_limit(),
+            Some(4)
+        );
+        assert_eq!(
+            ConcurrencyLimiter::adaptive(1, 2, 4).current_limit(),
+            Some(2)
+        );
+        assert_eq!(
+            ConcurrencyLimiter::adaptive(0, 0, 0).current_limit(),
+            Some(1)
+        );
+        assert_eq!(ConcurrencyLimiter::fixed(4).current_limit(), None);
+    }
+
+    #[test]
+    fn raises_the_limit_by_one_per_window_of_successes() {
+        let limiter = ConcurrencyLimiter::adaptive(2, 1, 4);
+
+        record(&limiter, 1, FAST, true);
+        assert_eq!(limiter.current_limit(), Some(2));
+
+        record(&limiter, 1, FAST, true);
+        assert_eq!(limiter.current_limit(), Some(3));
+        assert_eq!(limiter.semaphore.available_permits(), 3);
+
+        record(&limiter, 20, FAST, true);
+        assert_eq!(limiter.current_limit(), Some(4));
+        assert_eq!(limiter.semaphore.available_permits(), 4);
+    }
+
+    #[test]
+    fn halves_the_limit_once_per_window_of_errors() {
+        let limiter = ConcurrencyLimiter::adaptive(8, 3, 8);
+
+        record(&limiter, 7, FAST, false);
+        assert_eq!(limiter.current_limit(), Some(8));
+
+        record(&limiter, 1, FAST, false);
+        assert_eq!(limiter.current_limit(), Some(4));
+        assert_eq!(limiter.semaphore.available_permits(), 4);
+
+        record(&limiter, 4, FAST, false);
+        assert_eq!(limiter.current_limit(), Some(3));
+    }
+
+    #[test]
+    fn backs_off_when_batches_get_slower() {
+        let limiter = ConcurrencyLimiter::adaptive(2, 1, 2);
+
+        record(&limiter, 2, FAST, true);
+        record(&limiter, 10, FAST * 10, true);
+
+        assert_eq!(limiter.current_limit(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn retires_permits_released_during_a_reduction() {
+        let limiter = Arc::new(ConcurrencyLimiter::adaptive(4, 1, 4));
+        let permits = vec![
+            limiter.acquire().await.unwrap(),
+            limiter.acquire().await.unwrap(),
+            limiter.acquire().await.unwrap(),
+        ];
+
+        // One idle permit is forgotten right away, the other held one once it is released
+        record(&limiter, 4, FAST, false);
+        assert_eq!(limiter.current_limit(), Some(2));
+        assert_eq!(limiter.semaphore.available_permits(), 0);
+
+        drop(permits);
+        assert_eq!(limiter.semaphore.available_permits(), 2);
+    }
+}
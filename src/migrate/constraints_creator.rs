@@ -2,17 +2,25 @@ use anyhow::Context;
 use futures::future::join_all;
 use tokio::spawn;
 
+use crate::common::dependency_graph::DependencyGraph;
 use crate::common::helpers::print_error_chain;
+use crate::config::Dialect;
 use crate::insert::inserter::DatabaseInserter;
 use crate::migrate::migration_result::MigrationResult;
 
 pub struct ConstraintsCreator {
     inserter: DatabaseInserter,
+    dialect: Dialect,
+    recreate: bool,
 }
 
 impl ConstraintsCreator {
-    pub fn new(inserter: DatabaseInserter) -> Self {
-        ConstraintsCreator { inserter }
+    pub fn new(inserter: DatabaseInserter, dialect: Dialect, recreate: bool) -> Self {
+        ConstraintsCreator {
+            inserter,
+            dialect,
+            recreate,
+        }
     }
 
     pub async fn run(
@@ -20,29 +28,63 @@ impl ConstraintsCreator {
         successful_results: Vec<MigrationResult>,
         formatted_tables: Vec<String>,
     ) {
-        let tasks = successful_results
+        let created_results = successful_results
             .into_iter()
             .filter(|migration_result| migration_result.created)
-            .map(|migration_result| {
-                let mut inserter = self.inserter.clone();
-                let formatted_tables = formatted_tables.clone();
-                let table_name = migration_result.table_name.clone();
-                let schema = migration_result.schema;
-
-                spawn(async move {
-                    if let Err(err) = inserter
-                        .create_constraints(&table_name, &schema, &formatted_tables)
-                        .await
-                        .with_context(|| {
+            .collect::<Vec<_>>();
+
+        // Referenced tables get their constraints first, tables of one level are processed concurrently
+        let graph = DependencyGraph::from_schemas(
+            created_results
+                .iter()
+                .map(|result| (result.table_name.as_str(), result.schema.as_slice())),
+        );
+
+        let table_names = created_results
+            .iter()
+            .map(|result| result.table_name.clone())
+            .collect::<Vec<_>>();
+
+        for level in graph.levels(&table_names) {
+            let tasks = created_results
+                .iter()
+                .filter(|migration_result| level.contains(&migration_result.table_name))
+                .map(|migration_result| {
+                    let mut inserter = self.inserter.clone();
+                    let formatted_tables = formatted_tables.clone();
+                    let table_name = migration_result.table_name.clone();
+                    let schema = migration_result.schema.clone();
+                    let dialect = self.dialect;
+                    let recreate = self.recreate;
+
+                    spawn(async move {
+                        // Constraints of an earlier or resumed run are found on the target already
+                        let result = match inserter.fetch_constraints(&table_name).await {
+                            Ok(existing) => {
+                                inserter
+                                    .create_constraints(
+                                        &table_name,
+                                        &schema,
+                                        &formatted_tables,
+                                        dialect,
+                                        &existing,
+                                        recreate,
+                                    )
+                                    .await
+                            }
+                            Err(err) => Err(err),
+                        };
+
+                        if let Err(err) = result.with_context(|| {
                             format!("Error while creating constraints for table: {}", table_name)
-                        })
-                    {
-                        print_error_chain(&err);
-                    }
+                        }) {
+                            print_error_chain(&err);
+                        }
+                    })
                 })
-            })
-            .collect::<Vec<_>>();
+                .collect::<Vec<_>>();
 
-        join_all(tasks).await;
+            join_all(tasks).await;
+        }
     }
 }
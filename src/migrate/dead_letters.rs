@@ -0,0 +1,136 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde_json::{json, Map, Value};
+
+use crate::common::constraints::Constraint;
+use crate::common::schema::ColumnSchema;
+use crate::insert::inserter::DatabaseInserter;
+use crate::migrate::batch_sink::Batch;
+
+// Tables append to the same file from several tasks, one line at a time
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+// Appends rows MySQL rejected on their own to a JSON lines file, for `--on-error skip`
+pub struct DeadLetters {
+    path: PathBuf,
+    table_name: String,
+    columns: Vec<String>,
+    key_columns: Vec<usize>,
+    rows: AtomicUsize,
+}
+
+impl DeadLetters {
+    pub fn new(path: &Path, table_name: &str, schema: &[ColumnSchema]) -> Self {
+        DeadLetters {
+            path: path.to_path_buf(),
+            table_name: table_name.to_string(),
+            columns: schema
+                .iter()
+                .map(|column| column.column_name.clone())
+                .collect(),
+            key_columns: schema
+                .iter()
+                .enumerate()
+                .filter(|(_, column)| matches!(column.constraints, Some(Constraint::PrimaryKey)))
+                .map(|(index, _)| index)
+                .collect(),
+            rows: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows.load(Ordering::Relaxed)
+    }
+
+    // Retries the rows of a failed batch one at a time, writing the ones that still fail to the file
+    pub async fn salvage(
+        &self,
+        inserter: &mut DatabaseInserter,
+        batch: &Batch<'_>,
+    ) -> Result<usize> {
+        let mut failed = 0;
+
+        for row in batch.rows {
+            let query = format!("{}{}{}", batch.insert_statement, row, batch.suffix);
+
+            if let Err(err) = inserter.execute_transactional_query(&query).await {
+                self.append(row, &format!("{:#}", err))?;
+                failed += 1;
+            }
+        }
+
+        self.rows.fetch_add(failed, Ordering::Relaxed);
+
+        Ok(failed)
+    }
+
+    fn append(&self, row: &str, error: &str) -> Result<()> {
+        let values = split_values(row);
+        let value = |index: usize| {
+            (
+                self.columns[index].clone(),
+                Value::from(values.get(index).copied()),
+            )
+        };
+
+        let line = json!({
+            "table": self.table_name,
+            "key": self.key_columns.iter().map(|index| value(*index)).collect::<Map<_, _>>(),
+            "error": error,
+            "values": (0..self.columns.len()).map(value).collect::<Map<_, _>>(),
+            "failed_at": Utc::now().to_rfc3339(),
+        });
+
+        let _lock = FILE_LOCK.lock().unwrap();
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open dead-letter file {}", self.path.display()))?;
+
+        writeln!(file, "{}", line)
+            .with_context(|| format!("Failed to write dead-letter file {}", self.path.display()))
+    }
+}
+
+// Splits a value tuple like `(1, 'a, b', NULL)` into its SQL literals
+fn split_values(row: &str) -> Vec<&str> {
+    let row = row.trim();
+    let row = row
+        .strip_prefix('(')
+        .and_then(|row| row.strip_suffix(')'))
+        .unwrap_or(row);
+
+    let mut values = Vec::new();
+    let mut quoted = false;
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (index, char) in row.char_indices() {
+        match char {
+            // Doubled quotes inside a literal toggle twice
+            '\'' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                values.push(row[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    values.push(row[start..].trim());
+    values
+}
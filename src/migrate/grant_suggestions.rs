@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::common::identifiers::{quote_identifier, quote_literal};
+use crate::common::permission::{PrincipalType, RoleMembership, TablePermission};
+use crate::common::source_table::SourceTable;
+use crate::extract::extractor::DatabaseExtractor;
+use crate::migrate::migration_options::MigrationOptions;
+
+// Writes MySQL GRANT statements reconstructed from the table permissions of the source database,
+// the file is only a suggestion and is never executed
+pub async fn write_grant_suggestions(
+    extractor: &mut DatabaseExtractor,
+    tables: &[SourceTable],
+    options: &MigrationOptions,
+    path: &Path,
+) -> Result<()> {
+    let permissions = extractor
+        .fetch_table_permissions()
+        .await?
+        .into_iter()
+        .filter(|permission| tables.contains(&permission.table))
+        .collect::<Vec<_>>();
+    let memberships = extractor.fetch_role_memberships().await?;
+
+    fs::write(
+        path,
+        build_grant_suggestions(&permissions, &memberships, options),
+    )?;
+
+    info!(
+        "Wrote {} suggested grants to {}",
+        permissions.len(),
+        path.display()
+    );
+
+    Ok(())
+}
+
+fn build_grant_suggestions(
+    permissions: &[TablePermission],
+    memberships: &[RoleMembership],
+    options: &MigrationOptions,
+) -> String {
+    let mut lines = vec![
+        "-- Suggested MySQL grants generated from MSSQL table permissions, review before running"
+            .to_string(),
+        "-- User accounts default to the '%' host, adjust them to the hosts they connect from"
+            .to_string(),
+        String::new(),
+    ];
+
+    let mut roles = permissions
+        .iter()
+        .filter(|permission| {
+            permission.principal_type == PrincipalType::Role && permission.principal != "public"
+        })
+        .map(|permission| permission.principal.as_str())
+        .collect::<Vec<_>>();
+    roles.sort_unstable();
+    roles.dedup();
+
+    for role in &roles {
+        lines.push(format!("CREATE ROLE IF NOT EXISTS {};", quote_role(role)));
+
+        for membership in memberships.iter().filter(|m| m.role == *role) {
+            lines.push(format!(
+                "GRANT {} TO {};",
+                quote_role(role),
+                quote_user(&membership.member)
+            ));
+        }
+    }
+
+    if !roles.is_empty() {
+        lines.push(String::new());
+    }
+
+    // Privileges of one grantee on one table are merged into a single statement
+    let mut grants: BTreeMap<(String, String, bool), Vec<&str>> = BTreeMap::new();
+
+    for permission in permissions {
+        let table_name = options.target_table_name(&permission.table);
+
+        let grantee = match permission.principal_type {
+            PrincipalType::Role => quote_role(&permission.principal),
+            PrincipalType::User => quote_user(&permission.principal),
+        };
+
+        if permission.principal == "public" {
+            lines.push(format!(
+                "-- {} {} ON {} TO public has no MySQL equivalent",
+                permission.state, permission.permission, permission.table
+            ));
+            continue;
+        }
+
+        if permission.state == "DENY" {
+            lines.push(format!(
+                "-- DENY {} ON {} TO {} has no MySQL equivalent, leave the privilege out instead",
+                permission.permission, permission.table, grantee
+            ));
+            continue;
+        }
+
+        match map_privilege(&permission.permission) {
+            Some(privilege) => grants
+                .entry((
+                    table_name,
+                    grantee,
+                    permission.state == "GRANT_WITH_GRANT_OPTION",
+                ))
+                .or_default()
+                .push(privilege),
+            None => lines.push(format!(
+                "-- {} ON {} TO {} has no MySQL equivalent",
+                permission.permission, permission.table, grantee
+            )),
+        }
+    }
+
+    for ((table_name, grantee, grant_option), mut privileges) in grants {
+        privileges.sort_unstable();
+        privileges.dedup();
+
+        lines.push(format!(
+            "GRANT {} ON {} TO {}{};",
+            privileges.join(", "),
+            quote_identifier(&table_name),
+            grantee,
+            if grant_option {
+                " WITH GRANT OPTION"
+            } else {
+                ""
+            }
+        ));
+    }
+
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+fn map_privilege(permission: &str) -> Option<&'static str> {
+    match permission {
+        "SELECT" => Some("SELECT"),
+        "INSERT" => Some("INSERT"),
+        "UPDATE" => Some("UPDATE"),
+        "DELETE" => Some("DELETE"),
+        "REFERENCES" => Some("REFERENCES"),
+        "ALTER" => Some("ALTER"),
+        "CONTROL" => Some("ALL PRIVILEGES"),
+        _ => None,
+    }
+}
+
+fn quote_user(name: &str) -> String {
+    format!("{}@'%'", quote_literal(name))
+}
+
+fn quote_role(name: &str) -> String {
+    quote_literal(name)
+}
@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+
+use crate::common::constraints::Constraint;
+use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::migrate::key_mapping::KeyMapping;
+use crate::migrate::migration_options::MigrationOptions;
+
+pub const INTEGER_TYPES: [&str; 4] = ["tinyint", "smallint", "int", "bigint"];
+
+// Shifts integer keys by the configured offsets or replaces them with regenerated keys,
+// so the rows of several sources can be merged into one target
+#[derive(Debug, Clone)]
+pub struct IdOffsets {
+    // Row index, column name and new key
+    columns: Vec<(usize, String, NewKey)>,
+}
+
+#[derive(Debug, Clone)]
+enum NewKey {
+    Offset(i64),
+    Regenerated(Arc<KeyMapping>),
+}
+
+impl NewKey {
+    fn of(table: &SourceTable, options: &MigrationOptions) -> Option<Self> {
+        match options.key_mapping(table) {
+            Some(mapping) => Some(NewKey::Regenerated(mapping)),
+            None => {
+                let offset = options.id_offset(table);
+                (offset != 0).then_some(NewKey::Offset(offset))
+            }
+        }
+    }
+}
+
+impl IdOffsets {
+    pub fn new(
+        table: &SourceTable,
+        source_schema: &[ColumnSchema],
+        options: &MigrationOptions,
+    ) -> Option<Self> {
+        let columns = source_schema
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| INTEGER_TYPES.contains(&column.data_type.as_str()))
+            .filter_map(|(index, column)| {
+                let new_key = match &column.constraints {
+                    Some(Constraint::PrimaryKey) => NewKey::of(table, options),
+                    // Foreign keys follow the keys of the table they reference
+                    Some(Constraint::ForeignKey {
+                        referenced_table, ..
+                    }) => NewKey::of(&SourceTable::parse(referenced_table), options),
+                    _ => None,
+                };
+
+                new_key.map(|new_key| (index, column.column_name.clone(), new_key))
+            })
+            .collect::<Vec<_>>();
+
+        if columns.is_empty() {
+            return None;
+        }
+
+        for (_, column_name, new_key) in &columns {
+            match new_key {
+                NewKey::Offset(offset) => {
+                    debug!("Shifting IDs of {}.{} by {}", table, column_name, offset)
+                }
+                NewKey::Regenerated(_) => {
+                    debug!(
+                        "Replacing IDs of {}.{} with regenerated keys",
+                        table, column_name
+                    )
+                }
+            }
+        }
+
+        Some(IdOffsets { columns })
+    }
+
+    // Offsets of the given columns only, for rows made of just these columns like deleted keys
+    pub fn select(&self, indexes: &[usize]) -> Option<Self> {
+        let columns = indexes
+            .iter()
+            .enumerate()
+            .filter_map(|(position, index)| {
+                self.columns
+                    .iter()
+                    .find(|(column_index, _, _)| column_index == index)
+                    .map(|(_, column_name, new_key)| {
+                        (position, column_name.clone(), new_key.clone())
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        (!columns.is_empty()).then_some(IdOffsets { columns })
+    }
+
+    pub fn apply(&self, row: &mut [String]) -> Result<()> {
+        for (index, column_name, new_key) in &self.columns {
+            if row[*index] == "NULL" {
+                continue;
+            }
+
+            let id = row[*index]
+                .trim_matches('\'')
+                .parse::<i64>()
+                .map_err(|_| anyhow!("Invalid ID {} of column {}", row[*index], column_name))?;
+
+            let new_id = match new_key {
+                NewKey::Offset(offset) => id.checked_add(*offset).ok_or_else(|| {
+                    anyhow!(
+                        "ID {} of column {} overflows with the offset {}",
+                        id,
+                        column_name,
+                        offset
+                    )
+                })?,
+                // Rows added to the source after the keys were mapped have none
+                NewKey::Regenerated(mapping) => mapping.get(id).ok_or_else(|| {
+                    anyhow!(
+                        "ID {} of column {} has no regenerated key, the row was added after the keys were mapped",
+                        id,
+                        column_name
+                    )
+                })?,
+            };
+
+            row[*index] = new_id.to_string();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{SettingsConfig, TableConfig};
+
+    fn column(column_name: &str, data_type: &str, constraints: Option<Constraint>) -> ColumnSchema {
+        ColumnSchema {
+            column_name: column_name.to_string(),
+            data_type: data_type.to_string(),
+            character_maximum_length: None,
+            numeric_precision: None,
+            numeric_scale: None,
+            datetime_precision: None,
+            is_nullable: true,
+            constraints,
+            constraint_name: None,
+            collation: None,
+            computed_definition: None,
+        }
+    }
+
+    fn foreign_key(referenced_table: &str) -> Option<Constraint> {
+        Some(Constraint::ForeignKey {
+            referenced_table: referenced_table.to_string(),
+            referenced_column: "Id".to_string(),
+            on_delete: "NO ACTION".to_string(),
+            on_update: "NO ACTION".to_string(),
+        })
+    }
+
+    // Offset of 1000 for all tables but Customers, which is shifted by 5000
+    fn id_offsets() -> Option<IdOffsets> {
+        let mut options = MigrationOptions::new(SettingsConfig::default());
+        options.id_offset = Some(1000);
+        options.table_configs.insert(
+            "dbo.Customers".to_string(),
+            TableConfig {
+                id_offset: Some(5000),
+                ..TableConfig::default()
+            },
+        );
+
+        IdOffsets::new(
+            &SourceTable::new("dbo", "Orders"),
+            &[
+                column("Id", "int", Some(Constraint::PrimaryKey)),
+                column("CustomerId", "bigint", foreign_key("dbo.Customers")),
+                column("Code", "varchar", foreign_key("dbo.Codes")),
+                column("Quantity", "int", None),
+            ],
+            &options,
+        )
+    }
+
+    fn row(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn shifts_keys_and_the_foreign_keys_referencing_them() {
+        let mut values = row(&["1", "7", "'A1'", "3"]);
+        id_offsets().unwrap().apply(&mut values).unwrap();
+
+        assert_eq!(values, ["1001", "5007", "'A1'", "3"]);
+
+        let mut values = row(&["-5", "NULL", "NULL", "NULL"]);
+        id_offsets().unwrap().apply(&mut values).unwrap();
+
+        assert_eq!(values, ["995", "NULL", "NULL", "NULL"]);
+    }
+
+    #[test]
+    fn leaves_tables_without_offsets_alone() {
+        let options = MigrationOptions::new(SettingsConfig::default());
+        let schema = [column("Id", "int", Some(Constraint::PrimaryKey))];
+
+        assert!(IdOffsets::new(&SourceTable::new("dbo", "Orders"), &schema, &options).is_none());
+    }
+
+    #[test]
+    fn selects_the_columns_of_partial_rows() {
+        let id_offsets = id_offsets().unwrap();
+        let mut values = row(&["7"]);
+
+        id_offsets.select(&[1]).unwrap().apply(&mut values).unwrap();
+
+        assert_eq!(values, ["5007"]);
+        assert!(id_offsets.select(&[3]).is_none());
+    }
+
+    #[test]
+    fn rejects_ids_that_overflow_or_are_not_numbers() {
+        let id_offsets = id_offsets().unwrap();
+
+        assert_eq!(
+            id_offsets
+                .apply(&mut row(&[&i64::MAX.to_string(), "NULL", "NULL", "NULL"]))
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "ID {} of column Id overflows with the offset 1000",
+                i64::MAX
+            )
+        );
+        assert_eq!(
+            id_offsets
+                .apply(&mut row(&["x", "NULL", "NULL", "NULL"]))
+                .unwrap_err()
+                .to_string(),
+            "Invalid ID x of column Id"
+        );
+    }
+}
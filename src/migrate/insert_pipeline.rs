@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use tokio::spawn;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+
+use crate::insert::inserter::DatabaseInserter;
+use crate::migrate::batch_sink::{
+    flush_batch, write_batches, Batch, BatchSink, InsertPlan, InserterSink, RowStream,
+};
+use crate::migrate::checkpoint::TimeBox;
+use crate::migrate::concurrency_limiter::ConcurrencyLimiter;
+use crate::migrate::dead_letters::DeadLetters;
+use crate::migrate::memory_budget::MemoryReservation;
+use crate::migrate::observer::Observers;
+use crate::migrate::quarantine::Quarantine;
+
+// Owned parts of an `InserterSink`, cloned into every insert worker of a table
+#[derive(Clone)]
+pub struct InsertTarget {
+    pub inserter: DatabaseInserter,
+    pub limiter: Arc<ConcurrencyLimiter>,
+    pub quarantine: Option<Arc<Quarantine>>,
+    pub dead_letters: Option<Arc<DeadLetters>>,
+    pub table_name: String,
+    pub observers: Observers,
+    pub time_box: Option<Arc<TimeBox>>,
+}
+
+impl InsertTarget {
+    fn sink(&mut self) -> InserterSink<'_> {
+        InserterSink {
+            inserter: &mut self.inserter,
+            limiter: &self.limiter,
+            quarantine: self.quarantine.as_deref(),
+            dead_letters: self.dead_letters.as_deref(),
+            table_name: &self.table_name,
+            observers: &self.observers,
+            time_box: self.time_box.as_deref(),
+        }
+    }
+}
+
+// Writes the batches of a row stream with several insert workers, each sending through a connection of its own. The
+// stream is batched as usual and the batches are handed over a bounded channel, so reading and formatting rows goes on
+// while MySQL executes the previous batches.
+pub async fn write_pipelined_batches(
+    target: InsertTarget,
+    stream: RowStream<'_>,
+    plan: &Arc<InsertPlan>,
+    workers: usize,
+) -> Result<usize> {
+    let mut sink = PipelinedSink {
+        target,
+        plan: Arc::clone(plan),
+        workers,
+        sender: None,
+        tasks: Vec::new(),
+    };
+
+    let result = write_batches(&mut sink, stream, plan).await;
+
+    // Rows only count once the workers wrote them, and a failed worker explains a failed stream best
+    sink.finish().await?;
+
+    result
+}
+
+// Rows handed to the insert workers, with the memory they hold until they are written
+type QueuedBatch = (Vec<String>, MemoryReservation);
+
+struct PipelinedSink {
+    target: InsertTarget,
+    plan: Arc<InsertPlan>,
+    workers: usize,
+    sender: Option<mpsc::Sender<QueuedBatch>>,
+    tasks: Vec<JoinHandle<Result<()>>>,
+}
+
+impl PipelinedSink {
+    fn start(&mut self) {
+        // One batch waits for each worker, more would only hold memory
+        let (sender, receiver) = mpsc::channel::<QueuedBatch>(self.workers);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        self.tasks = (0..self.workers)
+            .map(|_| {
+                let mut target = self.target.clone();
+                let plan = Arc::clone(&self.plan);
+                let receiver = Arc::clone(&receiver);
+
+                spawn(async move {
+                    loop {
+                        // Locked only while waiting for the next batch, not while writing it
+                        let Some((mut rows, _reservation)) = receiver.lock().await.recv().await
+                        else {
+                            return Ok(());
+                        };
+
+                        if let Err(err) = flush_batch(&mut target.sink(), &plan, &mut rows).await {
+                            // No further batches are accepted, the queued ones are still written by the others
+                            receiver.lock().await.close();
+                            return Err(err);
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        self.sender = Some(sender);
+    }
+
+    // Waits until the workers wrote the batches handed over, failing with the error of the first failed worker
+    async fn finish(&mut self) -> Result<()> {
+        self.sender = None;
+
+        let mut result = Ok(());
+
+        for task in self.tasks.drain(..) {
+            let outcome = task.await.context("Insert worker panicked").and_then(|r| r);
+
+            if result.is_ok() {
+                result = outcome;
+            }
+        }
+
+        result
+    }
+}
+
+#[async_trait]
+impl BatchSink for PipelinedSink {
+    async fn write_batch(&mut self, batch: &Batch<'_>) -> Result<()> {
+        let mut reservation = self.plan.memory_reservation();
+
+        // Close to the memory ceiling the batch is written right away, after the queued ones, while its rows are still
+        // counted by the caller
+        if !reservation.try_grow(batch.query.len()) {
+            debug!(
+                "Writing a batch of {} without the insert workers, the memory ceiling is reached",
+                self.target.table_name
+            );
+
+            self.finish().await?;
+            return self.target.sink().write_batch(batch).await;
+        }
+
+        if self.sender.is_none() {
+            self.start();
+        }
+
+        let sent = match &self.sender {
+            Some(sender) => sender
+                .send((batch.rows.to_vec(), reservation))
+                .await
+                .is_ok(),
+            None => false,
+        };
+
+        if !sent {
+            self.finish().await?;
+            bail!("Insert workers stopped");
+        }
+
+        Ok(())
+    }
+
+    // Large values are appended to rows the workers may not have written yet
+    async fn execute_statement(&mut self, statement: &str) -> Result<()> {
+        self.finish().await?;
+        self.target.sink().execute_statement(statement).await
+    }
+
+    async fn quarantine_row(&mut self, row: &str, error: &str) -> Result<()> {
+        self.target.sink().quarantine_row(row, error).await
+    }
+}
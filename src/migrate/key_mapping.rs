@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use futures::TryStreamExt;
+
+use crate::common::constraints::Constraint;
+use crate::common::source_table::SourceTable;
+use crate::extract::extractor::{open_key_stream, DatabaseExtractor};
+use crate::insert::inserter::DatabaseInserter;
+use crate::migrate::id_offsets::INTEGER_TYPES;
+use crate::migrate::migration_options::MigrationOptions;
+
+// New keys of a table, the source keys in ascending order numbered on from the highest key of the target
+#[derive(Debug, Default)]
+pub struct KeyMapping {
+    // Runs of consecutive source keys as first source key, first new key and length,
+    // so gapless keys take up no memory per row
+    runs: Vec<(i64, i64, i64)>,
+}
+
+impl KeyMapping {
+    fn push(&mut self, key: i64, new_key: i64) {
+        match self.runs.last_mut() {
+            Some((first_key, first_new_key, length))
+                if *first_key + *length == key && *first_new_key + *length == new_key =>
+            {
+                *length += 1
+            }
+            _ => self.runs.push((key, new_key, 1)),
+        }
+    }
+
+    pub fn get(&self, key: i64) -> Option<i64> {
+        let index = self
+            .runs
+            .partition_point(|(first_key, ..)| *first_key <= key);
+
+        index
+            .checked_sub(1)
+            .map(|index| self.runs[index])
+            .filter(|(first_key, _, length)| key - first_key < *length)
+            .map(|(first_key, first_new_key, _)| first_new_key + (key - first_key))
+    }
+}
+
+// Key mappings by target table name
+#[derive(Debug, Default)]
+pub struct KeyMappings {
+    tables: HashMap<String, Arc<KeyMapping>>,
+}
+
+impl KeyMappings {
+    pub fn get(&self, table_name: &str) -> Option<Arc<KeyMapping>> {
+        self.tables.get(table_name).cloned()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+}
+
+// Maps the keys of all tables regenerating theirs, numbering them on from the target keys when a target is given
+pub async fn build_key_mappings(
+    extractor: &mut DatabaseExtractor,
+    mut inserter: Option<&mut DatabaseInserter>,
+    tables: &[SourceTable],
+    options: &MigrationOptions,
+) -> Result<KeyMappings> {
+    let mut mappings = KeyMappings::default();
+
+    for table in tables
+        .iter()
+        .filter(|table| options.regenerates_keys(table))
+    {
+        let schema = extractor.get_table_schema(table).await?;
+
+        let key_columns = schema
+            .iter()
+            .filter(|column| column.constraints == Some(Constraint::PrimaryKey))
+            .collect::<Vec<_>>();
+
+        let key_column = match key_columns[..] {
+            [column] if INTEGER_TYPES.contains(&column.data_type.as_str()) => column,
+            _ => {
+                warn!(
+                    "Table {} has no single integer primary key, its keys are kept",
+                    table
+                );
+                continue;
+            }
+        };
+
+        let table_name = options.target_table_name(table);
+
+        let max_key = match inserter.as_deref_mut() {
+            Some(inserter) => {
+                let column_name = options.target_column_name(&key_column.column_name);
+
+                inserter.fetch_max_key(&table_name, &column_name).await?
+            }
+            None => 0,
+        };
+
+        let mapping = map_table_keys(extractor, table, &key_column.column_name, max_key)
+            .await
+            .with_context(|| format!("Failed to map the keys of table {}", table))?;
+
+        debug!(
+            "Regenerating keys of table {} from {}, {} runs of consecutive keys",
+            table,
+            max_key + 1,
+            mapping.runs.len()
+        );
+
+        mappings.tables.insert(table_name, Arc::new(mapping));
+    }
+
+    Ok(mappings)
+}
+
+async fn map_table_keys(
+    extractor: &mut DatabaseExtractor,
+    table: &SourceTable,
+    column_name: &str,
+    max_key: i64,
+) -> Result<KeyMapping> {
+    let mut conn = extractor.connection().await?;
+    let mut stream = open_key_stream(&mut conn, table, column_name).await?;
+
+    let mut mapping = KeyMapping::default();
+    let mut new_key = max_key;
+
+    while let Some(row) = stream.try_next().await? {
+        let key = row[0]
+            .parse::<i64>()
+            .map_err(|_| anyhow!("Invalid key {}", row[0]))?;
+
+        new_key = new_key
+            .checked_add(1)
+            .ok_or_else(|| anyhow!("Regenerated keys overflow after {}", max_key))?;
+
+        mapping.push(key, new_key);
+    }
+
+    Ok(mapping)
+}
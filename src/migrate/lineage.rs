@@ -0,0 +1,100 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::Local;
+use serde_json::{json, Value};
+
+use crate::common::schema::ColumnSchema;
+use crate::insert::query::format_column_type;
+use crate::mappings::Mappings;
+use crate::migrate::migration_options::MigrationOptions;
+use crate::migrate::migration_result::MigrationResult;
+
+// Writes a JSON document tracing every target column back to its source column and applied mapping
+pub fn write_lineage_report(
+    path: &Path,
+    results: &[MigrationResult],
+    mappings: &Mappings,
+    options: &MigrationOptions,
+) -> Result<()> {
+    let mut results = results.iter().collect::<Vec<_>>();
+    results.sort_by(|a, b| a.table_name.cmp(&b.table_name));
+
+    let tables = results
+        .into_iter()
+        .map(|result| {
+            let columns = result
+                .source_schema
+                .iter()
+                .zip(&result.schema)
+                .map(|(source, target)| column_lineage(source, target, mappings, options))
+                .collect::<Vec<_>>();
+
+            json!({
+                "source": {
+                    "schema": result.source_table.schema,
+                    "table": result.source_table.name,
+                },
+                "target": {
+                    "table": result.table_name,
+                },
+                "renamed": result.source_table.name != result.table_name,
+                "columns": columns,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let report = json!({
+        "generated_at": Local::now().to_rfc3339(),
+        "tables": tables,
+    });
+
+    fs::write(path, serde_json::to_string_pretty(&report)?)?;
+
+    info!("Lineage report written to {}", path.display());
+
+    Ok(())
+}
+
+fn column_lineage(
+    source: &ColumnSchema,
+    target: &ColumnSchema,
+    mappings: &Mappings,
+    options: &MigrationOptions,
+) -> Value {
+    let mapping = mappings.get(&source.data_type);
+
+    let mut transforms = Vec::new();
+
+    if options.format_snake_case && source.column_name != target.column_name {
+        transforms.push("snake_case");
+    }
+    if source.character_maximum_length == Some(-1) && target.character_maximum_length.is_some() {
+        transforms.push("max_length_clamped");
+    }
+
+    json!({
+        "source": {
+            "column": source.column_name,
+            "type": source.data_type,
+            "length": source.character_maximum_length,
+            "precision": source.numeric_precision,
+            "scale": source.numeric_scale,
+            "nullable": source.is_nullable,
+        },
+        "target": {
+            "column": target.column_name,
+            "type": target.data_type,
+            "definition": format_column_type(target),
+            "nullable": target.is_nullable,
+        },
+        "mapping": mapping.map(|mapping| json!({
+            "from_type": source.data_type,
+            "to_type": mapping.to_type,
+            "type_parameters": mapping.type_parameters,
+        })),
+        "renamed": source.column_name != target.column_name,
+        "transforms": transforms,
+    })
+}
@@ -0,0 +1,104 @@
+use crate::common::constraints::Constraint;
+use crate::common::identifiers::quote_identifier;
+use crate::common::schema::ColumnSchema;
+
+const LOB_TYPES: [&str; 3] = ["binary", "varbinary", "image"];
+const RESERVED_BYTES: usize = 64;
+
+// Moves large binary values out of the INSERT into follow-up `UPDATE ... CONCAT` statements,
+// so a single value never has to fit into one packet along with the rest of its batch
+pub struct LobWriter {
+    table_name: String,
+    key_columns: Vec<(usize, String)>,
+    lob_columns: Vec<(usize, String)>,
+}
+
+impl LobWriter {
+    pub fn new(
+        table_name: &str,
+        source_schema: &[ColumnSchema],
+        mapped_schema: &[ColumnSchema],
+    ) -> Option<Self> {
+        let lob_columns = source_schema
+            .iter()
+            .zip(mapped_schema)
+            .enumerate()
+            .filter(|(_, (source, _))| LOB_TYPES.contains(&source.data_type.as_str()))
+            .map(|(index, (_, target))| (index, target.column_name.clone()))
+            .collect::<Vec<_>>();
+
+        if lob_columns.is_empty() {
+            return None;
+        }
+
+        let key_columns = mapped_schema
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| column.constraints == Some(Constraint::PrimaryKey))
+            .map(|(index, column)| (index, column.column_name.clone()))
+            .collect::<Vec<_>>();
+
+        if key_columns.is_empty() {
+            warn!(
+                "Table {} has binary columns but no primary key, large values are sent inline",
+                table_name
+            );
+            return None;
+        }
+
+        Some(LobWriter {
+            table_name: table_name.to_string(),
+            key_columns,
+            lob_columns,
+        })
+    }
+
+    // Empties the values too large to be sent inline and returns the statements appending them afterwards
+    pub fn detach(&self, row: &mut [String], max_packet_bytes: usize) -> Vec<String> {
+        let inline_limit = max_packet_bytes / 4;
+        let mut statements = Vec::new();
+
+        // Binary values are formatted as `0x<hex>` literals
+        let detached_columns = self
+            .lob_columns
+            .iter()
+            .filter(|(index, _)| row[*index].len() > inline_limit && row[*index].starts_with("0x"))
+            .collect::<Vec<_>>();
+
+        if detached_columns.is_empty() {
+            return statements;
+        }
+
+        let condition = self
+            .key_columns
+            .iter()
+            .map(|(index, name)| format!("{} = {}", quote_identifier(name), row[*index]))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+
+        for (index, name) in detached_columns {
+            let value = std::mem::replace(&mut row[*index], "X''".to_string());
+
+            let prefix = format!(
+                "UPDATE {} SET {1} = CONCAT({1}, 0x",
+                quote_identifier(&self.table_name),
+                quote_identifier(name)
+            );
+            let suffix = format!(") WHERE {}", condition);
+
+            // Whole bytes only, each piece is two hex digits per byte
+            let piece_length = max_packet_bytes
+                .saturating_sub(prefix.len() + suffix.len() + RESERVED_BYTES)
+                .max(2)
+                & !1;
+
+            statements.extend(
+                value.as_bytes()[2..]
+                    .chunks(piece_length)
+                    .map(|piece| format!("{}{}{}", prefix, String::from_utf8_lossy(piece), suffix)),
+            );
+        }
+
+        statements
+    }
+}
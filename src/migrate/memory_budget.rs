@@ -0,0 +1,177 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+// Ceiling on the bytes of rows all tables hold in their batches at the same time, for `--max-memory`
+#[derive(Debug)]
+pub struct MemoryBudget {
+    limit: usize,
+    used: AtomicUsize,
+    released: Notify,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> Self {
+        MemoryBudget {
+            limit,
+            used: AtomicUsize::new(0),
+            released: Notify::new(),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    // A single batch may always take the whole budget, so oversized rows can't wait forever
+    fn try_reserve(&self, bytes: usize) -> bool {
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                (used == 0 || used + bytes <= self.limit).then_some(used + bytes)
+            })
+            .is_ok()
+    }
+
+    async fn reserve(&self, bytes: usize) {
+        loop {
+            // Registered before checking, so a release in between isn't missed
+            let released = self.released.notified();
+
+            if self.try_reserve(bytes) {
+                return;
+            }
+
+            released.await;
+        }
+    }
+
+    fn release(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::SeqCst);
+        self.released.notify_waiters();
+    }
+}
+
+// Memory held by the rows of one batch, given back once they are written or the batch is dropped
+pub struct MemoryReservation {
+    budget: Option<Arc<MemoryBudget>>,
+    bytes: usize,
+}
+
+impl MemoryReservation {
+    pub fn new(budget: Option<Arc<MemoryBudget>>) -> Self {
+        MemoryReservation { budget, bytes: 0 }
+    }
+
+    // Always succeeds without a budget
+    pub fn try_grow(&mut self, bytes: usize) -> bool {
+        match &self.budget {
+            Some(budget) if !budget.try_reserve(bytes) => false,
+            _ => {
+                self.bytes += bytes;
+                true
+            }
+        }
+    }
+
+    pub async fn grow(&mut self, bytes: usize) {
+        if let Some(budget) = &self.budget {
+            budget.reserve(bytes).await;
+        }
+
+        self.bytes += bytes;
+    }
+
+    pub fn release(&mut self) {
+        if let Some(budget) = &self.budget {
+            if self.bytes > 0 {
+                budget.release(self.bytes);
+            }
+        }
+
+        self.bytes = 0;
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use super::*;
+
+    fn used(budget: &MemoryBudget) -> usize {
+        budget.used.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn reserves_up_to_the_limit() {
+        let budget = Arc::new(MemoryBudget::new(100));
+        let mut first = MemoryReservation::new(Some(Arc::clone(&budget)));
+        let mut second = MemoryReservation::new(Some(Arc::clone(&budget)));
+
+        assert!(first.try_grow(60));
+        assert!(second.try_grow(40));
+        assert!(!second.try_grow(1));
+        assert_eq!(used(&budget), 100);
+
+        first.release();
+        assert_eq!(used(&budget), 40);
+
+        drop(second);
+        assert_eq!(used(&budget), 0);
+    }
+
+    #[test]
+    fn lets_a_single_batch_exceed_the_limit() {
+        let budget = Arc::new(MemoryBudget::new(100));
+        let mut reservation = MemoryReservation::new(Some(Arc::clone(&budget)));
+
+        assert!(reservation.try_grow(500));
+        assert!(!MemoryReservation::new(Some(Arc::clone(&budget))).try_grow(1));
+    }
+
+    #[test]
+    fn grows_without_limit_without_a_budget() {
+        let mut reservation = MemoryReservation::new(None);
+
+        assert!(reservation.try_grow(usize::MAX));
+    }
+
+    #[tokio::test]
+    async fn waits_for_released_memory() {
+        let budget = Arc::new(MemoryBudget::new(100));
+        let mut held = MemoryReservation::new(Some(Arc::clone(&budget)));
+        held.grow(80).await;
+
+        let waiting = {
+            let budget = Arc::clone(&budget);
+            tokio::spawn(async move {
+                let mut reservation = MemoryReservation::new(Some(budget));
+                reservation.grow(50).await;
+                reservation
+            })
+        };
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        held.release();
+
+        let reservation = timeout(Duration::from_secs(5), waiting)
+            .await
+            .expect("reservation still waiting after the release")
+            .unwrap();
+        assert_eq!(used(&budget), 50);
+
+        drop(reservation);
+        assert_eq!(used(&budget), 0);
+    }
+}
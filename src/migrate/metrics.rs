@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::extract::extractor::DatabaseExtractor;
+use crate::insert::inserter::DatabaseInserter;
+use crate::migrate::observer::{MigrationEvent, MigrationObserver};
+
+// Name, type, help and value of the metrics reported per table
+type TableMetric = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&TableMetrics) -> Option<f64>,
+);
+
+const TABLE_METRICS: [TableMetric; 5] = [
+    (
+        "db_migrator_rows_migrated_total",
+        "counter",
+        "Rows inserted into the target table",
+        |metrics| Some(metrics.rows as f64),
+    ),
+    (
+        "db_migrator_batches_total",
+        "counter",
+        "Batches executed against the target table",
+        |metrics| Some(metrics.batches as f64),
+    ),
+    (
+        "db_migrator_bytes_sent_total",
+        "counter",
+        "Bytes of the batches sent for the target table",
+        |metrics| Some(metrics.bytes as f64),
+    ),
+    (
+        "db_migrator_table_errors_total",
+        "counter",
+        "Failed migrations of the table",
+        |metrics| Some(metrics.errors as f64),
+    ),
+    (
+        "db_migrator_table_duration_seconds",
+        "gauge",
+        "Time the migration of the table took, once it finished",
+        |metrics| metrics.duration_secs,
+    ),
+];
+
+#[derive(Debug, Default, Clone)]
+struct TableMetrics {
+    rows: usize,
+    batches: usize,
+    bytes: usize,
+    errors: usize,
+    // Set once the table finished
+    duration_secs: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+struct MetricsState {
+    tables: BTreeMap<String, TableMetrics>,
+    active_tables: usize,
+}
+
+// Counters of a migration run, collected from its events
+#[derive(Debug, Default)]
+pub struct Metrics {
+    state: Mutex<MetricsState>,
+}
+
+#[async_trait]
+impl MigrationObserver for Metrics {
+    async fn on_event(&self, event: &MigrationEvent) {
+        let mut state = self.state.lock().unwrap();
+
+        match event {
+            MigrationEvent::TableStarted { table } => {
+                state.active_tables += 1;
+                state.tables.entry(table.clone()).or_default();
+            }
+            MigrationEvent::BatchExecuted {
+                table, rows, bytes, ..
+            } => {
+                let metrics = state.tables.entry(table.clone()).or_default();
+                metrics.rows += rows;
+                metrics.batches += 1;
+                metrics.bytes += bytes;
+            }
+            MigrationEvent::TableFinished {
+                table, duration, ..
+            } => {
+                state.active_tables = state.active_tables.saturating_sub(1);
+                state.tables.entry(table.clone()).or_default().duration_secs =
+                    Some(duration.as_secs_f64());
+            }
+            MigrationEvent::TableFailed { table, .. } => {
+                state.active_tables = state.active_tables.saturating_sub(1);
+                state.tables.entry(table.clone()).or_default().errors += 1;
+            }
+            MigrationEvent::TableSuspended { table } => {
+                // Tables suspended before they started were never counted as active
+                if state.tables.contains_key(table) {
+                    state.active_tables = state.active_tables.saturating_sub(1);
+                }
+            }
+            MigrationEvent::MigrationFinished { .. } => state.active_tables = 0,
+        }
+    }
+}
+
+impl Metrics {
+    // Prometheus text exposition format
+    fn render(&self, extractor: &DatabaseExtractor, inserter: &DatabaseInserter) -> String {
+        let state = self.state.lock().unwrap();
+        let mut output = String::new();
+
+        for (name, kind, help, value) in TABLE_METRICS {
+            let _ = writeln!(output, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+
+            for (table, metrics) in &state.tables {
+                if let Some(value) = value(metrics) {
+                    let _ = writeln!(
+                        output,
+                        "{}{{table=\"{}\"}} {}",
+                        name,
+                        escape_label(table),
+                        value
+                    );
+                }
+            }
+        }
+
+        let _ = writeln!(
+            output,
+            "# HELP db_migrator_active_tables Tables being migrated\n# TYPE db_migrator_active_tables gauge\ndb_migrator_active_tables {}",
+            state.active_tables
+        );
+
+        let _ = writeln!(
+            output,
+            "# HELP db_migrator_connections_in_use Pooled connections in use\n# TYPE db_migrator_connections_in_use gauge"
+        );
+        for (database, usage) in [
+            ("mssql", extractor.pool_usage()),
+            ("mysql", inserter.pool_usage()),
+        ] {
+            let _ = writeln!(
+                output,
+                "db_migrator_connections_in_use{{database=\"{}\"}} {}",
+                database, usage.in_use
+            );
+        }
+
+        output
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// Serves the metrics of the run at `/metrics` until it is stopped
+pub struct MetricsServer {
+    task: JoinHandle<()>,
+}
+
+impl MetricsServer {
+    pub async fn start(
+        port: u16,
+        metrics: Arc<Metrics>,
+        extractor: &DatabaseExtractor,
+        inserter: &DatabaseInserter,
+    ) -> Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .await
+            .with_context(|| format!("Failed to listen for metrics requests on port {}", port))?;
+
+        info!("Serving metrics at http://0.0.0.0:{}/metrics", port);
+
+        let (extractor, inserter) = (extractor.clone(), inserter.clone());
+
+        let task = tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    continue;
+                };
+
+                let body = metrics.render(&extractor, &inserter);
+
+                tokio::spawn(async move {
+                    if let Err(err) = respond(stream, body).await {
+                        debug!("Failed to answer metrics request: {:#}", err);
+                    }
+                });
+            }
+        });
+
+        Ok(MetricsServer { task })
+    }
+
+    pub fn stop(self) {
+        self.task.abort();
+    }
+}
+
+async fn respond(mut stream: TcpStream, body: String) -> Result<()> {
+    let mut request = [0; 1024];
+    let length = stream.read(&mut request).await?;
+    let request = String::from_utf8_lossy(&request[..length]);
+
+    let response = if request.starts_with("GET /metrics ") {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+use crate::common::helpers::print_error_chain;
+use crate::common::identifiers::quote_literal;
+use crate::insert::inserter::DatabaseInserter;
+use crate::migrate::observer::{MigrationEvent, MigrationObserver};
+
+pub const MIGRATION_LOG_TABLE: &str = "_migration_log";
+
+// Audit trail of every table load in the target, kept for DBAs. Unlike the run history, which holds the latest state
+// of each table of a run, a row is appended for every attempt, so resumed and retried loads stay visible.
+pub struct MigrationLog {
+    inserter: DatabaseInserter,
+    run_id: String,
+    options_hash: String,
+    // Bytes sent of the tables being loaded
+    bytes: Mutex<HashMap<String, usize>>,
+}
+
+impl MigrationLog {
+    pub async fn start(
+        mut inserter: DatabaseInserter,
+        run_id: &str,
+        options_hash: &str,
+    ) -> Result<Self> {
+        inserter
+            .execute_transactional_query(&format!(
+                "CREATE TABLE IF NOT EXISTS `{}` (\
+                    `id` BIGINT NOT NULL AUTO_INCREMENT, \
+                    `run_id` VARCHAR(32) NOT NULL, \
+                    `table_name` VARCHAR(64) NOT NULL, \
+                    `status` VARCHAR(16) NOT NULL, \
+                    `started_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP, \
+                    `finished_at` TIMESTAMP NULL, \
+                    `rows_copied` BIGINT NULL, \
+                    `bytes_copied` BIGINT NULL, \
+                    `tool_version` VARCHAR(32) NOT NULL, \
+                    `options_hash` CHAR(16) NOT NULL, \
+                    PRIMARY KEY (`id`), \
+                    KEY `run_table` (`run_id`, `table_name`)\
+                )",
+                MIGRATION_LOG_TABLE
+            ))
+            .await?;
+
+        Ok(MigrationLog {
+            inserter,
+            run_id: run_id.to_string(),
+            options_hash: options_hash.to_string(),
+            bytes: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn record_start(&self, table_name: &str) -> Result<()> {
+        self.bytes.lock().unwrap().insert(table_name.to_string(), 0);
+
+        let query = format!(
+            "INSERT INTO `{}` (`run_id`, `table_name`, `status`, `tool_version`, `options_hash`) \
+             VALUES ({}, {}, 'running', {}, {})",
+            MIGRATION_LOG_TABLE,
+            quote_literal(&self.run_id),
+            quote_literal(table_name),
+            quote_literal(env!("CARGO_PKG_VERSION")),
+            quote_literal(&self.options_hash)
+        );
+
+        self.inserter
+            .clone()
+            .execute_transactional_query(&query)
+            .await
+    }
+
+    // Closes the open row of the table, rows are only known of completed loads
+    async fn record_end(&self, table_name: &str, status: &str, rows: Option<usize>) -> Result<()> {
+        let bytes = self.bytes.lock().unwrap().remove(table_name).unwrap_or(0);
+        let rows = rows.map_or("NULL".to_string(), |rows| rows.to_string());
+
+        let query = format!(
+            "UPDATE `{}` SET `status` = {}, `finished_at` = CURRENT_TIMESTAMP, `rows_copied` = {}, \
+             `bytes_copied` = {} WHERE `run_id` = {} AND `table_name` = {} AND `finished_at` IS NULL",
+            MIGRATION_LOG_TABLE,
+            quote_literal(status),
+            rows,
+            bytes,
+            quote_literal(&self.run_id),
+            quote_literal(table_name)
+        );
+
+        self.inserter
+            .clone()
+            .execute_transactional_query(&query)
+            .await
+    }
+}
+
+#[async_trait]
+impl MigrationObserver for MigrationLog {
+    async fn on_event(&self, event: &MigrationEvent) {
+        let (table, result) = match event {
+            MigrationEvent::TableStarted { table } => (table, self.record_start(table).await),
+            MigrationEvent::BatchExecuted { table, bytes, .. } => {
+                if let Some(total) = self.bytes.lock().unwrap().get_mut(table) {
+                    *total += bytes;
+                }
+                return;
+            }
+            MigrationEvent::TableFinished { table, rows, .. } => (
+                table,
+                self.record_end(table, "completed", Some(*rows)).await,
+            ),
+            MigrationEvent::TableFailed { table, .. } => {
+                (table, self.record_end(table, "failed", None).await)
+            }
+            MigrationEvent::TableSuspended { table } => {
+                (table, self.record_end(table, "suspended", None).await)
+            }
+            _ => return,
+        };
+
+        if let Err(err) =
+            result.with_context(|| format!("Failed to record table {} in the migration log", table))
+        {
+            print_error_chain(&err);
+        }
+    }
+}
@@ -1,9 +1,635 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Error};
+use sha2::{Digest, Sha256};
+
+use crate::args::MigrationArgs;
+use crate::cli::build_migration_options;
+use crate::common::collation::Collation;
+use crate::common::helpers::format_snake_case;
+use crate::common::identifiers::is_reserved_word;
+use crate::common::source_table::{SourceTable, DEFAULT_SCHEMA};
+use crate::config::{
+    AssertionConfig, CharPadding, CheckInLists, ComputedColumns, CsvConfig, Dialect, ExtraColumn,
+    ExtractionConsistency, IdentifierCase, IdentifierConfig, LongKeys, OnInvalid, SettingsConfig,
+    SparseColumnSets, TableConfig, TemporalTables, TimeZoneConfig,
+};
+use crate::migrate::checkpoint::TimeBox;
+use crate::migrate::key_mapping::{KeyMapping, KeyMappings};
+use crate::migrate::memory_budget::MemoryBudget;
+use crate::migrate::observer::{MigrationObserver, Observers};
+use crate::migrate::strict_types::GuidFormat;
+use crate::migrate::wait::WaitConditions;
+
 #[derive(Debug, Clone)]
 pub struct MigrationOptions {
     pub(crate) drop: bool,
+    pub(crate) backup: bool,
+    pub(crate) upsert: bool,
+    pub(crate) append: bool,
+    pub(crate) shadow: bool,
+    pub(crate) sync_schema: bool,
+    pub(crate) phase: Option<Phase>,
     pub(crate) constraints: bool,
+    pub(crate) recreate_constraints: bool,
+    pub(crate) check_triggers: bool,
+    pub(crate) check_fallback: CheckFallback,
+    pub(crate) quarantine: bool,
+    pub(crate) quarantine_report: Option<PathBuf>,
+    // Set with `--on-error skip`, rows failing on their own are appended to it instead of failing the table
+    pub(crate) dead_letter_file: Option<PathBuf>,
+    // Verify tables as soon as they are loaded
+    pub(crate) verify: bool,
+    pub(crate) verify_checksums: bool,
+    pub(crate) strict_types: bool,
+    pub(crate) guid_format: GuidFormat,
+    pub(crate) include_dependencies: bool,
     pub(crate) format_snake_case: bool,
+    pub(crate) table_comments: bool,
+    // Names the backups and table comments of the run
+    pub(crate) run_id: String,
+    // Given by the operator, kept in the run history and included in notifications
+    pub(crate) note: Option<String>,
     pub(crate) max_concurrent_tasks: usize,
+    // Tables in flight at once, on top of the parallelism slots their rows are loaded with
+    pub(crate) max_concurrent_tables: Option<usize>,
+    pub(crate) source_max_connections: Option<u32>,
+    pub(crate) target_max_connections: Option<u32>,
+    pub(crate) adaptive_parallelism: Option<ParallelismBounds>,
+    pub(crate) scheduler: SchedulerMode,
+    pub(crate) table_workers: usize,
+    pub(crate) insert_workers: usize,
     pub(crate) max_packet_bytes: usize,
+    pub(crate) chunk_size: Option<usize>,
+    pub(crate) collation: Collation,
+    pub(crate) column_collations: bool,
+    pub(crate) unchecked_reset_fallback: bool,
     pub(crate) whitelisted_tables: Vec<String>,
+    pub(crate) schema_prefixes: HashMap<String, String>,
+    pub(crate) prefix_schemas: bool,
+    // Referential actions replacing the ones of the source foreign keys
+    pub(crate) foreign_key_on_delete: Option<String>,
+    pub(crate) foreign_key_on_update: Option<String>,
+    #[cfg_attr(not(feature = "bundle"), allow(dead_code))]
+    pub(crate) bundle_signing_key: Option<String>,
+    pub(crate) table_configs: HashMap<String, TableConfig>,
+    pub(crate) id_offset: Option<i64>,
+    pub(crate) regenerate_keys: bool,
+    // Regenerated keys of the tables, built from the source before their rows are loaded
+    pub(crate) key_mappings: Option<Arc<KeyMappings>>,
+    pub(crate) time_zone: Option<TimeZoneConfig>,
+    pub(crate) char_padding: CharPadding,
+    pub(crate) on_invalid: OnInvalid,
+    pub(crate) check_in_lists: CheckInLists,
+    pub(crate) dialect: Dialect,
+    pub(crate) long_keys: LongKeys,
+    pub(crate) temporal_tables: TemporalTables,
+    pub(crate) sparse_column_sets: SparseColumnSets,
+    pub(crate) extraction_consistency: ExtractionConsistency,
+    pub(crate) computed_columns: ComputedColumns,
+    pub(crate) assertions: Vec<AssertionConfig>,
+    pub(crate) extra_columns: Vec<ExtraColumn>,
+    pub(crate) identifiers: IdentifierConfig,
+    // Set with `--output csv`, the rows are exported into CSV files of this format
+    pub(crate) csv: Option<CsvConfig>,
+    pub(crate) cache_dir: Option<PathBuf>,
+    pub(crate) max_cache_bytes: Option<u64>,
+    // Shared by all tables, and by all migrations of a `--config` run
+    pub(crate) memory_budget: Option<Arc<MemoryBudget>>,
+    pub(crate) grants_file: Option<PathBuf>,
+    pub(crate) lineage_file: Option<PathBuf>,
+    pub(crate) observers: Observers,
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    pub(crate) metrics_port: Option<u16>,
+    pub(crate) trace_file: Option<PathBuf>,
+    pub(crate) wait: Option<WaitConditions>,
+    pub(crate) max_duration: Option<Duration>,
+    pub(crate) resume: bool,
+    pub(crate) checkpoint_file: PathBuf,
+    // Set by the migrator for time-boxed and resumed runs
+    pub(crate) time_box: Option<Arc<TimeBox>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ParallelismBounds {
+    pub min: usize,
+    pub max: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SchedulerMode {
+    // Each table holds a parallelism slot until it is fully migrated
+    Table,
+    // Slots are taken per chunk, so small tables interleave with chunks of large ones
+    Fair,
+}
+
+impl FromStr for SchedulerMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(SchedulerMode::Table),
+            "fair" => Ok(SchedulerMode::Fair),
+            _ => bail!("Unknown scheduler mode: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    // Create missing target tables, existing ones are checked against the mapped schema
+    Schema,
+    // Load the rows into existing target tables
+    Data,
+    // Create the constraints of loaded tables
+    Constraints,
+    // Compare loaded tables with their source
+    Verify,
+}
+
+impl FromStr for Phase {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "schema" => Ok(Phase::Schema),
+            "data" => Ok(Phase::Data),
+            "constraints" => Ok(Phase::Constraints),
+            "verify" => Ok(Phase::Verify),
+            _ => bail!("Unknown phase: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnError {
+    // Fail the table on the first batch MySQL rejects
+    Abort,
+    // Retry rejected batches row by row and write the failing rows to the dead-letter file
+    Skip,
+}
+
+impl FromStr for OnError {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "abort" => Ok(OnError::Abort),
+            "skip" => Ok(OnError::Skip),
+            _ => bail!("Unknown error policy: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckFallback {
+    // Create the table without CHECK constraints that have no MySQL translation
+    Skip,
+    // Fail the table before loading it
+    Fail,
+}
+
+impl FromStr for CheckFallback {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(CheckFallback::Skip),
+            "fail" => Ok(CheckFallback::Fail),
+            _ => bail!("Unknown CHECK fallback: {}", s),
+        }
+    }
+}
+
+impl MigrationOptions {
+    // Options of a run without any command line flags
+    pub fn new(settings: SettingsConfig) -> Self {
+        build_migration_options(&MigrationArgs::default(), settings)
+    }
+
+    pub fn with_drop(mut self, drop: bool) -> Self {
+        self.drop = drop;
+        self
+    }
+
+    pub fn with_upsert(mut self, upsert: bool) -> Self {
+        self.upsert = upsert;
+        self
+    }
+
+    pub fn with_append(mut self, append: bool) -> Self {
+        self.append = append;
+        self
+    }
+
+    pub fn with_constraints(mut self, constraints: bool) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    pub fn with_quarantine(mut self, quarantine: bool) -> Self {
+        self.quarantine = quarantine;
+        self
+    }
+
+    pub fn with_strict_types(mut self, strict_types: bool) -> Self {
+        self.strict_types = strict_types;
+        self
+    }
+
+    pub fn with_include_dependencies(mut self, include_dependencies: bool) -> Self {
+        self.include_dependencies = include_dependencies;
+        self
+    }
+
+    pub fn with_format_snake_case(mut self, format_snake_case: bool) -> Self {
+        self.format_snake_case = format_snake_case;
+        self
+    }
+
+    pub fn with_parallelism(mut self, parallelism: usize) -> Self {
+        self.max_concurrent_tasks = parallelism.max(1);
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.note = Some(note.to_string());
+        self
+    }
+
+    pub fn with_observer(mut self, observer: Arc<dyn MigrationObserver>) -> Self {
+        self.observers.add(observer);
+        self
+    }
+
+    // Hash of the settings deciding what a run writes, recorded in the migration log so runs with changed settings
+    // stand out. Run ids, notes, parallelism and the files written next to the run leave it unchanged.
+    pub(crate) fn options_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+
+        hasher.update(format!(
+            "{:?}",
+            (
+                self.drop,
+                self.backup,
+                self.upsert,
+                self.append,
+                self.shadow,
+                self.sync_schema,
+                self.constraints,
+                self.quarantine,
+                self.strict_types,
+                self.guid_format,
+                self.format_snake_case,
+                self.column_collations,
+            )
+        ));
+        hasher.update(format!(
+            "{:?}",
+            (
+                &self.collation,
+                self.id_offset,
+                self.regenerate_keys,
+                self.time_zone,
+                self.char_padding,
+                self.on_invalid,
+                self.dialect,
+                self.long_keys,
+                self.temporal_tables,
+                self.sparse_column_sets,
+                self.computed_columns,
+            )
+        ));
+        hasher.update(format!(
+            "{:?}",
+            (
+                &self.whitelisted_tables,
+                sorted(&self.schema_prefixes),
+                self.prefix_schemas,
+                &self.foreign_key_on_delete,
+                &self.foreign_key_on_update,
+                &self.extra_columns,
+                &self.identifiers,
+            )
+        ));
+
+        // Debug output of hash maps follows no order, so table settings are hashed field by field
+        for (pattern, config) in sorted(&self.table_configs) {
+            hasher.update(format!(
+                "{:?}",
+                (
+                    pattern,
+                    &config.partitioning,
+                    sorted(&config.null_fallbacks),
+                    config.id_offset,
+                    config.regenerate_keys,
+                    sorted(&config.time_zones),
+                    sorted(&config.validations),
+                    config.on_invalid,
+                    config.max_packet_bytes,
+                    config.batch_rows,
+                    sorted(&config.long_keys),
+                )
+            ));
+        }
+
+        hex::encode(&hasher.finalize()[..8])
+    }
+
+    // Pool size covering the highest parallelism of the run
+    pub(crate) fn max_connections(&self) -> u32 {
+        self.adaptive_parallelism
+            .map_or(self.max_concurrent_tasks, |bounds| bounds.max)
+            .max(self.max_concurrent_tasks) as u32
+    }
+
+    // Connections of each pool, with one more for the verifications running next to the tasks
+    pub(crate) fn pool_size(&self) -> u32 {
+        self.max_connections() + self.verify as u32
+    }
+
+    pub(crate) fn source_pool_size(&self) -> u32 {
+        self.source_max_connections
+            .unwrap_or_else(|| self.pool_size())
+    }
+
+    // Insert workers write through connections of their own, next to the one of their task
+    pub(crate) fn target_pool_size(&self) -> u32 {
+        self.target_max_connections.unwrap_or_else(|| {
+            self.pool_size() + self.max_connections() * (self.insert_workers.max(1) as u32 - 1)
+        })
+    }
+
+    // Tables started at once by exports, dumps and applies, which load each table with a single slot
+    pub(crate) fn table_parallelism(&self) -> usize {
+        self.max_concurrent_tables
+            .map_or(self.max_concurrent_tasks, |tables| {
+                tables.min(self.max_concurrent_tasks)
+            })
+    }
+
+    pub fn target_table_name(&self, table: &SourceTable) -> String {
+        let prefix = match self.schema_prefixes.get(&table.schema) {
+            Some(prefix) => prefix.clone(),
+            None if self.prefix_schemas && table.schema != DEFAULT_SCHEMA => {
+                format!("{}_", table.schema)
+            }
+            None => String::new(),
+        };
+
+        let table_name = format!("{}{}", prefix, table.name);
+
+        if self.format_snake_case {
+            self.apply_identifier_policy(format_snake_case(&table_name))
+        } else {
+            self.apply_identifier_policy(table_name)
+        }
+    }
+
+    pub fn target_column_name(&self, column_name: &str) -> String {
+        if self.format_snake_case {
+            self.apply_identifier_policy(format_snake_case(column_name))
+        } else {
+            self.apply_identifier_policy(column_name.to_string())
+        }
+    }
+
+    fn apply_identifier_policy(&self, name: String) -> String {
+        let name = match self.identifiers.case {
+            IdentifierCase::Preserve => name,
+            IdentifierCase::Lower => name.to_lowercase(),
+        };
+
+        match &self.identifiers.reserved_prefix {
+            Some(prefix) if is_reserved_word(&name) => format!("{}{}", prefix, name),
+            _ => name,
+        }
+    }
+
+    // Settings of the most specific configured pattern matching the table
+    pub fn table_config(&self, table: &SourceTable) -> Option<&TableConfig> {
+        self.table_config_pattern(table)
+            .and_then(|pattern| self.table_configs.get(pattern))
+    }
+
+    // Offset added to the integer keys of the table and to the foreign keys referencing them
+    pub fn id_offset(&self, table: &SourceTable) -> i64 {
+        self.table_config(table)
+            .and_then(|config| config.id_offset)
+            .or(self.id_offset)
+            .unwrap_or_default()
+    }
+
+    pub fn is_protected(&self, table: &SourceTable) -> bool {
+        self.table_config(table)
+            .is_some_and(|config| config.protected)
+    }
+
+    // How the run would wipe the target tables, None if it keeps their rows
+    pub(crate) fn reset_action(&self) -> Option<&'static str> {
+        if self.backup {
+            Some("replaced by --backup")
+        } else if self.upsert || self.append {
+            None
+        } else if self.shadow {
+            Some("replaced by --shadow")
+        } else if self.drop {
+            Some("dropped by --drop")
+        } else {
+            Some("truncated, load them with --upsert or --append instead")
+        }
+    }
+
+    // Fails before anything is changed if the action would touch a protected table
+    pub(crate) fn check_protected_tables(
+        &self,
+        tables: &[SourceTable],
+        action: &str,
+    ) -> Result<(), Error> {
+        let protected = tables
+            .iter()
+            .filter(|table| self.is_protected(table))
+            .map(|table| table.to_string())
+            .collect::<Vec<_>>();
+
+        if !protected.is_empty() {
+            bail!(
+                "Protected tables would be {}: {}",
+                action,
+                protected.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn regenerates_keys(&self, table: &SourceTable) -> bool {
+        self.table_config(table)
+            .and_then(|config| config.regenerate_keys)
+            .unwrap_or(self.regenerate_keys)
+    }
+
+    pub fn on_invalid(&self, table: &SourceTable) -> OnInvalid {
+        self.table_config(table)
+            .and_then(|config| config.on_invalid)
+            .unwrap_or(self.on_invalid)
+    }
+
+    // Handling of a key column too long for an index, columns are matched by their source name
+    pub fn long_keys(&self, table: &SourceTable, column_name: &str) -> LongKeys {
+        self.table_config(table)
+            .and_then(|config| {
+                config
+                    .long_keys
+                    .iter()
+                    .find(|(column, _)| column.eq_ignore_ascii_case(column_name))
+            })
+            .map(|(_, policy)| *policy)
+            .unwrap_or(self.long_keys)
+    }
+
+    // Size limit of the table's batches
+    pub fn packet_bytes(&self, table: &SourceTable) -> usize {
+        self.table_config(table)
+            .and_then(|config| config.max_packet_bytes)
+            .unwrap_or(self.max_packet_bytes)
+    }
+
+    pub fn batch_rows(&self, table: &SourceTable) -> Option<usize> {
+        self.table_config(table)
+            .and_then(|config| config.batch_rows)
+    }
+
+    // Largest batch any table may send, to check against the server's `max_allowed_packet`
+    pub fn largest_packet_bytes(&self) -> usize {
+        self.table_configs
+            .values()
+            .filter_map(|config| config.max_packet_bytes)
+            .fold(self.max_packet_bytes, usize::max)
+    }
+
+    // Tables whose full batches fit in the memory ceiling at once, each worker holds a batch twice while writing it.
+    // Insert workers hold a batch each while writing it, and another one waits for each of them.
+    pub fn memory_task_limit(&self) -> Option<usize> {
+        self.memory_budget.as_ref().map(|budget| {
+            let batches = match self.insert_workers {
+                0 | 1 => 1,
+                workers => 1 + 2 * workers,
+            };
+            let table_bytes = 2 * self.largest_packet_bytes() * self.table_workers.max(1) * batches;
+            (budget.limit() / table_bytes.max(1)).max(1)
+        })
+    }
+
+    // Whether any table with validation rules quarantines the rows failing them
+    pub fn quarantines_invalid_rows(&self) -> bool {
+        self.table_configs.values().any(|config| {
+            !config.validations.is_empty()
+                && config.on_invalid.unwrap_or(self.on_invalid) == OnInvalid::Quarantine
+        })
+    }
+
+    pub fn key_mapping(&self, table: &SourceTable) -> Option<Arc<KeyMapping>> {
+        self.key_mappings
+            .as_ref()
+            .and_then(|mappings| mappings.get(&self.target_table_name(table)))
+    }
+
+    pub fn table_config_pattern(&self, table: &SourceTable) -> Option<&str> {
+        let mut patterns = self
+            .table_configs
+            .keys()
+            .filter(|pattern| table.matches(pattern))
+            .collect::<Vec<_>>();
+
+        // Qualified patterns are more specific than bare table names
+        patterns.sort_by_key(|pattern| !pattern.contains('.'));
+
+        patterns.first().map(|pattern| pattern.as_str())
+    }
+}
+
+fn sorted<K: Ord, V>(map: &HashMap<K, V>) -> BTreeMap<&K, &V> {
+    map.iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(prefix_schemas: bool) -> MigrationOptions {
+        let mut options = MigrationOptions::new(SettingsConfig::default());
+        options.prefix_schemas = prefix_schemas;
+        options
+            .schema_prefixes
+            .insert("sales".to_string(), "shop_".to_string());
+        options
+    }
+
+    #[test]
+    fn keeps_bare_table_names_unless_schemas_are_prefixed() {
+        let options = options(false);
+
+        assert_eq!(
+            options.target_table_name(&SourceTable::new("dbo", "Orders")),
+            "Orders"
+        );
+        assert_eq!(
+            options.target_table_name(&SourceTable::new("hr", "Staff")),
+            "Staff"
+        );
+        assert_eq!(
+            options.target_table_name(&SourceTable::new("sales", "Orders")),
+            "shop_Orders"
+        );
+    }
+
+    #[test]
+    fn prefixes_tables_outside_dbo_with_their_schema() {
+        let options = options(true);
+
+        assert_eq!(
+            options.target_table_name(&SourceTable::new("dbo", "Orders")),
+            "Orders"
+        );
+        assert_eq!(
+            options.target_table_name(&SourceTable::new("hr", "Staff")),
+            "hr_Staff"
+        );
+        assert_eq!(
+            options.target_table_name(&SourceTable::new("sales", "Orders")),
+            "shop_Orders"
+        );
+    }
+
+    #[test]
+    fn applies_the_identifier_policy() {
+        let mut options = MigrationOptions::new(SettingsConfig::default());
+
+        // Reserved words are kept as they are unless a prefix is configured
+        assert_eq!(options.target_column_name("Order"), "Order");
+
+        options.identifiers = IdentifierConfig {
+            case: IdentifierCase::Lower,
+            reserved_prefix: Some("x_".to_string()),
+        };
+
+        assert_eq!(options.target_column_name("Order"), "x_order");
+        assert_eq!(options.target_column_name("OrderDate"), "orderdate");
+        assert_eq!(
+            options.target_table_name(&SourceTable::new("dbo", "Select")),
+            "x_select"
+        );
+
+        // Reserved words are recognized once the name is converted
+        let options = options.with_format_snake_case(true);
+
+        assert_eq!(options.target_column_name("OrderDate"), "order_date");
+        assert_eq!(options.target_column_name("Key"), "x_key");
+    }
 }
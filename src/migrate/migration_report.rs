@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+// Outcome of a migration run, one entry per table that was migrated
+#[derive(Debug, Clone, Default)]
+pub struct MigrationReport {
+    pub tables: Vec<TableReport>,
+    pub duration: Duration,
+    // The time budget was used up before all tables were migrated, the run is continued by `--resume`
+    pub suspended: bool,
+    // The `--note` of the run
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TableReport {
+    pub source_table: String,
+    pub table_name: String,
+    pub rows: usize,
+    // Duplicates left out with --append
+    pub ignored_rows: usize,
+    pub duration: Duration,
+    // The error chain of a failed table
+    pub error: Option<String>,
+    // Not (completely) loaded because the time budget was used up
+    pub suspended: bool,
+    // Whether the table matched its source right after loading it, with --verify
+    pub verified: Option<bool>,
+}
+
+impl MigrationReport {
+    pub fn succeeded(&self) -> bool {
+        self.tables.iter().all(|table| table.error.is_none())
+    }
+
+    pub fn failed_tables(&self) -> impl Iterator<Item = &TableReport> {
+        self.tables.iter().filter(|table| table.error.is_some())
+    }
+
+    pub fn unverified_tables(&self) -> impl Iterator<Item = &TableReport> {
+        self.tables
+            .iter()
+            .filter(|table| table.verified == Some(false))
+    }
+
+    pub fn total_rows(&self) -> usize {
+        self.tables.iter().map(|table| table.rows).sum()
+    }
+
+    pub fn total_ignored_rows(&self) -> usize {
+        self.tables.iter().map(|table| table.ignored_rows).sum()
+    }
+}
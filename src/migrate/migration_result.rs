@@ -1,8 +1,16 @@
 use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::sync::state::SyncState;
 
 #[derive(Debug, Clone)]
 pub struct MigrationResult {
     pub table_name: String,
     pub schema: Vec<ColumnSchema>,
+    pub source_table: SourceTable,
+    pub source_schema: Vec<ColumnSchema>,
     pub created: bool,
+    pub rows: usize,
+    // Rows left out with --append because their key was already in the target
+    pub ignored_rows: usize,
+    pub sync_state: Option<SyncState>,
 }
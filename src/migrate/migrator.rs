@@ -1,21 +1,49 @@
+use std::fs;
 use std::sync::Arc;
 
 use anyhow::{bail, Context, Error, Result};
 use futures::future::join_all;
 use log::info;
 use tokio::spawn;
-use tokio::sync::Semaphore;
 use tokio::time::Instant;
 
-use crate::common::helpers::{format_snake_case, print_error_chain};
+use crate::common::dependency_graph::DependencyGraph;
+use crate::common::helpers::print_error_chain;
+use crate::common::source_table::SourceTable;
 use crate::extract::extractor::DatabaseExtractor;
 use crate::insert::inserter::DatabaseInserter;
 use crate::insert::table_action::TableAction;
 use crate::mappings::Mappings;
+use crate::migrate::assertions::run_assertions;
+use crate::migrate::backup::backup_tables;
+use crate::migrate::check_triggers::{create_check_triggers, enforces_check_constraints};
+use crate::migrate::checkpoint::{is_suspended, Checkpoint, Suspended, TimeBox};
+use crate::migrate::concurrency_limiter::ConcurrencyLimiter;
 use crate::migrate::constraints_creator::ConstraintsCreator;
-use crate::migrate::migration_options::MigrationOptions;
+use crate::migrate::grant_suggestions::write_grant_suggestions;
+use crate::migrate::key_mapping::build_key_mappings;
+use crate::migrate::lineage::write_lineage_report;
+#[cfg(feature = "metrics")]
+use crate::migrate::metrics::{Metrics, MetricsServer};
+use crate::migrate::migration_log::MigrationLog;
+use crate::migrate::migration_options::{MigrationOptions, Phase, SchedulerMode};
+use crate::migrate::migration_report::{MigrationReport, TableReport};
 use crate::migrate::migration_result::MigrationResult;
+use crate::migrate::name_map::NameMap;
+use crate::migrate::observer::MigrationEvent;
+use crate::migrate::parallelism::check_parallelism;
+use crate::migrate::pool_monitor::PoolMonitor;
+use crate::migrate::quarantine::{
+    reset_quarantine_tables, retry_quarantined_rows, write_quarantine_report,
+};
+use crate::migrate::run_history::RunHistory;
 use crate::migrate::table_migrator::TableMigrator;
+use crate::migrate::temporal::resolve_temporal_tables;
+use crate::migrate::trace::TraceRecorder;
+use crate::migrate::unique_check::check_unique_constraints;
+use crate::migrate::verifier::DatabaseVerifier;
+use crate::migrate::wait::wait_for_source;
+use crate::sync::state::save_sync_states;
 
 pub struct DatabaseMigrator {
     extractor: DatabaseExtractor,
@@ -39,92 +67,622 @@ impl DatabaseMigrator {
         }
     }
 
-    pub async fn run(&mut self) -> Result<()> {
+    pub async fn run(&mut self) -> Result<MigrationReport> {
+        let start_time = Instant::now();
+
+        #[cfg(feature = "metrics")]
+        let metrics_server = match self.options.metrics_port {
+            Some(port) => {
+                let metrics = Arc::new(Metrics::default());
+                self.options.observers.add(metrics.clone());
+
+                Some(MetricsServer::start(port, metrics, &self.extractor, &self.inserter).await?)
+            }
+            None => None,
+        };
+
+        if let Some(note) = &self.options.note {
+            info!("Run {}: {}", self.options.run_id, note);
+        }
+
+        let run_history = RunHistory::start(
+            self.inserter.clone(),
+            &self.options.run_id,
+            self.options.note.as_deref(),
+        )
+        .await
+        .with_context(|| "Failed to create the run history table")?;
+        self.options.observers.add(Arc::new(run_history));
+
+        let migration_log = MigrationLog::start(
+            self.inserter.clone(),
+            &self.options.run_id,
+            &self.options.options_hash(),
+        )
+        .await
+        .with_context(|| "Failed to create the migration log table")?;
+        self.options.observers.add(Arc::new(migration_log));
+
+        let trace_recorder = self.options.trace_file.is_some().then(|| {
+            let recorder = Arc::new(TraceRecorder::start());
+            self.options.observers.add(recorder.clone());
+            recorder
+        });
+
+        let mut result = self.run_phase().await;
+        let duration = start_time.elapsed();
+
+        if let Err(err) = self.extractor.end_consistent_reads().await {
+            print_error_chain(&err);
+        }
+
+        let event = match &mut result {
+            Ok(report) => {
+                report.duration = duration;
+                report.note = self.options.note.clone();
+
+                MigrationEvent::MigrationFinished {
+                    tables: report.tables.len(),
+                    failed_tables: report.failed_tables().count(),
+                    rows: report.total_rows(),
+                    duration,
+                    suspended: report.suspended,
+                    error: None,
+                }
+            }
+            Err(err) => MigrationEvent::MigrationFinished {
+                tables: 0,
+                failed_tables: 0,
+                rows: 0,
+                duration,
+                suspended: false,
+                error: Some(format!("{:#}", err)),
+            },
+        };
+        self.options.observers.emit(event).await;
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics_server) = metrics_server {
+            metrics_server.stop();
+        }
+
+        if let (Some(recorder), Some(trace_file)) = (trace_recorder, &self.options.trace_file) {
+            match recorder.save(trace_file) {
+                Ok(()) => info!("Wrote trace to {}", trace_file.display()),
+                Err(err) => print_error_chain(&err.context("Failed to write trace file")),
+            }
+        }
+
+        result
+    }
+
+    async fn run_phase(&mut self) -> Result<MigrationReport> {
+        if let Some(conditions) = &self.options.wait {
+            wait_for_source(&mut self.extractor, conditions).await?;
+        }
+
+        // Only loading the data reads rows
+        if matches!(self.options.phase, None | Some(Phase::Data)) {
+            self.extractor
+                .begin_consistent_reads(&self.options.run_id)
+                .await?;
+        }
+
         info!("Running table migrator");
 
-        let config_send_packet_size = self.options.max_packet_bytes;
+        let config_send_packet_size = self.options.largest_packet_bytes();
         let max_allowed_packet = self.inserter.get_max_allowed_packet().await?;
 
         check_packet_size(config_send_packet_size, max_allowed_packet).await?;
 
-        self.migrate_tables().await?;
+        if self.options.scheduler == SchedulerMode::Fair && self.options.chunk_size.is_none() {
+            warn!("Fair scheduling has no effect without a configured chunk size");
+        }
 
-        Ok(())
+        check_parallelism(&mut self.extractor, &self.inserter, &mut self.options).await;
+
+        match self.options.phase {
+            None => self.migrate_tables().await,
+            Some(Phase::Schema) => {
+                self.create_schemas().await?;
+                Ok(MigrationReport::default())
+            }
+            Some(Phase::Data) => {
+                if self.options.drop || self.options.backup {
+                    bail!("--drop and --backup recreate the target tables, use them with --phase schema");
+                }
+
+                self.options.constraints = false;
+                self.migrate_tables().await
+            }
+            Some(Phase::Constraints) => {
+                self.options.constraints = true;
+                self.create_constraints().await?;
+                Ok(MigrationReport::default())
+            }
+            Some(Phase::Verify) => {
+                self.verify().await?;
+                Ok(MigrationReport::default())
+            }
+        }
     }
 
-    pub async fn migrate_tables(&mut self) -> Result<()> {
-        let start_time = Instant::now();
+    async fn create_schemas(&mut self) -> Result<()> {
+        let (tables, formatted_tables) = self.fetch_and_format_tables().await?;
 
+        if self.options.backup {
+            self.options
+                .check_protected_tables(&tables, "replaced by --backup")?;
+        } else if self.options.drop {
+            self.options
+                .check_protected_tables(&tables, "dropped by --drop")?;
+        }
+
+        if self.options.backup {
+            backup_tables(&mut self.inserter, &formatted_tables, &self.options.run_id)
+                .await
+                .with_context(|| "Failed to back up tables")?;
+        } else if self.options.drop {
+            self.inserter
+                .reset_tables(
+                    &formatted_tables,
+                    TableAction::Drop,
+                    self.options.unchecked_reset_fallback,
+                )
+                .await?;
+        }
+
+        let mut failed_tables = 0;
+
+        for table in &tables {
+            let result = self
+                .table_migrator()
+                .create_table_schema(table)
+                .await
+                .with_context(|| format!("Error while creating the schema of table: {}", table));
+
+            match result {
+                Ok(result) if result.created => info!("Created table {}", result.table_name),
+                Ok(result) => info!("Table {} already exists", result.table_name),
+                Err(err) => {
+                    print_error_chain(&err);
+                    failed_tables += 1;
+                }
+            }
+        }
+
+        if failed_tables > 0 {
+            bail!(
+                "Schema phase failed for {} of {} tables",
+                failed_tables,
+                tables.len()
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn create_constraints(&mut self) -> Result<()> {
         let (tables, formatted_tables) = self.fetch_and_format_tables().await?;
+        let mut results = Vec::new();
+
+        for table in &tables {
+            results.push(self.table_migrator().existing_table(table).await?);
+        }
+
+        let mut constraints_creator = ConstraintsCreator::new(
+            self.inserter.clone(),
+            self.options.dialect,
+            self.options.recreate_constraints,
+        );
+        constraints_creator
+            .run(results.clone(), formatted_tables)
+            .await;
 
-        let action = if self.options.drop {
-            TableAction::Drop
+        self.emulate_check_constraints(&results)
+            .await
+            .with_context(|| "Failed to emulate CHECK constraints")
+    }
+
+    async fn verify(&mut self) -> Result<()> {
+        let (tables, _) = self.fetch_and_format_tables().await?;
+        self.check_tables_exist(&tables).await?;
+
+        DatabaseVerifier::new(
+            self.extractor.clone(),
+            self.inserter.clone(),
+            self.mappings.clone(),
+            self.options.clone(),
+            false,
+            false,
+        )
+        .run()
+        .await
+    }
+
+    // Phases following the schema phase need all target tables in place
+    async fn check_tables_exist(&mut self, tables: &[SourceTable]) -> Result<()> {
+        let mut missing_tables = Vec::new();
+
+        for table in tables {
+            let table_name = self.options.target_table_name(table);
+
+            if !self.inserter.table_exists(&table_name).await? {
+                missing_tables.push(table_name);
+            }
+        }
+
+        if !missing_tables.is_empty() {
+            bail!(
+                "Target tables {} don't exist, run --phase schema first",
+                missing_tables.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+
+    // Time-boxed and resumed runs share a deadline and the checkpoint of the loaded tables and chunks
+    fn start_time_box(&mut self) -> Result<Option<Arc<TimeBox>>> {
+        if self.options.max_duration.is_none() && !self.options.resume {
+            return Ok(None);
+        }
+
+        let checkpoint = if self.options.resume {
+            let checkpoint_file = &self.options.checkpoint_file;
+
+            info!(
+                "Resuming the migration saved in {}",
+                checkpoint_file.display()
+            );
+
+            Checkpoint::load(checkpoint_file).with_context(|| {
+                format!("Failed to load checkpoint {}", checkpoint_file.display())
+            })?
         } else {
-            TableAction::Truncate
+            Checkpoint::default()
         };
 
-        self.inserter
-            .reset_tables(&formatted_tables, action)
-            .await?;
+        let time_box = Arc::new(TimeBox::new(self.options.max_duration, checkpoint));
+        self.options.time_box = Some(Arc::clone(&time_box));
 
-        let migration_results = self.run_migration(tables).await;
-        let (successful_results, errors) = process_migration_results(migration_results).await;
+        Ok(Some(time_box))
+    }
 
-        // Handle errors
-        for err in errors {
+    fn table_migrator(&self) -> TableMigrator {
+        TableMigrator::new(
+            self.extractor.clone(),
+            self.inserter.clone(),
+            self.mappings.clone(),
+            self.options.clone(),
+            Arc::new(ConcurrencyLimiter::fixed(1)),
+        )
+    }
+
+    pub async fn migrate_tables(&mut self) -> Result<MigrationReport> {
+        let (tables, formatted_tables) = self.fetch_and_format_tables().await?;
+
+        if self.options.phase == Some(Phase::Data) {
+            self.check_tables_exist(&tables).await?;
+        }
+
+        let time_box = self.start_time_box()?;
+
+        // A resumed run would number the keys on from the rows already loaded
+        if time_box.is_some()
+            && tables
+                .iter()
+                .any(|table| self.options.regenerates_keys(table))
+        {
+            bail!("Regenerated keys cannot be combined with --max-duration and --resume");
+        }
+
+        if let Some(action) = self.options.reset_action() {
+            self.options.check_protected_tables(&tables, action)?;
+        }
+
+        // Tables loaded by the suspended run are only needed again for the constraints
+        let (completed_tables, tables): (Vec<_>, Vec<_>) = tables.into_iter().partition(|table| {
+            time_box.as_ref().is_some_and(|time_box| {
+                time_box.is_completed(&self.options.target_table_name(table))
+            })
+        });
+
+        // and keep their rows, as do tables suspended while loading their chunks
+        let reset_tables = formatted_tables
+            .iter()
+            .filter(|table_name| {
+                !time_box.as_ref().is_some_and(|time_box| {
+                    time_box.is_completed(table_name) || time_box.table_chunks(table_name).is_some()
+                })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if let Some(grants_file) = &self.options.grants_file {
+            if let Err(err) =
+                write_grant_suggestions(&mut self.extractor, &tables, &self.options, grants_file)
+                    .await
+                    .with_context(|| "Failed to write grant suggestions")
+            {
+                print_error_chain(&err);
+            }
+        }
+
+        if self.options.backup {
+            backup_tables(&mut self.inserter, &reset_tables, &self.options.run_id)
+                .await
+                .with_context(|| "Failed to back up tables")?;
+        } else if !self.options.upsert && !self.options.shadow {
+            let action = if self.options.drop {
+                TableAction::Drop
+            } else {
+                TableAction::Truncate
+            };
+
+            self.inserter
+                .reset_tables(&reset_tables, action, self.options.unchecked_reset_fallback)
+                .await?;
+        }
+
+        if self.options.quarantine {
+            reset_quarantine_tables(&mut self.inserter, &reset_tables)
+                .await
+                .with_context(|| "Failed to reset quarantine tables")?;
+        }
+
+        // Mapped once the tables are reset, new keys follow the rows the target keeps
+        let key_mappings = build_key_mappings(
+            &mut self.extractor,
+            Some(&mut self.inserter),
+            &tables,
+            &self.options,
+        )
+        .await
+        .with_context(|| "Failed to regenerate keys")?;
+
+        if !key_mappings.is_empty() {
+            self.options.key_mappings = Some(Arc::new(key_mappings));
+        }
+
+        let pool_monitor = PoolMonitor::start(&self.extractor, &self.inserter);
+        let (reports, migration_results): (Vec<_>, Vec<_>) =
+            self.run_migration(tables).await.into_iter().unzip();
+        pool_monitor.stop().await;
+        let (mut successful_results, errors) = process_migration_results(migration_results).await;
+
+        // Handle errors, suspended tables are reported along with the checkpoint
+        for err in errors.iter().filter(|err| !is_suspended(err)) {
+            print_error_chain(err);
+        }
+
+        let sync_states = successful_results
+            .iter()
+            .filter_map(|result| {
+                result
+                    .sync_state
+                    .map(|state| (result.table_name.clone(), state))
+            })
+            .collect::<Vec<_>>();
+
+        if let Err(err) = save_sync_states(&mut self.extractor, &mut self.inserter, &sync_states)
+            .await
+            .with_context(|| "Failed to save sync baselines")
+        {
             print_error_chain(&err);
         }
 
+        if let Some(time_box) = &time_box {
+            // Constraints and the other finishing steps are left to the run loading the last table
+            if reports.iter().any(|report| report.suspended) || time_box.is_expired() {
+                let checkpoint_file = &self.options.checkpoint_file;
+                time_box.save(checkpoint_file).with_context(|| {
+                    format!("Failed to save checkpoint {}", checkpoint_file.display())
+                })?;
+
+                info!(
+                    "Saved the progress of the suspended migration to {}, continue it with --resume",
+                    checkpoint_file.display()
+                );
+
+                return Ok(MigrationReport {
+                    tables: reports,
+                    suspended: true,
+                    ..Default::default()
+                });
+            }
+
+            for table in &completed_tables {
+                successful_results.push(self.table_migrator().existing_table(table).await?);
+            }
+        }
+
+        if let Some(lineage_file) = &self.options.lineage_file {
+            if let Err(err) = write_lineage_report(
+                lineage_file,
+                &successful_results,
+                &self.mappings,
+                &self.options,
+            )
+            .with_context(|| "Failed to write lineage report")
+            {
+                print_error_chain(&err);
+            }
+        }
+
         if self.options.constraints {
-            let mut constraints_creator = ConstraintsCreator::new(self.inserter.clone());
+            let mut constraints_creator = ConstraintsCreator::new(
+                self.inserter.clone(),
+                self.options.dialect,
+                self.options.recreate_constraints,
+            );
             constraints_creator
-                .run(successful_results, formatted_tables)
+                .run(successful_results.clone(), formatted_tables)
                 .await;
+
+            if let Err(err) = self
+                .emulate_check_constraints(&successful_results)
+                .await
+                .with_context(|| "Failed to emulate CHECK constraints")
+            {
+                print_error_chain(&err);
+            }
+        } else if let Err(err) = check_unique_constraints(&mut self.inserter, &successful_results)
+            .await
+            .with_context(|| "Failed to check unique constraints")
+        {
+            print_error_chain(&err);
+        }
+
+        if self.options.quarantine {
+            if let Err(err) = self
+                .retry_quarantine(&successful_results)
+                .await
+                .with_context(|| "Failed to retry quarantined rows")
+            {
+                print_error_chain(&err);
+            }
         }
 
-        let end_time = Instant::now();
+        run_assertions(&mut self.inserter, &self.options.assertions).await?;
 
-        info!(
-            "Migration finished, total time took: {}s",
-            end_time.saturating_duration_since(start_time).as_secs_f32()
-        );
+        // The migration is complete, a later --resume has nothing to continue
+        if time_box.is_some() && self.options.checkpoint_file.exists() {
+            fs::remove_file(&self.options.checkpoint_file)
+                .with_context(|| "Failed to remove checkpoint")?;
+        }
 
-        Ok(())
+        let report = MigrationReport {
+            tables: reports,
+            ..Default::default()
+        };
+
+        if self.options.append {
+            info!(
+                "{} rows were already present in the target and ignored",
+                report.total_ignored_rows()
+            );
+        }
+
+        let unverified_tables = report.unverified_tables().count();
+
+        if unverified_tables > 0 {
+            bail!(
+                "Verification failed for {} of {} tables",
+                unverified_tables,
+                report.tables.len()
+            );
+        }
+
+        Ok(report)
     }
 
-    async fn fetch_and_format_tables(&mut self) -> Result<(Vec<String>, Vec<String>)> {
-        let mut tables = self.extractor.fetch_tables().await?; // Fetch the list of tables from input database
-        let formatted_tables = format_table_names(&tables, self.options.format_snake_case); // Format to snake case if required
+    async fn emulate_check_constraints(&mut self, results: &[MigrationResult]) -> Result<()> {
+        let server_version = self.inserter.get_server_version().await?;
+
+        if enforces_check_constraints(&server_version) {
+            return Ok(());
+        }
+
+        if !self.options.check_triggers {
+            warn!(
+                "Target server {} doesn't enforce CHECK constraints, use --check-triggers to emulate them",
+                server_version
+            );
+            return Ok(());
+        }
+
+        let triggers = create_check_triggers(&mut self.inserter, results).await?;
 
-        if tables.is_empty() {
-            bail!("No tables to process");
+        if !triggers.is_empty() {
+            info!(
+                "Created CHECK constraint emulation triggers: {}",
+                triggers.join(", ")
+            );
         }
 
-        check_missing_tables(&tables, &self.options.whitelisted_tables);
+        Ok(())
+    }
 
-        // Filter and keep only the whitelisted tables
-        tables.retain(|table| self.options.whitelisted_tables.contains(table));
+    async fn retry_quarantine(&mut self, results: &[MigrationResult]) -> Result<()> {
+        let mut outcomes = Vec::new();
+
+        for result in results {
+            let outcome =
+                retry_quarantined_rows(&mut self.inserter, &result.table_name, &result.schema)
+                    .await?;
+
+            if outcome.recovered > 0 || !outcome.failed.is_empty() || outcome.invalid > 0 {
+                info!(
+                    "Quarantine retry of table {}, recovered: {}, still failing: {}, invalid: {}",
+                    outcome.table_name,
+                    outcome.recovered,
+                    outcome.failed.len(),
+                    outcome.invalid
+                );
+                outcomes.push(outcome);
+            }
+        }
 
-        if tables.is_empty() {
-            bail!("No tables to process after filtering whitelisted tables");
+        if let Some(report_file) = &self.options.quarantine_report {
+            write_quarantine_report(report_file, &outcomes)?;
         }
 
-        info!("Tables to migrate: {}", tables.join(", "));
+        Ok(())
+    }
+
+    async fn fetch_and_format_tables(&mut self) -> Result<(Vec<SourceTable>, Vec<String>)> {
+        let (tables, names) = resolve_source_tables(&mut self.extractor, &self.options).await?;
 
-        Ok((tables, formatted_tables))
+        Ok((tables, names.target_names()))
     }
 
-    async fn run_migration(&mut self, tables: Vec<String>) -> Vec<Result<MigrationResult, Error>> {
-        // Create a semaphore to limit the number of concurrent tasks
-        let semaphore = Arc::new(Semaphore::new(self.options.max_concurrent_tasks));
+    async fn run_migration(
+        &mut self,
+        tables: Vec<SourceTable>,
+    ) -> Vec<(TableReport, Result<MigrationResult, Error>)> {
+        // Fewer tables run at once when their batches wouldn't fit in the memory ceiling
+        let task_limit = self.options.memory_task_limit().unwrap_or(usize::MAX);
+        let max_concurrent_tasks = self.options.max_concurrent_tasks.min(task_limit);
+        let max_tasks = match self.options.adaptive_parallelism {
+            Some(bounds) => bounds.max,
+            None => self.options.max_concurrent_tasks,
+        };
+
+        if max_tasks > task_limit {
+            warn!(
+                "Running at most {} tables at once instead of {} to stay within the memory ceiling",
+                task_limit, max_tasks
+            );
+        }
+
+        // Create a limiter for the number of concurrent tasks, adjusted by batch throughput if enabled
+        let limiter = Arc::new(match self.options.adaptive_parallelism {
+            Some(bounds) => ConcurrencyLimiter::adaptive(
+                max_concurrent_tasks,
+                bounds.min.min(task_limit),
+                bounds.max.min(task_limit),
+            ),
+            None => ConcurrencyLimiter::fixed(max_concurrent_tasks),
+        });
+
+        // Verifications run one at a time on the extra connection of each pool
+        let verify_limiter = Arc::new(ConcurrencyLimiter::fixed(1));
+
+        let table_limiter = self
+            .options
+            .max_concurrent_tables
+            .map(|tables| Arc::new(ConcurrencyLimiter::fixed(tables)));
 
         // Create a Vec to store the JoinHandles for tasks
         let mut migration_tasks = Vec::new();
 
         // Spawn a task for each table to fetch the rows concurrently
         for table in tables {
-            // Clone the shared semaphore for each task
-            let semaphore_clone = Arc::clone(&semaphore);
+            // Clone the shared limiter for each task
+            let limiter = Arc::clone(&limiter);
+            let verify_limiter = Arc::clone(&verify_limiter);
+            let table_limiter = table_limiter.clone();
 
             let extractor = self.extractor.clone();
             let inserter = self.inserter.clone();
@@ -133,37 +691,224 @@ impl DatabaseMigrator {
 
             // Spawn a task for each table
             let task = spawn(async move {
-                // Acquire a semaphore permit before starting the task
-                let permit = semaphore_clone
-                    .acquire()
-                    .await
-                    .expect("Failed to acquire semaphore permit");
-
-                let mut table_migrator = TableMigrator::new(extractor, inserter, mappings, options);
-
-                let result = table_migrator
-                    .migrate_table(&table)
-                    .await
-                    .with_context(|| format!("Error while migrating table: {}", table));
-
-                // Release the semaphore permit when the task is done (whether successful or not)
+                // Tables wait for their turn before taking a parallelism slot, so slots aren't held idle
+                let table_permit = match &table_limiter {
+                    Some(table_limiter) => Some(
+                        table_limiter
+                            .acquire()
+                            .await
+                            .expect("Failed to acquire table limiter permit"),
+                    ),
+                    None => None,
+                };
+
+                // Acquire a limiter permit before starting the task, fair scheduling acquires per chunk instead
+                let permit = match options.scheduler {
+                    SchedulerMode::Table => Some(
+                        limiter
+                            .acquire()
+                            .await
+                            .expect("Failed to acquire limiter permit"),
+                    ),
+                    SchedulerMode::Fair => None,
+                };
+
+                let table_name = options.target_table_name(&table);
+                let start_time = Instant::now();
+
+                let observers = options.observers.clone();
+                let time_box = options.time_box.clone();
+
+                let mut verifier = options.verify.then(|| {
+                    DatabaseVerifier::new(
+                        extractor.clone(),
+                        inserter.clone(),
+                        mappings.clone(),
+                        options.clone(),
+                        options.verify_checksums,
+                        false,
+                    )
+                });
+
+                // Tables still waiting once the time budget is used up aren't started anymore
+                let result = if time_box
+                    .as_ref()
+                    .is_some_and(|time_box| time_box.is_expired())
+                {
+                    Err(Error::new(Suspended))
+                } else {
+                    observers
+                        .emit(MigrationEvent::TableStarted {
+                            table: table_name.clone(),
+                        })
+                        .await;
+
+                    let mut table_migrator =
+                        TableMigrator::new(extractor, inserter, mappings, options, limiter);
+
+                    table_migrator
+                        .migrate_table(&table)
+                        .await
+                        .with_context(|| format!("Error while migrating table: {}", table))
+                };
+
+                // Release the limiter permit when the task is done (whether successful or not)
                 drop(permit);
-                result
+                drop(table_permit);
+
+                // Verified outside of the permit, the next table is loaded meanwhile
+                let verified = match (&result, &mut verifier) {
+                    (Ok(_), Some(verifier)) => {
+                        let _permit = verify_limiter
+                            .acquire()
+                            .await
+                            .expect("Failed to acquire limiter permit");
+
+                        Some(
+                            verifier
+                                .check_table(&table)
+                                .await
+                                .with_context(|| format!("Failed to verify table {}", table))
+                                .unwrap_or_else(|err| {
+                                    print_error_chain(&err);
+                                    false
+                                }),
+                        )
+                    }
+                    _ => None,
+                };
+
+                if let (Ok(_), Some(time_box)) = (&result, &time_box) {
+                    time_box.complete_table(&table_name);
+                }
+
+                let suspended = result.as_ref().err().is_some_and(is_suspended);
+
+                let report = TableReport {
+                    source_table: table.to_string(),
+                    table_name,
+                    rows: result.as_ref().map_or(0, |result| result.rows),
+                    ignored_rows: result.as_ref().map_or(0, |result| result.ignored_rows),
+                    duration: start_time.elapsed(),
+                    error: result
+                        .as_ref()
+                        .err()
+                        .filter(|_| !suspended)
+                        .map(|err| format!("{:#}", err)),
+                    suspended,
+                    verified,
+                };
+
+                observers
+                    .emit(match &report.error {
+                        None if suspended => MigrationEvent::TableSuspended {
+                            table: report.table_name.clone(),
+                        },
+                        None => MigrationEvent::TableFinished {
+                            table: report.table_name.clone(),
+                            rows: report.rows,
+                            duration: report.duration,
+                        },
+                        Some(error) => MigrationEvent::TableFailed {
+                            table: report.table_name.clone(),
+                            error: error.clone(),
+                        },
+                    })
+                    .await;
+
+                (report, result)
             });
 
             migration_tasks.push(task);
         }
 
-        let migration_results: Vec<Result<MigrationResult, Error>> = join_all(migration_tasks)
-            .await
-            .into_iter()
-            .map(|join_handle_result| join_handle_result.expect("Error in JoinHandle"))
-            .collect();
+        let migration_results: Vec<(TableReport, Result<MigrationResult, Error>)> =
+            join_all(migration_tasks)
+                .await
+                .into_iter()
+                .map(|join_handle_result| join_handle_result.expect("Error in JoinHandle"))
+                .collect();
+
+        if let Some(limit) = limiter.current_limit() {
+            debug!("Adaptive parallelism settled at {}", limit);
+        }
 
         migration_results
     }
 }
 
+// Fetches the source tables to migrate along with the target names of all source tables
+pub async fn resolve_source_tables(
+    extractor: &mut DatabaseExtractor,
+    options: &MigrationOptions,
+) -> Result<(Vec<SourceTable>, NameMap)> {
+    let mut tables = extractor.fetch_tables().await?; // Fetch the list of tables from input database
+    let mut names = NameMap::new(&tables, options);
+
+    if tables.is_empty() {
+        bail!("No tables to process");
+    }
+
+    names.check_whitelist(&options.whitelisted_tables);
+
+    // Filter and keep only the whitelisted tables
+    tables.retain(|table| {
+        options
+            .whitelisted_tables
+            .iter()
+            .any(|pattern| table.matches(pattern))
+    });
+
+    if tables.is_empty() {
+        bail!("No tables to process after filtering whitelisted tables");
+    }
+
+    if options.constraints || options.include_dependencies {
+        let graph =
+            DependencyGraph::from_references(extractor.fetch_foreign_key_references().await?);
+
+        if options.include_dependencies {
+            let included = graph.with_dependencies(&tables).split_off(tables.len());
+
+            if !included.is_empty() {
+                info!(
+                    "Including referenced tables: {}",
+                    included
+                        .iter()
+                        .map(|table| table.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+
+            tables.extend(included);
+        } else {
+            for (table, dependency) in graph.missing_dependencies(&tables) {
+                warn!(
+                    "Table {} references {} which is not whitelisted, use --include-dependencies to migrate it as well",
+                    table, dependency
+                );
+            }
+        }
+    }
+
+    resolve_temporal_tables(extractor, &mut tables, options.temporal_tables).await?;
+
+    names.mark_migrated(&tables);
+    names.check_collisions()?;
+
+    info!(
+        "Tables to migrate: {}",
+        tables
+            .iter()
+            .map(|table| table.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    Ok((tables, names))
+}
+
 async fn check_packet_size(
     config_send_packet_size: usize,
     max_allowed_packet: usize,
@@ -181,35 +926,6 @@ async fn check_packet_size(
     Ok(())
 }
 
-fn check_missing_tables(tables: &[String], whitelisted_tables: &[String]) {
-    // Check for missing tables in whitelisted_tables
-    let missing_tables: Vec<_> = whitelisted_tables
-        .iter()
-        .filter(|table| !tables.contains(table))
-        .cloned()
-        .collect();
-
-    // If there are missing tables, print a warning
-    if !missing_tables.is_empty() {
-        let missing_tables_str = missing_tables.join(", ");
-        warn!(
-            "The following whitelisted tables were not found in the database: {}",
-            missing_tables_str
-        );
-    }
-}
-
-fn format_table_names(tables: &[String], format: bool) -> Vec<String> {
-    if format {
-        tables
-            .iter()
-            .map(|table_name| format_snake_case(table_name))
-            .collect()
-    } else {
-        tables.to_vec()
-    }
-}
-
 // Helper function to process migration results and separate successful results from errors
 async fn process_migration_results(
     migration_results: Vec<Result<MigrationResult, Error>>,
@@ -1,6 +1,48 @@
+pub mod assertions;
+pub mod backup;
+pub mod batch_sink;
+pub mod char_padding;
+pub mod check_triggers;
+pub mod checkpoint;
+pub mod checksum;
+pub mod concurrency_limiter;
 pub mod constraints_creator;
+pub mod dead_letters;
+pub mod grant_suggestions;
+pub mod id_offsets;
+pub mod insert_pipeline;
+pub mod key_mapping;
+pub mod lineage;
+pub mod lob_writer;
+pub mod memory_budget;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod migration_log;
 pub mod migration_options;
+pub mod migration_report;
 pub mod migration_result;
 pub mod migrator;
+pub mod name_map;
+pub mod null_fallback;
+pub mod observer;
+pub mod parallelism;
+pub mod partitioning;
+pub mod pool_monitor;
+pub mod provenance;
+pub mod quarantine;
+pub mod run_history;
+pub mod schema_drift;
+pub mod shadow;
+pub mod strict_types;
+pub mod table_diff;
 pub mod table_migrator;
 pub mod table_schema_mapper;
+pub mod temporal;
+pub mod time_zones;
+pub mod trace;
+pub mod unique_check;
+pub mod validation;
+pub mod verifier;
+pub mod wait;
+#[cfg(feature = "webhook")]
+pub mod webhook;
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+
+use crate::common::source_table::SourceTable;
+use crate::migrate::migration_options::MigrationOptions;
+
+// Target names of all source tables resolved in one pass, through the whitelist, schema prefixes, snake case and the
+// identifier policy. Resets, backups, constraints and verification take their table lists from here.
+#[derive(Debug, Clone, Default)]
+pub struct NameMap {
+    entries: Vec<NameEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NameEntry {
+    pub source: SourceTable,
+    pub target: String,
+    pub whitelisted: bool,
+    // Loaded by the run, whitelisted or included as a dependency or history table
+    pub migrated: bool,
+}
+
+impl NameMap {
+    pub fn new(source_tables: &[SourceTable], options: &MigrationOptions) -> Self {
+        let entries = source_tables
+            .iter()
+            .map(|table| NameEntry {
+                source: table.clone(),
+                target: options.target_table_name(table),
+                whitelisted: options
+                    .whitelisted_tables
+                    .iter()
+                    .any(|pattern| table.matches(pattern)),
+                migrated: false,
+            })
+            .collect();
+
+        NameMap { entries }
+    }
+
+    // Flattened temporal tables differ from the listed ones by their period column, they are matched by name
+    pub fn mark_migrated(&mut self, tables: &[SourceTable]) {
+        for entry in &mut self.entries {
+            entry.migrated = tables.iter().any(|table| {
+                table.schema == entry.source.schema && table.name == entry.source.name
+            });
+        }
+    }
+
+    pub fn entries(&self) -> &[NameEntry] {
+        &self.entries
+    }
+
+    // Target names of the tables the run loads
+    pub fn target_names(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.migrated)
+            .map(|entry| entry.target.clone())
+            .collect()
+    }
+
+    // Warns about whitelist entries matching no source table, hinting at the ones written as a target name
+    pub fn check_whitelist(&self, whitelisted_tables: &[String]) {
+        let mut missing_tables = Vec::new();
+
+        for pattern in whitelisted_tables {
+            if self
+                .entries
+                .iter()
+                .any(|entry| entry.source.matches(pattern))
+            {
+                continue;
+            }
+
+            match self
+                .entries
+                .iter()
+                .find(|entry| entry.target.eq_ignore_ascii_case(pattern))
+            {
+                Some(entry) => warn!(
+                    "Whitelisted table {} is the target name of {}, list the source name instead",
+                    pattern, entry.source
+                ),
+                None => missing_tables.push(pattern.as_str()),
+            }
+        }
+
+        if !missing_tables.is_empty() {
+            warn!(
+                "The following whitelisted tables were not found in the database: {}",
+                missing_tables.join(", ")
+            );
+        }
+    }
+
+    // MySQL compares table names case-insensitively on most platforms, so differing case still collides
+    pub fn check_collisions(&self) -> Result<()> {
+        let mut targets: HashMap<String, &SourceTable> = HashMap::new();
+
+        for entry in self.entries.iter().filter(|entry| entry.migrated) {
+            if let Some(other) = targets.insert(entry.target.to_lowercase(), &entry.source) {
+                bail!(
+                    "Source tables {} and {} both map to the target table {}",
+                    other,
+                    entry.source,
+                    entry.target
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
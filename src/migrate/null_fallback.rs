@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::common::schema::ColumnSchema;
+
+// Replaces NULL values of the configured columns with their fallback literal and counts the replacements
+pub struct NullFallbacks {
+    columns: Vec<(usize, String, String)>,
+    substitutions: Vec<AtomicUsize>,
+}
+
+impl NullFallbacks {
+    pub fn new(
+        table_name: &str,
+        fallbacks: &HashMap<String, String>,
+        source_schema: &[ColumnSchema],
+        mapped_schema: &[ColumnSchema],
+    ) -> Option<Self> {
+        let mut columns = Vec::new();
+
+        for (column_name, fallback) in fallbacks {
+            let index = source_schema
+                .iter()
+                .position(|column| column.column_name.eq_ignore_ascii_case(column_name));
+
+            match index {
+                Some(index) => columns.push((
+                    index,
+                    mapped_schema[index].column_name.clone(),
+                    fallback.clone(),
+                )),
+                None => warn!(
+                    "Table {} has no column {} for the configured NULL fallback",
+                    table_name, column_name
+                ),
+            }
+        }
+
+        if columns.is_empty() {
+            return None;
+        }
+
+        let substitutions = columns.iter().map(|_| AtomicUsize::new(0)).collect();
+
+        Some(NullFallbacks {
+            columns,
+            substitutions,
+        })
+    }
+
+    pub fn apply(&self, row: &mut [String]) {
+        for ((index, _, fallback), count) in self.columns.iter().zip(&self.substitutions) {
+            if row[*index] == "NULL" {
+                row[*index] = fallback.clone();
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn log_substitutions(&self, table_name: &str) {
+        for ((_, column_name, fallback), count) in self.columns.iter().zip(&self.substitutions) {
+            let count = count.load(Ordering::Relaxed);
+
+            if count > 0 {
+                info!(
+                    "Replaced {} NULL values of {}.{} with {}",
+                    count, table_name, column_name, fallback
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+// Events of a migration run, tables by target name
+#[derive(Debug, Clone)]
+pub enum MigrationEvent {
+    TableStarted {
+        table: String,
+    },
+    BatchExecuted {
+        table: String,
+        rows: usize,
+        bytes: usize,
+        duration: Duration,
+    },
+    TableFinished {
+        table: String,
+        rows: usize,
+        duration: Duration,
+    },
+    TableFailed {
+        table: String,
+        error: String,
+    },
+    // Stopped or not started because the time budget of the run was used up
+    TableSuspended {
+        table: String,
+    },
+    MigrationFinished {
+        tables: usize,
+        failed_tables: usize,
+        rows: usize,
+        duration: Duration,
+        // Continued by `--resume`
+        suspended: bool,
+        // The error chain when the run itself failed
+        error: Option<String>,
+    },
+}
+
+#[async_trait]
+pub trait MigrationObserver: Send + Sync {
+    async fn on_event(&self, event: &MigrationEvent);
+}
+
+#[async_trait]
+impl<F: Fn(&MigrationEvent) + Send + Sync> MigrationObserver for F {
+    async fn on_event(&self, event: &MigrationEvent) {
+        self(event)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Observers(Vec<Arc<dyn MigrationObserver>>);
+
+impl Observers {
+    pub fn add(&mut self, observer: Arc<dyn MigrationObserver>) {
+        self.0.push(observer);
+    }
+
+    pub async fn emit(&self, event: MigrationEvent) {
+        for observer in &self.0 {
+            observer.on_event(&event).await;
+        }
+    }
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Observers({})", self.0.len())
+    }
+}
+
+// Logs the progress of a run, registered by default
+pub struct LoggingObserver;
+
+#[async_trait]
+impl MigrationObserver for LoggingObserver {
+    async fn on_event(&self, event: &MigrationEvent) {
+        match event {
+            MigrationEvent::TableStarted { table } => info!("Migrating table: {}", table),
+            MigrationEvent::BatchExecuted {
+                table,
+                rows,
+                bytes,
+                duration,
+            } => debug!(
+                "Executed batch of table {} with {} transactions, bytes: {}, took: {}s",
+                table,
+                rows,
+                bytes,
+                duration.as_secs_f32()
+            ),
+            MigrationEvent::TableFinished {
+                table,
+                rows,
+                duration,
+            } => info!(
+                "Table {} migrated, rows: {}, took: {}s",
+                table,
+                rows,
+                duration.as_secs_f32()
+            ),
+            // Failed tables are reported along with their error chain once all tables are done
+            MigrationEvent::TableFailed { .. } => {}
+            MigrationEvent::TableSuspended { table } => {
+                info!("Table {} suspended, the time budget is used up", table)
+            }
+            MigrationEvent::MigrationFinished {
+                tables,
+                failed_tables,
+                rows,
+                duration,
+                suspended,
+                error,
+            } => match error {
+                None if *suspended => info!(
+                    "Migration suspended, tables: {}, failed: {}, rows: {}, total time took: {}s",
+                    tables,
+                    failed_tables,
+                    rows,
+                    duration.as_secs_f32()
+                ),
+                None => info!(
+                    "Migration finished, tables: {}, failed: {}, rows: {}, total time took: {}s",
+                    tables,
+                    failed_tables,
+                    rows,
+                    duration.as_secs_f32()
+                ),
+                Some(_) => info!(
+                    "Migration failed, total time took: {}s",
+                    duration.as_secs_f32()
+                ),
+            },
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::extract::extractor::DatabaseExtractor;
+use crate::insert::inserter::DatabaseInserter;
+use crate::migrate::migration_options::{MigrationOptions, SchedulerMode};
+
+// Tasks per source CPU above which reads mostly queue for the CPU instead of for I/O
+const TASKS_PER_CPU: usize = 2;
+
+// Checks that parallelism, table workers and pool sizes fit together before any table is started,
+// adjusting the settings that would otherwise stall or silently serialize the run
+pub async fn check_parallelism(
+    extractor: &mut DatabaseExtractor,
+    inserter: &DatabaseInserter,
+    options: &mut MigrationOptions,
+) {
+    if options.max_concurrent_tasks == 0 {
+        warn!("A parallelism of 0 would never start a table, using 1");
+        options.max_concurrent_tasks = 1;
+    }
+
+    if let Some(bounds) = &mut options.adaptive_parallelism {
+        if bounds.min == 0 {
+            bounds.min = 1;
+        }
+
+        if bounds.min > bounds.max {
+            warn!(
+                "--min-parallelism {} exceeds --max-parallelism {}, using {} as both bounds",
+                bounds.min, bounds.max, bounds.max
+            );
+            bounds.min = bounds.max;
+        }
+
+        let start = options.max_concurrent_tasks.clamp(bounds.min, bounds.max);
+
+        if start != options.max_concurrent_tasks {
+            warn!(
+                "--parallelism {} is outside the adaptive bounds {}..={}, starting at {}",
+                options.max_concurrent_tasks, bounds.min, bounds.max, start
+            );
+            options.max_concurrent_tasks = start;
+        }
+    }
+
+    // Every running task holds a connection of each pool, further tasks would wait for one while holding their slot
+    let pool_size = extractor.pool_size().min(inserter.pool_size()).max(1) as usize;
+    let max_tasks = options.max_connections() as usize;
+
+    if max_tasks > pool_size {
+        warn!(
+            "Up to {} tasks may run at once but the pools hold {} connections, limiting the parallelism to {}",
+            max_tasks, pool_size, pool_size
+        );
+
+        options.max_concurrent_tasks = options.max_concurrent_tasks.min(pool_size);
+
+        if let Some(bounds) = &mut options.adaptive_parallelism {
+            bounds.max = bounds.max.min(pool_size);
+            bounds.min = bounds.min.min(bounds.max);
+        }
+    }
+
+    let max_tasks = options.max_connections() as usize;
+
+    if options.table_workers > 1 && options.chunk_size.is_none() {
+        warn!(
+            "--table-workers {} has no effect without a configured chunk size, tables are read by one worker",
+            options.table_workers
+        );
+    } else if options.table_workers > max_tasks {
+        // Workers take the same slots as tables, the ones beyond the parallelism could never run
+        warn!(
+            "--table-workers {} exceeds the parallelism of {}, using {} workers per table",
+            options.table_workers, max_tasks, max_tasks
+        );
+        options.table_workers = max_tasks;
+    }
+
+    // Insert workers wait for connections of the target pool beyond the ones of the tasks
+    let insert_connections = max_tasks * options.insert_workers.max(1);
+
+    if options.insert_workers > 1 && (inserter.pool_size() as usize) < insert_connections {
+        warn!(
+            "--insert-workers {} needs up to {} connections but the MySQL pool holds {}, workers wait for them",
+            options.insert_workers,
+            insert_connections,
+            inserter.pool_size()
+        );
+    }
+
+    // Tables take one slot each when they are scheduled per table, more of them than slots can't run at once
+    if let Some(tables) = options.max_concurrent_tables {
+        if options.scheduler == SchedulerMode::Table && tables > max_tasks {
+            debug!(
+                "max_concurrent_tables {} exceeds the parallelism of {}, at most {} tables run at once",
+                tables, max_tasks, max_tasks
+            );
+        }
+    }
+
+    match extractor.fetch_cpu_count().await {
+        Ok(cpu_count) if max_tasks > cpu_count * TASKS_PER_CPU => warn!(
+            "Up to {} tasks read from a source server with {} CPUs, a parallelism above {} rarely speeds up the run",
+            max_tasks,
+            cpu_count,
+            cpu_count * TASKS_PER_CPU
+        ),
+        Ok(cpu_count) => debug!(
+            "Up to {} tasks read from a source server with {} CPUs",
+            max_tasks, cpu_count
+        ),
+        Err(e) => debug!("Failed to read the CPU count of the source server: {:#}", e),
+    }
+}
@@ -0,0 +1,213 @@
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Local};
+
+use crate::common::constraints::Constraint;
+use crate::common::identifiers::quote_identifier;
+use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::config::{PartitioningConfig, PartitioningMode};
+use crate::extract::extractor::DatabaseExtractor;
+use crate::migrate::migration_options::MigrationOptions;
+
+const DATE_TYPES: [&str; 2] = ["date", "datetime"];
+const INTEGER_TYPES: [&str; 5] = ["tinyint", "smallint", "mediumint", "int", "bigint"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PartitionKey {
+    // One partition per year of a date column
+    Year,
+    // Equally sized ranges of an integer column
+    Range,
+}
+
+// Partitioning clause for the created table when partitioning is applied, recommendations are only logged
+pub async fn plan_partitioning(
+    extractor: &mut DatabaseExtractor,
+    table: &SourceTable,
+    source_schema: &[ColumnSchema],
+    mapped_schema: &[ColumnSchema],
+    options: &MigrationOptions,
+) -> Result<Option<String>> {
+    let config = match options
+        .table_config(table)
+        .and_then(|table_config| table_config.partitioning.as_ref())
+    {
+        Some(config) => config,
+        None => return Ok(None),
+    };
+
+    let table_name = options.target_table_name(table);
+
+    let clause =
+        match build_partitioning_clause(extractor, table, source_schema, mapped_schema, config)
+            .await
+        {
+            Ok(clause) => clause,
+            Err(err) => {
+                warn!("Table {} cannot be partitioned: {:#}", table_name, err);
+                return Ok(None);
+            }
+        };
+
+    let has_foreign_keys = mapped_schema
+        .iter()
+        .any(|column| matches!(column.constraints, Some(Constraint::ForeignKey { .. })));
+
+    if has_foreign_keys && options.constraints {
+        warn!(
+            "Partitioned tables don't support foreign keys, the foreign keys of table {} will fail to be created",
+            table_name
+        );
+    }
+
+    match config.mode {
+        PartitioningMode::Recommend => {
+            info!(
+                "Recommended partitioning of table {}: {}",
+                table_name, clause
+            );
+            Ok(None)
+        }
+        PartitioningMode::Apply => {
+            info!("Partitioning table {}: {}", table_name, clause);
+            Ok(Some(clause))
+        }
+    }
+}
+
+async fn build_partitioning_clause(
+    extractor: &mut DatabaseExtractor,
+    table: &SourceTable,
+    source_schema: &[ColumnSchema],
+    mapped_schema: &[ColumnSchema],
+    config: &PartitioningConfig,
+) -> Result<String> {
+    let (index, key) = choose_partition_column(source_schema, mapped_schema, config)?;
+
+    let source_column = &source_schema[index].column_name;
+    let column = &mapped_schema[index].column_name;
+
+    match key {
+        PartitionKey::Year => {
+            let current_year = Local::now().year() as i64;
+            let (min_year, max_year) = extractor
+                .fetch_bounds(table, &format!("YEAR([{}])", source_column))
+                .await?
+                .unwrap_or((current_year, current_year));
+
+            let partitions = (min_year..=max_year)
+                .map(|year| format!("PARTITION p{} VALUES LESS THAN ({})", year, year + 1))
+                .chain(Some("PARTITION pmax VALUES LESS THAN MAXVALUE".to_string()))
+                .collect::<Vec<_>>();
+
+            Ok(format!(
+                "PARTITION BY RANGE (YEAR({})) ({})",
+                quote_identifier(column),
+                partitions.join(", ")
+            ))
+        }
+        PartitionKey::Range => {
+            let (min_key, max_key) = extractor
+                .fetch_bounds(table, &format!("[{}]", source_column))
+                .await?
+                .context("the table is empty, key ranges cannot be derived")?;
+
+            let width = max_key.saturating_sub(min_key) / config.partitions as i64 + 1;
+
+            let partitions = (1..config.partitions)
+                .map(|index| {
+                    format!(
+                        "PARTITION p{} VALUES LESS THAN ({})",
+                        index - 1,
+                        min_key.saturating_add(width * index as i64)
+                    )
+                })
+                .chain(Some("PARTITION pmax VALUES LESS THAN MAXVALUE".to_string()))
+                .collect::<Vec<_>>();
+
+            Ok(format!(
+                "PARTITION BY RANGE ({}) ({})",
+                quote_identifier(column),
+                partitions.join(", ")
+            ))
+        }
+    }
+}
+
+// MySQL requires the partitioning column to be part of every unique key of the table
+fn choose_partition_column(
+    source_schema: &[ColumnSchema],
+    mapped_schema: &[ColumnSchema],
+    config: &PartitioningConfig,
+) -> Result<(usize, PartitionKey)> {
+    let primary_key = mapped_schema
+        .iter()
+        .enumerate()
+        .filter(|(_, column)| column.constraints == Some(Constraint::PrimaryKey))
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+
+    let index = match &config.column {
+        Some(name) => {
+            let index = source_schema
+                .iter()
+                .zip(mapped_schema)
+                .position(|(source, mapped)| {
+                    source.column_name == *name || mapped.column_name == *name
+                })
+                .with_context(|| format!("column {} not found", name))?;
+
+            if !primary_key.is_empty() && !primary_key.contains(&index) {
+                bail!("column {} is not part of the primary key", name);
+            }
+
+            index
+        }
+        None => primary_key
+            .iter()
+            .copied()
+            .find(|index| partition_key(&mapped_schema[*index]) == Some(PartitionKey::Year))
+            .or_else(|| match primary_key.as_slice() {
+                [index] if partition_key(&mapped_schema[*index]).is_some() => Some(*index),
+                _ => None,
+            })
+            .context("no date or single integer primary key column to partition by")?,
+    };
+
+    let column = &mapped_schema[index];
+
+    if let Some(unique_column) = mapped_schema
+        .iter()
+        .enumerate()
+        .find(|(other, other_column)| {
+            *other != index && other_column.constraints == Some(Constraint::Unique)
+        })
+    {
+        bail!(
+            "unique column {} doesn't include the partitioning column {}",
+            unique_column.1.column_name,
+            column.column_name
+        );
+    }
+
+    let key = partition_key(column).with_context(|| {
+        format!(
+            "column {} of type {} cannot be partitioned by range",
+            column.column_name, column.data_type
+        )
+    })?;
+
+    Ok((index, key))
+}
+
+fn partition_key(column: &ColumnSchema) -> Option<PartitionKey> {
+    let data_type = column.data_type.to_lowercase();
+
+    if DATE_TYPES.contains(&data_type.as_str()) {
+        Some(PartitionKey::Year)
+    } else if INTEGER_TYPES.contains(&data_type.as_str()) {
+        Some(PartitionKey::Range)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+
+use crate::common::pool_stats::{PoolUsage, WaitSnapshot};
+use crate::extract::extractor::DatabaseExtractor;
+use crate::insert::inserter::DatabaseInserter;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+
+// Periodically logs the connection usage of both pools, to tell connection limits apart from slow databases
+pub struct PoolMonitor {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<(u32, u32)>,
+    extractor: DatabaseExtractor,
+    inserter: DatabaseInserter,
+}
+
+impl PoolMonitor {
+    pub fn start(extractor: &DatabaseExtractor, inserter: &DatabaseInserter) -> Self {
+        let (stop, mut stopped) = oneshot::channel();
+        let (sampled_extractor, sampled_inserter) = (extractor.clone(), inserter.clone());
+
+        let task = tokio::spawn(async move {
+            let mut ticker = interval(SAMPLE_INTERVAL);
+            let (mut mssql_peak, mut mysql_peak) = (0, 0);
+
+            // The first tick completes immediately
+            ticker.tick().await;
+
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = &mut stopped => break,
+                }
+
+                let (mssql, mysql) = (
+                    sampled_extractor.pool_usage(),
+                    sampled_inserter.pool_usage(),
+                );
+                mssql_peak = mssql_peak.max(mssql.in_use);
+                mysql_peak = mysql_peak.max(mysql.in_use);
+
+                debug!(
+                    "Pools - MSSQL: {} | MySQL: {}",
+                    format_usage(mssql, sampled_extractor.wait_stats()),
+                    format_usage(mysql, sampled_inserter.wait_stats())
+                );
+            }
+
+            (mssql_peak, mysql_peak)
+        });
+
+        PoolMonitor {
+            stop,
+            task,
+            extractor: extractor.clone(),
+            inserter: inserter.clone(),
+        }
+    }
+
+    pub async fn stop(self) {
+        let _ = self.stop.send(());
+        let (mssql_peak, mysql_peak) = self.task.await.unwrap_or_default();
+
+        debug!(
+            "Pool totals - MSSQL: peak {} in use, {} | MySQL: peak {} in use, {}",
+            mssql_peak,
+            format_waits(self.extractor.wait_stats()),
+            mysql_peak,
+            format_waits(self.inserter.wait_stats())
+        );
+    }
+}
+
+fn format_usage(usage: PoolUsage, waits: WaitSnapshot) -> String {
+    format!(
+        "{} in use, {} idle, {}",
+        usage.in_use,
+        usage.idle,
+        format_waits(waits)
+    )
+}
+
+fn format_waits(waits: WaitSnapshot) -> String {
+    format!(
+        "{} checkouts waiting {}ms on average, {}ms at most",
+        waits.waits,
+        waits.average().as_millis(),
+        waits.max.as_millis()
+    )
+}
@@ -0,0 +1,29 @@
+use anyhow::Result;
+use chrono::Local;
+
+use crate::common::source_table::SourceTable;
+use crate::extract::extractor::DatabaseExtractor;
+use crate::migrate::migration_options::MigrationOptions;
+
+// Comment of a created table tracing it back to its source and the run that created it
+pub async fn table_comment(
+    extractor: &mut DatabaseExtractor,
+    table: &SourceTable,
+    options: &MigrationOptions,
+) -> Result<Option<String>> {
+    if !options.table_comments {
+        return Ok(None);
+    }
+
+    let database = extractor.fetch_database_name().await?;
+
+    Ok(Some(format!(
+        "Migrated from MSSQL {}.{}.{} by db-migrator v{} on {}, run {}",
+        database,
+        table.schema,
+        table.name,
+        env!("CARGO_PKG_VERSION"),
+        Local::now().format("%Y-%m-%d"),
+        options.run_id
+    )))
+}
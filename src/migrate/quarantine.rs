@@ -0,0 +1,280 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_json::json;
+use tokio::sync::OnceCell;
+
+use crate::common::identifiers::{quote_identifier, quote_literal};
+use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::insert::inserter::DatabaseInserter;
+use crate::migrate::batch_sink::Batch;
+
+pub const QUARANTINE_SCHEMA: &str = "_quarantine";
+
+// Keeps rows MySQL rejected in a TEXT-only mirror of the target table, along with the error
+pub struct Quarantine {
+    source_table: String,
+    table_name: String,
+    columns: Vec<String>,
+    created: OnceCell<()>,
+}
+
+impl Quarantine {
+    pub fn new(source_table: &SourceTable, table_name: &str, schema: &[ColumnSchema]) -> Self {
+        Quarantine {
+            source_table: source_table.to_string(),
+            table_name: table_name.to_string(),
+            columns: schema
+                .iter()
+                .map(|column| column.column_name.clone())
+                .collect(),
+            created: OnceCell::new(),
+        }
+    }
+
+    pub fn qualified_name(&self) -> String {
+        format!("{}.{}", QUARANTINE_SCHEMA, self.table_name)
+    }
+
+    // Retries the rows of a failed batch one at a time, quarantining the ones that still fail
+    pub async fn salvage(
+        &self,
+        inserter: &mut DatabaseInserter,
+        batch: &Batch<'_>,
+    ) -> Result<usize> {
+        let mut quarantined = 0;
+
+        for row in batch.rows {
+            let query = format!("{}{}{}", batch.insert_statement, row, batch.suffix);
+
+            if let Err(err) = inserter.execute_transactional_query(&query).await {
+                self.ensure_table(inserter).await?;
+
+                inserter
+                    .execute_transactional_query(&self.build_insert_query(
+                        row,
+                        &format!("{:#}", err),
+                        false,
+                    ))
+                    .await
+                    .with_context(|| {
+                        format!("Failed to quarantine row into {}", self.qualified_name())
+                    })?;
+
+                quarantined += 1;
+            }
+        }
+
+        Ok(quarantined)
+    }
+
+    // Keeps a row that failed validation, it is left out of the final retry pass
+    pub async fn store_invalid(
+        &self,
+        inserter: &mut DatabaseInserter,
+        row: &str,
+        error: &str,
+    ) -> Result<()> {
+        self.ensure_table(inserter).await?;
+
+        inserter
+            .execute_transactional_query(&self.build_insert_query(row, error, true))
+            .await
+            .with_context(|| format!("Failed to quarantine row into {}", self.qualified_name()))
+    }
+
+    async fn ensure_table(&self, inserter: &mut DatabaseInserter) -> Result<()> {
+        self.created
+            .get_or_try_init(|| async {
+                inserter
+                    .execute_transactional_query(&format!(
+                        "CREATE DATABASE IF NOT EXISTS `{}`",
+                        QUARANTINE_SCHEMA
+                    ))
+                    .await?;
+
+                inserter
+                    .execute_transactional_query(&self.build_create_table_query())
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "Failed to create quarantine table {}",
+                            self.qualified_name()
+                        )
+                    })
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    fn build_create_table_query(&self) -> String {
+        let columns = self
+            .columns
+            .iter()
+            .map(|column| format!("{} LONGTEXT NULL", quote_identifier(column)))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        format!(
+            "CREATE TABLE IF NOT EXISTS `{}`.{} (`_quarantine_id` BIGINT AUTO_INCREMENT PRIMARY KEY, {}, `_source_table` VARCHAR(255) NOT NULL, `_error` TEXT NOT NULL, `_invalid` BOOLEAN NOT NULL DEFAULT FALSE, `_quarantined_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP)",
+            QUARANTINE_SCHEMA,
+            quote_identifier(&self.table_name),
+            columns
+        )
+    }
+
+    // Reuses the row's value tuple, appending the error metadata to it
+    fn build_insert_query(&self, row: &str, error: &str, invalid: bool) -> String {
+        let values = row.trim_end().strip_suffix(')').unwrap_or(row);
+
+        let columns = self
+            .columns
+            .iter()
+            .map(|column| quote_identifier(column))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        format!(
+            "INSERT INTO `{}`.{} ({}, `_source_table`, `_error`, `_invalid`) VALUES {}, {}, {}, {})",
+            QUARANTINE_SCHEMA,
+            quote_identifier(&self.table_name),
+            columns,
+            values,
+            quote_literal(&self.source_table),
+            quote_literal(error),
+            invalid
+        )
+    }
+}
+
+// Drops quarantine tables left over from previous runs, their rows would otherwise be retried again
+pub async fn reset_quarantine_tables(
+    inserter: &mut DatabaseInserter,
+    tables: &[String],
+) -> Result<()> {
+    let queries = tables
+        .iter()
+        .map(|table_name| {
+            format!(
+                "DROP TABLE IF EXISTS `{}`.{}",
+                QUARANTINE_SCHEMA,
+                quote_identifier(table_name)
+            )
+        })
+        .collect::<Vec<_>>();
+
+    inserter
+        .execute_transactional_query(&format!(
+            "CREATE DATABASE IF NOT EXISTS `{}`",
+            QUARANTINE_SCHEMA
+        ))
+        .await?;
+    inserter.execute_transactional_queries(&queries).await
+}
+
+pub struct RetryOutcome {
+    pub table_name: String,
+    pub recovered: usize,
+    // Rows that failed validation, never retried
+    pub invalid: usize,
+    pub failed: Vec<(i64, String)>,
+}
+
+// Moves quarantined rows into the target table once the whole migration loaded, with foreign keys enforced
+pub async fn retry_quarantined_rows(
+    inserter: &mut DatabaseInserter,
+    table_name: &str,
+    schema: &[ColumnSchema],
+) -> Result<RetryOutcome> {
+    let columns = schema
+        .iter()
+        .map(|column| quote_identifier(&column.column_name))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut outcome = RetryOutcome {
+        table_name: table_name.to_string(),
+        recovered: 0,
+        invalid: 0,
+        failed: Vec::new(),
+    };
+
+    let ids = inserter.fetch_quarantined_ids(table_name, false).await?;
+    outcome.invalid = inserter
+        .fetch_quarantined_ids(table_name, true)
+        .await?
+        .len()
+        - ids.len();
+
+    for id in ids {
+        let queries = [
+            format!(
+                "INSERT INTO {0} ({1}) SELECT {1} FROM `{2}`.{0} WHERE `_quarantine_id` = {3}",
+                quote_identifier(table_name),
+                columns,
+                QUARANTINE_SCHEMA,
+                id
+            ),
+            format!(
+                "DELETE FROM `{}`.{} WHERE `_quarantine_id` = {}",
+                QUARANTINE_SCHEMA,
+                quote_identifier(table_name),
+                id
+            ),
+        ];
+
+        match inserter.execute_checked_queries(&queries).await {
+            Ok(()) => outcome.recovered += 1,
+            Err(err) => {
+                let error = format!("{:#}", err);
+
+                // Keep the latest reason next to the row
+                inserter
+                    .execute_transactional_query(&format!(
+                        "UPDATE `{}`.{} SET `_error` = {} WHERE `_quarantine_id` = {}",
+                        QUARANTINE_SCHEMA,
+                        quote_identifier(table_name),
+                        quote_literal(&error),
+                        id
+                    ))
+                    .await?;
+
+                outcome.failed.push((id, error));
+            }
+        }
+    }
+
+    Ok(outcome)
+}
+
+// Lists the rows that stayed in quarantine after the retry, along with their errors
+pub fn write_quarantine_report(path: &Path, outcomes: &[RetryOutcome]) -> Result<()> {
+    let tables = outcomes
+        .iter()
+        .map(|outcome| {
+            json!({
+                "table": outcome.table_name,
+                "quarantine_table": format!("{}.{}", QUARANTINE_SCHEMA, outcome.table_name),
+                "recovered": outcome.recovered,
+                "invalid": outcome.invalid,
+                "failed": outcome
+                    .failed
+                    .iter()
+                    .map(|(id, error)| json!({ "quarantine_id": id, "error": error }))
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    fs::write(
+        path,
+        serde_json::to_string_pretty(&json!({ "tables": tables }))?,
+    )?;
+
+    info!("Quarantine report written to {}", path.display());
+
+    Ok(())
+}
@@ -0,0 +1,409 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+use crate::common::helpers::print_error_chain;
+use crate::common::identifiers::{quote_identifier, quote_literal};
+use crate::insert::inserter::DatabaseInserter;
+use crate::migrate::backup::BACKUP_RECORDS_TABLE;
+use crate::migrate::observer::{MigrationEvent, MigrationObserver};
+use crate::migrate::quarantine::QUARANTINE_SCHEMA;
+use crate::migrate::shadow::{retired_name, shadow_name};
+use crate::preflight::PROBE_TABLE;
+use crate::sync::state::SYNC_STATE_TABLE;
+
+pub const RUN_HISTORY_TABLE: &str = "_db_migrator_runs";
+
+const RUNNING: &str = "running";
+const COMPLETED: &str = "completed";
+const FAILED: &str = "failed";
+const SUSPENDED: &str = "suspended";
+const CLEANED: &str = "cleaned";
+
+// A table of a run as recorded in the run history, timestamps in seconds since the epoch
+pub struct RunRecord {
+    pub run_id: String,
+    pub table_name: String,
+    pub status: String,
+    pub rows: Option<i64>,
+    pub duration_ms: Option<i64>,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+    pub note: Option<String>,
+}
+
+impl RunRecord {
+    // Rows per second of a completed load
+    fn throughput(&self) -> Option<f64> {
+        match (self.rows, self.duration_ms) {
+            (Some(rows), Some(duration_ms)) if self.status == COMPLETED => {
+                Some(rows as f64 * 1000.0 / duration_ms.max(1) as f64)
+            }
+            _ => None,
+        }
+    }
+}
+
+// Records the state of every table a run loads, so `cleanup` knows what an aborted run left behind
+pub struct RunHistory {
+    inserter: DatabaseInserter,
+    run_id: String,
+    note: Option<String>,
+}
+
+impl RunHistory {
+    pub async fn start(
+        mut inserter: DatabaseInserter,
+        run_id: &str,
+        note: Option<&str>,
+    ) -> Result<Self> {
+        inserter
+            .execute_transactional_query(&format!(
+                "CREATE TABLE IF NOT EXISTS `{}` (\
+                    `run_id` VARCHAR(32) NOT NULL, \
+                    `table_name` VARCHAR(64) NOT NULL, \
+                    `status` VARCHAR(16) NOT NULL, \
+                    `started_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP, \
+                    `finished_at` TIMESTAMP NULL, \
+                    `rows` BIGINT NULL, \
+                    `duration_ms` BIGINT NULL, \
+                    `note` TEXT NULL, \
+                    PRIMARY KEY (`run_id`, `table_name`)\
+                )",
+                RUN_HISTORY_TABLE
+            ))
+            .await?;
+
+        // Tables created before runs had notes
+        if !inserter
+            .get_column_names(RUN_HISTORY_TABLE)
+            .await?
+            .iter()
+            .any(|column| column == "note")
+        {
+            inserter
+                .execute_transactional_query(&format!(
+                    "ALTER TABLE `{}` ADD COLUMN `note` TEXT NULL",
+                    RUN_HISTORY_TABLE
+                ))
+                .await
+                .with_context(|| format!("Failed to upgrade {}", RUN_HISTORY_TABLE))?;
+        }
+
+        Ok(RunHistory {
+            inserter,
+            run_id: run_id.to_string(),
+            note: note.map(|note| note.to_string()),
+        })
+    }
+
+    // Rows and duration are only known of completed tables
+    async fn record(
+        &self,
+        table_name: &str,
+        status: &str,
+        load: Option<(usize, Duration)>,
+    ) -> Result<()> {
+        let query = if status == RUNNING {
+            format!(
+                "INSERT INTO `{}` (`run_id`, `table_name`, `status`, `note`) VALUES ({}, {}, {}, {}) \
+                 ON DUPLICATE KEY UPDATE `status` = VALUES(`status`), `started_at` = CURRENT_TIMESTAMP, \
+                 `finished_at` = NULL, `rows` = NULL, `duration_ms` = NULL, `note` = VALUES(`note`)",
+                RUN_HISTORY_TABLE,
+                quote_literal(&self.run_id),
+                quote_literal(table_name),
+                quote_literal(status),
+                self.note.as_deref().map_or("NULL".to_string(), quote_literal)
+            )
+        } else {
+            let (rows, duration_ms) = match load {
+                Some((rows, duration)) => (rows.to_string(), duration.as_millis().to_string()),
+                None => ("NULL".to_string(), "NULL".to_string()),
+            };
+
+            format!(
+                "UPDATE `{}` SET `status` = {}, `finished_at` = CURRENT_TIMESTAMP, `rows` = {}, `duration_ms` = {} \
+                 WHERE `run_id` = {} AND `table_name` = {}",
+                RUN_HISTORY_TABLE,
+                quote_literal(status),
+                rows,
+                duration_ms,
+                quote_literal(&self.run_id),
+                quote_literal(table_name)
+            )
+        };
+
+        self.inserter
+            .clone()
+            .execute_transactional_query(&query)
+            .await
+    }
+}
+
+#[async_trait]
+impl MigrationObserver for RunHistory {
+    async fn on_event(&self, event: &MigrationEvent) {
+        let (table, status, load) = match event {
+            MigrationEvent::TableStarted { table } => (table, RUNNING, None),
+            MigrationEvent::TableFinished {
+                table,
+                rows,
+                duration,
+            } => (table, COMPLETED, Some((*rows, *duration))),
+            MigrationEvent::TableFailed { table, .. } => (table, FAILED, None),
+            // Left for `--resume`, which continues the load
+            MigrationEvent::TableSuspended { table } => (table, SUSPENDED, None),
+            _ => return,
+        };
+
+        if let Err(err) = self
+            .record(table, status, load)
+            .await
+            .with_context(|| format!("Failed to record table {} in the run history", table))
+        {
+            print_error_chain(&err);
+        }
+    }
+}
+
+// Drops what aborted and failed runs left behind, along with the records of tables that no longer exist
+pub async fn cleanup_incomplete_runs(inserter: &mut DatabaseInserter) -> Result<()> {
+    let existing_tables = inserter.get_all_tables().await?;
+    let exists = |name: &str| {
+        existing_tables
+            .iter()
+            .any(|table| table.eq_ignore_ascii_case(name))
+    };
+
+    let quarantine_tables = inserter.fetch_quarantine_tables().await?;
+
+    // Records are ordered by run id, so the latest run of each table wins
+    let latest_runs = inserter
+        .fetch_run_records()
+        .await?
+        .into_iter()
+        .map(|record| (record.table_name, (record.run_id, record.status)))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut queries = Vec::new();
+
+    for (table, (run_id, status)) in &latest_runs {
+        if status != RUNNING && status != FAILED {
+            continue;
+        }
+
+        let leftovers = [shadow_name(table), retired_name(table)]
+            .into_iter()
+            .filter(|name| exists(name))
+            .collect::<Vec<_>>();
+
+        // Without a shadow table the run loaded the live table directly
+        if leftovers.is_empty() && exists(table) {
+            warn!(
+                "Table {} was left {} by run {}, migrate it again to complete it",
+                table,
+                if status == RUNNING {
+                    "incomplete"
+                } else {
+                    "failed"
+                },
+                run_id
+            );
+        }
+
+        for leftover in leftovers {
+            info!("Dropping table {} left by run {}", leftover, run_id);
+            queries.push(format!("DROP TABLE {}", quote_identifier(&leftover)));
+        }
+
+        if quarantine_tables
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(table))
+        {
+            info!(
+                "Dropping quarantine table {}.{} left by run {}",
+                QUARANTINE_SCHEMA, table, run_id
+            );
+            queries.push(format!(
+                "DROP TABLE {}.{}",
+                quote_identifier(QUARANTINE_SCHEMA),
+                quote_identifier(table)
+            ));
+        }
+
+        queries.push(format!(
+            "UPDATE `{}` SET `status` = {} WHERE `run_id` = {} AND `table_name` = {}",
+            RUN_HISTORY_TABLE,
+            quote_literal(CLEANED),
+            quote_literal(run_id),
+            quote_literal(table)
+        ));
+    }
+
+    for table in inserter.fetch_synced_tables().await? {
+        if !exists(&table) {
+            info!("Removing the sync baseline of dropped table {}", table);
+            queries.push(format!(
+                "DELETE FROM `{}` WHERE `table_name` = {}",
+                SYNC_STATE_TABLE,
+                quote_literal(&table)
+            ));
+        }
+    }
+
+    for (run_id, table, backup) in inserter.fetch_backup_records().await? {
+        if !exists(&backup) {
+            info!(
+                "Removing the record of dropped backup {} of run {}",
+                backup, run_id
+            );
+            queries.push(format!(
+                "DELETE FROM `{}` WHERE `run_id` = {} AND `table_name` = {}",
+                BACKUP_RECORDS_TABLE,
+                quote_literal(&run_id),
+                quote_literal(&table)
+            ));
+        }
+    }
+
+    // Left by an interrupted `check`
+    if exists(PROBE_TABLE) {
+        info!("Dropping probe table {}", PROBE_TABLE);
+        queries.push(format!("DROP TABLE {}", quote_identifier(PROBE_TABLE)));
+    }
+
+    if queries.is_empty() {
+        info!("No leftovers of incomplete runs to clean up");
+        return Ok(());
+    }
+
+    inserter.execute_transactional_queries(&queries).await
+}
+
+// Prints the latest runs and compares the throughput of their tables with the run before
+pub async fn print_history(
+    inserter: &mut DatabaseInserter,
+    runs: usize,
+    slowdown_percent: f64,
+) -> Result<()> {
+    let mut records_by_run: BTreeMap<String, Vec<RunRecord>> = BTreeMap::new();
+
+    for record in inserter.fetch_run_records().await? {
+        records_by_run
+            .entry(record.run_id.clone())
+            .or_default()
+            .push(record);
+    }
+
+    if records_by_run.is_empty() {
+        info!("No migration runs recorded yet");
+        return Ok(());
+    }
+
+    // Run ids are timestamps, so the last ones are the latest
+    let latest_runs = records_by_run
+        .values()
+        .skip(records_by_run.len().saturating_sub(runs))
+        .collect::<Vec<_>>();
+
+    println!(
+        "{:<16} {:>7} {:>7} {:>15} {:>12}  NOTE",
+        "RUN", "TABLES", "FAILED", "ROWS", "DURATION"
+    );
+
+    for records in &latest_runs {
+        let failed = records
+            .iter()
+            .filter(|record| record.status != COMPLETED && record.status != SUSPENDED)
+            .count();
+        let rows = records.iter().filter_map(|record| record.rows).sum::<i64>();
+
+        // Tables are loaded side by side, the run lasts from the first start to the last finish
+        let started_at = records.iter().map(|record| record.started_at).min();
+        let finished_at = records.iter().filter_map(|record| record.finished_at).max();
+        let duration = match (started_at, finished_at) {
+            (Some(started_at), Some(finished_at)) => format!("{}s", finished_at - started_at),
+            _ => "-".to_string(),
+        };
+
+        // Every table of a run is recorded with its note
+        let note = records
+            .iter()
+            .find_map(|record| record.note.as_deref())
+            .unwrap_or_default();
+
+        println!(
+            "{:<16} {:>7} {:>7} {:>15} {:>12}  {}",
+            records[0].run_id,
+            records.len(),
+            failed,
+            rows,
+            duration,
+            note
+        );
+    }
+
+    let Some((latest, earlier)) = latest_runs.split_last() else {
+        return Ok(());
+    };
+
+    println!();
+    println!(
+        "{:<40} {:>15} {:>12} {:>12} {:>8}",
+        "TABLE", "ROWS", "ROWS/S", "BEFORE", "CHANGE"
+    );
+
+    let mut slower_tables = 0;
+
+    for record in latest.iter() {
+        let Some(throughput) = record.throughput() else {
+            continue;
+        };
+
+        // The last completed load of the table in one of the earlier runs shown
+        let previous = earlier
+            .iter()
+            .rev()
+            .filter_map(|records| {
+                records
+                    .iter()
+                    .find(|previous| previous.table_name == record.table_name)
+            })
+            .find_map(|previous| previous.throughput());
+
+        let (before, change) = match previous {
+            Some(previous) => {
+                let change = (throughput - previous) / previous.max(f64::EPSILON) * 100.0;
+                (format!("{:.0}", previous), Some(change))
+            }
+            None => ("-".to_string(), None),
+        };
+
+        let slower = change.is_some_and(|change| change <= -slowdown_percent);
+
+        if slower {
+            slower_tables += 1;
+        }
+
+        println!(
+            "{:<40} {:>15} {:>12.0} {:>12} {:>8}{}",
+            record.table_name,
+            record.rows.unwrap_or_default(),
+            throughput,
+            before,
+            change.map_or("-".to_string(), |change| format!("{:+.1}%", change)),
+            if slower { "  SLOWER" } else { "" }
+        );
+    }
+
+    if slower_tables > 0 {
+        warn!(
+            "{} tables of run {} got more than {}% slower than before",
+            slower_tables, latest[0].run_id, slowdown_percent
+        );
+    }
+
+    Ok(())
+}
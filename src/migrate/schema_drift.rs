@@ -0,0 +1,133 @@
+use crate::common::schema::ColumnSchema;
+use crate::config::ExtraColumn;
+use crate::insert::query::{format_column_definition, format_column_type};
+
+// Column of an existing MySQL table as name, `COLUMN_TYPE` and nullability
+pub type ExistingColumn = (String, String, bool);
+
+pub enum SchemaChange<'a> {
+    Missing(&'a ColumnSchema),
+    Changed {
+        column: &'a ColumnSchema,
+        existing_type: String,
+        existing_nullable: bool,
+    },
+    Extra(String),
+}
+
+impl SchemaChange<'_> {
+    pub fn describe(&self) -> String {
+        match self {
+            SchemaChange::Missing(column) => format!(
+                "column {} {} is missing",
+                column.column_name,
+                describe_type(&format_column_type(column), column.is_nullable)
+            ),
+            SchemaChange::Changed {
+                column,
+                existing_type,
+                existing_nullable,
+            } => format!(
+                "column {} is {}, expected {}",
+                column.column_name,
+                describe_type(existing_type, *existing_nullable),
+                describe_type(&format_column_type(column), column.is_nullable)
+            ),
+            SchemaChange::Extra(name) => format!("column {} does not exist in the source", name),
+        }
+    }
+
+    // `ALTER TABLE` clause bringing the column in line with the mapped schema, extra columns are kept
+    pub fn alter_clause(&self) -> Option<String> {
+        match self {
+            SchemaChange::Missing(column) => {
+                Some(format!("ADD COLUMN {}", format_column_definition(column)))
+            }
+            SchemaChange::Changed { column, .. } => Some(format!(
+                "MODIFY COLUMN {}",
+                format_column_definition(column)
+            )),
+            SchemaChange::Extra(_) => None,
+        }
+    }
+}
+
+// Differences of an existing table to the mapped schema, by column name, type and nullability
+pub fn diff_schema<'a>(
+    mapped_schema: &'a [ColumnSchema],
+    existing_columns: &[ExistingColumn],
+    extra_columns: &[ExtraColumn],
+) -> Vec<SchemaChange<'a>> {
+    let mut changes = Vec::new();
+
+    for column in mapped_schema {
+        let existing = existing_columns
+            .iter()
+            .find(|(name, _, _)| name.eq_ignore_ascii_case(&column.column_name));
+
+        match existing {
+            None => changes.push(SchemaChange::Missing(column)),
+            Some((_, existing_type, existing_nullable)) => {
+                if normalize_type(existing_type) != normalize_type(&format_column_type(column))
+                    || *existing_nullable != column.is_nullable
+                {
+                    changes.push(SchemaChange::Changed {
+                        column,
+                        existing_type: existing_type.clone(),
+                        existing_nullable: *existing_nullable,
+                    });
+                }
+            }
+        }
+    }
+
+    for (name, _, _) in existing_columns {
+        if !mapped_schema
+            .iter()
+            .any(|column| column.column_name.eq_ignore_ascii_case(name))
+            && !extra_columns
+                .iter()
+                .any(|column| column.name.eq_ignore_ascii_case(name))
+        {
+            changes.push(SchemaChange::Extra(name.clone()));
+        }
+    }
+
+    changes
+}
+
+fn describe_type(column_type: &str, nullable: bool) -> String {
+    format!(
+        "{}{}",
+        column_type.to_uppercase(),
+        if nullable { " NULL" } else { " NOT NULL" }
+    )
+}
+
+// Spells the type the way MySQL reports it in `COLUMN_TYPE`
+fn normalize_type(column_type: &str) -> String {
+    let column_type = column_type.to_lowercase().replace(' ', "");
+    let (name, parameters) = match column_type.split_once('(') {
+        Some((name, parameters)) => (name.to_string(), Some(parameters.trim_end_matches(')'))),
+        None => (column_type, None),
+    };
+
+    match (name.as_str(), parameters) {
+        // Display widths of integers are not part of the type
+        ("integer", _) => "int".to_string(),
+        ("tinyint" | "smallint" | "mediumint" | "int" | "bigint", _) => name,
+        ("real" | "double", _) => "double".to_string(),
+        ("float", Some(precision)) => match precision.parse::<u8>() {
+            Ok(precision) if precision > 24 => "double".to_string(),
+            _ => "float".to_string(),
+        },
+        ("decimal" | "numeric", None) => "decimal(10,0)".to_string(),
+        ("decimal" | "numeric", Some(parameters)) if !parameters.contains(',') => {
+            format!("decimal({},0)", parameters)
+        }
+        ("decimal" | "numeric", Some(parameters)) => format!("decimal({})", parameters),
+        ("char" | "binary", None) => format!("{}(1)", name),
+        (_, Some(parameters)) => format!("{}({})", name, parameters),
+        (_, None) => name,
+    }
+}
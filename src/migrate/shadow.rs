@@ -0,0 +1,61 @@
+use anyhow::{Context, Result};
+
+use crate::common::identifiers::quote_identifier;
+use crate::insert::inserter::DatabaseInserter;
+use crate::migrate::backup::MAX_IDENTIFIER_LENGTH;
+
+const SHADOW_SUFFIX: &str = "__new";
+const RETIRED_SUFFIX: &str = "__old";
+
+// Name of the table a shadow load writes to before it replaces the live table
+pub fn shadow_name(table_name: &str) -> String {
+    suffixed_name(table_name, SHADOW_SUFFIX)
+}
+
+// Name the live table is moved to while its shadow is swapped in
+pub fn retired_name(table_name: &str) -> String {
+    suffixed_name(table_name, RETIRED_SUFFIX)
+}
+
+// Replaces the live table with its loaded shadow in a single RENAME, then drops the old table
+pub async fn swap_shadow_table(inserter: &mut DatabaseInserter, table_name: &str) -> Result<()> {
+    let shadow_name = shadow_name(table_name);
+    let retired_name = retired_name(table_name);
+
+    let queries = if inserter.table_exists(table_name).await? {
+        vec![
+            format!("DROP TABLE IF EXISTS {}", quote_identifier(&retired_name)),
+            format!(
+                "RENAME TABLE {0} TO {1}, {2} TO {0}",
+                quote_identifier(table_name),
+                quote_identifier(&retired_name),
+                quote_identifier(&shadow_name)
+            ),
+            format!("DROP TABLE {}", quote_identifier(&retired_name)),
+        ]
+    } else {
+        vec![format!(
+            "RENAME TABLE {} TO {}",
+            quote_identifier(&shadow_name),
+            quote_identifier(table_name)
+        )]
+    };
+
+    inserter
+        .execute_transactional_queries(&queries)
+        .await
+        .with_context(|| format!("Failed to swap {} into {}", shadow_name, table_name))?;
+
+    info!("Table {} swapped in from {}", table_name, shadow_name);
+
+    Ok(())
+}
+
+fn suffixed_name(table_name: &str, suffix: &str) -> String {
+    let table_part = table_name
+        .chars()
+        .take(MAX_IDENTIFIER_LENGTH - suffix.len())
+        .collect::<String>();
+
+    format!("{}{}", table_part, suffix)
+}
@@ -0,0 +1,209 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Error, Result};
+
+use crate::common::schema::ColumnSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GuidFormat {
+    // Hyphenated text, `CHAR(36)`
+    Char,
+    // The 16 bytes in text order, `BINARY(16)` like `UUID_TO_BIN`
+    Binary,
+}
+
+impl FromStr for GuidFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "char" => Ok(GuidFormat::Char),
+            "binary" => Ok(GuidFormat::Binary),
+            _ => bail!("Unknown GUID format: {}", s),
+        }
+    }
+}
+
+// Target type of `uniqueidentifier` columns in strict mode, as data type and length
+pub fn guid_column_type(format: GuidFormat) -> (&'static str, i32) {
+    match format {
+        GuidFormat::Char => ("char", 36),
+        GuidFormat::Binary => ("binary", 16),
+    }
+}
+
+enum ValueCheck {
+    Integer { min: i128, max: i128 },
+    Decimal { precision: usize, scale: usize },
+    Characters(usize),
+    Bytes(usize),
+    Guid(GuidFormat),
+}
+
+// Checks every value against its MySQL column type before it is sent, instead of relying on
+// implicit casts, and writes decimals as exact numeric literals
+pub struct StrictTypes {
+    columns: Vec<(usize, String, ValueCheck)>,
+}
+
+impl StrictTypes {
+    pub fn new(
+        source_schema: &[ColumnSchema],
+        mapped_schema: &[ColumnSchema],
+        guid_format: GuidFormat,
+    ) -> Self {
+        let columns = source_schema
+            .iter()
+            .zip(mapped_schema)
+            .enumerate()
+            .filter_map(|(index, (source, target))| {
+                value_check(source, target, guid_format)
+                    .map(|check| (index, target.column_name.clone(), check))
+            })
+            .collect();
+
+        StrictTypes { columns }
+    }
+
+    pub fn apply(&self, row: &mut [String]) -> Result<()> {
+        for (index, name, check) in &self.columns {
+            let value = &mut row[*index];
+
+            if value == "NULL" {
+                continue;
+            }
+
+            if let Err(err) = apply_check(check, value) {
+                let preview = value.chars().take(64).collect::<String>();
+                bail!("Value {} of column {} {}", preview, name, err);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn value_check(
+    source: &ColumnSchema,
+    target: &ColumnSchema,
+    guid_format: GuidFormat,
+) -> Option<ValueCheck> {
+    match source.data_type.as_str() {
+        "uniqueidentifier" => return Some(ValueCheck::Guid(guid_format)),
+        // Formatted as `true` and `false`
+        "bit" => return None,
+        _ => {}
+    }
+
+    let length = target
+        .character_maximum_length
+        .filter(|length| *length > 0)
+        .map(|length| length as usize);
+
+    let check = match target.data_type.to_lowercase().as_str() {
+        "tinyint" => ValueCheck::Integer {
+            min: i8::MIN as i128,
+            max: i8::MAX as i128,
+        },
+        "smallint" => ValueCheck::Integer {
+            min: i16::MIN as i128,
+            max: i16::MAX as i128,
+        },
+        "mediumint" => ValueCheck::Integer {
+            min: -(1 << 23),
+            max: (1 << 23) - 1,
+        },
+        "int" | "integer" => ValueCheck::Integer {
+            min: i32::MIN as i128,
+            max: i32::MAX as i128,
+        },
+        "bigint" => ValueCheck::Integer {
+            min: i64::MIN as i128,
+            max: i64::MAX as i128,
+        },
+        "decimal" | "numeric" => ValueCheck::Decimal {
+            precision: target.numeric_precision.unwrap_or(10) as usize,
+            scale: target.numeric_scale.unwrap_or(0) as usize,
+        },
+        "char" | "varchar" => ValueCheck::Characters(length?),
+        "binary" | "varbinary" => ValueCheck::Bytes(length?),
+        "tinytext" | "tinyblob" => ValueCheck::Bytes(255),
+        "text" | "blob" => ValueCheck::Bytes(65_535),
+        "mediumtext" | "mediumblob" => ValueCheck::Bytes(16_777_215),
+        _ => return None,
+    };
+
+    Some(check)
+}
+
+fn apply_check(check: &ValueCheck, value: &mut String) -> Result<()> {
+    match check {
+        ValueCheck::Integer { min, max } => {
+            let Ok(number) = value.parse::<i128>() else {
+                bail!("is not an integer");
+            };
+
+            if number < *min || number > *max {
+                bail!("is outside the range {} to {}", min, max);
+            }
+        }
+        ValueCheck::Decimal { precision, scale } => {
+            let number = unquote(value);
+            let digits = number.trim_start_matches('-');
+            let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+            if integer.is_empty() || !integer.chars().all(|c| c.is_ascii_digit()) {
+                bail!("is not a decimal number");
+            }
+
+            if fraction.trim_end_matches('0').len() > *scale {
+                bail!("has more than {} decimal places", scale);
+            }
+
+            if integer.trim_start_matches('0').len() > precision.saturating_sub(*scale) {
+                bail!("does not fit DECIMAL({}, {})", precision, scale);
+            }
+
+            *value = number;
+        }
+        ValueCheck::Characters(length) => {
+            if unquote(value).chars().count() > *length {
+                bail!("is longer than {} characters", length);
+            }
+        }
+        ValueCheck::Bytes(length) => {
+            let bytes = match value.strip_prefix("0x") {
+                Some(hex) => hex.len() / 2,
+                None => unquote(value).len(),
+            };
+
+            if bytes > *length {
+                bail!("is longer than {} bytes", length);
+            }
+        }
+        ValueCheck::Guid(format) => {
+            let hex = unquote(value).replace('-', "");
+
+            if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                bail!("is not a GUID");
+            }
+
+            if *format == GuidFormat::Binary {
+                *value = format!("0x{}", hex);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Content of a formatted string literal
+fn unquote(value: &str) -> String {
+    match value
+        .strip_prefix('\'')
+        .and_then(|value| value.strip_suffix('\''))
+    {
+        Some(value) => value.replace("''", "'"),
+        None => value.to_string(),
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+
+use crate::common::constraints::Constraint;
+use crate::common::identifiers::{quote_identifier, quote_literal};
+use crate::common::source_table::SourceTable;
+use crate::extract::extractor::DatabaseExtractor;
+use crate::extract::format::format_string_value;
+use crate::insert::inserter::DatabaseInserter;
+use crate::inspect::unquote;
+use crate::mappings::Mappings;
+use crate::migrate::checksum::{normalize_column, source_row_text, target_row_text};
+use crate::migrate::migration_options::MigrationOptions;
+use crate::migrate::table_schema_mapper::TableSchemaMapper;
+use crate::migrate::temporal::resolve_temporal_table;
+
+// Column rendered as text the same way on both sides, like for checksums
+struct DiffColumn {
+    name: String,
+    source: String,
+    target: String,
+    key: bool,
+}
+
+type RowHashes = HashMap<Vec<String>, String>;
+
+// Matches the rows of a table and its migrated copy by primary key and prints up to `limit` of each kind of difference.
+// Only the keys and a hash of every row are held in memory, the values are read for the differing rows printed.
+pub async fn diff_table(
+    extractor: &mut DatabaseExtractor,
+    inserter: &mut DatabaseInserter,
+    mappings: &Mappings,
+    options: &MigrationOptions,
+    table_name: &str,
+    limit: usize,
+) -> Result<()> {
+    let mut table = SourceTable::parse(table_name);
+    resolve_temporal_table(extractor, &mut table, options.temporal_tables).await?;
+    let schema = extractor.get_table_schema(&table).await?;
+
+    if schema.is_empty() {
+        bail!("Table {} not found", table);
+    }
+
+    if options.id_offset.is_some() || options.regenerate_keys {
+        bail!("Rows can't be matched by key, the configured id_offset or regenerate_keys changes the keys");
+    }
+
+    let target_table_name = options.target_table_name(&table);
+
+    if !inserter.table_exists(&target_table_name).await? {
+        bail!("Target table {} does not exist", target_table_name);
+    }
+
+    let mapped_schema = TableSchemaMapper::map_schema(mappings, &table, &schema, options);
+
+    let columns = schema
+        .iter()
+        .zip(&mapped_schema)
+        .filter_map(|(source, target)| {
+            normalize_column(source, &target.column_name).map(|(source_text, target_text)| {
+                DiffColumn {
+                    name: target.column_name.clone(),
+                    source: source_text,
+                    target: target_text,
+                    key: source.constraints == Some(Constraint::PrimaryKey),
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let key_columns = columns
+        .iter()
+        .filter(|column| column.key)
+        .collect::<Vec<_>>();
+
+    if key_columns.is_empty() {
+        bail!("Table {} has no primary key to match its rows by", table);
+    }
+
+    let source_hashes = fetch_source_hashes(extractor, &table, &columns, &key_columns)
+        .await
+        .with_context(|| "Failed to read the source rows")?;
+    let target_hashes = fetch_target_hashes(inserter, &target_table_name, &columns, &key_columns)
+        .await
+        .with_context(|| "Failed to read the target rows")?;
+
+    let missing = sorted_keys(&source_hashes, |key, _| !target_hashes.contains_key(key));
+    let extra = sorted_keys(&target_hashes, |key, _| !source_hashes.contains_key(key));
+    let changed = sorted_keys(&source_hashes, |key, hash| {
+        target_hashes
+            .get(key)
+            .is_some_and(|target_hash| target_hash != hash)
+    });
+
+    for key in missing.iter().take(limit) {
+        println!("missing  {}", format_key(&key_columns, key));
+    }
+
+    for key in extra.iter().take(limit) {
+        println!("extra    {}", format_key(&key_columns, key));
+    }
+
+    for key in changed.iter().take(limit) {
+        println!("changed  {}", format_key(&key_columns, key));
+
+        let condition = key_columns
+            .iter()
+            .zip(key)
+            // SQL Server doesn't escape backslashes, the key is quoted like the rows it was read from
+            .map(|(column, value)| {
+                format!("{} = N{}", column.source, format_string_value(Some(value)))
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let source_values = extractor
+            .fetch_formatted_rows(format!(
+                "SELECT {} FROM {} WHERE {}",
+                columns
+                    .iter()
+                    .map(|column| column.source.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                table.row_source(),
+                condition
+            ))
+            .await?
+            .into_iter()
+            .next()
+            .unwrap_or_default()
+            .iter()
+            .map(|value| (value != "NULL").then(|| unquote(value)))
+            .collect::<Vec<_>>();
+
+        let condition = key_columns
+            .iter()
+            .zip(key)
+            .map(|(column, value)| format!("{} = {}", column.target, quote_literal(value)))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let target_values = inserter
+            .fetch_text_rows(&format!(
+                "SELECT {} FROM {} WHERE {}",
+                columns
+                    .iter()
+                    .map(|column| column.target.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                quote_identifier(&target_table_name),
+                condition
+            ))
+            .await?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+
+        // Rows changed since they were hashed are left out
+        for ((column, source_value), target_value) in
+            columns.iter().zip(&source_values).zip(&target_values)
+        {
+            if source_value != target_value {
+                println!(
+                    "    {}: {} -> {}",
+                    column.name,
+                    format_value(source_value),
+                    format_value(target_value)
+                );
+            }
+        }
+    }
+
+    if missing.is_empty() && extra.is_empty() && changed.is_empty() {
+        info!(
+            "Table {} matches its source, {} rows compared",
+            target_table_name,
+            source_hashes.len()
+        );
+        return Ok(());
+    }
+
+    bail!(
+        "Table {} differs from its source: {} missing, {} extra and {} changed rows",
+        target_table_name,
+        missing.len(),
+        extra.len(),
+        changed.len()
+    )
+}
+
+async fn fetch_source_hashes(
+    extractor: &mut DatabaseExtractor,
+    table: &SourceTable,
+    columns: &[DiffColumn],
+    key_columns: &[&DiffColumn],
+) -> Result<RowHashes> {
+    let query = format!(
+        "SELECT {}, LOWER(CONVERT(NVARCHAR(64), HASHBYTES('SHA2_256', {}), 2)) FROM {}",
+        key_columns
+            .iter()
+            .map(|column| column.source.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        source_row_text(
+            &columns
+                .iter()
+                .map(|column| column.source.clone())
+                .collect::<Vec<_>>()
+        ),
+        table.row_source()
+    );
+
+    let hashes = extractor
+        .fetch_formatted_rows(query)
+        .await?
+        .into_iter()
+        .map(|mut row| {
+            let hash = unquote(&row.pop().unwrap_or_default());
+            let key = row.iter().map(|value| unquote(value)).collect();
+
+            (key, hash)
+        })
+        .collect();
+
+    Ok(hashes)
+}
+
+async fn fetch_target_hashes(
+    inserter: &mut DatabaseInserter,
+    target_table_name: &str,
+    columns: &[DiffColumn],
+    key_columns: &[&DiffColumn],
+) -> Result<RowHashes> {
+    let query = format!(
+        "SELECT {}, SHA2(CONVERT({} USING utf16le), 256) FROM {}",
+        key_columns
+            .iter()
+            .map(|column| column.target.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        target_row_text(
+            &columns
+                .iter()
+                .map(|column| column.target.clone())
+                .collect::<Vec<_>>()
+        ),
+        quote_identifier(target_table_name)
+    );
+
+    let hashes = inserter
+        .fetch_text_rows(&query)
+        .await?
+        .into_iter()
+        .map(|mut row| {
+            let hash = row.pop().flatten().unwrap_or_default();
+            let key = row.into_iter().map(Option::unwrap_or_default).collect();
+
+            (key, hash)
+        })
+        .collect();
+
+    Ok(hashes)
+}
+
+fn sorted_keys(
+    hashes: &RowHashes,
+    filter: impl Fn(&Vec<String>, &String) -> bool,
+) -> Vec<Vec<String>> {
+    let mut keys = hashes
+        .iter()
+        .filter(|(key, hash)| filter(key, hash))
+        .map(|(key, _)| key.clone())
+        .collect::<Vec<_>>();
+    keys.sort();
+
+    keys
+}
+
+// `id=1, line=2`
+fn format_key(key_columns: &[&DiffColumn], key: &[String]) -> String {
+    key_columns
+        .iter()
+        .zip(key)
+        .map(|(column, value)| format!("{}={}", column.name, value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn format_value(value: &Option<String>) -> String {
+    match value {
+        Some(value) => format!("'{}'", value),
+        None => "NULL".to_string(),
+    }
+}
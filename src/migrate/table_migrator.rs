@@ -1,27 +1,43 @@
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use anyhow::{anyhow, Context, Error, Result};
-use futures::TryStreamExt;
+use anyhow::{anyhow, Context, Result};
+use futures::future::join_all;
 use log::info;
-use tokio::time::Instant;
+use tokio::spawn;
 
-use crate::common::helpers::format_snake_case;
+use crate::common::constraints::{translate_check_clause, Constraint};
+use crate::common::identifiers::quote_identifier;
 use crate::common::schema::ColumnSchema;
-use crate::extract::extractor::{open_row_stream, DatabaseExtractor};
+use crate::common::source_table::SourceTable;
+use crate::extract::chunk::ChunkPlan;
+use crate::extract::extractor::{open_source_stream, DatabaseExtractor};
+use crate::extract::row_cache::RowCache;
 use crate::insert::inserter::DatabaseInserter;
-use crate::insert::query::build_insert_statement;
 use crate::mappings::Mappings;
-use crate::migrate::migration_options::MigrationOptions;
+use crate::migrate::batch_sink::{write_batches, InsertPlan, InserterSink};
+use crate::migrate::checkpoint::TimeBox;
+use crate::migrate::concurrency_limiter::{ConcurrencyLimiter, LimiterPermit};
+use crate::migrate::dead_letters::DeadLetters;
+use crate::migrate::insert_pipeline::{write_pipelined_batches, InsertTarget};
+use crate::migrate::migration_options::{CheckFallback, MigrationOptions, SchedulerMode};
 use crate::migrate::migration_result::MigrationResult;
-use crate::migrate::table_schema_mapper::TableSchemaMapper;
-
-const RESERVED_BYTES: usize = 10;
+use crate::migrate::observer::Observers;
+use crate::migrate::partitioning::plan_partitioning;
+use crate::migrate::provenance::table_comment;
+use crate::migrate::quarantine::Quarantine;
+use crate::migrate::schema_drift::diff_schema;
+use crate::migrate::shadow::{shadow_name, swap_shadow_table};
+use crate::migrate::table_schema_mapper::{loaded_columns, TableSchemaMapper};
+use crate::sync::state::SyncState;
 
 pub struct TableMigrator {
     extractor: DatabaseExtractor,
     inserter: DatabaseInserter,
     mappings: Mappings,
     options: MigrationOptions,
+    limiter: Arc<ConcurrencyLimiter>,
 }
 
 impl TableMigrator {
@@ -30,47 +46,83 @@ impl TableMigrator {
         inserter: DatabaseInserter,
         mappings: Mappings,
         options: MigrationOptions,
+        limiter: Arc<ConcurrencyLimiter>,
     ) -> Self {
         TableMigrator {
             extractor,
             inserter,
             mappings,
             options,
+            limiter,
         }
     }
 
-    pub async fn migrate_table(&mut self, table_name: &str) -> Result<MigrationResult> {
-        let output_table_name = if self.options.format_snake_case {
-            format_snake_case(table_name)
-        } else {
-            table_name.to_string()
-        };
+    pub async fn migrate_table(&mut self, table: &SourceTable) -> Result<MigrationResult> {
+        let output_table_name = self.options.target_table_name(table);
 
-        info!("Migrating table: {}", &output_table_name);
+        if log_enabled!(log::Level::Debug) {
+            self.log_effective_settings(table, &output_table_name);
+        }
 
-        let start_time = Instant::now();
+        // A table suspended while loading its chunks keeps its rows, and chunks that were cut off are
+        // upserted when they're loaded again
+        if self
+            .options
+            .time_box
+            .as_ref()
+            .is_some_and(|time_box| time_box.table_chunks(&output_table_name).is_some())
+            && !self.options.append
+        {
+            self.options.upsert = true;
+        }
 
-        // Fetch and map table schema
-        let table_schema = self
-            .extractor
-            .get_table_schema(table_name)
-            .await
-            .with_context(|| "Failed to get table schema".to_string())?;
+        let setup_permit = self.acquire_fair_permit().await?;
 
-        let mapped_schema = TableSchemaMapper::map_schema(
-            &self.mappings,
-            &table_schema,
-            self.options.format_snake_case,
-        );
+        let (table_schema, mapped_schema) = self.map_table_schema(table).await?;
+
+        let has_primary_key = mapped_schema
+            .iter()
+            .any(|column| column.constraints == Some(Constraint::PrimaryKey));
+
+        if self.options.upsert && !has_primary_key {
+            warn!(
+                "Table {} has no primary key, upserted rows are appended instead of updated",
+                &output_table_name
+            );
+        }
+
+        if self.options.append && !has_primary_key {
+            warn!(
+                "Table {} has no primary key, all rows are appended without detecting duplicates",
+                &output_table_name
+            );
+        }
+
+        // Shadow loads go to a fresh table that only replaces the live one once it is complete
+        let load_table_name = if self.options.shadow {
+            let shadow_table_name = shadow_name(&output_table_name);
+
+            self.inserter
+                .execute_transactional_query(&format!(
+                    "DROP TABLE IF EXISTS {}",
+                    quote_identifier(&shadow_table_name)
+                ))
+                .await
+                .with_context(|| "Failed to drop leftover shadow table".to_string())?;
+
+            shadow_table_name
+        } else {
+            output_table_name.clone()
+        };
 
         let table_exists = self
             .inserter
-            .table_exists(&output_table_name)
+            .table_exists(&load_table_name)
             .await
             .with_context(|| "Failed to check table existence".to_string())?;
 
-        if table_exists {
-            let count = self.inserter.table_rows_count(&output_table_name).await?;
+        if table_exists && !self.options.upsert && !self.options.append {
+            let count = self.inserter.table_rows_count(&load_table_name).await?;
 
             if count > 0 {
                 return Err(anyhow!(
@@ -80,123 +132,743 @@ impl TableMigrator {
             }
         }
 
-        if !table_exists {
-            // Create table in the output database
-            self.inserter
-                .create_table(&output_table_name, &mapped_schema)
-                .await
-                .with_context(|| "Failed to create table".to_string())?;
-        }
+        self.ensure_table(
+            table,
+            &load_table_name,
+            table_exists,
+            &table_schema,
+            &mapped_schema,
+        )
+        .await?;
+
+        let sync_state = SyncState::capture(&mut self.extractor, table, &table_schema)
+            .await
+            .with_context(|| "Failed to capture sync baseline".to_string())?;
+
+        // Rows the target already has, the rows that were sent but not added are the ignored duplicates
+        let existing_rows = if self.options.append && table_exists {
+            self.inserter.table_rows_count(&load_table_name).await? as usize
+        } else {
+            0
+        };
+
+        drop(setup_permit);
+
+        let insert_plan = Arc::new(InsertPlan::new(
+            table,
+            &load_table_name,
+            &table_schema,
+            &mapped_schema,
+            &self.options,
+        ));
+
+        // Failed rows are kept with the columns they were inserted with
+        let (_, loaded_schema) = loaded_columns(&table_schema, &mapped_schema);
+
+        let dead_letters = self
+            .options
+            .dead_letter_file
+            .as_ref()
+            .map(|path| Arc::new(DeadLetters::new(path, &output_table_name, &loaded_schema)));
 
         // Migrate rows from input table to output table
         let migrated_count = self
-            .migrate_table_rows(table_name, &output_table_name, &mapped_schema)
+            .migrate_table_rows(
+                table,
+                &table_schema,
+                &output_table_name,
+                &loaded_schema,
+                &insert_plan,
+                dead_letters.as_ref(),
+            )
             .await
             .with_context(|| "Failed to migrate rows".to_string())?;
 
-        let end_time = Instant::now();
-        info!(
-            "Table {} migrated, rows: {}, took: {}s",
+        insert_plan.log_row_changes(&output_table_name);
+
+        let dead_letter_rows = dead_letters
+            .as_ref()
+            .map(|dead_letters| dead_letters.rows())
+            .unwrap_or_default();
+
+        if dead_letter_rows > 0 {
+            warn!(
+                "{} rows of {} failed and were written to the dead-letter file",
+                dead_letter_rows, output_table_name
+            );
+        }
+
+        let ignored_rows = if self.options.append {
+            self.count_ignored_rows(
+                &output_table_name,
+                existing_rows,
+                migrated_count.saturating_sub(dead_letter_rows),
+            )
+            .await?
+        } else {
+            0
+        };
+
+        if self.options.shadow {
+            self.verify_shadow_table(
+                table,
+                &output_table_name,
+                &load_table_name,
+                insert_plan.skipped_rows() + dead_letter_rows,
+            )
+            .await?;
+
+            swap_shadow_table(&mut self.inserter, &output_table_name).await?;
+        }
+
+        Ok(MigrationResult {
+            table_name: output_table_name,
+            schema: mapped_schema,
+            source_table: table.clone(),
+            source_schema: table_schema,
+            created: !table_exists,
+            rows: migrated_count - ignored_rows,
+            ignored_rows,
+            sync_state,
+        })
+    }
+
+    // Creates the table or checks the existing one, without loading any rows
+    pub async fn create_table_schema(&mut self, table: &SourceTable) -> Result<MigrationResult> {
+        let output_table_name = self.options.target_table_name(table);
+        let (table_schema, mapped_schema) = self.map_table_schema(table).await?;
+
+        let table_exists = self
+            .inserter
+            .table_exists(&output_table_name)
+            .await
+            .with_context(|| "Failed to check table existence".to_string())?;
+
+        self.ensure_table(
+            table,
             &output_table_name,
-            migrated_count,
-            end_time.saturating_duration_since(start_time).as_secs_f32()
-        );
+            table_exists,
+            &table_schema,
+            &mapped_schema,
+        )
+        .await?;
 
         Ok(MigrationResult {
             table_name: output_table_name,
             schema: mapped_schema,
+            source_table: table.clone(),
+            source_schema: table_schema,
             created: !table_exists,
+            rows: 0,
+            ignored_rows: 0,
+            sync_state: None,
         })
     }
 
+    // Result of a table migrated by an earlier run, for the phases following the data load
+    pub async fn existing_table(&mut self, table: &SourceTable) -> Result<MigrationResult> {
+        let output_table_name = self.options.target_table_name(table);
+
+        if !self.inserter.table_exists(&output_table_name).await? {
+            return Err(anyhow!(
+                "Target table {} doesn't exist, run the schema and data phases first",
+                output_table_name
+            ));
+        }
+
+        let (table_schema, mapped_schema) = self.map_table_schema(table).await?;
+
+        Ok(MigrationResult {
+            table_name: output_table_name,
+            schema: mapped_schema,
+            source_table: table.clone(),
+            source_schema: table_schema,
+            // Constraints are only created on tables the migrator owns
+            created: true,
+            rows: 0,
+            ignored_rows: 0,
+            sync_state: None,
+        })
+    }
+
+    async fn map_table_schema(
+        &mut self,
+        table: &SourceTable,
+    ) -> Result<(Vec<ColumnSchema>, Vec<ColumnSchema>)> {
+        let table_schema = self
+            .extractor
+            .get_table_schema(table)
+            .await
+            .with_context(|| "Failed to get table schema".to_string())?;
+
+        if self.options.constraints && self.options.check_fallback == CheckFallback::Fail {
+            for column in &table_schema {
+                if let Some(Constraint::Check(check_clause)) = &column.constraints {
+                    translate_check_clause(check_clause, str::to_string).with_context(|| {
+                        format!(
+                            "CHECK constraint {} of column {} has no MySQL translation",
+                            check_clause, column.column_name
+                        )
+                    })?;
+                }
+            }
+        }
+
+        let mapped_schema =
+            TableSchemaMapper::map_schema(&self.mappings, table, &table_schema, &self.options);
+
+        Ok((table_schema, mapped_schema))
+    }
+
+    // Creates a missing table, an existing one is checked against the mapped schema
+    async fn ensure_table(
+        &mut self,
+        table: &SourceTable,
+        table_name: &str,
+        table_exists: bool,
+        table_schema: &[ColumnSchema],
+        mapped_schema: &[ColumnSchema],
+    ) -> Result<()> {
+        if table_exists {
+            return self.check_schema_drift(table_name, mapped_schema).await;
+        }
+
+        let partitioning = plan_partitioning(
+            &mut self.extractor,
+            table,
+            table_schema,
+            mapped_schema,
+            &self.options,
+        )
+        .await?;
+        let comment = table_comment(&mut self.extractor, table, &self.options).await?;
+
+        // Create table in the output database
+        self.inserter
+            .create_table(
+                table_name,
+                mapped_schema,
+                &self.options.extra_columns,
+                &self.options.collation,
+                comment.as_deref(),
+                partitioning.as_deref(),
+            )
+            .await
+            .with_context(|| "Failed to create table".to_string())
+    }
+
     async fn migrate_table_rows(
         &mut self,
-        input_table: &str,
+        input_table: &SourceTable,
+        table_schema: &[ColumnSchema],
         output_table: &str,
         mapped_schema: &[ColumnSchema],
+        insert_plan: &Arc<InsertPlan>,
+        dead_letters: Option<&Arc<DeadLetters>>,
     ) -> Result<usize> {
-        info!("Migrating {} rows", output_table);
+        let plan = match self.options.chunk_size {
+            Some(chunk_size) => {
+                self.extractor
+                    .plan_chunks(input_table, table_schema, chunk_size)
+                    .await?
+            }
+            None => None,
+        };
+
+        let cache = RowCache::from_options(
+            &self.options,
+            &mut self.extractor,
+            input_table,
+            table_schema,
+        )
+        .await?;
+
+        let quarantine = self
+            .options
+            .quarantine
+            .then(|| Arc::new(Quarantine::new(input_table, output_table, mapped_schema)));
+
+        let Some(plan) = plan else {
+            info!("Migrating {} rows", output_table);
+
+            let _permit = self.acquire_fair_permit().await?;
+
+            let mut conn = self.extractor.connection().await?;
+            let stream = open_source_stream(
+                &mut conn,
+                input_table,
+                &insert_plan.select_list,
+                None,
+                0,
+                cache.as_ref(),
+            )
+            .await?;
+
+            if self.options.insert_workers > 1 {
+                let target = InsertTarget {
+                    inserter: self.inserter.clone(),
+                    limiter: Arc::clone(&self.limiter),
+                    quarantine,
+                    dead_letters: dead_letters.cloned(),
+                    table_name: output_table.to_string(),
+                    observers: self.options.observers.clone(),
+                    time_box: self.options.time_box.clone(),
+                };
+
+                return write_pipelined_batches(
+                    target,
+                    stream,
+                    insert_plan,
+                    self.options.insert_workers,
+                )
+                .await;
+            }
+
+            let mut sink = InserterSink {
+                inserter: &mut self.inserter,
+                limiter: &self.limiter,
+                quarantine: quarantine.as_deref(),
+                dead_letters: dead_letters.map(Arc::as_ref),
+                table_name: output_table,
+                observers: &self.options.observers,
+                time_box: self.options.time_box.as_deref(),
+            };
+
+            return write_batches(&mut sink, stream, insert_plan).await;
+        };
 
-        let insert_statement = build_insert_statement(output_table, mapped_schema);
+        let chunk_count = plan.chunk_count();
+        let workers = self.options.table_workers.clamp(1, chunk_count.max(1));
 
-        let mut conn = self.extractor.pool.get().await?;
-        let mut stream = open_row_stream(&mut conn, input_table).await?;
+        let completed_chunks = self.resumed_chunks(output_table, &plan);
+
+        info!(
+            "Migrating {} rows in {} chunks with {} workers",
+            output_table, chunk_count, workers
+        );
+
+        if !completed_chunks.is_empty() {
+            info!(
+                "Resuming table {}, {} of {} chunks were loaded by the suspended run",
+                output_table,
+                completed_chunks.len(),
+                chunk_count
+            );
+        }
+
+        let completed_chunks = Arc::new(completed_chunks);
+
+        let plan = Arc::new(plan);
+        let next_chunk = Arc::new(AtomicUsize::new(0));
+
+        let tasks = (0..workers)
+            .map(|worker_index| {
+                let worker = ChunkWorker {
+                    extractor: self.extractor.clone(),
+                    inserter: self.inserter.clone(),
+                    limiter: Arc::clone(&self.limiter),
+                    table: input_table.clone(),
+                    output_table: output_table.to_string(),
+                    plan: Arc::clone(&plan),
+                    cache: cache.clone(),
+                    quarantine: quarantine.clone(),
+                    dead_letters: dead_letters.cloned(),
+                    observers: self.options.observers.clone(),
+                    time_box: self.options.time_box.clone(),
+                    completed_chunks: Arc::clone(&completed_chunks),
+                    next_chunk: Arc::clone(&next_chunk),
+                    insert_plan: Arc::clone(insert_plan),
+                    insert_workers: self.options.insert_workers,
+                    // The first worker runs on the table's own slot unless slots are taken per chunk
+                    uses_table_permit: worker_index == 0
+                        && self.options.scheduler == SchedulerMode::Table,
+                };
+
+                spawn(worker.run())
+            })
+            .collect::<Vec<_>>();
 
-        let mut insert_query = String::with_capacity(self.options.max_packet_bytes);
-        let mut total_bytes = insert_statement.len();
-        let mut transaction_count = 0;
         let mut total_transaction_count = 0;
 
-        while let Some(row_values) = stream.try_next().await? {
-            let values = row_values.join(", ");
-            let value_set = format!("({}) ", values);
-            let value_set_bytes = value_set.len();
+        for result in join_all(tasks).await {
+            total_transaction_count += result.context("Chunk worker panicked")??;
+        }
+
+        Ok(total_transaction_count)
+    }
 
-            if RESERVED_BYTES + total_bytes + value_set_bytes > self.options.max_packet_bytes {
-                execute_batch(&mut self.inserter, &insert_query, transaction_count).await?;
+    // Rows can't be loaded reliably into an existing table whose columns differ from the mapped schema
+    async fn check_schema_drift(
+        &mut self,
+        table_name: &str,
+        mapped_schema: &[ColumnSchema],
+    ) -> Result<()> {
+        let existing_columns = self.inserter.get_column_definitions(table_name).await?;
+        let changes = diff_schema(
+            mapped_schema,
+            &existing_columns,
+            &self.options.extra_columns,
+        );
 
-                total_transaction_count += transaction_count;
-                insert_query.clear();
-                total_bytes = insert_statement.len();
-                transaction_count = 0;
-            }
+        if changes.is_empty() {
+            return Ok(());
+        }
 
-            if !insert_query.is_empty() {
-                insert_query.push(',');
-                total_bytes += 1;
-            }
+        let diff = changes
+            .iter()
+            .map(|change| format!("\n  - {}", change.describe()))
+            .collect::<String>();
+
+        if !self.options.sync_schema {
+            return Err(anyhow!(
+                "Existing table {} differs from the mapped schema, rerun with --sync-schema to alter it:{}",
+                table_name,
+                diff
+            ));
+        }
 
-            if transaction_count == 0 {
-                insert_query.push_str(&insert_statement);
-            }
+        let clauses = changes
+            .iter()
+            .filter_map(|change| change.alter_clause())
+            .collect::<Vec<_>>();
 
-            insert_query.push_str(&value_set);
-            total_bytes += value_set_bytes;
-            transaction_count += 1;
+        if !clauses.is_empty() {
+            self.inserter
+                .execute_transactional_query(&format!(
+                    "ALTER TABLE {} {}",
+                    quote_identifier(table_name),
+                    clauses.join(", ")
+                ))
+                .await
+                .with_context(|| format!("Failed to alter table {}", table_name))?;
         }
 
-        if transaction_count > 0 {
-            // If there are remaining rows in the insert_query, execute them
-            execute_batch(&mut self.inserter, &insert_query, transaction_count).await?;
-            total_transaction_count += transaction_count;
+        info!("Altered table {} to the mapped schema:{}", table_name, diff);
+
+        Ok(())
+    }
+
+    // Keeps the live table untouched unless the shadow holds every source row not quarantined or skipped
+    async fn verify_shadow_table(
+        &mut self,
+        table: &SourceTable,
+        output_table: &str,
+        shadow_table: &str,
+        skipped_rows: usize,
+    ) -> Result<()> {
+        let source_rows = self.extractor.table_rows_count(table).await?;
+        // Quarantine tables are reset when a run starts, rows left by earlier runs don't count
+        let quarantined_rows = if self.options.quarantine {
+            self.inserter
+                .count_quarantined_rows(output_table, &table.to_string())
+                .await?
+        } else {
+            0
+        };
+        let shadow_rows = self.inserter.table_rows_count(shadow_table).await?;
+
+        if shadow_rows + (quarantined_rows + skipped_rows) as i64 != source_rows {
+            return Err(anyhow!(
+                "Shadow table {} has {} rows but the source has {}, live table left unchanged",
+                shadow_table,
+                shadow_rows,
+                source_rows
+            ));
         }
 
-        Ok(total_transaction_count)
+        Ok(())
     }
-}
 
-async fn execute_batch(
-    inserter: &mut DatabaseInserter,
-    insert_query: &String,
-    transaction_count: usize,
-) -> Result<(), Error> {
-    if !insert_query.is_empty() {
-        let cloned_insert_query = Arc::new(insert_query.clone());
+    // Rows written with --append that were left out as duplicates of rows already in the target
+    async fn count_ignored_rows(
+        &mut self,
+        table_name: &str,
+        existing_rows: usize,
+        written_rows: usize,
+    ) -> Result<usize> {
+        let quarantined_rows = if self.options.quarantine {
+            self.inserter
+                .fetch_quarantined_ids(table_name, false)
+                .await?
+                .len()
+        } else {
+            0
+        };
+        let total_rows = self.inserter.table_rows_count(table_name).await? as usize;
 
-        let start_time = Instant::now();
+        let ignored_rows = written_rows
+            .saturating_sub(quarantined_rows)
+            .saturating_sub(total_rows.saturating_sub(existing_rows));
 
-        let query_str = cloned_insert_query.as_str();
+        if ignored_rows > 0 {
+            info!(
+                "{} of {} rows of table {} were already present and ignored",
+                ignored_rows, written_rows, table_name
+            );
+        }
+
+        Ok(ignored_rows)
+    }
+
+    // Global settings, per-table overrides and flags as they apply to this table
+    fn log_effective_settings(&self, table: &SourceTable, output_table_name: &str) {
+        let options = &self.options;
+
+        let whitelist_patterns = options
+            .whitelisted_tables
+            .iter()
+            .filter(|pattern| table.matches(pattern))
+            .map(|pattern| pattern.as_str())
+            .collect::<Vec<_>>();
+
+        let mut settings = vec![
+            format!("source: {}", table),
+            format!(
+                "whitelisted by: {}",
+                if whitelist_patterns.is_empty() {
+                    "none, included as a dependency".to_string()
+                } else {
+                    whitelist_patterns.join(", ")
+                }
+            ),
+            format!(
+                "target name: {} (schema prefix \"{}\", snake case {})",
+                output_table_name,
+                options
+                    .schema_prefixes
+                    .get(&table.schema)
+                    .map(|prefix| prefix.as_str())
+                    .unwrap_or("default"),
+                on_off(options.format_snake_case)
+            ),
+            format!(
+                "batches: {} bytes{}, chunks: {}, scheduler: {:?}",
+                options.packet_bytes(table),
+                options
+                    .batch_rows(table)
+                    .map_or(String::new(), |rows| format!(" or {} rows", rows)),
+                options
+                    .chunk_size
+                    .map_or("off".to_string(), |size| format!("{} rows", size)),
+                options.scheduler
+            ),
+            format!(
+                "existing rows: {}, drop: {}, shadow: {}, sync schema: {}, quarantine: {}",
+                if options.upsert {
+                    "upsert"
+                } else if options.append {
+                    "append"
+                } else {
+                    "fail"
+                },
+                on_off(options.drop),
+                on_off(options.shadow),
+                on_off(options.sync_schema),
+                on_off(options.quarantine)
+            ),
+            format!(
+                "strict types: {}{}",
+                on_off(options.strict_types),
+                if options.strict_types {
+                    format!(", GUIDs as {:?}", options.guid_format)
+                } else {
+                    String::new()
+                }
+            ),
+            format!(
+                "constraints: {}, CHECK fallback: {:?}, CHECK triggers: {}, ON DELETE: {}, ON UPDATE: {}",
+                on_off(options.constraints),
+                options.check_fallback,
+                on_off(options.check_triggers),
+                options.foreign_key_on_delete.as_deref().unwrap_or("source"),
+                options.foreign_key_on_update.as_deref().unwrap_or("source")
+            ),
+        ];
+
+        match (
+            options.table_config_pattern(table),
+            options.table_config(table),
+        ) {
+            (Some(pattern), Some(config)) => {
+                settings.push(format!("table settings: [settings.tables.\"{}\"]", pattern));
+
+                if let Some(partitioning) = &config.partitioning {
+                    settings.push(format!(
+                        "partitioning: {:?}, column: {}, partitions: {}",
+                        partitioning.mode,
+                        partitioning.column.as_deref().unwrap_or("auto"),
+                        partitioning.partitions
+                    ));
+                }
+
+                if !config.null_fallbacks.is_empty() {
+                    let mut columns = config.null_fallbacks.iter().collect::<Vec<_>>();
+                    columns.sort();
+
+                    settings.push(format!(
+                        "NULL fallbacks: {}",
+                        columns
+                            .iter()
+                            .map(|(column, fallback)| format!("{} = {}", column, fallback))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ));
+                }
+            }
+            _ => settings.push("table settings: none".to_string()),
+        }
 
         debug!(
-            "Sending {} bytes batch with {} transactions",
-            query_str.len(),
-            transaction_count
+            "Effective settings of table {}:\n  {}",
+            output_table_name,
+            settings.join("\n  ")
         );
+    }
 
-        inserter
-            .execute_transactional_query(query_str)
-            .await
-            .with_context(|| "Failed to execute transactional query batch".to_string())?;
+    // Chunks loaded by the suspended run, as long as the table is still split into the same chunks. The key bounds or
+    // row count may change while the chunk count stays the same, chunk N then covers other rows.
+    fn resumed_chunks(&self, output_table: &str, plan: &ChunkPlan) -> BTreeSet<usize> {
+        let Some(time_box) = &self.options.time_box else {
+            return BTreeSet::new();
+        };
 
-        let end_time = Instant::now();
+        match time_box.table_chunks(output_table) {
+            Some(chunks) if chunks.belong_to(plan) => chunks.completed,
+            Some(chunks) => {
+                warn!(
+                    "Table {} was split into {} chunks ({}) by the suspended run and {} chunks ({}) now, loading all chunks again",
+                    output_table,
+                    chunks.chunk_count,
+                    chunks.bounds,
+                    plan.chunk_count(),
+                    plan.bounds()
+                );
+
+                time_box.discard_chunks(output_table);
+                BTreeSet::new()
+            }
+            None => BTreeSet::new(),
+        }
+    }
 
-        debug!(
-            "Executed batch with {} transactions, bytes: {}, took: {}s",
-            transaction_count,
-            query_str.len(),
-            end_time.saturating_duration_since(start_time).as_secs_f32()
-        );
+    async fn acquire_fair_permit(&self) -> Result<Option<LimiterPermit>> {
+        match self.options.scheduler {
+            SchedulerMode::Fair => Ok(Some(self.limiter.acquire().await?)),
+            SchedulerMode::Table => Ok(None),
+        }
+    }
+}
+
+struct ChunkWorker {
+    extractor: DatabaseExtractor,
+    inserter: DatabaseInserter,
+    limiter: Arc<ConcurrencyLimiter>,
+    table: SourceTable,
+    output_table: String,
+    plan: Arc<ChunkPlan>,
+    cache: Option<RowCache>,
+    quarantine: Option<Arc<Quarantine>>,
+    dead_letters: Option<Arc<DeadLetters>>,
+    observers: Observers,
+    time_box: Option<Arc<TimeBox>>,
+    completed_chunks: Arc<BTreeSet<usize>>,
+    next_chunk: Arc<AtomicUsize>,
+    insert_plan: Arc<InsertPlan>,
+    insert_workers: usize,
+    uses_table_permit: bool,
+}
+
+impl ChunkWorker {
+    async fn run(mut self) -> Result<usize> {
+        let chunk_count = self.plan.chunk_count();
+        let mut total_transaction_count = 0;
+
+        loop {
+            let index = self.next_chunk.fetch_add(1, Ordering::SeqCst);
+
+            if index >= chunk_count {
+                break;
+            }
+
+            if self.completed_chunks.contains(&index) {
+                continue;
+            }
+
+            let _permit = if self.uses_table_permit {
+                None
+            } else {
+                Some(self.limiter.acquire().await?)
+            };
+
+            // Each chunk uses its own connection so long tables don't pin one for the whole run
+            let mut conn = self.extractor.connection().await?;
+            let stream = open_source_stream(
+                &mut conn,
+                &self.table,
+                &self.insert_plan.select_list,
+                Some(&self.plan),
+                index,
+                self.cache.as_ref(),
+            )
+            .await?;
+
+            let result = if self.insert_workers > 1 {
+                let target = InsertTarget {
+                    inserter: self.inserter.clone(),
+                    limiter: Arc::clone(&self.limiter),
+                    quarantine: self.quarantine.clone(),
+                    dead_letters: self.dead_letters.clone(),
+                    table_name: self.output_table.clone(),
+                    observers: self.observers.clone(),
+                    time_box: self.time_box.clone(),
+                };
+
+                write_pipelined_batches(target, stream, &self.insert_plan, self.insert_workers)
+                    .await
+            } else {
+                let mut sink = InserterSink {
+                    inserter: &mut self.inserter,
+                    limiter: &self.limiter,
+                    quarantine: self.quarantine.as_deref(),
+                    dead_letters: self.dead_letters.as_deref(),
+                    table_name: &self.output_table,
+                    observers: &self.observers,
+                    time_box: self.time_box.as_deref(),
+                };
+
+                write_batches(&mut sink, stream, &self.insert_plan).await
+            }
+            .with_context(|| format!("Failed to migrate chunk {}", index));
+
+            match result {
+                Ok(count) => total_transaction_count += count,
+                Err(err) => {
+                    // Stop the other workers from picking up further chunks
+                    self.next_chunk.store(chunk_count, Ordering::SeqCst);
+                    return Err(err);
+                }
+            }
+
+            if let Some(time_box) = &self.time_box {
+                time_box.complete_chunk(&self.output_table, &self.plan, index);
+            }
+
+            debug!(
+                "Table {} chunk {}/{} migrated",
+                self.output_table,
+                index + 1,
+                chunk_count
+            );
+        }
+
+        Ok(total_transaction_count)
     }
+}
 
-    Ok(())
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
 }
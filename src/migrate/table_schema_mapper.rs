@@ -1,16 +1,40 @@
-use crate::common::constraints::Constraint;
+use sha2::{Digest, Sha256};
+
+use crate::common::collation::Collation;
+use crate::common::constraints::{
+    parse_check_in_list, translate_check_clause, translate_computed_expression,
+    translate_default_expression, Constraint,
+};
 use crate::common::helpers::format_snake_case;
+use crate::common::identifiers::quote_literal;
 use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::config::{CharPadding, CheckInLists, ComputedColumns, Dialect, LongKeys};
+use crate::insert::query::{format_column_type, key_length_limit};
 use crate::mappings::Mappings;
+use crate::migrate::char_padding::is_char_column;
+use crate::migrate::migration_options::MigrationOptions;
+use crate::migrate::strict_types::guid_column_type;
+
+const MAX_IDENTIFIER_LENGTH: usize = 64;
+
+// MySQL keeps fractional seconds down to microseconds
+const MAX_DATETIME_PRECISION: i16 = 6;
+
+// Source types whose CHECK IN lists may become an ENUM
+const ENUM_SOURCE_TYPES: [&str; 4] = ["char", "varchar", "nchar", "nvarchar"];
 
 pub struct TableSchemaMapper;
 
 impl TableSchemaMapper {
     pub fn map_schema(
         mappings: &Mappings,
+        table: &SourceTable,
         table_schema: &[ColumnSchema],
-        format: bool,
+        options: &MigrationOptions,
     ) -> Vec<ColumnSchema> {
+        let format = options.format_snake_case;
+
         table_schema
             .iter()
             .map(|column| {
@@ -18,25 +42,82 @@ impl TableSchemaMapper {
                     panic!("Mapping not found for data type: {}", column.data_type)
                 });
 
-                let new_column_name = if format {
-                    format_snake_case(&column.column_name)
-                } else {
-                    column.column_name.clone()
-                };
+                let new_column_name = options.target_column_name(&column.column_name);
 
                 let new_constraints = column.constraints.clone();
-                let new_data_type = mapping.to_type.clone();
+                let max_type = mapping
+                    .max_type
+                    .as_ref()
+                    .filter(|_| column.character_maximum_length == Some(-1));
+                let new_data_type = max_type.unwrap_or(&mapping.to_type).clone();
 
-                // Check if new_constraints contain foreign key and format snake case
+                // Resolve foreign key references to their target table names
                 let updated_constraints = if let Some(new_constraints) = new_constraints {
                     match new_constraints {
                         Constraint::ForeignKey {
                             referenced_table,
                             referenced_column,
-                        } if format => Some(Constraint::ForeignKey {
-                            referenced_table: format_snake_case(&referenced_table),
-                            referenced_column: format_snake_case(&referenced_column),
+                            on_delete,
+                            on_update,
+                        } => Some(Constraint::ForeignKey {
+                            referenced_table: options
+                                .target_table_name(&SourceTable::parse(&referenced_table)),
+                            referenced_column: options.target_column_name(&referenced_column),
+                            on_delete: map_referential_action(
+                                options.foreign_key_on_delete.as_deref().unwrap_or(&on_delete),
+                                &column.column_name,
+                            ),
+                            on_update: map_referential_action(
+                                options.foreign_key_on_update.as_deref().unwrap_or(&on_update),
+                                &column.column_name,
+                            ),
                         }),
+                        Constraint::Check(check_clause) => {
+                            let translated = translate_check_clause(&check_clause, |name| {
+                                options.target_column_name(name)
+                            });
+
+                            match translated {
+                                Ok(translated) => Some(Constraint::Check(translated)),
+                                Err(err) => {
+                                    warn!(
+                                        "Skipping CHECK constraint {} of column {}, it {}",
+                                        check_clause, column.column_name, err
+                                    );
+                                    None
+                                }
+                            }
+                        }
+                        Constraint::Default(definition) => {
+                            match translate_default_expression(&definition) {
+                                // MySQL 5.7 has neither expression defaults nor defaults of text and blob columns
+                                Some(default)
+                                    if options.dialect == Dialect::Mysql57
+                                        && (is_blob_type(&new_data_type)
+                                            || default.starts_with('(')) =>
+                                {
+                                    warn!(
+                                        "Skipping default {} of column {}, MySQL 5.7 doesn't support it",
+                                        definition, column.column_name
+                                    );
+                                    None
+                                }
+                                // Text and blob columns only take expression defaults
+                                Some(default)
+                                    if is_blob_type(&new_data_type) && !default.starts_with('(') =>
+                                {
+                                    Some(Constraint::Default(format!("({})", default)))
+                                }
+                                Some(default) => Some(Constraint::Default(default)),
+                                None => {
+                                    warn!(
+                                        "Skipping default {} of column {}, it has no MySQL equivalent",
+                                        definition, column.column_name
+                                    );
+                                    None
+                                }
+                            }
+                        }
                         other_constraint => Some(other_constraint),
                     }
                 } else {
@@ -44,7 +125,7 @@ impl TableSchemaMapper {
                 };
 
                 let (new_characters_maximum_length, new_numeric_precision, new_numeric_scale) =
-                    if !mapping.type_parameters {
+                    if !mapping.type_parameters || max_type.is_some() {
                         (None, None, None)
                     } else {
                         let new_characters_maximum_length = column
@@ -77,16 +158,311 @@ impl TableSchemaMapper {
                         )
                     };
 
-                ColumnSchema {
+                // Strict mode gives GUIDs an explicit fixed size type instead of the mapped one
+                let (new_data_type, new_characters_maximum_length) =
+                    if options.strict_types && column.data_type == "uniqueidentifier" {
+                        let (data_type, length) = guid_column_type(options.guid_format);
+                        (data_type.to_string(), Some(length))
+                    } else {
+                        (new_data_type, new_characters_maximum_length)
+                    };
+
+                // VARCHAR keeps the trailing spaces MySQL strips from CHAR values
+                let new_data_type = if options.char_padding == CharPadding::Preserve
+                    && is_char_column(column)
+                    && new_data_type.eq_ignore_ascii_case("char")
+                {
+                    "varchar".to_string()
+                } else {
+                    new_data_type
+                };
+
+                // The ENUM carries the list of allowed strings, the CHECK constraint is left out
+                let enum_members = match &column.constraints {
+                    Some(Constraint::Check(check_clause))
+                        if options.check_in_lists == CheckInLists::Enum
+                            && ENUM_SOURCE_TYPES.contains(&column.data_type.as_str()) =>
+                    {
+                        parse_check_in_list(check_clause, &column.column_name)
+                    }
+                    _ => None,
+                };
+
+                let (new_data_type, new_characters_maximum_length, updated_constraints) =
+                    match &enum_members {
+                        Some(members) => (format_enum_type(members), None, None),
+                        None => (
+                            new_data_type,
+                            new_characters_maximum_length,
+                            updated_constraints,
+                        ),
+                    };
+
+                // Fractional seconds of the source are kept, e.g. `datetime(3)` for a `datetime2(3)` column
+                let new_numeric_precision = match new_data_type.to_lowercase().as_str() {
+                    "datetime" | "time" | "timestamp" if new_numeric_precision.is_none() => column
+                        .datetime_precision
+                        .filter(|precision| *precision > 0)
+                        .map(|precision| precision.min(MAX_DATETIME_PRECISION) as u8),
+                    _ => new_numeric_precision,
+                };
+
+                // MySQL only takes the current time as the default of a column with the same fractional seconds
+                let updated_constraints = match (updated_constraints, new_numeric_precision) {
+                    (Some(Constraint::Default(default)), Some(precision))
+                        if matches!(
+                            new_data_type.to_lowercase().as_str(),
+                            "datetime" | "timestamp"
+                        ) =>
+                    {
+                        Some(Constraint::Default(match default.as_str() {
+                            "CURRENT_TIMESTAMP" => format!("CURRENT_TIMESTAMP({})", precision),
+                            "(UTC_TIMESTAMP())" => format!("(UTC_TIMESTAMP({}))", precision),
+                            _ => default,
+                        }))
+                    }
+                    (constraints, _) => constraints,
+                };
+
+                // Only text columns deviating from the table collation get their own
+                let is_text_type = ["char", "text", "enum", "set"]
+                    .iter()
+                    .any(|text_type| new_data_type.to_lowercase().contains(text_type));
+
+                let new_collation = column
+                    .collation
+                    .as_deref()
+                    .filter(|_| options.column_collations && is_text_type)
+                    .map(|collation| Collation::resolve(collation).for_dialect(options.dialect).name)
+                    .filter(|collation| *collation != options.collation.name);
+
+                // Portable expressions are computed by MySQL, the others are loaded with their values
+                let computed_definition = column
+                    .computed_definition
+                    .as_deref()
+                    .filter(|_| options.computed_columns == ComputedColumns::Generated)
+                    .and_then(|definition| {
+                        let translated =
+                            translate_computed_expression(definition, &column.data_type, |name| {
+                                options.target_column_name(name)
+                            });
+
+                        match translated {
+                            Ok(translated) => Some(translated),
+                            Err(err) => {
+                                warn!(
+                                    "Loading computed column {} of table {} with its values, its expression {} {}",
+                                    column.column_name, table, definition, err
+                                );
+                                None
+                            }
+                        }
+                    });
+
+                let mapped_column = ColumnSchema {
                     column_name: new_column_name,
                     data_type: new_data_type,
                     character_maximum_length: new_characters_maximum_length,
                     numeric_precision: new_numeric_precision,
                     numeric_scale: new_numeric_scale,
+                    datetime_precision: column.datetime_precision,
                     is_nullable: column.is_nullable,
                     constraints: updated_constraints,
+                    constraint_name: column
+                        .constraint_name
+                        .as_deref()
+                        .filter(|_| enum_members.is_none())
+                        .and_then(|name| map_constraint_name(name, format)),
+                    collation: new_collation,
+                    computed_definition,
+                };
+
+                let is_key = matches!(
+                    mapped_column.constraints,
+                    Some(Constraint::PrimaryKey | Constraint::Unique)
+                );
+
+                match key_length_limit(&mapped_column) {
+                    Some(max_length)
+                        if is_key
+                            && options.long_keys(table, &column.column_name)
+                                == LongKeys::Varchar =>
+                    {
+                        shorten_key_column(table, mapped_column, max_length)
+                    }
+                    _ => mapped_column,
                 }
             })
             .collect()
     }
 }
+
+// Source and mapped columns the rows are read and inserted with, MySQL computes generated columns itself
+pub fn loaded_columns(
+    source_schema: &[ColumnSchema],
+    mapped_schema: &[ColumnSchema],
+) -> (Vec<ColumnSchema>, Vec<ColumnSchema>) {
+    source_schema
+        .iter()
+        .zip(mapped_schema)
+        .filter(|(_, mapped)| mapped.computed_definition.is_none())
+        .map(|(source, mapped)| (source.clone(), mapped.clone()))
+        .unzip()
+}
+
+// Written the way MySQL reports the column type, e.g. `enum('open','closed')`
+fn format_enum_type(members: &[String]) -> String {
+    let members = members
+        .iter()
+        .map(|member| quote_literal(member))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    format!("enum({})", members)
+}
+
+// Gives a key column too long for an index the longest VARCHAR or VARBINARY that fits instead
+fn shorten_key_column(table: &SourceTable, column: ColumnSchema, max_length: i32) -> ColumnSchema {
+    let mapped_type = format_column_type(&column);
+    let data_type = column.data_type.to_lowercase();
+    let new_data_type = if data_type.contains("binary") || data_type.ends_with("blob") {
+        "varbinary"
+    } else {
+        "varchar"
+    };
+
+    let shortened = ColumnSchema {
+        data_type: new_data_type.to_string(),
+        character_maximum_length: Some(max_length),
+        ..column
+    };
+
+    warn!(
+        "Key column {} of table {} is created as {} instead of {}, longer values don't fit",
+        shortened.column_name,
+        table,
+        format_column_type(&shortened),
+        mapped_type
+    );
+
+    shortened
+}
+
+fn is_blob_type(data_type: &str) -> bool {
+    let data_type = data_type.to_lowercase();
+
+    data_type.ends_with("text") || data_type.ends_with("blob") || data_type == "json"
+}
+
+// InnoDB rejects `SET DEFAULT`, the closest it gets is refusing the change
+fn map_referential_action(action: &str, column_name: &str) -> String {
+    if action == "SET DEFAULT" {
+        warn!(
+            "Foreign key of column {} uses SET DEFAULT, which MySQL doesn't support, using NO ACTION instead",
+            column_name
+        );
+        "NO ACTION".to_string()
+    } else {
+        action.to_string()
+    }
+}
+
+// MySQL identifiers are limited to 64 characters, longer names are cut and end in a hash of the whole name. Each run
+// gives a constraint the same name, which finds it on the target again, unlike a name MySQL generates.
+fn map_constraint_name(name: &str, format: bool) -> Option<String> {
+    let name = if format {
+        format_snake_case(name)
+    } else {
+        name.to_string()
+    };
+
+    if name.chars().count() <= MAX_IDENTIFIER_LENGTH {
+        return Some(name);
+    }
+
+    let suffix = format!("_{}", hex::encode(&Sha256::digest(name.as_bytes())[..4]));
+    let shortened = name
+        .chars()
+        .take(MAX_IDENTIFIER_LENGTH - suffix.len())
+        .chain(suffix.chars())
+        .collect::<String>();
+
+    warn!(
+        "Constraint name {} is longer than {} characters, shortened to {}",
+        name, MAX_IDENTIFIER_LENGTH, shortened
+    );
+
+    Some(shortened)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SettingsConfig;
+    use crate::insert::query::format_column_definition;
+
+    fn column(data_type: &str, datetime_precision: i16, default: &str) -> ColumnSchema {
+        ColumnSchema {
+            column_name: "CreatedAt".to_string(),
+            data_type: data_type.to_string(),
+            character_maximum_length: None,
+            numeric_precision: None,
+            numeric_scale: None,
+            datetime_precision: Some(datetime_precision),
+            is_nullable: false,
+            constraints: Some(Constraint::Default(default.to_string())),
+            constraint_name: None,
+            collation: None,
+            computed_definition: None,
+        }
+    }
+
+    fn map_column(column: ColumnSchema) -> ColumnSchema {
+        let mappings =
+            Mappings::load(concat!(env!("CARGO_MANIFEST_DIR"), "/mappings.toml")).unwrap();
+        let options = MigrationOptions::new(SettingsConfig::default());
+
+        TableSchemaMapper::map_schema(
+            &mappings,
+            &SourceTable::new("dbo", "Orders"),
+            &[column],
+            &options,
+        )
+        .remove(0)
+    }
+
+    #[test]
+    fn current_time_defaults_keep_the_fractional_seconds_of_the_column() {
+        let mapped = map_column(column("datetime", 3, "(getdate())"));
+
+        assert_eq!(mapped.data_type, "datetime");
+        assert_eq!(mapped.numeric_precision, Some(3));
+        assert_eq!(
+            mapped.constraints,
+            Some(Constraint::Default("CURRENT_TIMESTAMP(3)".to_string()))
+        );
+        assert_eq!(
+            format_column_definition(&mapped),
+            "`CreatedAt` datetime(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3)"
+        );
+
+        let mapped = map_column(column("datetime2", 7, "(sysutcdatetime())"));
+
+        assert_eq!(mapped.numeric_precision, Some(MAX_DATETIME_PRECISION as u8));
+        assert_eq!(
+            mapped.constraints,
+            Some(Constraint::Default("(UTC_TIMESTAMP(6))".to_string()))
+        );
+    }
+
+    #[test]
+    fn current_time_defaults_of_whole_seconds_stay_bare() {
+        let mapped = map_column(column("datetime2", 0, "(getdate())"));
+
+        assert_eq!(mapped.numeric_precision, None);
+        assert_eq!(
+            mapped.constraints,
+            Some(Constraint::Default("CURRENT_TIMESTAMP".to_string()))
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::Result;
+
+use crate::common::source_table::SourceTable;
+use crate::config::TemporalTables;
+use crate::extract::extractor::DatabaseExtractor;
+
+// System-versioned temporal table of the source, with the table keeping the earlier versions of its rows
+#[derive(Debug, Clone)]
+pub struct TemporalTable {
+    pub table: SourceTable,
+    pub history_table: SourceTable,
+    pub period_start: String,
+    // Hidden period columns are left out of the rows read
+    pub period_hidden: bool,
+}
+
+// Adds or leaves out the history tables of the selected temporal tables, and marks the ones flattened with their history
+pub async fn resolve_temporal_tables(
+    extractor: &mut DatabaseExtractor,
+    tables: &mut Vec<SourceTable>,
+    temporal_tables: TemporalTables,
+) -> Result<()> {
+    let temporal = extractor.fetch_temporal_tables().await?;
+
+    if temporal.is_empty() {
+        return Ok(());
+    }
+
+    let is_history = |table: &SourceTable| {
+        temporal
+            .iter()
+            .any(|temporal| &temporal.history_table == table)
+    };
+
+    match temporal_tables {
+        TemporalTables::Both => {
+            let included = temporal
+                .iter()
+                .filter(|temporal| tables.contains(&temporal.table))
+                .map(|temporal| temporal.history_table.clone())
+                .filter(|history_table| !tables.contains(history_table))
+                .collect::<Vec<_>>();
+
+            if !included.is_empty() {
+                info!(
+                    "Including history tables: {}",
+                    included
+                        .iter()
+                        .map(|table| table.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+
+            tables.extend(included);
+        }
+        TemporalTables::Current => tables.retain(|table| !is_history(table)),
+        TemporalTables::Flatten => {
+            tables.retain(|table| !is_history(table));
+
+            for table in tables.iter_mut() {
+                let Some(temporal) = temporal.iter().find(|temporal| &temporal.table == table)
+                else {
+                    continue;
+                };
+
+                if temporal.period_hidden {
+                    warn!(
+                        "Migrating only the current rows of temporal table {}, its period columns are hidden",
+                        table
+                    );
+                    continue;
+                }
+
+                info!(
+                    "Flattening temporal table {} with its history table {}",
+                    table, temporal.history_table
+                );
+                table.period_start = Some(temporal.period_start.clone());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Marks a table given by name the way a migration would, when it is a flattened temporal table
+pub async fn resolve_temporal_table(
+    extractor: &mut DatabaseExtractor,
+    table: &mut SourceTable,
+    temporal_tables: TemporalTables,
+) -> Result<()> {
+    if temporal_tables != TemporalTables::Flatten {
+        return Ok(());
+    }
+
+    table.period_start = extractor
+        .fetch_temporal_tables()
+        .await?
+        .into_iter()
+        .find(|temporal| &temporal.table == table && !temporal.period_hidden)
+        .map(|temporal| temporal.period_start);
+
+    Ok(())
+}
@@ -0,0 +1,252 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset, LocalResult, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+
+use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::migrate::migration_options::MigrationOptions;
+
+const LOCAL_DATETIME_TYPES: [&str; 3] = ["datetime", "datetime2", "smalldatetime"];
+
+const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// Converts datetime values from the source time zone into the configured target zones
+#[derive(Debug, Clone)]
+pub struct TimeZoneConversion {
+    // Row index, column name, zone of the value (none for values carrying their offset) and target zone
+    columns: Vec<(usize, String, Option<Tz>, Tz)>,
+}
+
+impl TimeZoneConversion {
+    pub fn new(
+        table: &SourceTable,
+        source_schema: &[ColumnSchema],
+        options: &MigrationOptions,
+    ) -> Option<Self> {
+        let time_zone = options.time_zone?;
+        let column_zones = options.table_config(table).map(|config| &config.time_zones);
+
+        let columns = source_schema
+            .iter()
+            .enumerate()
+            .filter_map(|(index, column)| {
+                let source = match column.data_type.as_str() {
+                    "datetimeoffset" => None,
+                    data_type if LOCAL_DATETIME_TYPES.contains(&data_type) => {
+                        Some(time_zone.source)
+                    }
+                    _ => return None,
+                };
+
+                let target = column_zones
+                    .and_then(|zones| zones.get(&column.column_name))
+                    .copied()
+                    .unwrap_or(time_zone.target);
+
+                // Values already in the target zone are left as they are
+                (source != Some(target))
+                    .then(|| (index, column.column_name.clone(), source, target))
+            })
+            .collect::<Vec<_>>();
+
+        if columns.is_empty() {
+            return None;
+        }
+
+        for (_, column_name, _, target) in &columns {
+            debug!(
+                "Converting {}.{} to time zone {}",
+                table, column_name, target
+            );
+        }
+
+        Some(TimeZoneConversion { columns })
+    }
+
+    // Conversions of the given columns only, for rows made of just these columns like deleted keys
+    pub fn select(&self, indexes: &[usize]) -> Option<Self> {
+        let columns = indexes
+            .iter()
+            .enumerate()
+            .filter_map(|(position, index)| {
+                self.columns
+                    .iter()
+                    .find(|(column_index, ..)| column_index == index)
+                    .map(|(_, column_name, source, target)| {
+                        (position, column_name.clone(), *source, *target)
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        (!columns.is_empty()).then_some(TimeZoneConversion { columns })
+    }
+
+    pub fn apply(&self, row: &mut [String]) -> Result<()> {
+        for (index, column_name, source, target) in &self.columns {
+            if row[*index] == "NULL" {
+                continue;
+            }
+
+            let value = row[*index].trim_matches('\'');
+
+            // Zones are whole seconds apart, the fractional seconds stay as they are
+            let fraction = value
+                .split_once('.')
+                .map(|(_, fraction)| fraction.split(' ').next().unwrap_or_default())
+                .filter(|fraction| !fraction.is_empty())
+                .map(|fraction| format!(".{}", fraction))
+                .unwrap_or_default();
+
+            let datetime = match source {
+                Some(source) => NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+                    .ok()
+                    .map(|datetime| resolve_local(source, datetime).with_timezone(target)),
+                None => DateTime::<FixedOffset>::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f %z")
+                    .ok()
+                    .map(|datetime| datetime.with_timezone(target)),
+            }
+            .ok_or_else(|| anyhow!("Invalid datetime {} of column {}", row[*index], column_name))?;
+
+            row[*index] = format!("'{}{}'", datetime.format(DATETIME_FORMAT), fraction);
+        }
+
+        Ok(())
+    }
+}
+
+// Local times repeated when clocks go back map to their first occurrence, skipped ones to the hour after
+fn resolve_local(zone: &Tz, datetime: NaiveDateTime) -> DateTime<Tz> {
+    match zone.from_local_datetime(&datetime) {
+        LocalResult::Single(resolved) | LocalResult::Ambiguous(resolved, _) => resolved,
+        LocalResult::None => zone
+            .from_local_datetime(&(datetime + chrono::Duration::hours(1)))
+            .earliest()
+            .unwrap_or_else(|| zone.from_utc_datetime(&datetime)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{SettingsConfig, TimeZoneConfig};
+
+    fn column(column_name: &str, data_type: &str) -> ColumnSchema {
+        ColumnSchema {
+            column_name: column_name.to_string(),
+            data_type: data_type.to_string(),
+            character_maximum_length: None,
+            numeric_precision: None,
+            numeric_scale: None,
+            datetime_precision: Some(7),
+            is_nullable: true,
+            constraints: None,
+            constraint_name: None,
+            collation: None,
+            computed_definition: None,
+        }
+    }
+
+    fn conversion(source: Tz, target: Tz) -> Option<TimeZoneConversion> {
+        let mut options = MigrationOptions::new(SettingsConfig::default());
+        options.time_zone = Some(TimeZoneConfig { source, target });
+
+        TimeZoneConversion::new(
+            &SourceTable::new("dbo", "Events"),
+            &[
+                column("Id", "int"),
+                column("CreatedAt", "datetime2"),
+                column("SentAt", "datetimeoffset"),
+            ],
+            &options,
+        )
+    }
+
+    fn convert(row: &[&str]) -> Vec<String> {
+        let mut row = row
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>();
+        conversion(Tz::Europe__Berlin, Tz::UTC)
+            .unwrap()
+            .apply(&mut row)
+            .unwrap();
+        row
+    }
+
+    #[test]
+    fn converts_local_and_offset_values_into_the_target_zone() {
+        assert_eq!(
+            convert(&[
+                "1",
+                "'2024-01-15 10:00:00.1234567'",
+                "'2024-01-15 10:00:00.5 +0500'"
+            ]),
+            [
+                "1",
+                "'2024-01-15 09:00:00.1234567'",
+                "'2024-01-15 05:00:00.5'"
+            ]
+        );
+        // Summer time is an hour further from UTC
+        assert_eq!(
+            convert(&["1", "'2024-07-15 10:00:00'", "NULL"]),
+            ["1", "'2024-07-15 08:00:00'", "NULL"]
+        );
+    }
+
+    #[test]
+    fn resolves_times_around_clock_changes() {
+        // 02:30 was skipped on 2024-03-31 and taken as 03:30, and repeated on 2024-10-27, taken the first time
+        assert_eq!(
+            convert(&["1", "'2024-03-31 02:30:00'", "NULL"])[1],
+            "'2024-03-31 01:30:00'"
+        );
+        assert_eq!(
+            convert(&["1", "'2024-10-27 02:30:00'", "NULL"])[1],
+            "'2024-10-27 00:30:00'"
+        );
+    }
+
+    #[test]
+    fn leaves_values_already_in_the_target_zone() {
+        let conversion = conversion(Tz::UTC, Tz::UTC).unwrap();
+        let mut row = vec![
+            "1".to_string(),
+            "'2024-01-15 10:00:00'".to_string(),
+            "'2024-01-15 10:00:00 +0100'".to_string(),
+        ];
+        conversion.apply(&mut row).unwrap();
+
+        assert_eq!(row[1], "'2024-01-15 10:00:00'");
+        assert_eq!(row[2], "'2024-01-15 09:00:00'");
+    }
+
+    #[test]
+    fn selects_the_columns_of_partial_rows() {
+        let conversion = conversion(Tz::Europe__Berlin, Tz::UTC).unwrap();
+        let mut row = vec!["'2024-01-15 10:00:00'".to_string()];
+
+        conversion.select(&[1]).unwrap().apply(&mut row).unwrap();
+
+        assert_eq!(row, ["'2024-01-15 09:00:00'"]);
+        assert!(conversion.select(&[0]).is_none());
+    }
+
+    #[test]
+    fn rejects_unreadable_values() {
+        let mut row = vec![
+            "1".to_string(),
+            "'yesterday'".to_string(),
+            "NULL".to_string(),
+        ];
+
+        assert_eq!(
+            conversion(Tz::Europe__Berlin, Tz::UTC)
+                .unwrap()
+                .apply(&mut row)
+                .unwrap_err()
+                .to_string(),
+            "Invalid datetime 'yesterday' of column CreatedAt"
+        );
+    }
+}
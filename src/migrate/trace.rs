@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tokio::time::Instant;
+
+use crate::migrate::observer::{MigrationEvent, MigrationObserver};
+
+const TABLES_PROCESS: u32 = 1;
+const BATCHES_PROCESS: u32 = 2;
+
+// Spans placed on the first lane that is free at their start, so concurrent spans never overlap
+#[derive(Debug, Default)]
+struct Lanes {
+    ends: Vec<u64>,
+}
+
+impl Lanes {
+    fn assign(&mut self, start: u64, end: u64) -> usize {
+        let lane = match self.ends.iter().position(|lane_end| *lane_end <= start) {
+            Some(lane) => lane,
+            None => {
+                self.ends.push(0);
+                self.ends.len() - 1
+            }
+        };
+
+        self.ends[lane] = end;
+        lane
+    }
+}
+
+#[derive(Debug, Default)]
+struct TraceState {
+    events: Vec<Value>,
+    table_starts: HashMap<String, u64>,
+    table_lanes: Lanes,
+    batch_lanes: Lanes,
+}
+
+// Records tables and batches as Chrome trace events, viewable in chrome://tracing or Perfetto
+#[derive(Debug)]
+pub struct TraceRecorder {
+    start_time: Instant,
+    state: Mutex<TraceState>,
+}
+
+impl TraceRecorder {
+    pub fn start() -> Self {
+        TraceRecorder {
+            start_time: Instant::now(),
+            state: Mutex::new(TraceState::default()),
+        }
+    }
+
+    // Microseconds since the start of the run
+    fn now(&self) -> u64 {
+        self.start_time.elapsed().as_micros() as u64
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let state = self.state.lock().unwrap();
+
+        let mut events = vec![
+            process_name(TABLES_PROCESS, "Tables"),
+            process_name(BATCHES_PROCESS, "Batches"),
+        ];
+        events.extend(state.events.iter().cloned());
+
+        let trace = json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ms",
+        });
+
+        fs::write(path, serde_json::to_string(&trace)?)?;
+
+        Ok(())
+    }
+}
+
+fn process_name(pid: u32, name: &str) -> Value {
+    json!({
+        "name": "process_name",
+        "ph": "M",
+        "pid": pid,
+        "args": { "name": name },
+    })
+}
+
+fn span(name: &str, pid: u32, lane: usize, start: u64, end: u64, args: Value) -> Value {
+    json!({
+        "name": name,
+        "ph": "X",
+        "pid": pid,
+        "tid": lane,
+        "ts": start,
+        "dur": end - start,
+        "args": args,
+    })
+}
+
+#[async_trait]
+impl MigrationObserver for TraceRecorder {
+    async fn on_event(&self, event: &MigrationEvent) {
+        let now = self.now();
+        let mut state = self.state.lock().unwrap();
+
+        let (table, outcome) = match event {
+            MigrationEvent::TableStarted { table } => {
+                state.table_starts.insert(table.clone(), now);
+                return;
+            }
+            MigrationEvent::BatchExecuted {
+                table,
+                rows,
+                bytes,
+                duration,
+            } => {
+                let start = now.saturating_sub(duration.as_micros() as u64);
+                let lane = state.batch_lanes.assign(start, now);
+                let event = span(
+                    table,
+                    BATCHES_PROCESS,
+                    lane,
+                    start,
+                    now,
+                    json!({ "rows": rows, "bytes": bytes }),
+                );
+
+                state.events.push(event);
+                return;
+            }
+            MigrationEvent::TableFinished { table, rows, .. } => {
+                (table, json!({ "outcome": "finished", "rows": rows }))
+            }
+            MigrationEvent::TableFailed { table, error } => {
+                (table, json!({ "outcome": "failed", "error": error }))
+            }
+            MigrationEvent::TableSuspended { table } => (table, json!({ "outcome": "suspended" })),
+            MigrationEvent::MigrationFinished { .. } => return,
+        };
+
+        if let Some(start) = state.table_starts.remove(table) {
+            let lane = state.table_lanes.assign(start, now);
+            let event = span(table, TABLES_PROCESS, lane, start, now, outcome);
+
+            state.events.push(event);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+
+use crate::common::constraints::Constraint;
+use crate::insert::inserter::DatabaseInserter;
+use crate::migrate::migration_result::MigrationResult;
+
+const EXAMPLE_DUPLICATES: usize = 5;
+
+// Checks the UNIQUE constraints of the source against the migrated rows, as they are left out without --constraints
+pub async fn check_unique_constraints(
+    inserter: &mut DatabaseInserter,
+    results: &[MigrationResult],
+) -> Result<()> {
+    let (mut checked, mut violated) = (0, 0);
+
+    for result in results {
+        for (name, columns) in unique_constraints(result) {
+            let (count, examples) = inserter
+                .fetch_duplicates(&result.table_name, &columns, EXAMPLE_DUPLICATES)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to check unique constraint {} of table {}",
+                        name, result.table_name
+                    )
+                })?;
+
+            checked += 1;
+
+            if count == 0 {
+                debug!(
+                    "Unique constraint {} of table {} holds",
+                    name, result.table_name
+                );
+                continue;
+            }
+
+            violated += 1;
+
+            let examples = examples
+                .iter()
+                .map(|(value, rows)| format!("({}) in {} rows", value, rows))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            warn!(
+                "Unique constraint {} of table {} on {} is violated by {} duplicated values, e.g. {}",
+                name,
+                result.table_name,
+                columns.join(", "),
+                count,
+                examples
+            );
+        }
+    }
+
+    if checked > 0 {
+        info!(
+            "Unique constraints checked: {}, violated: {}",
+            checked, violated
+        );
+    }
+
+    Ok(())
+}
+
+// Columns of the unique constraints by constraint name, unnamed constraints cover their column only
+fn unique_constraints(result: &MigrationResult) -> BTreeMap<String, Vec<&str>> {
+    let mut constraints = BTreeMap::<String, Vec<&str>>::new();
+
+    for column in &result.schema {
+        if column.constraints != Some(Constraint::Unique) {
+            continue;
+        }
+
+        let name = column
+            .constraint_name
+            .clone()
+            .unwrap_or_else(|| column.column_name.clone());
+
+        constraints
+            .entry(name)
+            .or_default()
+            .push(&column.column_name);
+    }
+
+    constraints
+}
@@ -0,0 +1,150 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::config::{OnInvalid, ValidationRule};
+use crate::migrate::migration_options::MigrationOptions;
+
+// Checks the extracted values against the configured rules of their columns and counts the violations
+pub struct Validation {
+    columns: Vec<(usize, String, ValidationRule)>,
+    violations: Vec<AtomicUsize>,
+    pub on_invalid: OnInvalid,
+}
+
+impl Validation {
+    pub fn new(
+        table: &SourceTable,
+        table_name: &str,
+        source_schema: &[ColumnSchema],
+        options: &MigrationOptions,
+    ) -> Option<Self> {
+        let rules = &options.table_config(table)?.validations;
+        let mut columns = Vec::new();
+
+        for (column_name, rule) in rules {
+            let index = source_schema
+                .iter()
+                .position(|column| column.column_name.eq_ignore_ascii_case(column_name));
+
+            match index {
+                Some(index) => columns.push((
+                    index,
+                    source_schema[index].column_name.clone(),
+                    rule.clone(),
+                )),
+                None => warn!(
+                    "Table {} has no column {} for the configured validation rule",
+                    table_name, column_name
+                ),
+            }
+        }
+
+        if columns.is_empty() {
+            return None;
+        }
+
+        let violations = columns.iter().map(|_| AtomicUsize::new(0)).collect();
+
+        Some(Validation {
+            columns,
+            violations,
+            on_invalid: options.on_invalid(table),
+        })
+    }
+
+    // Describes the first violation of the row, NULL values pass every rule
+    pub fn check(&self, row: &[String]) -> Option<String> {
+        for ((index, column_name, rule), count) in self.columns.iter().zip(&self.violations) {
+            let value = &row[*index];
+
+            if value == "NULL" {
+                continue;
+            }
+
+            if let Some(reason) = violation(rule, &unquote(value)) {
+                count.fetch_add(1, Ordering::Relaxed);
+
+                return Some(format!(
+                    "Invalid value {} of column {}: {}",
+                    value, column_name, reason
+                ));
+            }
+        }
+
+        None
+    }
+
+    // Rows left out of the load, each row counts towards the first rule it violates
+    pub fn skipped_rows(&self) -> usize {
+        match self.on_invalid {
+            OnInvalid::Skip => self
+                .violations
+                .iter()
+                .map(|count| count.load(Ordering::Relaxed))
+                .sum(),
+            OnInvalid::Quarantine | OnInvalid::Fail => 0,
+        }
+    }
+
+    pub fn log_violations(&self, table_name: &str) {
+        // A failing row already failed the table
+        let action = match self.on_invalid {
+            OnInvalid::Skip => "skipped",
+            OnInvalid::Quarantine => "quarantined",
+            OnInvalid::Fail => return,
+        };
+
+        for ((_, column_name, _), count) in self.columns.iter().zip(&self.violations) {
+            let count = count.load(Ordering::Relaxed);
+
+            if count > 0 {
+                warn!(
+                    "{} rows of {} failed the validation of column {} and were {}",
+                    count, table_name, column_name, action
+                );
+            }
+        }
+    }
+}
+
+fn violation(rule: &ValidationRule, value: &str) -> Option<String> {
+    if let Some(pattern) = &rule.pattern {
+        if !pattern.is_match(value) {
+            return Some(format!("doesn't match {}", pattern));
+        }
+    }
+
+    if rule.min.is_some() || rule.max.is_some() {
+        let Ok(number) = value.parse::<f64>() else {
+            return Some("not a number".to_string());
+        };
+
+        if let Some(min) = rule.min.filter(|min| number < *min) {
+            return Some(format!("below the minimum {}", min));
+        }
+
+        if let Some(max) = rule.max.filter(|max| number > *max) {
+            return Some(format!("above the maximum {}", max));
+        }
+    }
+
+    if let Some(values) = &rule.values {
+        if !values.iter().any(|allowed| allowed == value) {
+            return Some(format!("not one of {}", values.join(", ")));
+        }
+    }
+
+    None
+}
+
+// Content of a formatted string literal
+fn unquote(value: &str) -> String {
+    match value
+        .strip_prefix('\'')
+        .and_then(|value| value.strip_suffix('\''))
+    {
+        Some(value) => value.replace("''", "'"),
+        None => value.to_string(),
+    }
+}
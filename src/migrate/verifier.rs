@@ -0,0 +1,181 @@
+use anyhow::{bail, Result};
+
+use crate::common::source_table::SourceTable;
+use crate::extract::extractor::DatabaseExtractor;
+use crate::insert::inserter::DatabaseInserter;
+use crate::mappings::Mappings;
+use crate::migrate::assertions::run_assertions;
+use crate::migrate::checksum::compare_checksums;
+use crate::migrate::migration_options::MigrationOptions;
+use crate::migrate::migrator::resolve_source_tables;
+use crate::migrate::table_schema_mapper::TableSchemaMapper;
+
+// Compares the whitelisted source tables with their migrated counterparts
+pub struct DatabaseVerifier {
+    extractor: DatabaseExtractor,
+    inserter: DatabaseInserter,
+    mappings: Mappings,
+    options: MigrationOptions,
+    checksums: bool,
+    lock: bool,
+}
+
+impl DatabaseVerifier {
+    pub fn new(
+        extractor: DatabaseExtractor,
+        inserter: DatabaseInserter,
+        mappings: Mappings,
+        options: MigrationOptions,
+        checksums: bool,
+        lock: bool,
+    ) -> Self {
+        DatabaseVerifier {
+            extractor,
+            inserter,
+            mappings,
+            options,
+            checksums,
+            lock,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        let (tables, names) = resolve_source_tables(&mut self.extractor, &self.options).await?;
+        let formatted_tables = names.target_names();
+
+        let mut existing_tables = Vec::new();
+
+        if self.lock {
+            existing_tables = self.inserter.get_all_tables().await?;
+            existing_tables.retain(|table| {
+                formatted_tables
+                    .iter()
+                    .any(|t| t.to_lowercase() == table.to_lowercase())
+            });
+        }
+
+        let lock = if !existing_tables.is_empty() {
+            let lock = self.inserter.lock_tables_for_read(&existing_tables).await?;
+            info!("Locked {} target tables for reading", existing_tables.len());
+
+            Some(lock)
+        } else {
+            None
+        };
+
+        let result = self.verify_tables(&tables).await;
+
+        if let Some(lock) = lock {
+            lock.release().await?;
+            info!("Target tables unlocked");
+        }
+
+        result
+    }
+
+    async fn verify_tables(&mut self, tables: &[SourceTable]) -> Result<()> {
+        let mut failed_tables = 0;
+
+        for table in tables {
+            if !self.check_table(table).await? {
+                failed_tables += 1;
+            }
+        }
+
+        let assertions = run_assertions(&mut self.inserter, &self.options.assertions).await;
+
+        if failed_tables > 0 {
+            bail!(
+                "Verification failed for {} of {} tables",
+                failed_tables,
+                tables.len()
+            );
+        }
+
+        info!("All {} tables verified successfully", tables.len());
+
+        assertions
+    }
+
+    // Verifies a single table and logs the outcome, returning whether it matches its source
+    pub async fn check_table(&mut self, table: &SourceTable) -> Result<bool> {
+        let issues = self.verify_table(table).await?;
+
+        if issues.is_empty() {
+            info!("Table {} verified successfully", table);
+        } else {
+            for issue in &issues {
+                warn!("Table {}: {}", table, issue);
+            }
+        }
+
+        Ok(issues.is_empty())
+    }
+
+    async fn verify_table(&mut self, table: &SourceTable) -> Result<Vec<String>> {
+        let target_table_name = self.options.target_table_name(table);
+
+        if !self.inserter.table_exists(&target_table_name).await? {
+            return Ok(vec![format!(
+                "target table {} does not exist",
+                target_table_name
+            )]);
+        }
+
+        let mut issues = Vec::new();
+
+        let schema = self.extractor.get_table_schema(table).await?;
+        let mapped_schema =
+            TableSchemaMapper::map_schema(&self.mappings, table, &schema, &self.options);
+        let expected_columns = mapped_schema
+            .iter()
+            .map(|column| column.column_name.clone())
+            .chain(
+                self.options
+                    .extra_columns
+                    .iter()
+                    .map(|column| column.name.clone()),
+            )
+            .collect::<Vec<_>>();
+        let target_columns = self.inserter.get_column_names(&target_table_name).await?;
+
+        for column in &expected_columns {
+            if !target_columns.contains(column) {
+                issues.push(format!("column `{}` is missing in the target", column));
+            }
+        }
+
+        for column in &target_columns {
+            if !expected_columns.contains(column) {
+                issues.push(format!("column `{}` does not exist in the source", column));
+            }
+        }
+
+        let source_rows = self.extractor.table_rows_count(table).await?;
+        let target_rows = self.inserter.table_rows_count(&target_table_name).await?;
+
+        if source_rows != target_rows {
+            issues.push(format!(
+                "row count mismatch, source: {}, target: {}",
+                source_rows, target_rows
+            ));
+        }
+
+        // Checksums are only meaningful once the columns and row counts line up
+        if self.checksums && issues.is_empty() {
+            let mismatch = compare_checksums(
+                &mut self.extractor,
+                &mut self.inserter,
+                table,
+                &target_table_name,
+                &schema,
+                &mapped_schema,
+            )
+            .await?;
+
+            issues.extend(mismatch);
+        }
+
+        Ok(issues)
+    }
+}
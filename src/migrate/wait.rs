@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use tokio::time::{sleep, Instant};
+
+use crate::extract::extractor::DatabaseExtractor;
+
+// Source conditions a migration waits for before it starts, all of them have to be met
+#[derive(Debug, Clone)]
+pub struct WaitConditions {
+    // Query returning a row once the source is ready, like a maintenance flag
+    pub query: Option<String>,
+    pub max_replica_lag: Option<u64>,
+    pub until: Option<DateTime<Local>>,
+    pub interval: Duration,
+    pub timeout: Option<Duration>,
+}
+
+pub async fn wait_for_source(
+    extractor: &mut DatabaseExtractor,
+    conditions: &WaitConditions,
+) -> Result<()> {
+    let start_time = Instant::now();
+    let mut last_pending = Vec::new();
+
+    loop {
+        let pending = pending_conditions(extractor, conditions).await?;
+
+        if pending.is_empty() {
+            if !last_pending.is_empty() {
+                info!(
+                    "Source is ready after waiting {}s",
+                    start_time.elapsed().as_secs()
+                );
+            }
+
+            return Ok(());
+        }
+
+        if let Some(timeout) = conditions.timeout {
+            if start_time.elapsed() >= timeout {
+                bail!(
+                    "Source wasn't ready within {}s, still waiting for {}",
+                    timeout.as_secs(),
+                    pending.join(", ")
+                );
+            }
+        }
+
+        if pending != last_pending {
+            info!("Waiting for {}", pending.join(", "));
+        } else {
+            debug!("Still waiting for {}", pending.join(", "));
+        }
+        last_pending = pending;
+
+        // Don't oversleep the start time or the timeout
+        let mut delay = conditions.interval;
+        if let Some(until) = conditions.until {
+            delay = delay.min((until - Local::now()).to_std().unwrap_or_default());
+        }
+        if let Some(timeout) = conditions.timeout {
+            delay = delay.min(timeout.saturating_sub(start_time.elapsed()));
+        }
+
+        sleep(delay).await;
+    }
+}
+
+// Descriptions of the conditions that aren't met yet
+async fn pending_conditions(
+    extractor: &mut DatabaseExtractor,
+    conditions: &WaitConditions,
+) -> Result<Vec<String>> {
+    let mut pending = Vec::new();
+
+    if let Some(until) = conditions.until {
+        if Local::now() < until {
+            pending.push(format!("{}", until.format("%Y-%m-%d %H:%M")));
+        }
+    }
+
+    if let Some(query) = &conditions.query {
+        let ready = extractor
+            .query_returns_rows(query)
+            .await
+            .with_context(|| format!("Failed to run wait query {}", query))?;
+
+        if !ready {
+            pending.push("the wait query to return a row".to_string());
+        }
+    }
+
+    if let Some(max_replica_lag) = conditions.max_replica_lag {
+        let lag = extractor
+            .fetch_replica_lag()
+            .await
+            .with_context(|| "Failed to fetch the replica lag")?;
+
+        match lag {
+            None => bail!("Replica lag is only available for databases in an availability group"),
+            Some(lag) if lag > max_replica_lag as i64 => pending.push(format!(
+                "the replica lag of {}s to drop to {}s",
+                lag, max_replica_lag
+            )),
+            Some(_) => {}
+        }
+    }
+
+    Ok(pending)
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+
+use crate::common::helpers::print_error_chain;
+use crate::config::{NotificationConfig, NotificationFormat};
+use crate::migrate::observer::{MigrationEvent, MigrationObserver};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Posts the outcome of a migration to the configured webhook
+pub struct WebhookNotifier {
+    client: Client,
+    config: NotificationConfig,
+    // The `--note` of the run
+    note: Option<String>,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: NotificationConfig, note: Option<String>) -> Self {
+        WebhookNotifier {
+            client: Client::new(),
+            config,
+            note,
+        }
+    }
+
+    async fn notify(&self, payload: Value) -> Result<()> {
+        self.client
+            .post(&self.config.webhook_url)
+            .header("Content-Type", "application/json")
+            .body(payload.to_string())
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MigrationObserver for WebhookNotifier {
+    async fn on_event(&self, event: &MigrationEvent) {
+        let MigrationEvent::MigrationFinished {
+            tables,
+            failed_tables,
+            rows,
+            duration,
+            suspended,
+            error,
+        } = event
+        else {
+            return;
+        };
+
+        let failed = *failed_tables > 0 || error.is_some();
+
+        if self.config.failures_only && !failed {
+            return;
+        }
+
+        let payload = match self.config.format {
+            NotificationFormat::Json => json!({
+                "status": if failed {
+                    "failed"
+                } else if *suspended {
+                    "suspended"
+                } else {
+                    "succeeded"
+                },
+                "tables": tables,
+                "failed_tables": failed_tables,
+                "rows": rows,
+                "duration_secs": duration.as_secs_f64(),
+                "error": error,
+                "note": self.note,
+            }),
+            NotificationFormat::Slack => {
+                let message = slack_message(
+                    *tables,
+                    *failed_tables,
+                    *rows,
+                    *duration,
+                    *suspended,
+                    error.as_deref(),
+                );
+
+                json!({
+                    "text": match &self.note {
+                        Some(note) => format!("{}\n> {}", message, note),
+                        None => message,
+                    },
+                })
+            }
+        };
+
+        if let Err(err) = self
+            .notify(payload)
+            .await
+            .with_context(|| "Failed to send webhook notification")
+        {
+            print_error_chain(&err);
+        }
+    }
+}
+
+fn slack_message(
+    tables: usize,
+    failed_tables: usize,
+    rows: usize,
+    duration: Duration,
+    suspended: bool,
+    error: Option<&str>,
+) -> String {
+    let took = format!("{:.1}s", duration.as_secs_f32());
+
+    match error {
+        Some(error) => format!(":x: Migration failed after {}: {}", took, error),
+        None if failed_tables > 0 => format!(
+            ":warning: Migration finished with {} of {} tables failed, {} rows, took {}",
+            failed_tables, tables, rows, took
+        ),
+        None if suspended => format!(
+            ":double_vertical_bar: Migration suspended after {}, {} tables, {} rows, continue it with --resume",
+            took, tables, rows
+        ),
+        None => format!(
+            ":white_check_mark: Migration finished, {} tables, {} rows, took {}",
+            tables, rows, took
+        ),
+    }
+}
@@ -0,0 +1,337 @@
+use std::io::{stdout, IsTerminal};
+
+use anyhow::{Error, Result};
+
+use crate::common::source_table::SourceTable;
+use crate::config::{Config, Dialect};
+use crate::connection::{DatabaseConnectionFactory, SqlxMySqlConnection, TiberiusConnection};
+use crate::extract::extractor::DatabaseExtractor;
+use crate::insert::inserter::DatabaseInserter;
+use crate::migrate::migration_options::MigrationOptions;
+
+pub const PROBE_TABLE: &str = "_db_migrator_check";
+const SMALL_BUFFER_POOL_BYTES: u64 = 512 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum Status {
+    Ready,
+    Warning,
+    Failed,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Status::Ready => "OK",
+            Status::Warning => "WARN",
+            Status::Failed => "FAIL",
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Status::Ready => "\x1b[32m",
+            Status::Warning => "\x1b[33m",
+            Status::Failed => "\x1b[31m",
+        }
+    }
+}
+
+struct Report {
+    worst: Status,
+    colored: bool,
+}
+
+impl Report {
+    fn add(&mut self, status: Status, check: &str, detail: impl AsRef<str>) {
+        if status > self.worst {
+            self.worst = status;
+        }
+
+        let label = format!("{:<4}", status.label());
+        let label = if self.colored {
+            format!("{}{}\x1b[0m", status.color(), label)
+        } else {
+            label
+        };
+
+        println!("[{}] {:<24} {}", label, check, detail.as_ref());
+    }
+
+    fn add_error(&mut self, check: &str, err: &Error) {
+        self.add(Status::Failed, check, format!("{:#}", err));
+    }
+}
+
+// Prints a readiness report of both servers and returns whether nothing failed
+pub async fn run_check(config: &Config, options: &MigrationOptions) -> Result<bool> {
+    let mut report = Report {
+        worst: Status::Ready,
+        colored: stdout().is_terminal(),
+    };
+
+    let mssql_offset = if config.generator().is_some() {
+        report.add(Status::Ready, "Source", "generated tables");
+        None
+    } else {
+        match connect_mssql(config).await {
+            Ok(connection) => {
+                report.add(Status::Ready, "MSSQL connection", "connected");
+
+                let mut extractor = DatabaseExtractor::new(connection.pool);
+                check_mssql(&mut report, &mut extractor, options).await
+            }
+            Err(err) => {
+                report.add_error("MSSQL connection", &err);
+                None
+            }
+        }
+    };
+
+    if config.null_target() {
+        report.add(Status::Ready, "Target", "null, rows are discarded");
+    } else {
+        match connect_mysql(config).await {
+            Ok(connection) => {
+                report.add(Status::Ready, "MySQL connection", "connected");
+
+                let mut inserter = DatabaseInserter::new(connection);
+                check_mysql(&mut report, &mut inserter, options, mssql_offset).await;
+            }
+            Err(err) => report.add_error("MySQL connection", &err),
+        }
+    }
+
+    println!();
+    match report.worst {
+        Status::Ready => println!("Ready to migrate"),
+        Status::Warning => println!("Ready to migrate, review the warnings first"),
+        Status::Failed => println!("Not ready, fix the failed checks first"),
+    }
+
+    Ok(report.worst != Status::Failed)
+}
+
+async fn connect_mssql(config: &Config) -> Result<TiberiusConnection> {
+    DatabaseConnectionFactory::<TiberiusConnection>::new(config.mssql_database()?.clone())
+        .create_connection(1)
+        .await
+}
+
+async fn connect_mysql(config: &Config) -> Result<SqlxMySqlConnection> {
+    DatabaseConnectionFactory::<SqlxMySqlConnection>::new(config.mysql_database()?.clone())
+        .create_connection(1)
+        .await
+}
+
+// Returns the UTC offset of the MSSQL server in minutes, for the time zone comparison
+async fn check_mssql(
+    report: &mut Report,
+    extractor: &mut DatabaseExtractor,
+    options: &MigrationOptions,
+) -> Option<i64> {
+    let tables = match extractor.fetch_tables().await {
+        Ok(tables) => {
+            report.add(
+                Status::Ready,
+                "MSSQL metadata",
+                format!("{} tables visible in INFORMATION_SCHEMA", tables.len()),
+            );
+            tables
+        }
+        Err(err) => {
+            report.add_error("MSSQL metadata", &err);
+            Vec::new()
+        }
+    };
+
+    for pattern in &options.whitelisted_tables {
+        if !tables.iter().any(|table| table.matches(pattern)) {
+            report.add(
+                Status::Warning,
+                "MSSQL table",
+                format!("{} matches no source table", pattern),
+            );
+        }
+    }
+
+    let whitelisted = tables
+        .iter()
+        .filter(|table| {
+            options
+                .whitelisted_tables
+                .iter()
+                .any(|pattern| table.matches(pattern))
+        })
+        .collect::<Vec<&SourceTable>>();
+
+    let mut unreadable = 0;
+
+    for table in &whitelisted {
+        let query = format!("SELECT TOP (0) * FROM {}", table.quoted());
+
+        if let Err(err) = extractor.fetch_formatted_rows(query).await {
+            report.add(
+                Status::Failed,
+                "MSSQL table",
+                format!("{} is not readable: {:#}", table, err),
+            );
+            unreadable += 1;
+        }
+    }
+
+    if !whitelisted.is_empty() && unreadable == 0 {
+        report.add(
+            Status::Ready,
+            "MSSQL tables",
+            format!("all {} whitelisted tables are readable", whitelisted.len()),
+        );
+    }
+
+    extractor
+        .fetch_formatted_rows("SELECT DATEDIFF(MINUTE, GETUTCDATE(), GETDATE())".to_string())
+        .await
+        .ok()
+        .and_then(|rows| rows.into_iter().next())
+        .and_then(|row| row.into_iter().next())
+        .and_then(|value| value.trim_matches('\'').parse().ok())
+}
+
+async fn check_mysql(
+    report: &mut Report,
+    inserter: &mut DatabaseInserter,
+    options: &MigrationOptions,
+    mssql_offset: Option<i64>,
+) {
+    match inserter.get_server_version().await {
+        Ok(version) => match server_dialect(&version) {
+            Some(dialect) if dialect != options.dialect => report.add(
+                Status::Warning,
+                "MySQL version",
+                format!("{}, doesn't match the configured dialect", version),
+            ),
+            _ => report.add(Status::Ready, "MySQL version", version),
+        },
+        Err(err) => report.add_error("MySQL version", &err),
+    }
+
+    check_privileges(report, inserter).await;
+
+    // Tables may send larger batches than the global setting
+    let packet_bytes = options.largest_packet_bytes();
+
+    match inserter.get_max_allowed_packet().await {
+        Ok(max_allowed_packet) if max_allowed_packet < packet_bytes => report.add(
+            Status::Failed,
+            "max_allowed_packet",
+            format!(
+                "{} bytes, below the configured max_packet_bytes of {}",
+                max_allowed_packet, packet_bytes
+            ),
+        ),
+        Ok(max_allowed_packet) => report.add(
+            Status::Ready,
+            "max_allowed_packet",
+            format!("{} bytes", max_allowed_packet),
+        ),
+        Err(err) => report.add_error("max_allowed_packet", &err),
+    }
+
+    match fetch_number(inserter, "SELECT @@innodb_buffer_pool_size").await {
+        Ok(size) if (size as u64) < SMALL_BUFFER_POOL_BYTES => report.add(
+            Status::Warning,
+            "innodb_buffer_pool_size",
+            format!("{} MB, large loads will be slow", size / 1_048_576),
+        ),
+        Ok(size) => report.add(
+            Status::Ready,
+            "innodb_buffer_pool_size",
+            format!("{} MB", size / 1_048_576),
+        ),
+        Err(err) => report.add_error("innodb_buffer_pool_size", &err),
+    }
+
+    let time_zone = inserter
+        .fetch_scalar("SELECT CONCAT(@@time_zone, ' / ', @@system_time_zone)")
+        .await
+        .map(|value| value.unwrap_or_default());
+    let mysql_offset = fetch_number(
+        inserter,
+        "SELECT TIMESTAMPDIFF(MINUTE, UTC_TIMESTAMP(), NOW())",
+    )
+    .await;
+
+    match (time_zone, mysql_offset) {
+        (Ok(time_zone), Ok(mysql_offset)) => match mssql_offset {
+            Some(mssql_offset) if mssql_offset != mysql_offset => report.add(
+                Status::Warning,
+                "time zone",
+                format!(
+                    "{} at UTC{:+} min differs from MSSQL at UTC{:+} min, TIMESTAMP columns and NOW() shift",
+                    time_zone, mysql_offset, mssql_offset
+                ),
+            ),
+            _ => report.add(Status::Ready, "time zone", time_zone),
+        },
+        (Err(err), _) | (_, Err(err)) => report.add_error("time zone", &err),
+    }
+}
+
+// Creates, fills and drops a probe table, which is what a migration needs from the target
+async fn check_privileges(report: &mut Report, inserter: &mut DatabaseInserter) {
+    let steps = [
+        (
+            "MySQL CREATE",
+            format!(
+                "CREATE TABLE `{}` (`id` INT NOT NULL PRIMARY KEY)",
+                PROBE_TABLE
+            ),
+        ),
+        (
+            "MySQL INSERT",
+            format!("INSERT INTO `{}` (`id`) VALUES (1)", PROBE_TABLE),
+        ),
+        ("MySQL DROP", format!("DROP TABLE `{}`", PROBE_TABLE)),
+    ];
+
+    // A leftover of an interrupted check would fail the CREATE step
+    let _ = inserter
+        .execute_transactional_query(&format!("DROP TABLE IF EXISTS `{}`", PROBE_TABLE))
+        .await;
+
+    for (index, (check, query)) in steps.iter().enumerate() {
+        match inserter.execute_transactional_query(query).await {
+            Ok(()) => report.add(Status::Ready, check, "granted"),
+            Err(err) => {
+                report.add_error(check, &err);
+
+                // Without the probe table there is nothing to insert into or drop
+                if index == 0 {
+                    for (check, _) in &steps[1..] {
+                        report.add(Status::Failed, check, "not tested");
+                    }
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn fetch_number(inserter: &mut DatabaseInserter, query: &str) -> Result<i64> {
+    let value = inserter.fetch_scalar(query).await?.unwrap_or_default();
+
+    Ok(value.parse()?)
+}
+
+// Dialect matching the reported server version, newer MySQL versions are treated like 8
+fn server_dialect(version: &str) -> Option<Dialect> {
+    if version.to_lowercase().contains("mariadb") {
+        Some(Dialect::MariaDb)
+    } else if version.starts_with("5.7") {
+        Some(Dialect::Mysql57)
+    } else if version.starts_with("8.") || version.starts_with("9.") {
+        Some(Dialect::Mysql8)
+    } else {
+        None
+    }
+}
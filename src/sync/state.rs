@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Error, Result};
+
+use crate::common::identifiers::quote_literal;
+use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::extract::extractor::DatabaseExtractor;
+use crate::insert::inserter::DatabaseInserter;
+
+pub const SYNC_STATE_TABLE: &str = "_db_migrator_sync";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncMethod {
+    // SQL Server change tracking, captures deletes as well
+    ChangeTracking,
+    // Rows with a rowversion above the synced one, deletes are not detected
+    RowVersion,
+}
+
+impl fmt::Display for SyncMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SyncMethod::ChangeTracking => write!(f, "change_tracking"),
+            SyncMethod::RowVersion => write!(f, "rowversion"),
+        }
+    }
+}
+
+impl FromStr for SyncMethod {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "change_tracking" => Ok(SyncMethod::ChangeTracking),
+            "rowversion" => Ok(SyncMethod::RowVersion),
+            _ => bail!("Unknown sync method: {}", s),
+        }
+    }
+}
+
+// Source version the target table contains all changes up to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyncState {
+    pub method: SyncMethod,
+    pub version: i64,
+}
+
+impl SyncState {
+    // Captured before the rows are read, so changes made during the full load are synced afterwards
+    pub async fn capture(
+        extractor: &mut DatabaseExtractor,
+        table: &SourceTable,
+        schema: &[ColumnSchema],
+    ) -> Result<Option<Self>> {
+        let (change_tracking_version, rowversion) = extractor.fetch_sync_versions(table).await?;
+
+        let state = match (change_tracking_version, rowversion) {
+            (Some(version), _) => Some(SyncState {
+                method: SyncMethod::ChangeTracking,
+                version,
+            }),
+            (None, Some(version)) if rowversion_column(schema).is_some() => Some(SyncState {
+                method: SyncMethod::RowVersion,
+                version,
+            }),
+            _ => None,
+        };
+
+        Ok(state)
+    }
+
+    pub fn build_save_query(&self, source: &str, table_name: &str) -> String {
+        format!(
+            "REPLACE INTO `{}` (`source`, `table_name`, `method`, `version`) VALUES ({}, '{}', '{}', {})",
+            SYNC_STATE_TABLE,
+            quote_literal(source),
+            table_name,
+            self.method,
+            self.version
+        )
+    }
+}
+
+pub fn rowversion_column(schema: &[ColumnSchema]) -> Option<&ColumnSchema> {
+    schema
+        .iter()
+        .find(|column| matches!(column.data_type.as_str(), "timestamp" | "rowversion"))
+}
+
+pub fn build_create_state_table_query() -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS `{}` (\
+            `source` VARCHAR(255) NOT NULL, \
+            `table_name` VARCHAR(64) NOT NULL, \
+            `method` VARCHAR(32) NOT NULL, \
+            `version` BIGINT NOT NULL, \
+            `synced_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP, \
+            PRIMARY KEY (`source`, `table_name`)\
+        )",
+        SYNC_STATE_TABLE
+    )
+}
+
+// States saved before they were kept per source are taken over by the first source using the table
+async fn upgrade_state_table(inserter: &mut DatabaseInserter, source: &str) -> Result<()> {
+    let columns = inserter.get_column_names(SYNC_STATE_TABLE).await?;
+
+    if columns.is_empty() || columns.iter().any(|column| column == "source") {
+        return Ok(());
+    }
+
+    info!(
+        "Assigning the sync baselines in {} to source {}",
+        SYNC_STATE_TABLE, source
+    );
+
+    inserter
+        .execute_transactional_queries(&[
+            format!(
+                "ALTER TABLE `{}` ADD COLUMN `source` VARCHAR(255) NOT NULL DEFAULT '' FIRST, DROP PRIMARY KEY, ADD PRIMARY KEY (`source`, `table_name`)",
+                SYNC_STATE_TABLE
+            ),
+            format!(
+                "UPDATE `{}` SET `source` = {}",
+                SYNC_STATE_TABLE,
+                quote_literal(source)
+            ),
+        ])
+        .await
+        .with_context(|| format!("Failed to upgrade {}", SYNC_STATE_TABLE))
+}
+
+pub async fn save_sync_states(
+    extractor: &mut DatabaseExtractor,
+    inserter: &mut DatabaseInserter,
+    states: &[(String, SyncState)],
+) -> Result<()> {
+    if states.is_empty() {
+        return Ok(());
+    }
+
+    let source = extractor.fetch_source_name().await?;
+
+    upgrade_state_table(inserter, &source).await?;
+
+    let queries = Some(build_create_state_table_query())
+        .into_iter()
+        .chain(
+            states
+                .iter()
+                .map(|(table_name, state)| state.build_save_query(&source, table_name)),
+        )
+        .collect::<Vec<_>>();
+
+    inserter.execute_transactional_queries(&queries).await
+}
+
+// States of the tables synced from this source, other sources syncing into the same target keep their own
+pub async fn load_sync_states(
+    inserter: &mut DatabaseInserter,
+    source: &str,
+) -> Result<HashMap<String, SyncState>> {
+    upgrade_state_table(inserter, source).await?;
+
+    inserter
+        .fetch_sync_states(source)
+        .await?
+        .into_iter()
+        .map(|(table_name, method, version)| {
+            Ok((
+                table_name,
+                SyncState {
+                    method: method.parse()?,
+                    version,
+                },
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,541 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Error, Result};
+use tokio::signal::ctrl_c;
+use tokio::time::{sleep, Instant};
+
+use crate::common::constraints::Constraint;
+use crate::common::helpers::print_error_chain;
+use crate::common::identifiers::quote_identifier;
+use crate::common::schema::ColumnSchema;
+use crate::common::source_table::SourceTable;
+use crate::extract::extractor::{select_list, DatabaseExtractor};
+use crate::insert::inserter::DatabaseInserter;
+use crate::insert::query::{build_insert_statement, build_upsert_clause, extra_column_values};
+use crate::mappings::Mappings;
+use crate::migrate::id_offsets::IdOffsets;
+use crate::migrate::migration_options::MigrationOptions;
+use crate::migrate::migrator::resolve_source_tables;
+use crate::migrate::schema_drift::diff_schema;
+use crate::migrate::table_schema_mapper::{loaded_columns, TableSchemaMapper};
+use crate::migrate::time_zones::TimeZoneConversion;
+use crate::sync::state::{load_sync_states, rowversion_column, SyncMethod, SyncState};
+
+struct SyncTable {
+    source_table: SourceTable,
+    table_name: String,
+    source_schema: Vec<ColumnSchema>,
+    mapped_schema: Vec<ColumnSchema>,
+    // Computed by MySQL, left out of the schemas changes are read and written with
+    generated_columns: Vec<ColumnSchema>,
+    key_columns: Vec<usize>,
+    id_offsets: Option<IdOffsets>,
+    time_zones: Option<TimeZoneConversion>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SchemaDriftAction {
+    // Leave the table out of the pass until its target table matches the source again
+    Warn,
+    // Stop syncing
+    Abort,
+}
+
+impl FromStr for SchemaDriftAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "warn" => Ok(SchemaDriftAction::Warn),
+            "abort" => Ok(SchemaDriftAction::Abort),
+            _ => bail!("Unknown schema drift action: {}", s),
+        }
+    }
+}
+
+// Changes of a single pass over a table
+#[derive(Default)]
+struct ChangeSet {
+    upserts: Vec<Vec<String>>,
+    deletes: Vec<Vec<String>>,
+}
+
+// Repeatedly applies the source changes made since the full migration to the target tables
+pub struct ChangeSyncer {
+    extractor: DatabaseExtractor,
+    inserter: DatabaseInserter,
+    mappings: Mappings,
+    options: MigrationOptions,
+    interval: Duration,
+    once: bool,
+    on_schema_drift: SchemaDriftAction,
+}
+
+impl ChangeSyncer {
+    pub fn new(
+        extractor: DatabaseExtractor,
+        inserter: DatabaseInserter,
+        mappings: Mappings,
+        options: MigrationOptions,
+        interval: Duration,
+        once: bool,
+        on_schema_drift: SchemaDriftAction,
+    ) -> Self {
+        ChangeSyncer {
+            extractor,
+            inserter,
+            mappings,
+            options,
+            interval,
+            once,
+            on_schema_drift,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        let (tables, _) = resolve_source_tables(&mut self.extractor, &self.options).await?;
+        let source = self.extractor.fetch_source_name().await?;
+        let mut states = load_sync_states(&mut self.inserter, &source).await?;
+
+        let mut sync_tables = Vec::new();
+
+        for table in tables {
+            let table_name = self.options.target_table_name(&table);
+
+            if !states.contains_key(&table_name) {
+                warn!(
+                    "Table {} has no sync baseline, enable change tracking or add a rowversion column and migrate it again",
+                    table
+                );
+                continue;
+            }
+
+            sync_tables.push(self.prepare_table(table, table_name).await?);
+        }
+
+        if sync_tables.is_empty() {
+            bail!("No tables to sync");
+        }
+
+        loop {
+            self.sync_pass(&source, &mut sync_tables, &mut states)
+                .await?;
+
+            if self.once {
+                break;
+            }
+
+            tokio::select! {
+                _ = sleep(self.interval) => {}
+                _ = ctrl_c() => {
+                    info!("Sync stopped");
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn prepare_table(&mut self, table: SourceTable, table_name: String) -> Result<SyncTable> {
+        let source_schema = self
+            .extractor
+            .get_table_schema(&table)
+            .await
+            .with_context(|| format!("Failed to get schema of table {}", table))?;
+        let mapped_schema =
+            TableSchemaMapper::map_schema(&self.mappings, &table, &source_schema, &self.options);
+
+        let generated_columns = mapped_schema
+            .iter()
+            .filter(|column| column.computed_definition.is_some())
+            .cloned()
+            .collect::<Vec<_>>();
+        let (source_schema, mapped_schema) = loaded_columns(&source_schema, &mapped_schema);
+
+        let key_columns = source_schema
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| column.constraints == Some(Constraint::PrimaryKey))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        if key_columns.is_empty() {
+            bail!(
+                "Table {} has no primary key, changes cannot be applied",
+                table
+            );
+        }
+
+        // The keys of rows added since the migration were never mapped
+        let regenerated_table = std::iter::once(table.clone())
+            .chain(
+                source_schema
+                    .iter()
+                    .filter_map(|column| match &column.constraints {
+                        Some(Constraint::ForeignKey {
+                            referenced_table, ..
+                        }) => Some(SourceTable::parse(referenced_table)),
+                        _ => None,
+                    }),
+            )
+            .find(|table| self.options.regenerates_keys(table));
+
+        if let Some(regenerated_table) = regenerated_table {
+            bail!(
+                "Table {} has regenerated keys, changes cannot be applied",
+                regenerated_table
+            );
+        }
+
+        let id_offsets = IdOffsets::new(&table, &source_schema, &self.options);
+        let time_zones = TimeZoneConversion::new(&table, &source_schema, &self.options);
+
+        Ok(SyncTable {
+            source_table: table,
+            table_name,
+            source_schema,
+            mapped_schema,
+            generated_columns,
+            key_columns,
+            id_offsets,
+            time_zones,
+        })
+    }
+
+    async fn sync_pass(
+        &mut self,
+        source: &str,
+        tables: &mut [SyncTable],
+        states: &mut HashMap<String, SyncState>,
+    ) -> Result<()> {
+        let start_time = Instant::now();
+        let (mut upserted, mut deleted) = (0, 0);
+
+        for table in tables.iter_mut() {
+            let drift =
+                match self.refresh_table(table).await.with_context(|| {
+                    format!("Failed to check schema of table {}", table.table_name)
+                }) {
+                    Ok(drift) => drift,
+                    Err(err) => {
+                        print_error_chain(&err);
+                        continue;
+                    }
+                };
+
+            if let Some(drift) = drift {
+                match self.on_schema_drift {
+                    SchemaDriftAction::Abort => bail!(
+                        "Schema of table {} changed in the source since it was migrated:{}",
+                        table.source_table,
+                        drift
+                    ),
+                    SchemaDriftAction::Warn => {
+                        warn!(
+                            "SCHEMA DRIFT: table {} changed in the source since it was migrated, its changes are not synced until {} matches it again:{}",
+                            table.source_table, table.table_name, drift
+                        );
+                        continue;
+                    }
+                }
+            }
+
+            let state = states[&table.table_name];
+
+            match self
+                .sync_table(source, table, state)
+                .await
+                .with_context(|| format!("Failed to sync table {}", table.table_name))
+            {
+                Ok((new_state, changes)) => {
+                    if !changes.upserts.is_empty() || !changes.deletes.is_empty() {
+                        debug!(
+                            "Table {} synced, upserted: {}, deleted: {}",
+                            table.table_name,
+                            changes.upserts.len(),
+                            changes.deletes.len()
+                        );
+                    }
+
+                    upserted += changes.upserts.len();
+                    deleted += changes.deletes.len();
+                    states.insert(table.table_name.clone(), new_state);
+                }
+                Err(err) => print_error_chain(&err),
+            }
+        }
+
+        info!(
+            "Sync pass finished, upserted: {}, deleted: {}, took: {}s",
+            upserted,
+            deleted,
+            Instant::now()
+                .saturating_duration_since(start_time)
+                .as_secs_f32()
+        );
+
+        Ok(())
+    }
+
+    // Reads the source schema again, describing how the target table differs from it
+    async fn refresh_table(&mut self, table: &mut SyncTable) -> Result<Option<String>> {
+        let refreshed = self
+            .prepare_table(table.source_table.clone(), table.table_name.clone())
+            .await?;
+        let existing_columns = self
+            .inserter
+            .get_column_definitions(&table.table_name)
+            .await?;
+
+        let target_schema = [
+            refreshed.mapped_schema.as_slice(),
+            &refreshed.generated_columns,
+        ]
+        .concat();
+
+        let drift = diff_schema(
+            &target_schema,
+            &existing_columns,
+            &self.options.extra_columns,
+        )
+        .iter()
+        .map(|change| format!("\n  - {}", change.describe()))
+        .collect::<String>();
+
+        if !drift.is_empty() {
+            return Ok(Some(drift));
+        }
+
+        *table = refreshed;
+
+        Ok(None)
+    }
+
+    async fn sync_table(
+        &mut self,
+        source: &str,
+        table: &SyncTable,
+        state: SyncState,
+    ) -> Result<(SyncState, ChangeSet)> {
+        let (new_state, mut changes) = match state.method {
+            SyncMethod::ChangeTracking => self.fetch_tracked_changes(table, state).await?,
+            SyncMethod::RowVersion => self.fetch_rowversion_changes(table, state).await?,
+        };
+
+        if let Some(id_offsets) = &table.id_offsets {
+            for row in &mut changes.upserts {
+                id_offsets.apply(row)?;
+            }
+
+            // Deleted rows consist of their key columns only
+            if let Some(key_offsets) = id_offsets.select(&table.key_columns) {
+                for row in &mut changes.deletes {
+                    key_offsets.apply(row)?;
+                }
+            }
+        }
+
+        if let Some(time_zones) = &table.time_zones {
+            for row in &mut changes.upserts {
+                time_zones.apply(row)?;
+            }
+
+            if let Some(key_zones) = time_zones.select(&table.key_columns) {
+                for row in &mut changes.deletes {
+                    key_zones.apply(row)?;
+                }
+            }
+        }
+
+        let extra_values = extra_column_values(&self.options.extra_columns);
+
+        for row in &mut changes.upserts {
+            row.extend(extra_values.iter().cloned());
+        }
+
+        let mut queries = Vec::new();
+
+        let key_names = table
+            .key_columns
+            .iter()
+            .map(|index| quote_identifier(&table.mapped_schema[*index].column_name))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        queries.extend(build_batches(
+            &format!(
+                "DELETE FROM {} WHERE ({}) IN (",
+                quote_identifier(&table.table_name),
+                key_names
+            ),
+            &changes.deletes,
+            ")",
+            self.options.packet_bytes(&table.source_table),
+        ));
+
+        queries.extend(build_batches(
+            &build_insert_statement(
+                &table.table_name,
+                &table.mapped_schema,
+                &self.options.extra_columns,
+            ),
+            &changes.upserts,
+            &build_upsert_clause(&table.mapped_schema),
+            self.options.packet_bytes(&table.source_table),
+        ));
+
+        // The new version is stored along with the changes, so an interrupted pass is simply repeated
+        queries.push(new_state.build_save_query(source, &table.table_name));
+
+        self.inserter
+            .execute_transactional_queries(&queries)
+            .await?;
+
+        Ok((new_state, changes))
+    }
+
+    async fn fetch_tracked_changes(
+        &mut self,
+        table: &SyncTable,
+        state: SyncState,
+    ) -> Result<(SyncState, ChangeSet)> {
+        let min_version = self
+            .extractor
+            .fetch_change_tracking_min_version(&table.source_table)
+            .await?;
+
+        if state.version < min_version {
+            bail!(
+                "Changes since version {} were already cleaned up by change tracking, migrate the table again",
+                state.version
+            );
+        }
+
+        // Read before the changes, changes committed in between are applied again by the next pass
+        let version = self
+            .extractor
+            .fetch_sync_versions(&table.source_table)
+            .await?
+            .0
+            .context("Change tracking was disabled")?;
+
+        let key_names = table
+            .key_columns
+            .iter()
+            .map(|index| table.source_schema[*index].column_name.as_str())
+            .collect::<Vec<_>>();
+
+        let query = format!(
+            "SELECT CASE WHEN T.[{}] IS NULL THEN 0 ELSE 1 END, {}, {} \
+            FROM CHANGETABLE(CHANGES {}, {}) AS CT LEFT JOIN {} AS T ON {}",
+            key_names[0],
+            key_names
+                .iter()
+                .map(|name| format!("CT.[{}]", name))
+                .collect::<Vec<_>>()
+                .join(", "),
+            select_list(&table.source_schema, &table.mapped_schema, "T."),
+            table.source_table.quoted(),
+            state.version,
+            table.source_table.quoted(),
+            key_names
+                .iter()
+                .map(|name| format!("T.[{0}] = CT.[{0}]", name))
+                .collect::<Vec<_>>()
+                .join(" AND ")
+        );
+
+        let mut changes = ChangeSet::default();
+
+        // Rows no longer present in the source were deleted, whatever the recorded operation
+        for mut row in self.extractor.fetch_formatted_rows(query).await? {
+            let values = row.split_off(1 + key_names.len());
+
+            if row[0] == "1" {
+                changes.upserts.push(values);
+            } else {
+                changes.deletes.push(row.split_off(1));
+            }
+        }
+
+        Ok((
+            SyncState {
+                method: SyncMethod::ChangeTracking,
+                version,
+            },
+            changes,
+        ))
+    }
+
+    async fn fetch_rowversion_changes(
+        &mut self,
+        table: &SyncTable,
+        state: SyncState,
+    ) -> Result<(SyncState, ChangeSet)> {
+        let version_column =
+            rowversion_column(&table.source_schema).context("The rowversion column was removed")?;
+
+        let version = self
+            .extractor
+            .fetch_sync_versions(&table.source_table)
+            .await?
+            .1
+            .context("Failed to retrieve the current rowversion")?;
+
+        let query = format!(
+            "SELECT {0} FROM {1} WHERE [{2}] > CAST({3} AS BINARY(8)) AND [{2}] <= CAST({4} AS BINARY(8))",
+            select_list(&table.source_schema, &table.mapped_schema, ""),
+            table.source_table.quoted(),
+            version_column.column_name,
+            state.version,
+            version
+        );
+
+        let changes = ChangeSet {
+            upserts: self.extractor.fetch_formatted_rows(query).await?,
+            deletes: Vec::new(),
+        };
+
+        Ok((
+            SyncState {
+                method: SyncMethod::RowVersion,
+                version,
+            },
+            changes,
+        ))
+    }
+}
+
+// Splits the value tuples into statements staying below the packet size
+fn build_batches(
+    prefix: &str,
+    rows: &[Vec<String>],
+    suffix: &str,
+    max_packet_bytes: usize,
+) -> Vec<String> {
+    let mut batches = Vec::new();
+    let mut tuples: Vec<String> = Vec::new();
+    let mut length = prefix.len() + suffix.len();
+
+    for row in rows {
+        let tuple = format!("({})", row.join(", "));
+
+        if !tuples.is_empty() && length + tuple.len() + 1 > max_packet_bytes {
+            batches.push(format!("{}{}{}", prefix, tuples.join(","), suffix));
+            tuples.clear();
+            length = prefix.len() + suffix.len();
+        }
+
+        length += tuple.len() + 1;
+        tuples.push(tuple);
+    }
+
+    if !tuples.is_empty() {
+        batches.push(format!("{}{}{}", prefix, tuples.join(","), suffix));
+    }
+
+    batches
+}
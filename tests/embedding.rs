@@ -0,0 +1,117 @@
+use std::fs;
+
+use anyhow::Result;
+use db_migrator::{
+    Config, DatabaseConfig, Mapping, Mappings, MigrationEvent, MigrationOptions, Migrator,
+    PoolConfig, SettingsConfig,
+};
+
+const CONFIG: &str = r#"
+[mssql_database]
+host = "mssql.example.com"
+port = 1433
+username = "reader"
+password = "secret"
+database = "sales"
+
+[mysql_database]
+host = "mysql.example.com"
+port = 3306
+username = "writer"
+password = "secret"
+database = "sales"
+
+[settings]
+max_packet_bytes = 1048576
+collation = "Latin1_General_CI_AS"
+whitelisted_tables = ["dbo.orders"]
+"#;
+
+fn database(host: &str) -> DatabaseConfig {
+    DatabaseConfig {
+        host: host.to_string(),
+        port: 3306,
+        username: "user".to_string(),
+        password: "secret".to_string(),
+        database: "sales".to_string(),
+        instance: None,
+        socket: None,
+        connect_timeout: None,
+        ssl_mode: None,
+        ssl_ca: None,
+        init_sql: Vec::new(),
+        session_variables: Vec::new(),
+        pool: PoolConfig::default(),
+    }
+}
+
+#[test]
+fn builds_a_migrator_from_config_files() -> Result<()> {
+    let config_file = std::env::temp_dir().join(format!("db-migrator-{}.toml", std::process::id()));
+    fs::write(&config_file, CONFIG)?;
+
+    let config = Config::load(&config_file);
+    fs::remove_file(&config_file)?;
+    let config = config?;
+
+    assert_eq!(config.settings().whitelisted_tables, vec!["dbo.orders"]);
+
+    let options = MigrationOptions::new(config.settings().clone())
+        .with_drop(true)
+        .with_constraints(true);
+
+    Migrator::builder()
+        .source(config.mssql_database()?.clone())
+        .target(config.mysql_database()?.clone())
+        .mappings_file(concat!(env!("CARGO_MANIFEST_DIR"), "/mappings.toml"))
+        .options(options)
+        .observer(|event: &MigrationEvent| println!("{:?}", event))
+        .build()?;
+
+    Ok(())
+}
+
+#[test]
+fn builds_a_migrator_without_files() -> Result<()> {
+    let settings = SettingsConfig {
+        whitelisted_tables: vec!["dbo.orders".to_string()],
+        ..SettingsConfig::default()
+    };
+
+    let mappings = Mappings::default().with_mapping(
+        "int",
+        Mapping {
+            to_type: "int".to_string(),
+            type_parameters: false,
+            numeric_precision: None,
+            numeric_scale: None,
+            max_characters_length: None,
+            max_type: None,
+        },
+    );
+
+    assert_eq!(
+        mappings.get("int").map(|mapping| mapping.to_type.as_str()),
+        Some("int")
+    );
+
+    Migrator::builder()
+        .source(database("mssql.example.com"))
+        .target(database("mysql.example.com"))
+        .mappings(mappings)
+        .options(MigrationOptions::new(settings).with_parallelism(2))
+        .build()?;
+
+    Ok(())
+}
+
+#[test]
+fn fails_to_build_without_a_source() {
+    let result = Migrator::builder()
+        .target(database("mysql.example.com"))
+        .mappings(Mappings::default())
+        .options(MigrationOptions::new(SettingsConfig::default()))
+        .build();
+
+    assert!(result.is_err());
+}